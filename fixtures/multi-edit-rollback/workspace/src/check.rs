@@ -0,0 +1,5 @@
+use crate::config::LIMIT;
+
+pub fn check(n: usize) -> bool {
+    n < LIMIT
+}
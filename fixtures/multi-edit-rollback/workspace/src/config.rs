@@ -0,0 +1 @@
+pub const LIMIT: usize = 10;
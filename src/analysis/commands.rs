@@ -0,0 +1,148 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// A command the project defines for building, testing or running it
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectCommand {
+    pub command: String,
+    /// What it runs or where it comes from
+    pub description: String,
+}
+
+#[derive(Deserialize, Default)]
+struct ScriptsManifest {
+    #[serde(default)]
+    scripts: BTreeMap<String, serde_json::Value>,
+}
+
+/// Commands of the project at `root`: npm scripts, Makefile targets, cargo
+/// commands, composer scripts and tox environments or pytest
+pub fn detect_commands(root: &Path) -> Vec<ProjectCommand> {
+    let mut commands = Vec::new();
+    npm_commands(root, &mut commands);
+    make_commands(root, &mut commands);
+    cargo_commands(root, &mut commands);
+    composer_commands(root, &mut commands);
+    python_commands(root, &mut commands);
+    commands
+}
+
+fn npm_commands(root: &Path, commands: &mut Vec<ProjectCommand>) {
+    let manifest = match read_manifest(&root.join("package.json")) {
+        Some(manifest) => manifest,
+        None => return,
+    };
+    // The package manager whose lockfile is checked in
+    let runner = if root.join("pnpm-lock.yaml").exists() {
+        "pnpm run"
+    } else if root.join("yarn.lock").exists() {
+        "yarn"
+    } else if root.join("bun.lockb").exists() || root.join("bun.lock").exists() {
+        "bun run"
+    } else {
+        "npm run"
+    };
+    for (name, script) in manifest.scripts {
+        if let Some(script) = script.as_str() {
+            commands.push(ProjectCommand { command: format!("{} {}", runner, name), description: script.to_string() });
+        }
+    }
+}
+
+fn make_commands(root: &Path, commands: &mut Vec<ProjectCommand>) {
+    let content = ["GNUmakefile", "makefile", "Makefile"]
+        .iter()
+        .find_map(|name| std::fs::read_to_string(root.join(name)).ok());
+    let content = match content {
+        Some(content) => content,
+        None => return,
+    };
+    // Rule targets, leaving out special targets, pattern rules, file targets
+    // and variable assignments
+    let rule = Regex::new(r"(?m)^([A-Za-z0-9_][A-Za-z0-9_-]*(?:[ \t]+[A-Za-z0-9_][A-Za-z0-9_-]*)*)[ \t]*::?(?:[^=]|$)")
+        .expect("valid make rule pattern");
+    let mut seen: Vec<&str> = Vec::new();
+    for captures in rule.captures_iter(&content) {
+        for name in captures.get(1).map_or("", |m| m.as_str()).split_whitespace() {
+            if !seen.contains(&name) {
+                seen.push(name);
+                commands.push(ProjectCommand { command: format!("make {}", name), description: "Makefile target".to_string() });
+            }
+        }
+    }
+}
+
+fn cargo_commands(root: &Path, commands: &mut Vec<ProjectCommand>) {
+    if !root.join("Cargo.toml").exists() {
+        return;
+    }
+    let mut cargo = vec![
+        ("cargo build", "build"),
+        ("cargo test", "run the tests"),
+        ("cargo clippy --all-targets", "lint"),
+        ("cargo fmt", "format"),
+    ];
+    if root.join("src/main.rs").exists() || root.join("src/bin").is_dir() {
+        cargo.insert(1, ("cargo run", "build and run"));
+    }
+    for (command, description) in cargo {
+        commands.push(ProjectCommand { command: command.to_string(), description: description.to_string() });
+    }
+}
+
+fn composer_commands(root: &Path, commands: &mut Vec<ProjectCommand>) {
+    let manifest = match read_manifest(&root.join("composer.json")) {
+        Some(manifest) => manifest,
+        None => return,
+    };
+    for (name, script) in manifest.scripts {
+        // A script is a command or a list of them
+        let description = match script {
+            serde_json::Value::String(script) => script,
+            serde_json::Value::Array(steps) => steps.iter().filter_map(|step| step.as_str()).collect::<Vec<_>>().join(" && "),
+            _ => continue,
+        };
+        commands.push(ProjectCommand { command: format!("composer run-script {}", name), description });
+    }
+}
+
+fn python_commands(root: &Path, commands: &mut Vec<ProjectCommand>) {
+    let tox = std::fs::read_to_string(root.join("tox.ini")).unwrap_or_default();
+    let envlist = Regex::new(r"(?m)^envlist\s*=\s*((?:.*\n?)(?:[ \t]+.*\n?)*)").expect("valid tox envlist pattern");
+    if let Some(captures) = envlist.captures(&tox) {
+        commands.push(ProjectCommand { command: "tox".to_string(), description: "run every tox environment".to_string() });
+        for env in captures[1].split(|c: char| c == ',' || c.is_whitespace()).filter(|env| !env.is_empty()) {
+            // Generative names like `py{38,39}` aren't runnable as listed
+            if env.contains(['{', '}']) {
+                continue;
+            }
+            commands.push(ProjectCommand { command: format!("tox -e {}", env), description: "tox environment".to_string() });
+        }
+    }
+
+    let setup_cfg = std::fs::read_to_string(root.join("setup.cfg")).unwrap_or_default();
+    let pyproject = std::fs::read_to_string(root.join("pyproject.toml")).unwrap_or_default();
+    let configured = if root.join("pytest.ini").exists() {
+        Some("pytest.ini")
+    } else if pyproject.contains("[tool.pytest.ini_options]") {
+        Some("pyproject.toml")
+    } else if tox.contains("[pytest]") {
+        Some("tox.ini")
+    } else if setup_cfg.contains("[tool:pytest]") {
+        Some("setup.cfg")
+    } else if root.join("conftest.py").exists() {
+        Some("conftest.py")
+    } else {
+        None
+    };
+    if let Some(source) = configured {
+        commands.push(ProjectCommand { command: "pytest".to_string(), description: format!("configured in {}", source) });
+    }
+}
+
+fn read_manifest(path: &Path) -> Option<ScriptsManifest> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
@@ -0,0 +1,1292 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use tree_sitter::Node;
+
+use crate::analysis::syntax::{self, Grammar};
+use crate::analysis::xref::{self, Location, SymbolIndex};
+
+/// Names in scope in every Rust module; a project type with one of these
+/// names is never imported over them
+const RUST_PRELUDE: &[&str] = &[
+    "Self", "String", "Vec", "Option", "Some", "None", "Result", "Ok", "Err", "Box", "ToString", "ToOwned",
+    "Default", "Clone", "Copy", "Send", "Sync", "Sized", "Unpin", "Drop", "Fn", "FnMut", "FnOnce", "From",
+    "Into", "TryFrom", "TryInto", "AsRef", "AsMut", "Iterator", "IntoIterator", "DoubleEndedIterator",
+    "ExactSizeIterator", "Extend", "FromIterator", "PartialEq", "Eq", "PartialOrd", "Ord", "Debug", "Hash",
+];
+
+/// Standard library types and their modules. None of them are traits, so an
+/// unused import of one can be removed without knowing what methods are called.
+const RUST_STD_TYPES: &[(&str, &str)] = &[
+    ("HashMap", "std::collections"),
+    ("HashSet", "std::collections"),
+    ("BTreeMap", "std::collections"),
+    ("BTreeSet", "std::collections"),
+    ("VecDeque", "std::collections"),
+    ("BinaryHeap", "std::collections"),
+    ("Path", "std::path"),
+    ("PathBuf", "std::path"),
+    ("Arc", "std::sync"),
+    ("Mutex", "std::sync"),
+    ("RwLock", "std::sync"),
+    ("Rc", "std::rc"),
+    ("RefCell", "std::cell"),
+    ("Cell", "std::cell"),
+    ("Cow", "std::borrow"),
+    ("Duration", "std::time"),
+    ("Instant", "std::time"),
+    ("SystemTime", "std::time"),
+    ("File", "std::fs"),
+    ("OpenOptions", "std::fs"),
+    ("Command", "std::process"),
+    ("Stdio", "std::process"),
+    ("PhantomData", "std::marker"),
+];
+
+const PYTHON_BUILTINS: &[&str] = &[
+    "print", "len", "range", "str", "int", "float", "bool", "bytes", "dict", "list", "set", "tuple", "object",
+    "type", "super", "isinstance", "issubclass", "open", "iter", "next", "map", "filter", "zip", "enumerate",
+    "sorted", "reversed", "min", "max", "sum", "any", "all", "abs", "getattr", "setattr", "hasattr", "repr",
+    "property", "staticmethod", "classmethod", "Exception", "ValueError", "TypeError", "KeyError",
+    "RuntimeError", "NotImplementedError", "self", "cls",
+];
+
+const JS_GLOBALS: &[&str] = &[
+    "console", "window", "document", "globalThis", "process", "require", "module", "Math", "JSON", "Promise",
+    "Object", "Array", "String", "Number", "Boolean", "Symbol", "Error", "Date", "Map", "Set", "RegExp",
+    "Record", "Partial", "Readonly", "Pick", "Omit", "ReturnType", "Exclude",
+];
+
+/// Top-level modules of the Python standard library, for isort's sections
+const PYTHON_STDLIB: &[&str] = &[
+    "abc", "argparse", "ast", "asyncio", "base64", "bisect", "collections", "concurrent", "contextlib", "copy",
+    "csv", "dataclasses", "datetime", "decimal", "enum", "functools", "glob", "hashlib", "heapq", "hmac",
+    "http", "importlib", "inspect", "io", "itertools", "json", "logging", "math", "multiprocessing", "operator",
+    "os", "pathlib", "pickle", "platform", "pprint", "queue", "random", "re", "shlex", "shutil", "signal",
+    "socket", "sqlite3", "statistics", "string", "struct", "subprocess", "sys", "tempfile", "textwrap",
+    "threading", "time", "timeit", "traceback", "types", "typing", "unittest", "urllib", "uuid", "warnings",
+    "weakref", "xml", "zipfile",
+];
+
+/// Line length above which a Python `from` import is wrapped in parentheses
+const PYTHON_LINE_LENGTH: usize = 88;
+
+/// Imports added to and removed from a file by [`organize`]
+#[derive(Debug, Default)]
+pub struct ImportChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Names used in the file that several project files define
+    pub ambiguous: Vec<String>,
+    /// The file with its imports organized
+    pub content: String,
+}
+
+impl ImportChanges {
+    /// One line description, e.g. "added Config; removed HashMap"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.added.is_empty() {
+            parts.push(format!("added {}", self.added.join(", ")));
+        }
+        if !self.removed.is_empty() {
+            parts.push(format!("removed {}", self.removed.join(", ")));
+        }
+        if !self.ambiguous.is_empty() {
+            parts.push(format!("not added, defined in several files: {}", self.ambiguous.join(", ")));
+        }
+        if parts.is_empty() {
+            parts.push("sorted".to_string());
+        }
+        parts.join("; ")
+    }
+}
+
+/// Whether imports in files with this extension can be organized
+pub fn is_supported(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(Grammar::from_extension)
+        .and_then(Lang::from_grammar)
+        .is_some()
+}
+
+/// Adds missing imports, removes unused ones and sorts them the way the
+/// project does (rustfmt, isort or the file's own grouping). Missing names
+/// are resolved through the symbol index and only added when exactly one
+/// project file exports them. Returns None for unsupported languages.
+pub fn organize(path: &Path, content: &str, root: &Path, index: &SymbolIndex) -> Result<Option<ImportChanges>> {
+    let grammar = match path.extension().and_then(|e| e.to_str()).and_then(Grammar::from_extension) {
+        Some(grammar) => grammar,
+        None => return Ok(None),
+    };
+    let lang = match Lang::from_grammar(grammar) {
+        Some(lang) => lang,
+        None => return Ok(None),
+    };
+    let tree = syntax::parse(grammar, content)?;
+    let conventions = Conventions::load(root);
+
+    let mut file = SourceFile {
+        lang,
+        path,
+        root,
+        source: content.as_bytes(),
+        lines: content.split_inclusive('\n').collect(),
+        imports: Vec::new(),
+        used: HashSet::new(),
+        bound: HashSet::new(),
+        candidates: Vec::new(),
+        has_jsx: false,
+    };
+    file.collect(tree.root_node());
+
+    let mut changes = ImportChanges::default();
+    // Several statements on one line are left for a formatter to split first
+    let mut rows: Vec<(usize, usize)> = file.imports.iter().filter_map(|i| i.rows).collect();
+    rows.sort();
+    if rows.windows(2).any(|pair| pair[1].0 <= pair[0].1) {
+        changes.content = content.to_string();
+        return Ok(Some(changes));
+    }
+
+    file.remove_unused(index, &mut changes);
+    file.add_missing(index, &mut changes);
+    changes.content = file.render(&conventions);
+    Ok(Some(changes))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lang {
+    Rust,
+    Python,
+    Js,
+}
+
+impl Lang {
+    fn from_grammar(grammar: Grammar) -> Option<Self> {
+        match grammar {
+            Grammar::Rust => Some(Lang::Rust),
+            Grammar::Python => Some(Lang::Python),
+            Grammar::JavaScript | Grammar::TypeScript | Grammar::Tsx => Some(Lang::Js),
+            Grammar::Go | Grammar::Php => None,
+        }
+    }
+
+    fn is_import(self, kind: &str) -> bool {
+        match self {
+            Lang::Rust => kind == "use_declaration",
+            Lang::Python => matches!(kind, "import_statement" | "import_from_statement" | "future_import_statement"),
+            Lang::Js => kind == "import_statement",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ImportKind {
+    /// `use a::b::{C, D};`
+    RustUse,
+    /// `import os, sys as system`
+    PythonImport,
+    /// `from a.b import C, D as E`
+    PythonFrom,
+    /// `import D, { a, b as c } from 'm';` or `import * as ns from 'm';`
+    Js,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Binding {
+    Named,
+    /// JS default import
+    Default,
+    /// JS `* as name`
+    Namespace,
+}
+
+/// An item of an import statement and the names it brings into scope
+#[derive(Debug, Clone)]
+struct ImportedName {
+    item: String,
+    locals: Vec<String>,
+    binding: Binding,
+}
+
+#[derive(Debug)]
+struct Import {
+    /// First and last row, attributes included; None for added imports
+    rows: Option<(usize, usize)>,
+    original: String,
+    kind: ImportKind,
+    /// Path or module the names come from
+    module: String,
+    names: Vec<ImportedName>,
+    /// Re-exports, side effects, wildcards and attributed imports are never changed
+    pinned: bool,
+    changed: bool,
+    /// `import type` in TypeScript
+    type_only: bool,
+}
+
+impl Import {
+    fn is_empty(&self) -> bool {
+        !self.pinned && self.names.is_empty()
+    }
+}
+
+struct SourceFile<'a> {
+    lang: Lang,
+    path: &'a Path,
+    root: &'a Path,
+    source: &'a [u8],
+    lines: Vec<&'a str>,
+    imports: Vec<Import>,
+    /// Identifiers used outside import statements
+    used: HashSet<String>,
+    /// Names the file defines or binds itself
+    bound: HashSet<String>,
+    /// Identifiers in positions where a missing import could supply them
+    candidates: Vec<String>,
+    has_jsx: bool,
+}
+
+impl<'a> SourceFile<'a> {
+    fn text(&self, node: Node) -> &'a str {
+        node.utf8_text(self.source).unwrap_or("")
+    }
+
+    fn collect(&mut self, root: Node) {
+        let mut cursor = root.walk();
+        let children: Vec<Node> = root.named_children(&mut cursor).collect();
+        for (i, child) in children.iter().enumerate() {
+            if self.lang.is_import(child.kind()) {
+                // Attributes such as #[cfg(test)] belong to the use below them
+                let mut start = child.start_position().row;
+                let mut attributed = false;
+                let mut j = i;
+                while j > 0 && children[j - 1].kind() == "attribute_item" {
+                    j -= 1;
+                    start = children[j].start_position().row;
+                    attributed = true;
+                }
+                if let Some(mut import) = self.parse_import(*child) {
+                    let end = child.end_position().row;
+                    import.rows = Some((start, end));
+                    // Whole lines, so attributes and trailing comments survive sorting
+                    import.original = self.lines[start..=end].concat().trim_end_matches('\n').to_string();
+                    import.pinned |= attributed;
+                    self.imports.push(import);
+                }
+            } else {
+                self.visit(*child);
+            }
+        }
+    }
+
+    /// Records identifier usage, bindings and candidates in a non-import node
+    fn visit(&mut self, node: Node) {
+        if self.lang.is_import(node.kind()) {
+            // Imports inside functions bind their names locally
+            self.bind_all(node);
+            return;
+        }
+        if node.kind().starts_with("jsx_") {
+            self.has_jsx = true;
+        }
+        if matches!(node.kind(), "identifier" | "type_identifier" | "shorthand_property_identifier" | "name") {
+            let name = self.text(node).to_string();
+            if self.is_binding(node) {
+                self.bound.insert(name.clone());
+            } else if self.is_candidate(node) {
+                self.candidates.push(name.clone());
+            }
+            self.used.insert(name);
+        }
+        if self.lang == Lang::Python && node.kind() == "string" && node.parent().is_some_and(|p| p.kind() == "type") {
+            // Forward references such as "Config" in annotations
+            let words = self.text(node).split(|c: char| !c.is_alphanumeric() && c != '_');
+            self.used.extend(words.map(String::from));
+        }
+        if self.lang == Lang::Python && node.kind() == "assignment" {
+            // Names listed in __all__ are re-exported
+            let exports_all = node.child_by_field_name("left").is_some_and(|l| self.text(l) == "__all__");
+            if exports_all {
+                let words = self.text(node).split(|c: char| !c.is_alphanumeric() && c != '_');
+                self.used.extend(words.map(String::from));
+            }
+        }
+        if let Some(name) = node.child_by_field_name("name") {
+            if is_definition(self.lang, node.kind()) {
+                self.bound.insert(self.text(name).to_string());
+            }
+        }
+
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            self.visit(child);
+        }
+    }
+
+    fn bind_all(&mut self, node: Node) {
+        if matches!(node.kind(), "identifier" | "type_identifier") {
+            self.bound.insert(self.text(node).to_string());
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            self.bind_all(child);
+        }
+    }
+
+    /// Whether the identifier declares a variable, parameter or generic type
+    fn is_binding(&self, node: Node) -> bool {
+        let parent = match node.parent() {
+            Some(parent) => parent,
+            None => return false,
+        };
+        let in_field = |field: &str| parent.child_by_field_name(field).is_some_and(|n| n.id() == node.id());
+        match self.lang {
+            Lang::Rust => match parent.kind() {
+                "type_parameters" | "constrained_type_parameter" | "type_parameter" | "let_declaration"
+                | "parameter" | "closure_parameters" | "tuple_pattern" | "for_expression" => {
+                    !in_field("type") && !in_field("value") && !in_field("bounds")
+                }
+                _ => false,
+            },
+            Lang::Python => match parent.kind() {
+                "parameters" | "lambda_parameters" | "list_splat_pattern" | "dictionary_splat_pattern"
+                | "pattern_list" | "tuple_pattern" | "list_pattern" | "as_pattern_target" | "global_statement"
+                | "nonlocal_statement" => true,
+                "default_parameter" | "typed_default_parameter" | "keyword_argument" => in_field("name"),
+                "typed_parameter" => parent.named_child(0).is_some_and(|n| n.id() == node.id()),
+                "assignment" | "augmented_assignment" | "for_statement" | "for_in_clause" => in_field("left"),
+                "as_pattern" => in_field("alias"),
+                _ => false,
+            },
+            Lang::Js => match parent.kind() {
+                "variable_declarator" => in_field("name"),
+                "required_parameter" | "optional_parameter" | "assignment_pattern" => {
+                    in_field("pattern") || in_field("left")
+                }
+                "formal_parameters" | "array_pattern" | "object_pattern" | "rest_pattern" | "catch_clause" => true,
+                "arrow_function" => in_field("parameter"),
+                "pair_pattern" => in_field("value"),
+                "type_parameter" => in_field("name"),
+                "for_in_statement" => in_field("left"),
+                _ => false,
+            },
+        }
+    }
+
+    /// Whether the identifier is used where an imported type, class or
+    /// function would be, e.g. `Config::load`, `Foo(...)` or `<Widget />`
+    fn is_candidate(&self, node: Node) -> bool {
+        let parent = match node.parent() {
+            Some(parent) => parent,
+            None => return false,
+        };
+        let name = self.text(node);
+        let in_field = |field: &str| parent.child_by_field_name(field).is_some_and(|n| n.id() == node.id());
+        let capitalized = name.starts_with(|c: char| c.is_ascii_uppercase());
+        match self.lang {
+            Lang::Rust => {
+                capitalized
+                    && match (node.kind(), parent.kind()) {
+                        ("type_identifier", "scoped_type_identifier") => in_field("path"),
+                        ("type_identifier", _) => true,
+                        ("identifier", "scoped_identifier") => in_field("path"),
+                        ("identifier", "call_expression") => in_field("function"),
+                        _ => false,
+                    }
+            }
+            Lang::Python => match parent.kind() {
+                "call" => in_field("function"),
+                "attribute" => in_field("object"),
+                "decorator" | "type" | "generic_type" => true,
+                "argument_list" => parent.parent().is_some_and(|p| p.kind() == "class_definition"),
+                _ => false,
+            },
+            Lang::Js => match (node.kind(), parent.kind()) {
+                ("type_identifier", "type_parameter") => false,
+                ("type_identifier", _) => true,
+                (_, "call_expression") => in_field("function"),
+                (_, "new_expression") => in_field("constructor"),
+                (_, "member_expression") => in_field("object") && capitalized,
+                (_, "jsx_opening_element" | "jsx_self_closing_element") => capitalized,
+                (_, "class_heritage" | "extends_clause") => true,
+                _ => false,
+            },
+        }
+    }
+
+    fn parse_import(&self, node: Node) -> Option<Import> {
+        let original = self.text(node).to_string();
+        let mut import = Import {
+            rows: None,
+            original: original.clone(),
+            kind: ImportKind::RustUse,
+            module: String::new(),
+            names: Vec::new(),
+            pinned: false,
+            changed: false,
+            type_only: false,
+        };
+
+        match (self.lang, node.kind()) {
+            (Lang::Rust, _) => {
+                // pub use re-exports
+                import.pinned = children_of_kind(node, "visibility_modifier").next().is_some();
+                let argument = node.child_by_field_name("argument")?;
+                match argument.kind() {
+                    "scoped_use_list" => {
+                        import.module = argument.child_by_field_name("path").map(|p| self.text(p).to_string())?;
+                        let list = argument.child_by_field_name("list")?;
+                        let mut cursor = list.walk();
+                        for item in list.named_children(&mut cursor) {
+                            let locals = self.rust_locals(item, &import.module);
+                            if locals.is_empty() {
+                                import.pinned = true;
+                            }
+                            import.names.push(named(self.text(item), locals));
+                        }
+                    }
+                    "scoped_identifier" => {
+                        import.module = self.text(argument.child_by_field_name("path")?).to_string();
+                        let item = self.text(argument.child_by_field_name("name")?);
+                        import.names.push(named(item, self.rust_locals(argument, &import.module)));
+                    }
+                    "use_as_clause" => {
+                        // use a::b::C as D;
+                        let path = argument.child_by_field_name("path")?;
+                        let alias = self.text(argument.child_by_field_name("alias")?);
+                        let (module, item) = match path.kind() {
+                            "scoped_identifier" => (
+                                self.text(path.child_by_field_name("path")?),
+                                self.text(path.child_by_field_name("name")?),
+                            ),
+                            _ => ("", self.text(path)),
+                        };
+                        let locals = self.rust_locals(argument, module);
+                        import.pinned = locals.is_empty();
+                        import.module = module.to_string();
+                        import.names.push(named(&format!("{} as {}", item, alias), locals));
+                    }
+                    "identifier" => import.names.push(named(self.text(argument), vec![self.text(argument).to_string()])),
+                    // Globs and `use {a, b};`
+                    _ => import.pinned = true,
+                }
+            }
+            (Lang::Python, "future_import_statement") => {
+                import.kind = ImportKind::PythonFrom;
+                import.module = "__future__".to_string();
+                import.pinned = true;
+            }
+            (Lang::Python, "import_statement") => {
+                import.kind = ImportKind::PythonImport;
+                let mut cursor = node.walk();
+                for item in node.children_by_field_name("name", &mut cursor) {
+                    let (module, local) = match item.kind() {
+                        "aliased_import" => (
+                            self.text(item.child_by_field_name("name")?),
+                            self.text(item.child_by_field_name("alias")?),
+                        ),
+                        _ => {
+                            let module = self.text(item);
+                            (module, module.split('.').next().unwrap_or(module))
+                        }
+                    };
+                    if import.module.is_empty() {
+                        import.module = module.to_string();
+                    }
+                    import.names.push(named(self.text(item), vec![local.to_string()]));
+                }
+            }
+            (Lang::Python, _) => {
+                import.kind = ImportKind::PythonFrom;
+                import.module = self.text(node.child_by_field_name("module_name")?).to_string();
+                import.pinned = children_of_kind(node, "wildcard_import").next().is_some();
+                let mut cursor = node.walk();
+                for item in node.children_by_field_name("name", &mut cursor) {
+                    let local = match item.kind() {
+                        "aliased_import" => self.text(item.child_by_field_name("alias")?),
+                        _ => self.text(item),
+                    };
+                    import.names.push(named(self.text(item), vec![local.to_string()]));
+                }
+            }
+            (Lang::Js, _) => {
+                import.kind = ImportKind::Js;
+                import.type_only = original.starts_with("import type ");
+                let source = node.child_by_field_name("source")?;
+                import.module = self.text(source).trim_matches(|c| c == '\'' || c == '"').to_string();
+                let clause = match children_of_kind(node, "import_clause").next() {
+                    Some(clause) => clause,
+                    None => {
+                        // import './styles.css';
+                        import.pinned = true;
+                        return Some(import);
+                    }
+                };
+                let mut cursor = clause.walk();
+                for part in clause.named_children(&mut cursor) {
+                    match part.kind() {
+                        "identifier" => {
+                            let mut name = named(self.text(part), vec![self.text(part).to_string()]);
+                            name.binding = Binding::Default;
+                            import.names.push(name);
+                        }
+                        "namespace_import" => {
+                            let local = children_of_kind(part, "identifier").next().map(|n| self.text(n))?;
+                            let mut name = named(self.text(part), vec![local.to_string()]);
+                            name.binding = Binding::Namespace;
+                            import.names.push(name);
+                        }
+                        "named_imports" => {
+                            let mut cursor = part.walk();
+                            for specifier in part.named_children(&mut cursor) {
+                                let local = specifier
+                                    .child_by_field_name("alias")
+                                    .or_else(|| specifier.child_by_field_name("name"))?;
+                                import.names.push(named(self.text(specifier), vec![self.text(local).to_string()]));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Some(import)
+    }
+
+    /// Names a Rust use tree binds; empty for globs and `as _`
+    fn rust_locals(&self, item: Node, module: &str) -> Vec<String> {
+        match item.kind() {
+            "identifier" => vec![self.text(item).to_string()],
+            "self" => module.rsplit("::").next().map(String::from).into_iter().collect(),
+            "scoped_identifier" => item.child_by_field_name("name").map(|n| self.text(n).to_string()).into_iter().collect(),
+            "use_as_clause" => {
+                let alias = item.child_by_field_name("alias").map(|a| self.text(a)).unwrap_or("_");
+                if alias == "_" {
+                    Vec::new()
+                } else {
+                    vec![alias.to_string()]
+                }
+            }
+            "scoped_use_list" | "use_list" => {
+                let list = if item.kind() == "use_list" { Some(item) } else { item.child_by_field_name("list") };
+                let mut locals = Vec::new();
+                if let Some(list) = list {
+                    let mut cursor = list.walk();
+                    for child in list.named_children(&mut cursor) {
+                        let nested = self.rust_locals(child, module);
+                        if nested.is_empty() {
+                            return Vec::new();
+                        }
+                        locals.extend(nested);
+                    }
+                }
+                locals
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn remove_unused(&mut self, index: &SymbolIndex, changes: &mut ImportChanges) {
+        // Everything an __init__.py imports is part of the package's interface
+        if self.lang == Lang::Python && self.path.file_name().is_some_and(|n| n == "__init__.py") {
+            return;
+        }
+
+        let used = &self.used;
+        let has_jsx = self.has_jsx;
+        let lang = self.lang;
+        // A Rust type may be a trait imported only for its methods; only
+        // types the project defines, none of them traits, can be removed
+        let may_be_trait = |name: &str| {
+            if RUST_STD_TYPES.iter().any(|(n, _)| *n == name) {
+                return false;
+            }
+            let definitions = index.definitions(name);
+            definitions.is_empty() || definitions.iter().any(|d| d.kind.as_deref() == Some("trait"))
+        };
+
+        for import in self.imports.iter_mut().filter(|i| !i.pinned) {
+            let before = import.names.len();
+            import.names.retain(|name| {
+                let keep = name.locals.iter().any(|local| {
+                    used.contains(local)
+                        // The classic JSX transform needs React in scope
+                        || (lang == Lang::Js && has_jsx && local == "React")
+                        || (lang == Lang::Rust && local.starts_with(|c: char| c.is_ascii_uppercase()) && may_be_trait(local))
+                });
+                if !keep {
+                    changes.removed.push(name.locals.join(", "));
+                }
+                keep
+            });
+            import.changed |= import.names.len() != before;
+        }
+    }
+
+    fn add_missing(&mut self, index: &SymbolIndex, changes: &mut ImportChanges) {
+        // A glob may already supply any name
+        if self.imports.iter().any(|i| i.pinned && i.original.contains('*')) {
+            return;
+        }
+
+        let imported: HashSet<String> = self.imports.iter().flat_map(|i| i.names.iter().flat_map(|n| n.locals.clone())).collect();
+        let builtins = match self.lang {
+            Lang::Rust => RUST_PRELUDE,
+            Lang::Python => PYTHON_BUILTINS,
+            Lang::Js => JS_GLOBALS,
+        };
+
+        let mut seen = HashSet::new();
+        let candidates = std::mem::take(&mut self.candidates);
+        for name in candidates {
+            if !seen.insert(name.clone())
+                || self.bound.contains(&name)
+                || imported.contains(&name)
+                || builtins.contains(&name.as_str())
+            {
+                continue;
+            }
+
+            let mut definitions: Vec<Location> = index
+                .definitions(&name)
+                .into_iter()
+                .filter(|d| d.path != self.path && self.is_importable(d))
+                .collect();
+            definitions.dedup_by(|a, b| a.path == b.path);
+
+            match definitions.as_slice() {
+                // Standard library names the index doesn't know about
+                [] => match self.lang {
+                    Lang::Rust => {
+                        if let Some((_, module)) = RUST_STD_TYPES.iter().find(|(n, _)| *n == name) {
+                            self.add_import(module, &name);
+                            changes.added.push(name);
+                        }
+                    }
+                    Lang::Python if PYTHON_STDLIB.contains(&name.as_str()) => {
+                        self.imports.push(Import {
+                            rows: None,
+                            original: String::new(),
+                            kind: ImportKind::PythonImport,
+                            module: name.clone(),
+                            names: vec![named(&name, vec![name.clone()])],
+                            pinned: false,
+                            changed: true,
+                            type_only: false,
+                        });
+                        changes.added.push(name);
+                    }
+                    _ => {}
+                },
+                [definition] => {
+                    if let Some(module) = self.module_path(&definition.path) {
+                        self.add_import(&module, &name);
+                        changes.added.push(name);
+                    }
+                }
+                _ => changes.ambiguous.push(name),
+            }
+        }
+    }
+
+    /// Whether another file's definition could be imported here: top-level,
+    /// of the same language and exported
+    fn is_importable(&self, definition: &Location) -> bool {
+        if definition.parent.is_some() {
+            return false;
+        }
+        let grammar = definition.path.extension().and_then(|e| e.to_str()).and_then(Grammar::from_extension);
+        if grammar.and_then(Lang::from_grammar) != Some(self.lang) {
+            return false;
+        }
+        let kind = definition.kind.as_deref().unwrap_or("");
+        let line = std::fs::read_to_string(&definition.path)
+            .map(|content| xref::line_text(&content, definition.line).to_string())
+            .unwrap_or_default();
+        match self.lang {
+            Lang::Rust => matches!(kind, "struct" | "enum" | "trait" | "type" | "constant") && line.starts_with("pub"),
+            Lang::Python => {
+                matches!(kind, "class" | "function")
+                    && !line.contains("def _")
+                    && !line.contains("class _")
+            }
+            Lang::Js => line.starts_with("export ") && !line.starts_with("export default"),
+        }
+    }
+
+    /// How this file refers to the module at `target`
+    fn module_path(&self, target: &Path) -> Option<String> {
+        match self.lang {
+            Lang::Rust => {
+                let src = crate_src_dir(target)?;
+                if crate_src_dir(self.path)? != src || target.starts_with(src.join("bin")) {
+                    return None;
+                }
+                let relative = target.strip_prefix(&src).ok()?.with_extension("");
+                let mut segments: Vec<String> =
+                    relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+                if matches!(segments.last().map(String::as_str), Some("mod" | "lib" | "main")) {
+                    segments.pop();
+                }
+                segments.insert(0, "crate".to_string());
+                Some(segments.join("::"))
+            }
+            Lang::Python => {
+                let mut relative = target.strip_prefix(self.root).ok()?.with_extension("");
+                // src layout: src/ is on the path, not a package
+                if let Ok(stripped) = relative.strip_prefix("src") {
+                    if !self.root.join("src/__init__.py").exists() {
+                        relative = stripped.to_path_buf();
+                    }
+                }
+                let mut segments: Vec<String> =
+                    relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+                if segments.last().map(String::as_str) == Some("__init__") {
+                    segments.pop();
+                }
+                Some(segments.join(".")).filter(|m| !m.is_empty())
+            }
+            Lang::Js => {
+                let from = self.path.parent()?;
+                let keep_js_extension = self
+                    .imports
+                    .iter()
+                    .any(|i| i.module.starts_with('.') && i.module.ends_with(".js"));
+                let target = if keep_js_extension {
+                    target.with_extension("js")
+                } else {
+                    target.with_extension("")
+                };
+                let mut specifier = relative_path(from, &target).to_string_lossy().replace('\\', "/");
+                if !keep_js_extension {
+                    if specifier == "index" {
+                        specifier = ".".to_string();
+                    } else if let Some(dir) = specifier.strip_suffix("/index") {
+                        specifier = dir.to_string();
+                    }
+                }
+                if !specifier.starts_with('.') {
+                    specifier = format!("./{}", specifier);
+                }
+                Some(specifier)
+            }
+        }
+    }
+
+    /// Adds `name` to an existing import of `module` or a new statement
+    fn add_import(&mut self, module: &str, name: &str) {
+        let kind = match self.lang {
+            Lang::Rust => ImportKind::RustUse,
+            Lang::Python => ImportKind::PythonFrom,
+            Lang::Js => ImportKind::Js,
+        };
+
+        let existing = self.imports.iter_mut().find(|i| {
+            !i.pinned && !i.type_only && i.kind == kind && i.module == module
+                && i.names.iter().all(|n| n.binding != Binding::Namespace)
+        });
+        match existing {
+            Some(import) => {
+                import.names.push(named(name, vec![name.to_string()]));
+                import.changed = true;
+            }
+            None => self.imports.push(Import {
+                rows: None,
+                original: String::new(),
+                kind,
+                module: module.to_string(),
+                names: vec![named(name, vec![name.to_string()])],
+                pinned: false,
+                changed: true,
+                type_only: false,
+            }),
+        }
+    }
+
+    /// The content with changed imports rewritten, new ones inserted, and the
+    /// import block sorted when nothing but imports and blank lines are in it
+    fn render(&self, conventions: &Conventions) -> String {
+        let lines = &self.lines;
+        let style = JsStyle::detect(&self.imports);
+        let render = |import: &Import| -> Option<String> {
+            if import.is_empty() {
+                None
+            } else if import.changed {
+                Some(render_import(import, &style) + "\n")
+            } else {
+                Some(import.original.clone() + "\n")
+            }
+        };
+
+        let existing: Vec<(usize, usize)> = self.imports.iter().filter_map(|i| i.rows).collect();
+        let block = match (existing.first(), existing.last()) {
+            (Some(first), Some(last)) => Some((first.0, last.1)),
+            _ => None,
+        };
+        let is_blank = |row: usize| lines.get(row).is_none_or(|l| l.trim().is_empty());
+
+        // Imports are sorted when a formatter config asks for it or the file
+        // already keeps them sorted, unless moving a JS side-effect import
+        // could change what it affects
+        let configured = match self.lang {
+            Lang::Rust => conventions.rustfmt,
+            Lang::Python => conventions.isort,
+            Lang::Js => false,
+        };
+        let side_effects = self.lang == Lang::Js && self.imports.iter().any(|i| i.pinned && i.names.is_empty());
+        let sorting = (configured || self.is_sorted(conventions)) && !side_effects;
+
+        // A sorted block of nothing but imports and blank lines is rebuilt
+        if let (Some((start, end)), true) = (block, sorting) {
+            let covered = |row: usize| existing.iter().any(|(s, e)| (*s..=*e).contains(&row));
+            if (start..=end).all(|row| covered(row) || is_blank(row)) {
+                let separator = if self.separates_groups(start, end, conventions) { "\n" } else { "" };
+                let rendered: Vec<String> = self
+                    .sorted_groups(conventions)
+                    .iter()
+                    .map(|group| group.iter().filter_map(|i| render(&self.imports[*i])).collect::<String>())
+                    .filter(|group| !group.is_empty())
+                    .collect();
+
+                let mut after = (end + 1).min(lines.len());
+                if rendered.is_empty() {
+                    // No imports left; drop the blank line that followed them
+                    while after < lines.len() && is_blank(after) {
+                        after += 1;
+                    }
+                }
+                return lines[..start].concat() + &rendered.join(separator) + &lines[after..].concat();
+            }
+        }
+
+        // Otherwise rewrite statements in place and add new ones after the last import
+        let mut added: Vec<&Import> = self.imports.iter().filter(|i| i.rows.is_none()).collect();
+        added.sort_by_key(|i| (self.group(i, conventions), self.sort_key(i, conventions)));
+        let added: String = added.into_iter().filter_map(render).collect();
+        let insert_row = match block {
+            Some((_, end)) => end + 1,
+            None => self.default_insert_row(),
+        };
+
+        let mut output = String::new();
+        let mut row = 0;
+        loop {
+            if row == insert_row && !added.is_empty() {
+                if output.ends_with(|c| c != '\n') {
+                    output.push('\n');
+                }
+                output.push_str(&added);
+                if block.is_none() && !is_blank(row) {
+                    output.push('\n');
+                }
+            }
+            if row >= lines.len() {
+                break;
+            }
+            match self.imports.iter().find(|i| i.rows.is_some_and(|(s, _)| s == row)) {
+                Some(import) => {
+                    output.push_str(&render(import).unwrap_or_default());
+                    row = import.rows.map_or(row, |(_, e)| e) + 1;
+                }
+                None => {
+                    output.push_str(lines[row]);
+                    row += 1;
+                }
+            }
+        }
+        output
+    }
+
+    /// Import indices grouped and sorted per the project's conventions
+    fn sorted_groups(&self, conventions: &Conventions) -> Vec<Vec<usize>> {
+        let regroup = match self.lang {
+            Lang::Rust => conventions.rust_group_imports,
+            Lang::Python | Lang::Js => true,
+        };
+
+        let mut groups: Vec<Vec<usize>> = if regroup {
+            let mut keyed: Vec<(usize, usize)> = (0..self.imports.len()).map(|i| (self.group(&self.imports[i], conventions), i)).collect();
+            keyed.sort_by_key(|(group, _)| *group);
+            let mut groups: Vec<Vec<usize>> = Vec::new();
+            let mut last = None;
+            for (group, i) in keyed {
+                if last != Some(group) {
+                    groups.push(Vec::new());
+                    last = Some(group);
+                }
+                groups.last_mut().unwrap().push(i);
+            }
+            groups
+        } else {
+            // rustfmt keeps blank-line separated groups and sorts within them
+            let mut groups: Vec<Vec<usize>> = vec![Vec::new()];
+            let mut previous_end = None;
+            for (i, import) in self.imports.iter().enumerate() {
+                let (start, end) = match import.rows {
+                    Some(rows) => rows,
+                    None => continue,
+                };
+                if let Some(previous_end) = previous_end {
+                    if (previous_end + 1..start).any(|row| self.lines.get(row).is_some_and(|l| l.trim().is_empty())) {
+                        groups.push(Vec::new());
+                    }
+                }
+                groups.last_mut().unwrap().push(i);
+                previous_end = Some(end);
+            }
+            for (i, import) in self.imports.iter().enumerate().filter(|(_, i)| i.rows.is_none()) {
+                let key = self.group(import, conventions);
+                let target = groups
+                    .iter()
+                    .position(|g| g.iter().any(|j| self.group(&self.imports[*j], conventions) == key))
+                    .unwrap_or(groups.len() - 1);
+                groups[target].push(i);
+            }
+            groups
+        };
+
+        for group in &mut groups {
+            group.sort_by_key(|i| self.sort_key(&self.imports[*i], conventions));
+        }
+        groups
+    }
+
+    /// Section of an import: std/external/crate in Rust, isort's sections in
+    /// Python, built-in/package/relative in JavaScript
+    fn group(&self, import: &Import, conventions: &Conventions) -> usize {
+        let root = import
+            .module
+            .split([':', '.', '/'])
+            .next()
+            .unwrap_or("");
+        match self.lang {
+            Lang::Rust => {
+                let root = if import.module.is_empty() { import.names.first().map_or("", |n| n.item.as_str()) } else { root };
+                match root {
+                    "std" | "core" | "alloc" => 0,
+                    "crate" | "self" | "super" => 2,
+                    _ => 1,
+                }
+            }
+            Lang::Python => {
+                if import.module == "__future__" {
+                    0
+                } else if import.module.starts_with('.') {
+                    4
+                } else if PYTHON_STDLIB.contains(&root) {
+                    1
+                } else if conventions.python_first_party.iter().any(|p| p == root)
+                    || self.root.join(root).exists()
+                    || self.root.join(format!("{}.py", root)).exists()
+                    || self.root.join("src").join(root).exists()
+                {
+                    3
+                } else {
+                    2
+                }
+            }
+            Lang::Js => {
+                if import.module.starts_with("node:") {
+                    0
+                } else if import.module.starts_with('.') {
+                    2
+                } else {
+                    1
+                }
+            }
+        }
+    }
+
+    fn sort_key(&self, import: &Import, conventions: &Conventions) -> (bool, Vec<(u8, String)>) {
+        match import.kind {
+            // rustfmt compares segment by segment: keywords, then snake_case,
+            // then CamelCase, then SCREAMING_CASE
+            ImportKind::RustUse => {
+                let first = import.names.first().map_or("", |n| n.item.as_str());
+                let segments = import
+                    .module
+                    .split("::")
+                    .chain(std::iter::once(first))
+                    .filter(|s| !s.is_empty())
+                    .map(|segment| {
+                        let class = if matches!(segment, "self" | "super" | "crate") {
+                            0
+                        } else if segment.starts_with(|c: char| c.is_lowercase()) {
+                            1
+                        } else if segment.chars().any(|c| c.is_lowercase()) {
+                            2
+                        } else {
+                            3
+                        };
+                        (class, segment.to_lowercase())
+                    })
+                    .collect();
+                (false, segments)
+            }
+            // isort puts `import x` before `from x import y` unless told otherwise
+            ImportKind::PythonImport | ImportKind::PythonFrom => (
+                !conventions.python_force_sort_within_sections && import.kind == ImportKind::PythonFrom,
+                vec![(0, import.module.to_lowercase())],
+            ),
+            ImportKind::Js => (false, vec![(0, import.module.to_lowercase())]),
+        }
+    }
+
+    /// Whether the file's imports are already in the order sorting would give
+    fn is_sorted(&self, conventions: &Conventions) -> bool {
+        let order: Vec<usize> = self
+            .sorted_groups(conventions)
+            .concat()
+            .into_iter()
+            .filter(|i| self.imports[*i].rows.is_some())
+            .collect();
+        order.iter().enumerate().all(|(position, i)| position == *i)
+    }
+
+    fn separates_groups(&self, start: usize, end: usize, conventions: &Conventions) -> bool {
+        match self.lang {
+            Lang::Python if conventions.isort => true,
+            Lang::Rust if conventions.rust_group_imports => true,
+            _ => (start..=end).any(|row| self.lines.get(row).is_some_and(|l| l.trim().is_empty())),
+        }
+    }
+
+    /// Where the first import goes when the file has none: after inner
+    /// attributes and module docs, docstrings or directives
+    fn default_insert_row(&self) -> usize {
+        let lines = &self.lines;
+        let mut row = 0;
+        let mut in_docstring = false;
+        while row < lines.len() {
+            let line = lines[row].trim();
+            let skip = match self.lang {
+                Lang::Rust => line.starts_with("//!") || line.starts_with("#![") || line.is_empty(),
+                Lang::Python => {
+                    if in_docstring {
+                        in_docstring = !(line.ends_with("\"\"\"") || line.ends_with("'''"));
+                        true
+                    } else if line.starts_with("\"\"\"") || line.starts_with("'''") {
+                        let rest = &line[3..];
+                        in_docstring = !(rest.ends_with("\"\"\"") || rest.ends_with("'''"));
+                        true
+                    } else {
+                        line.starts_with('#') || line.is_empty()
+                    }
+                }
+                Lang::Js => {
+                    line.starts_with("'use ") || line.starts_with("\"use ") || line.starts_with("//") || line.is_empty()
+                }
+            };
+            if !skip {
+                break;
+            }
+            row += 1;
+        }
+        row
+    }
+}
+
+fn named(item: &str, locals: Vec<String>) -> ImportedName {
+    ImportedName {
+        item: item.trim().to_string(),
+        locals,
+        binding: Binding::Named,
+    }
+}
+
+fn children_of_kind<'t>(node: Node<'t>, kind: &'static str) -> impl Iterator<Item = Node<'t>> {
+    let mut cursor = node.walk();
+    let children: Vec<Node<'t>> = node.named_children(&mut cursor).collect();
+    children.into_iter().filter(move |c| c.kind() == kind)
+}
+
+fn is_definition(lang: Lang, kind: &str) -> bool {
+    match lang {
+        Lang::Rust => matches!(
+            kind,
+            "function_item" | "struct_item" | "enum_item" | "trait_item" | "type_item" | "mod_item" | "const_item"
+                | "static_item" | "union_item" | "macro_definition"
+        ),
+        Lang::Python => matches!(kind, "function_definition" | "class_definition"),
+        Lang::Js => matches!(
+            kind,
+            "function_declaration" | "generator_function_declaration" | "class_declaration"
+                | "abstract_class_declaration" | "interface_declaration" | "type_alias_declaration"
+                | "enum_declaration"
+        ),
+    }
+}
+
+/// Quote and semicolon style of the file's existing JS imports
+struct JsStyle {
+    quote: char,
+    semicolon: bool,
+}
+
+impl JsStyle {
+    fn detect(imports: &[Import]) -> Self {
+        let sample = imports.iter().find(|i| i.kind == ImportKind::Js && !i.original.is_empty());
+        match sample {
+            Some(import) => Self {
+                quote: if import.original.contains('"') { '"' } else { '\'' },
+                semicolon: import.original.trim_end().ends_with(';'),
+            },
+            None => Self { quote: '\'', semicolon: true },
+        }
+    }
+}
+
+fn render_import(import: &Import, style: &JsStyle) -> String {
+    match import.kind {
+        ImportKind::RustUse => {
+            let mut items: Vec<&str> = import.names.iter().map(|n| n.item.as_str()).collect();
+            items.sort_by_key(|item| (*item != "self", item.to_lowercase(), item.to_string()));
+            let prefix = if import.module.is_empty() { String::new() } else { format!("{}::", import.module) };
+            match items.as_slice() {
+                ["self"] => format!("use {};", import.module),
+                [item] => format!("use {}{};", prefix, item),
+                _ => format!("use {}{{{}}};", prefix, items.join(", ")),
+            }
+        }
+        ImportKind::PythonImport => {
+            let items: Vec<&str> = import.names.iter().map(|n| n.item.as_str()).collect();
+            format!("import {}", items.join(", "))
+        }
+        ImportKind::PythonFrom => {
+            // isort's order_by_type: CONSTANTS, then Classes, then functions
+            let mut items: Vec<&str> = import.names.iter().map(|n| n.item.as_str()).collect();
+            items.sort_by_key(|item| {
+                let rank = if item.chars().all(|c| !c.is_lowercase()) {
+                    0
+                } else if item.starts_with(|c: char| c.is_uppercase()) {
+                    1
+                } else {
+                    2
+                };
+                (rank, item.to_lowercase())
+            });
+            let line = format!("from {} import {}", import.module, items.join(", "));
+            if line.len() <= PYTHON_LINE_LENGTH {
+                line
+            } else {
+                let body: String = items.iter().map(|item| format!("    {},\n", item)).collect();
+                format!("from {} import (\n{})", import.module, body)
+            }
+        }
+        ImportKind::Js => {
+            let mut parts = Vec::new();
+            if let Some(default) = import.names.iter().find(|n| n.binding == Binding::Default) {
+                parts.push(default.item.clone());
+            }
+            if let Some(namespace) = import.names.iter().find(|n| n.binding == Binding::Namespace) {
+                parts.push(namespace.item.clone());
+            }
+            let named: Vec<&str> = import
+                .names
+                .iter()
+                .filter(|n| n.binding == Binding::Named)
+                .map(|n| n.item.as_str())
+                .collect();
+            if !named.is_empty() {
+                parts.push(format!("{{ {} }}", named.join(", ")));
+            }
+            format!(
+                "import {}{} from {q}{}{q}{}",
+                if import.type_only { "type " } else { "" },
+                parts.join(", "),
+                import.module,
+                if style.semicolon { ";" } else { "" },
+                q = style.quote
+            )
+        }
+    }
+}
+
+/// Import ordering settings from rustfmt.toml and isort's configuration
+#[derive(Default)]
+struct Conventions {
+    /// A rustfmt.toml, so imports are kept sorted
+    rustfmt: bool,
+    /// rustfmt's `group_imports = "StdExternalCrate"`
+    rust_group_imports: bool,
+    /// An isort configuration, so imports are kept sorted in sections
+    isort: bool,
+    python_force_sort_within_sections: bool,
+    python_first_party: Vec<String>,
+}
+
+impl Conventions {
+    fn load(root: &Path) -> Self {
+        let mut conventions = Conventions::default();
+
+        for name in ["rustfmt.toml", ".rustfmt.toml"] {
+            if let Some(config) = read_toml(&root.join(name)) {
+                conventions.rustfmt = true;
+                conventions.rust_group_imports =
+                    config.get("group_imports").and_then(|v| v.as_str()) == Some("StdExternalCrate");
+                break;
+            }
+        }
+
+        let isort = read_toml(&root.join("pyproject.toml"))
+            .and_then(|pyproject| pyproject.get("tool")?.get("isort").cloned());
+        if let Some(isort) = isort {
+            conventions.isort = true;
+            conventions.python_force_sort_within_sections =
+                isort.get("force_sort_within_sections").and_then(|v| v.as_bool()).unwrap_or(false);
+            conventions.python_first_party = isort
+                .get("known_first_party")
+                .and_then(|v| v.as_array())
+                .map(|names| names.iter().filter_map(|n| n.as_str().map(String::from)).collect())
+                .unwrap_or_default();
+        } else if let Ok(config) = std::fs::read_to_string(root.join(".isort.cfg")) {
+            conventions.isort = true;
+            for line in config.lines() {
+                match line.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+                    Some(("force_sort_within_sections", value)) => {
+                        conventions.python_force_sort_within_sections = value.eq_ignore_ascii_case("true")
+                    }
+                    Some(("known_first_party", value)) => {
+                        conventions.python_first_party = value.split(',').map(|n| n.trim().to_string()).collect()
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        conventions
+    }
+}
+
+fn read_toml(path: &Path) -> Option<toml::Value> {
+    let content = std::fs::read_to_string(path).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// The `src` directory of the Cargo package containing `path`
+pub fn crate_src_dir(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .find(|dir| dir.file_name().is_some_and(|n| n == "src") && dir.parent().is_some_and(|p| p.join("Cargo.toml").exists()))
+        .map(Path::to_path_buf)
+}
+
+/// `to` relative to the directory `from`, both absolute
+pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    for component in &to[common..] {
+        relative.push(component.as_os_str());
+    }
+    relative
+}
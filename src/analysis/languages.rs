@@ -0,0 +1,318 @@
+use rayon::prelude::*;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Languages by file extension
+const LANGUAGES: &[(&str, &str)] = &[
+    ("rs", "Rust"),
+    ("py", "Python"),
+    ("js", "JavaScript"),
+    ("jsx", "JavaScript"),
+    ("ts", "TypeScript"),
+    ("tsx", "TypeScript"),
+    ("go", "Go"),
+    ("php", "PHP"),
+    ("module", "PHP"),
+    ("inc", "PHP"),
+    ("install", "PHP"),
+    ("theme", "PHP"),
+    ("java", "Java"),
+    ("kt", "Kotlin"),
+    ("c", "C"),
+    ("h", "C/C++ header"),
+    ("cpp", "C++"),
+    ("cc", "C++"),
+    ("hpp", "C/C++ header"),
+    ("rb", "Ruby"),
+    ("cs", "C#"),
+    ("swift", "Swift"),
+    ("scala", "Scala"),
+    ("sh", "Shell"),
+    ("css", "CSS"),
+    ("scss", "CSS"),
+    ("html", "HTML"),
+    ("twig", "Twig"),
+    ("vue", "Vue"),
+    ("pl", "Perl"),
+    ("make", "Makefile"),
+    ("dockerfile", "Dockerfile"),
+];
+
+/// Extensionless files known by name, with the extension of their language
+const NAMED_FILES: &[(&str, &str)] = &[
+    ("Makefile", "make"),
+    ("makefile", "make"),
+    ("GNUmakefile", "make"),
+    ("Dockerfile", "dockerfile"),
+    ("Containerfile", "dockerfile"),
+    ("Rakefile", "rb"),
+    ("Gemfile", "rb"),
+    ("Vagrantfile", "rb"),
+];
+
+/// Interpreters named on a shebang line, with the extension of their language
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("python", "py"),
+    ("node", "js"),
+    ("deno", "ts"),
+    ("bun", "ts"),
+    ("ts-node", "ts"),
+    ("bash", "sh"),
+    ("sh", "sh"),
+    ("zsh", "sh"),
+    ("dash", "sh"),
+    ("ksh", "sh"),
+    ("ruby", "rb"),
+    ("php", "php"),
+    ("perl", "pl"),
+    ("make", "make"),
+];
+
+/// Bytes read from an extensionless file to tell its language
+const DETECTION_BYTES: usize = 4096;
+
+/// Line comment markers and block comment delimiters of a language
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: &'static [(&'static str, &'static str)],
+}
+
+const C_STYLE: CommentSyntax = CommentSyntax { line: &["//"], block: &[("/*", "*/")] };
+const PHP_STYLE: CommentSyntax = CommentSyntax { line: &["//", "#"], block: &[("/*", "*/")] };
+const HASH_STYLE: CommentSyntax = CommentSyntax { line: &["#"], block: &[] };
+const PYTHON_STYLE: CommentSyntax = CommentSyntax { line: &["#"], block: &[("\"\"\"", "\"\"\""), ("'''", "'''")] };
+const RUBY_STYLE: CommentSyntax = CommentSyntax { line: &["#"], block: &[("=begin", "=end")] };
+const CSS_STYLE: CommentSyntax = CommentSyntax { line: &[], block: &[("/*", "*/")] };
+const MARKUP_STYLE: CommentSyntax = CommentSyntax { line: &[], block: &[("<!--", "-->")] };
+const TWIG_STYLE: CommentSyntax = CommentSyntax { line: &[], block: &[("{#", "#}"), ("<!--", "-->")] };
+const VUE_STYLE: CommentSyntax = CommentSyntax { line: &["//"], block: &[("<!--", "-->"), ("/*", "*/")] };
+
+/// Lines of code, comments and blanks in the files of one language
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LanguageLines {
+    pub name: String,
+    pub files: usize,
+    pub code: usize,
+    pub comments: usize,
+    pub blanks: usize,
+    /// All lines: code, comments and blanks
+    pub lines: usize,
+}
+
+impl LanguageLines {
+    pub fn add(&mut self, counts: &LineCounts) {
+        self.files += 1;
+        self.code += counts.code;
+        self.comments += counts.comments;
+        self.blanks += counts.blanks;
+        self.lines += counts.code + counts.comments + counts.blanks;
+    }
+}
+
+/// Line counts of one file
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineCounts {
+    pub code: usize,
+    pub comments: usize,
+    pub blanks: usize,
+}
+
+/// Extension of the file at `path`, or for a file without one, the
+/// extension of the language its name, shebang line or content shows:
+/// `Makefile` is `make`, a script starting `#!/usr/bin/env python3` is `py`
+pub fn detected_extension(path: &Path) -> Option<String> {
+    if let Some(ext) = path.extension() {
+        return Some(ext.to_string_lossy().to_string());
+    }
+    let name = path.file_name()?.to_string_lossy();
+    if let Some((_, ext)) = NAMED_FILES.iter().find(|(known, _)| *known == name) {
+        return Some(ext.to_string());
+    }
+    // Only the start of the file is needed, and binaries must not be read whole
+    let mut head = vec![0; DETECTION_BYTES];
+    let read = std::fs::File::open(path).and_then(|mut file| std::io::Read::read(&mut file, &mut head)).ok()?;
+    let head = String::from_utf8_lossy(&head[..read]);
+    content_extension(&head).map(String::from)
+}
+
+/// Language extension of a file from its first bytes: the interpreter of a
+/// shebang line, a PHP open tag, or Dockerfile and Makefile syntax
+fn content_extension(head: &str) -> Option<&'static str> {
+    let first = head.lines().next()?;
+    if let Some(shebang) = first.strip_prefix("#!") {
+        // `#!/usr/bin/env -S python3 -u` names the interpreter after env and its flags
+        let mut words = shebang.split_whitespace();
+        let mut program = words.next()?.rsplit('/').next()?;
+        if program == "env" {
+            program = words.find(|word| !word.starts_with('-'))?;
+        }
+        let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        return INTERPRETERS.iter().find(|(name, _)| *name == program).map(|(_, ext)| *ext);
+    }
+    if first.starts_with("<?php") {
+        return Some("php");
+    }
+
+    let code: Vec<&str> = head.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#')).collect();
+    if code.first().is_some_and(|line| line.trim_start().to_uppercase().starts_with("FROM ")) {
+        return Some("dockerfile");
+    }
+    // A rule followed by a tab-indented recipe
+    let rule = Regex::new(r"^[A-Za-z0-9_./%$() -]+::?(?:[^=]|$)").expect("valid make rule pattern");
+    if code.windows(2).any(|pair| rule.is_match(pair[0]) && pair[1].starts_with('\t')) {
+        return Some("make");
+    }
+    None
+}
+
+/// Language of a source file from its extension, or its name and content
+/// when it has none
+pub fn language_name(path: &Path) -> String {
+    let ext = detected_extension(path).map(|ext| ext.to_lowercase()).unwrap_or_default();
+    LANGUAGES
+        .iter()
+        .find(|(known, _)| *known == ext)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or(ext)
+}
+
+fn comment_syntax(ext: &str) -> &'static CommentSyntax {
+    match ext {
+        "php" | "module" | "inc" | "install" | "theme" => &PHP_STYLE,
+        "py" => &PYTHON_STYLE,
+        "rb" => &RUBY_STYLE,
+        "sh" | "pl" | "make" | "dockerfile" => &HASH_STYLE,
+        "css" => &CSS_STYLE,
+        "html" => &MARKUP_STYLE,
+        "twig" => &TWIG_STYLE,
+        "vue" => &VUE_STYLE,
+        _ => &C_STYLE,
+    }
+}
+
+/// Counts the code, comment and blank lines of a file at `path` holding
+/// `content`. A line with both code and a comment counts as code.
+pub fn count_lines(path: &Path, content: &str) -> LineCounts {
+    let ext = detected_extension(path).map(|ext| ext.to_lowercase()).unwrap_or_default();
+    let syntax = comment_syntax(&ext);
+    let mut counts = LineCounts::default();
+    // The end delimiter of the block comment the current line is in
+    let mut block_end: Option<&str> = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            counts.blanks += 1;
+            continue;
+        }
+        if let Some(end) = block_end {
+            counts.comments += 1;
+            if line.contains(end) {
+                block_end = None;
+            }
+            continue;
+        }
+        if syntax.line.iter().any(|marker| line.starts_with(marker)) {
+            counts.comments += 1;
+            continue;
+        }
+        match syntax.block.iter().find(|(start, _)| line.starts_with(start)) {
+            Some((start, end)) => {
+                counts.comments += 1;
+                if !line[start.len()..].contains(end) {
+                    block_end = Some(end);
+                }
+            }
+            None => {
+                counts.code += 1;
+                // A block comment opened after code, as in `int x; /* ...`
+                for (start, end) in syntax.block {
+                    let opened = if start == end {
+                        line.matches(start).count() % 2 == 1
+                    } else {
+                        line.rfind(start).is_some_and(|idx| !line[idx + start.len()..].contains(end))
+                    };
+                    if opened {
+                        block_end = Some(end);
+                    }
+                }
+            }
+        }
+    }
+    counts
+}
+
+/// Code, comment and blank lines per language of the source files in
+/// `files_by_type` (paths relative to `root`), the most code first.
+/// Extensions that aren't a known language, such as data files, are left out.
+pub fn language_stats(root: &Path, files_by_type: &HashMap<String, Vec<PathBuf>>) -> Vec<LanguageLines> {
+    let files: Vec<&PathBuf> = files_by_type
+        .iter()
+        .filter(|(ext, _)| LANGUAGES.iter().any(|(known, _)| known == &ext.to_lowercase()))
+        .flat_map(|(_, files)| files)
+        .collect();
+    let counted: Vec<(String, LineCounts)> = files
+        .par_iter()
+        .filter_map(|path| {
+            let path = root.join(path);
+            let content = std::fs::read_to_string(&path).ok()?;
+            Some((language_name(&path), count_lines(&path, &content)))
+        })
+        .collect();
+
+    let mut languages: BTreeMap<String, LanguageLines> = BTreeMap::new();
+    for (name, counts) in counted {
+        languages
+            .entry(name.clone())
+            .or_insert_with(|| LanguageLines { name, ..Default::default() })
+            .add(&counts);
+    }
+    let mut languages: Vec<LanguageLines> = languages.into_values().collect();
+    languages.sort_by(|a, b| b.code.cmp(&a.code).then_with(|| a.name.cmp(&b.name)));
+    languages
+}
+
+/// Compact table of `languages` for the context, each with its share of
+/// the code
+pub fn format_stats(languages: &[LanguageLines], max_rows: usize) -> String {
+    let total_code: usize = languages.iter().map(|language| language.code).sum();
+    let mut rows = vec![[
+        "Language".to_string(),
+        "Files".to_string(),
+        "Code".to_string(),
+        "Comments".to_string(),
+        "Blanks".to_string(),
+        "Share".to_string(),
+    ]];
+    for language in languages.iter().take(max_rows) {
+        let share = if total_code == 0 { 0.0 } else { language.code as f64 * 100.0 / total_code as f64 };
+        rows.push([
+            language.name.clone(),
+            language.files.to_string(),
+            language.code.to_string(),
+            language.comments.to_string(),
+            language.blanks.to_string(),
+            format!("{:.1}%", share),
+        ]);
+    }
+    let widths: Vec<usize> = (0..6).map(|col| rows.iter().map(|row| row[col].len()).max().unwrap_or(0)).collect();
+    let mut table = String::new();
+    for row in &rows {
+        // Names left-aligned, numbers right-aligned
+        let cells: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(col, cell)| match col {
+                0 => format!("{:<width$}", cell, width = widths[col]),
+                _ => format!("{:>width$}", cell, width = widths[col]),
+            })
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    if languages.len() > max_rows {
+        table.push_str(&format!("... and {} more languages\n", languages.len() - max_rows));
+    }
+    table
+}
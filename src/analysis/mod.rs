@@ -1,2 +1,12 @@
+pub mod commands;
+pub mod imports;
+pub mod languages;
 pub mod parser;
+pub mod plugins;
+pub mod report;
 pub mod structure;
+pub mod syntax;
+pub mod testmap;
+pub mod todos;
+pub mod tree;
+pub mod xref;
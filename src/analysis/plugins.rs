@@ -0,0 +1,192 @@
+use crate::analysis::structure::ProjectStructure;
+use crate::config::{Config, ProjectPluginConfig, WasmHook};
+use crate::ui::interface::UserInterface;
+use crate::wasm::{self, WasmPlugin};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::json;
+use std::path::Path;
+use std::process::Command;
+
+/// Lines of a plugin's project info kept in the context
+const MAX_PLUGIN_INFO_LINES: usize = 60;
+
+/// Recognizes projects of a type the built-in analyzers don't know, such as
+/// an in-house framework or a proprietary build system
+pub trait ProjectTypeDetector: Send + Sync {
+    /// Name of the project type, shown along with the detected one
+    fn name(&self) -> &str;
+
+    /// Whether the project at `root` is of this type. `structure` is what the
+    /// built-in analyzers found.
+    fn detect(&self, root: &Path, structure: &ProjectStructure) -> Result<bool>;
+}
+
+/// Gathers the details of a project of a plugin's type for the context
+pub trait ProjectInfoGatherer: Send + Sync {
+    /// Text of the project's section in the context
+    fn gather(&self, root: &Path, structure: &ProjectStructure) -> Result<String>;
+}
+
+/// A project type plugin: detects its projects and describes them.
+///
+/// Compiled plugins implement both traits and are registered in
+/// [`ProjectPlugins`]; script plugins are configured under
+/// `[[project.plugins]]`.
+pub trait ProjectTypePlugin: ProjectTypeDetector + ProjectInfoGatherer {}
+
+impl<T: ProjectTypeDetector + ProjectInfoGatherer> ProjectTypePlugin for T {}
+
+/// What the plugin matching a project found
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginProjectInfo {
+    pub name: String,
+    pub details: String,
+}
+
+/// Runs commands from `[[project.plugins]]`: the project matches when any
+/// marker exists and the detect command exits 0, and the info command's
+/// output is its section in the context. Both commands run in the project
+/// root with `CAULK_PROJECT_TYPE` set to the detected type.
+pub struct ScriptPlugin {
+    config: ProjectPluginConfig,
+}
+
+impl ScriptPlugin {
+    pub fn new(config: ProjectPluginConfig) -> Self {
+        Self { config }
+    }
+
+    fn run(&self, command: &str, root: &Path, structure: &ProjectStructure) -> Result<std::process::Output> {
+        let (shell, shell_arg) = if cfg!(target_os = "windows") {
+            ("cmd", "/C")
+        } else {
+            ("bash", "-c")
+        };
+
+        Command::new(shell)
+            .arg(shell_arg)
+            .arg(command)
+            .current_dir(root)
+            .env("CAULK_PROJECT_TYPE", structure.project_type.map_or("generic", |t| t.id()))
+            .output()
+            .with_context(|| format!("Failed to run plugin command: {}", command))
+    }
+}
+
+impl ProjectTypeDetector for ScriptPlugin {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn detect(&self, root: &Path, structure: &ProjectStructure) -> Result<bool> {
+        if self.config.markers.is_empty() && self.config.detect.is_none() {
+            return Ok(false);
+        }
+        if !self.config.markers.is_empty() && !self.config.markers.iter().any(|marker| root.join(marker).exists()) {
+            return Ok(false);
+        }
+        match &self.config.detect {
+            Some(command) => Ok(self.run(command, root, structure)?.status.success()),
+            None => Ok(true),
+        }
+    }
+}
+
+impl ProjectInfoGatherer for ScriptPlugin {
+    fn gather(&self, root: &Path, structure: &ProjectStructure) -> Result<String> {
+        let command = match &self.config.info {
+            Some(command) => command,
+            None => return Ok(String::new()),
+        };
+        let output = self.run(command, root, structure)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            anyhow::bail!(
+                "'{}' exited with {:?}{}",
+                command,
+                output.status.code(),
+                if stderr.is_empty() { String::new() } else { format!(": {}", stderr) }
+            );
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout.lines().take(MAX_PLUGIN_INFO_LINES).collect::<Vec<_>>().join("\n"))
+    }
+}
+
+/// Calls a `[[plugins.wasm]]` analyzer with `{"hook": "detect"}` and then
+/// `{"hook": "gather"}`, both with the detected `project_type`. It matches
+/// the project by printing `true` and describes it with what it prints.
+impl ProjectTypeDetector for WasmPlugin {
+    fn name(&self) -> &str {
+        WasmPlugin::name(self)
+    }
+
+    fn detect(&self, _root: &Path, structure: &ProjectStructure) -> Result<bool> {
+        let output = self.call(&plugin_request("detect", structure))?;
+        Ok(output.trim() == "true")
+    }
+}
+
+impl ProjectInfoGatherer for WasmPlugin {
+    fn gather(&self, _root: &Path, structure: &ProjectStructure) -> Result<String> {
+        let output = self.call(&plugin_request("gather", structure))?;
+        Ok(output.lines().take(MAX_PLUGIN_INFO_LINES).collect::<Vec<_>>().join("\n"))
+    }
+}
+
+fn plugin_request(hook: &str, structure: &ProjectStructure) -> serde_json::Value {
+    json!({
+        "hook": hook,
+        "root": wasm::GUEST_ROOT,
+        "project_type": structure.project_type.map_or("generic", |t| t.id()),
+    })
+}
+
+/// Project type plugins, tried in the order they were registered
+#[derive(Default)]
+pub struct ProjectPlugins {
+    plugins: Vec<Box<dyn ProjectTypePlugin>>,
+}
+
+impl ProjectPlugins {
+    /// Script plugins of `[[project.plugins]]`, then WebAssembly analyzers
+    /// of `[[plugins.wasm]]`
+    pub fn from_config(config: &Config, project_root: &Path, ui: &dyn UserInterface) -> Self {
+        let mut plugins = Self::default();
+        for plugin in &config.project.plugins {
+            plugins.register(Box::new(ScriptPlugin::new(plugin.clone())));
+        }
+        for plugin in wasm::load_plugins(&config.plugins, project_root, WasmHook::Analyze, ui) {
+            plugins.register(Box::new(plugin));
+        }
+        plugins
+    }
+
+    pub fn register(&mut self, plugin: Box<dyn ProjectTypePlugin>) {
+        self.plugins.push(plugin);
+    }
+
+    /// Info of the first plugin recognizing the project. A failing plugin is
+    /// reported and skipped.
+    pub fn analyze(&self, root: &Path, structure: &ProjectStructure, ui: &dyn UserInterface) -> Option<PluginProjectInfo> {
+        for plugin in &self.plugins {
+            let result = plugin.detect(root, structure).and_then(|detected| {
+                if detected {
+                    plugin.gather(root, structure).map(Some)
+                } else {
+                    Ok(None)
+                }
+            });
+            match result {
+                Ok(Some(details)) => {
+                    return Some(PluginProjectInfo { name: plugin.name().to_string(), details });
+                }
+                Ok(None) => {}
+                Err(e) => ui.error(&format!("{} Project plugin '{}' failed: {:#}", "!".yellow(), plugin.name(), e)),
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,420 @@
+use crate::analysis::languages::{self, LanguageLines, LineCounts};
+use crate::analysis::structure::ProjectAnalyzer;
+use crate::analysis::tree::FileTree;
+use crate::fs::{self, relocate};
+use crate::git::history::GitHistory;
+use crate::llm::vendor;
+use anyhow::{Context, Result};
+use log::debug;
+use minijinja::Environment;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Task name used to route the executive summary to a model (`[llm.routes]`)
+pub const TASK: &str = "report";
+
+pub const SUMMARY_SYSTEM_PROMPT: &str = "You write the executive summary of a codebase report for engineering \
+managers and new team members. From the facts given, describe in three or four short paragraphs what the project \
+is, how it is organized, where the risk is concentrated (hotspots, large files, outstanding TODOs) and what you \
+would look at first. Only state what the facts support. Reply in plain text without headings.";
+
+/// Bytes of the report facts sent for the executive summary
+const MAX_SUMMARY_FACTS_BYTES: usize = 16000;
+const TREE_MAX_DEPTH: usize = 3;
+const TREE_MAX_LINES: usize = 120;
+/// Commits on HEAD counted when ranking hotspots
+const HISTORY_COMMITS: usize = 1000;
+const MAX_LARGEST_FILES: usize = 10;
+const MAX_HOTSPOTS: usize = 15;
+const MAX_DEPENDENCY_EDGES: usize = 40;
+const MAX_MOST_IMPORTED: usize = 15;
+const MAX_TODOS: usize = 200;
+
+const MARKDOWN_TEMPLATE: &str = r#"# {{ project }} — codebase report
+
+Generated {{ generated }}{% if project_type %} · {{ project_type }}{% endif %}
+
+{% if summary %}## Executive summary
+
+{{ summary }}
+
+{% endif %}## Metrics
+
+{{ total_files }} source files, {{ total_lines }} lines.
+
+| Language | Files | Code | Comments | Blanks | Lines |
+|---|---:|---:|---:|---:|---:|
+{% for language in languages %}| {{ language.name }} | {{ language.files }} | {{ language.code }} | {{ language.comments }} | {{ language.blanks }} | {{ language.lines }} |
+{% endfor %}
+Largest files:
+
+{% for file in largest_files %}- `{{ file.path }}` — {{ file.lines }} lines
+{% endfor %}
+## Structure
+
+```
+{{ tree }}```
+
+## Dependencies
+
+{% if dependencies %}Imports between directories (most frequent first):
+
+```mermaid
+graph LR
+{% for edge in dependencies %}    {{ edge.from_id }}["{{ edge.from }}"] -->|{{ edge.count }}| {{ edge.to_id }}["{{ edge.to }}"]
+{% endfor %}```
+
+Most imported files:
+
+{% for file in most_imported %}- `{{ file.path }}` — imported by {{ file.count }} file(s)
+{% endfor %}{% else %}No imports between project directories were found.
+{% endif %}
+## Hotspots
+
+{% if hotspots is none %}Not available: the project has no git history.
+{% elif hotspots %}Files changed most often in the last {{ history_commits }} commits, weighted by size:
+
+| File | Changes | Lines |
+|---|---:|---:|
+{% for file in hotspots %}| `{{ file.path }}` | {{ file.changes }} | {{ file.lines }} |
+{% endfor %}{% else %}No source files were changed in the recent history.
+{% endif %}
+## TODOs
+
+{% if todos %}{{ todo_count }} marker(s){% if todo_count > todos|length %}, first {{ todos|length }} shown{% endif %}:
+
+{% for todo in todos %}- `{{ todo.path }}:{{ todo.line }}` **{{ todo.kind }}** {{ todo.text }}
+{% endfor %}{% else %}No TODO, FIXME, HACK or XXX markers found.
+{% endif %}"#;
+
+const HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{ project }} — codebase report</title>
+<style>
+body { font-family: system-ui, sans-serif; max-width: 60rem; margin: 2rem auto; padding: 0 1rem; color: #222; }
+table { border-collapse: collapse; margin: 1rem 0; }
+th, td { border: 1px solid #ddd; padding: 0.3rem 0.6rem; text-align: left; }
+td.num { text-align: right; }
+pre { background: #f6f8fa; padding: 1rem; overflow-x: auto; }
+.summary { white-space: pre-wrap; background: #eef5ff; padding: 1rem; border-radius: 4px; }
+.meta { color: #666; }
+</style>
+</head>
+<body>
+<h1>{{ project }} — codebase report</h1>
+<p class="meta">Generated {{ generated }}{% if project_type %} · {{ project_type }}{% endif %}</p>
+{% if summary %}<h2>Executive summary</h2>
+<div class="summary">{{ summary }}</div>
+{% endif %}
+<h2>Metrics</h2>
+<p>{{ total_files }} source files, {{ total_lines }} lines.</p>
+<table>
+<tr><th>Language</th><th>Files</th><th>Code</th><th>Comments</th><th>Blanks</th><th>Lines</th></tr>
+{% for language in languages %}<tr><td>{{ language.name }}</td><td class="num">{{ language.files }}</td><td class="num">{{ language.code }}</td><td class="num">{{ language.comments }}</td><td class="num">{{ language.blanks }}</td><td class="num">{{ language.lines }}</td></tr>
+{% endfor %}</table>
+<p>Largest files:</p>
+<ul>
+{% for file in largest_files %}<li><code>{{ file.path }}</code> — {{ file.lines }} lines</li>
+{% endfor %}</ul>
+<h2>Structure</h2>
+<pre>{{ tree }}</pre>
+<h2>Dependencies</h2>
+{% if dependencies %}<p>Imports between directories (most frequent first):</p>
+<table>
+<tr><th>From</th><th>To</th><th>Imports</th></tr>
+{% for edge in dependencies %}<tr><td><code>{{ edge.from }}</code></td><td><code>{{ edge.to }}</code></td><td class="num">{{ edge.count }}</td></tr>
+{% endfor %}</table>
+<p>Most imported files:</p>
+<ul>
+{% for file in most_imported %}<li><code>{{ file.path }}</code> — imported by {{ file.count }} file(s)</li>
+{% endfor %}</ul>
+{% else %}<p>No imports between project directories were found.</p>
+{% endif %}
+<h2>Hotspots</h2>
+{% if hotspots is none %}<p>Not available: the project has no git history.</p>
+{% elif hotspots %}<p>Files changed most often in the last {{ history_commits }} commits, weighted by size:</p>
+<table>
+<tr><th>File</th><th>Changes</th><th>Lines</th></tr>
+{% for file in hotspots %}<tr><td><code>{{ file.path }}</code></td><td class="num">{{ file.changes }}</td><td class="num">{{ file.lines }}</td></tr>
+{% endfor %}</table>
+{% else %}<p>No source files were changed in the recent history.</p>
+{% endif %}
+<h2>TODOs</h2>
+{% if todos %}<p>{{ todo_count }} marker(s){% if todo_count > todos|length %}, first {{ todos|length }} shown{% endif %}:</p>
+<ul>
+{% for todo in todos %}<li><code>{{ todo.path }}:{{ todo.line }}</code> <strong>{{ todo.kind }}</strong> {{ todo.text }}</li>
+{% endfor %}</ul>
+{% else %}<p>No TODO, FIXME, HACK or XXX markers found.</p>
+{% endif %}
+</body>
+</html>
+"#;
+
+/// Output format of `code-assist report`
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ReportFormat {
+    Md,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Md => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileLines {
+    pub path: String,
+    pub lines: usize,
+}
+
+/// Imports from files in one directory to files in another
+#[derive(Debug, Serialize)]
+pub struct DependencyEdge {
+    pub from: String,
+    pub to: String,
+    pub from_id: String,
+    pub to_id: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportedFile {
+    pub path: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Hotspot {
+    pub path: String,
+    pub changes: usize,
+    pub lines: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Todo {
+    pub path: String,
+    pub line: usize,
+    pub kind: String,
+    pub text: String,
+}
+
+/// Project structure, metrics, dependencies, hotspots and TODOs gathered
+/// into one document, rendered as markdown or HTML
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub project: String,
+    pub generated: String,
+    pub project_type: Option<String>,
+    pub summary: Option<String>,
+    pub total_files: usize,
+    pub total_lines: usize,
+    pub languages: Vec<LanguageLines>,
+    pub largest_files: Vec<FileLines>,
+    pub tree: String,
+    pub dependencies: Vec<DependencyEdge>,
+    pub most_imported: Vec<ImportedFile>,
+    /// None when the project is not in a git repository
+    pub hotspots: Option<Vec<Hotspot>>,
+    pub history_commits: usize,
+    pub todos: Vec<Todo>,
+    pub todo_count: usize,
+}
+
+/// One scanned source file
+struct SourceFile {
+    path: PathBuf,
+    lines: usize,
+    counts: LineCounts,
+    todos: Vec<Todo>,
+}
+
+impl Report {
+    /// Scans the project at `root`. The executive summary is left empty.
+    pub fn gather(root: &Path) -> Result<Self> {
+        let project_structure = ProjectAnalyzer.analyze_project_structure(root)?;
+        let tree = FileTree::from_structure(&project_structure, root).render(TREE_MAX_DEPTH, TREE_MAX_LINES);
+
+        // Only markers in comments, not identifiers or strings that mention them
+        let todo_regex = Regex::new(r"(?://|#|/\*|\*|--|<!--|\{#)\s*(TODO|FIXME|HACK|XXX)\b[:\s]*(.*)").unwrap();
+        let mut files: Vec<SourceFile> = fs::project_files(root)
+            .par_iter()
+            .filter(|path| vendor::is_source_file(path))
+            .filter_map(|path| {
+                let content = std::fs::read_to_string(path).ok()?;
+                let relative = path.strip_prefix(root).unwrap_or(path).to_path_buf();
+                let todos = content
+                    .lines()
+                    .enumerate()
+                    .filter_map(|(idx, line)| {
+                        let captures = todo_regex.captures(line)?;
+                        Some(Todo {
+                            path: relative.display().to_string(),
+                            line: idx + 1,
+                            kind: captures[1].to_string(),
+                            text: vendor::truncate(captures[2].trim_end_matches(['*', '/', '-', '>', '#', '}', ' ']), 200),
+                        })
+                    })
+                    .collect();
+                let counts = languages::count_lines(&relative, &content);
+                Some(SourceFile { path: relative, lines: content.lines().count(), counts, todos })
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut languages: BTreeMap<String, LanguageLines> = BTreeMap::new();
+        for file in &files {
+            let name = languages::language_name(&file.path);
+            languages
+                .entry(name.clone())
+                .or_insert_with(|| LanguageLines { name, ..Default::default() })
+                .add(&file.counts);
+        }
+        let mut languages: Vec<LanguageLines> = languages.into_values().collect();
+        languages.sort_by_key(|b| std::cmp::Reverse(b.lines));
+
+        let mut by_size: Vec<&SourceFile> = files.iter().collect();
+        by_size.sort_by_key(|b| std::cmp::Reverse(b.lines));
+        let largest_files = by_size
+            .iter()
+            .take(MAX_LARGEST_FILES)
+            .map(|file| FileLines { path: file.path.display().to_string(), lines: file.lines })
+            .collect();
+
+        let (dependencies, most_imported) = match relocate::dependency_graph(root) {
+            Ok(graph) => summarize_graph(&graph),
+            Err(e) => {
+                debug!("Could not build the dependency graph: {}", e);
+                (Vec::new(), Vec::new())
+            }
+        };
+
+        let hotspots = match GitHistory::change_counts(root, HISTORY_COMMITS) {
+            Ok(counts) => Some(rank_hotspots(&files, &counts)),
+            Err(e) => {
+                debug!("No git history for hotspots: {}", e);
+                None
+            }
+        };
+
+        let todo_count = files.iter().map(|file| file.todos.len()).sum();
+        let todos = files.into_iter().flat_map(|file| file.todos).take(MAX_TODOS).collect();
+
+        Ok(Self {
+            project: root
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| root.display().to_string()),
+            generated: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+            project_type: project_structure.project_type.as_ref().map(|t| t.description().to_string()),
+            summary: None,
+            total_files: languages.iter().map(|language| language.files).sum(),
+            total_lines: languages.iter().map(|language| language.lines).sum(),
+            languages,
+            largest_files,
+            tree,
+            dependencies,
+            most_imported,
+            hotspots,
+            history_commits: HISTORY_COMMITS,
+            todos,
+            todo_count,
+        })
+    }
+
+    pub fn render(&self, format: ReportFormat) -> Result<String> {
+        // The template name's extension turns on HTML auto-escaping
+        let (name, source) = match format {
+            ReportFormat::Md => ("report.md", MARKDOWN_TEMPLATE),
+            ReportFormat::Html => ("report.html", HTML_TEMPLATE),
+        };
+        let mut env = Environment::new();
+        env.add_template(name, source)
+            .with_context(|| format!("Invalid report template: {}", name))?;
+        env.get_template(name)
+            .and_then(|template| template.render(minijinja::Value::from_serialize(self)))
+            .with_context(|| format!("Failed to render report template: {}", name))
+    }
+
+    /// The report's facts as markdown, for the model writing the executive summary
+    pub fn summary_facts(&self) -> Result<String> {
+        Ok(vendor::truncate(&self.render(ReportFormat::Md)?, MAX_SUMMARY_FACTS_BYTES))
+    }
+}
+
+/// Directory-level edges of the file dependency graph, most imports first,
+/// and the files most other files depend on
+fn summarize_graph(graph: &BTreeMap<PathBuf, BTreeSet<PathBuf>>) -> (Vec<DependencyEdge>, Vec<ImportedFile>) {
+    let directory = |path: &Path| match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.display().to_string(),
+        _ => ".".to_string(),
+    };
+
+    let mut edges: BTreeMap<(String, String), usize> = BTreeMap::new();
+    let mut fan_in: BTreeMap<&PathBuf, usize> = BTreeMap::new();
+    for (source, targets) in graph {
+        for target in targets {
+            *fan_in.entry(target).or_insert(0) += 1;
+            // A whole package imported as a directory counts as that directory
+            let to = if graph.contains_key(target) { directory(target) } else { target.display().to_string() };
+            let from = directory(source);
+            if from != to {
+                *edges.entry((from, to)).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut edges: Vec<((String, String), usize)> = edges.into_iter().collect();
+    edges.sort_by_key(|b| std::cmp::Reverse(b.1));
+    edges.truncate(MAX_DEPENDENCY_EDGES);
+
+    // Mermaid node IDs, one per directory
+    let mut ids: HashMap<String, String> = HashMap::new();
+    let mut id_for = |dir: &str| {
+        let next = format!("d{}", ids.len());
+        ids.entry(dir.to_string()).or_insert(next).clone()
+    };
+    let dependencies = edges
+        .into_iter()
+        .map(|((from, to), count)| DependencyEdge {
+            from_id: id_for(&from),
+            to_id: id_for(&to),
+            from,
+            to,
+            count,
+        })
+        .collect();
+
+    let mut most_imported: Vec<(&PathBuf, usize)> = fan_in.into_iter().collect();
+    most_imported.sort_by_key(|b| std::cmp::Reverse(b.1));
+    let most_imported = most_imported
+        .into_iter()
+        .take(MAX_MOST_IMPORTED)
+        .map(|(path, count)| ImportedFile { path: path.display().to_string(), count })
+        .collect();
+
+    (dependencies, most_imported)
+}
+
+/// Source files ranked by how often they changed times their size
+fn rank_hotspots(files: &[SourceFile], counts: &HashMap<PathBuf, usize>) -> Vec<Hotspot> {
+    let mut hotspots: Vec<Hotspot> = files
+        .iter()
+        .filter_map(|file| {
+            let changes = *counts.get(&file.path)?;
+            Some(Hotspot { path: file.path.display().to_string(), changes, lines: file.lines })
+        })
+        .collect();
+    hotspots.sort_by_key(|b| std::cmp::Reverse(b.changes * b.lines.max(1)));
+    hotspots.truncate(MAX_HOTSPOTS);
+    hotspots
+}
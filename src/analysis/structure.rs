@@ -1,15 +1,47 @@
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
 use anyhow::Result;
 use regex::Regex;
 use glob::glob;
+use ignore::WalkBuilder;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::languages::{self, LanguageLines};
+use crate::analysis::plugins::PluginProjectInfo;
+use crate::fs::{project_walker, walk_parallel};
+
+/// Manifests marking the root of a sub-project, most telling first, with
+/// the type they indicate
+const PROJECT_MANIFESTS: &[(&str, ProjectType)] = &[
+    ("angular.json", ProjectType::Angular),
+    ("Cargo.toml", ProjectType::Rust),
+    ("composer.json", ProjectType::PHP),
+    ("package.json", ProjectType::JavaScript),
+    ("go.mod", ProjectType::Go),
+    ("pyproject.toml", ProjectType::Python),
+    ("setup.py", ProjectType::Python),
+    ("pom.xml", ProjectType::Java),
+    ("build.gradle.kts", ProjectType::Kotlin),
+    ("build.gradle", ProjectType::Java),
+    ("CMakeLists.txt", ProjectType::Cpp),
+];
+/// How many directories below the root sub-projects are looked for
+const MAX_SUB_PROJECT_DEPTH: usize = 3;
 
 pub struct ProjectAnalyzer;
 
 impl ProjectAnalyzer {
     /// Analyzes the structure of a project to determine its type and organize files
     pub fn analyze_project_structure(&self, project_path: &Path) -> Result<ProjectStructure> {
+        self.analyze_project_structure_as(project_path, None)
+    }
+
+    /// Analyzes the project as `forced_type` when given, for when detection
+    /// guesses wrong, e.g. a PHP library inside a JavaScript monorepo
+    pub fn analyze_project_structure_as(&self, project_path: &Path, forced_type: Option<ProjectType>) -> Result<ProjectStructure> {
         let mut directories = Vec::new();
         let mut files_by_type = HashMap::new();
         
@@ -17,13 +49,22 @@ impl ProjectAnalyzer {
         let project_features = self.scan_project_features(project_path, &mut directories, &mut files_by_type)?;
         
         // Determine project type based on detected features
-        let (project_type, modules) = self.determine_project_type(project_path, &project_features, &files_by_type)?;
+        let (project_type, modules) = match forced_type {
+            Some(project_type @ (ProjectType::Drupal | ProjectType::DrupalModule)) => {
+                (project_type, self.find_all_drupal_modules(project_path)?)
+            }
+            Some(project_type) => (project_type, Vec::new()),
+            None => self.determine_project_type(project_path, &project_features, &files_by_type)?,
+        };
         
         // Gather specific details for the detected project type
         let specific_info = match project_type {
             ProjectType::DrupalModule => SpecificProjectInfo::Drupal(
                 self.gather_drupal_module_info(project_path, &files_by_type)?
             ),
+            ProjectType::DrupalTheme => SpecificProjectInfo::DrupalTheme(
+                self.gather_drupal_theme_info(project_path, &files_by_type)?
+            ),
             ProjectType::Rust => SpecificProjectInfo::Rust(
                 self.gather_rust_project_info(project_path, &files_by_type)?
             ),
@@ -36,33 +77,106 @@ impl ProjectAnalyzer {
             ProjectType::Python => SpecificProjectInfo::Python(
                 self.gather_python_project_info(project_path, &files_by_type)?
             ),
+            ProjectType::Java | ProjectType::Kotlin => SpecificProjectInfo::Java(
+                self.gather_java_project_info(project_path, &files_by_type)?
+            ),
+            ProjectType::CSharp => SpecificProjectInfo::DotNet(
+                self.gather_dotnet_project_info(project_path, &files_by_type)?
+            ),
+            ProjectType::C | ProjectType::Cpp => SpecificProjectInfo::Cpp(
+                self.gather_cpp_project_info(project_path, &files_by_type)?
+            ),
+            ProjectType::Laravel | ProjectType::Symfony => SpecificProjectInfo::PhpFramework(
+                self.gather_php_framework_info(project_path, &project_type, &files_by_type)?
+            ),
             _ => SpecificProjectInfo::None,
         };
         
+        let sub_projects = self.find_sub_projects(project_path, &files_by_type);
+        let languages = languages::language_stats(project_path, &files_by_type);
+        
         Ok(ProjectStructure {
             directories,
             files_by_type,
+            languages,
             project_type: Some(project_type),
             specific_info,
             modules,
+            sub_projects,
+            plugin: None,
         })
     }
     
-    /// Scans project directories and files to detect project features
+    /// Directories below the root holding a project of their own (a Rust
+    /// backend and a React frontend in one repository, the members of a
+    /// workspace), recognized by their manifests. Projects nested in another
+    /// one are part of it.
+    fn find_sub_projects(&self, project_path: &Path, files_by_type: &HashMap<String, Vec<PathBuf>>) -> Vec<SubProject> {
+        let mut manifests: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+        for file in files_by_type.values().flatten() {
+            let name = match file.file_name().and_then(|n| n.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+            let is_manifest = name.ends_with(".csproj") || PROJECT_MANIFESTS.iter().any(|(manifest, _)| *manifest == name);
+            let dir = file.parent().unwrap_or(Path::new(""));
+            let depth = dir.components().count();
+            if is_manifest && depth > 0 && depth <= MAX_SUB_PROJECT_DEPTH {
+                manifests.entry(dir.to_path_buf()).or_default().push(name.to_string());
+            }
+        }
+        
+        let mut sub_projects: Vec<SubProject> = Vec::new();
+        for (dir, names) in manifests {
+            if sub_projects.iter().any(|sub| dir.starts_with(&sub.path)) {
+                continue;
+            }
+            let project_type = PROJECT_MANIFESTS
+                .iter()
+                .find(|(manifest, _)| names.iter().any(|name| name == manifest))
+                .map_or(ProjectType::CSharp, |(_, project_type)| *project_type);
+            let project_type = Self::refine_sub_project_type(&project_path.join(&dir), project_type);
+            sub_projects.push(SubProject { path: dir, project_type });
+        }
+        sub_projects
+    }
+    
+    /// The framework of a sub-project whose manifest only tells the language
+    fn refine_sub_project_type(dir: &Path, project_type: ProjectType) -> ProjectType {
+        match project_type {
+            ProjectType::JavaScript => match PackageJson::read(&dir.join("package.json")) {
+                Some(package) if package.depends_on("react") => ProjectType::React,
+                _ if dir.join("tsconfig.json").exists() => ProjectType::TypeScript,
+                _ => project_type,
+            },
+            ProjectType::PHP if dir.join("artisan").exists() => ProjectType::Laravel,
+            ProjectType::PHP if dir.join("bin/console").exists() => ProjectType::Symfony,
+            _ => project_type,
+        }
+    }
+    
+    /// Scans project directories and files to detect project features.
+    /// Paths matched by .gitignore or `.caulkignore` are skipped, as are
+    /// dependency and build directories, so they don't skew the detection.
     fn scan_project_features(&self, project_path: &Path, 
                             directories: &mut Vec<PathBuf>,
                             files_by_type: &mut HashMap<String, Vec<PathBuf>>) -> Result<ProjectFeatures> {
         let mut features = ProjectFeatures::default();
+        let walker = Self::walker(project_path);
         
-        for entry in WalkDir::new(project_path)
-            .max_depth(10)
-            .into_iter()
-            .filter_map(|e| e.ok()) {
-                
+        // Files whose content is checked for Drupal markers after the walk
+        let mut info_files = Vec::new();
+        let mut php_files = Vec::new();
+        
+        for entry in walk_parallel(&walker) {
             let path = entry.path();
+            let file_type = match entry.file_type() {
+                Some(file_type) => file_type,
+                None => continue,
+            };
             
-            if path.is_dir() {
-                if !self.should_ignore_dir(path) {
+            if file_type.is_dir() {
+                if entry.depth() > 0 {
                     directories.push(path.strip_prefix(project_path)?.to_path_buf());
                     
                     // Check for key directories
@@ -70,20 +184,15 @@ impl ProjectAnalyzer {
                         match dir_name {
                             "core" => features.has_drupal_core = true,
                             "src" => features.has_src_dir = true,
-                            "node_modules" => features.has_node_modules = true,
-                            ".git" => features.has_git = true,
-                            "target" => features.has_rust_target = true,
-                            "Plugin" => {
-                                if path.starts_with(project_path.join("src")) {
-                                    features.has_drupal_plugin_dir = true;
-                                }
+                            "Plugin" if path.starts_with(project_path.join("src")) => {
+                                features.has_drupal_plugin_dir = true;
                             }
                             _ => {}
                         }
                     }
                 }
-            } else if path.is_file() {
-                if !self.should_ignore_file(path) {
+            } else if file_type.is_file()
+                && !self.should_ignore_file(path) {
                     // Check for specific files by name/extension
                     if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
                         match file_name {
@@ -91,20 +200,26 @@ impl ProjectAnalyzer {
                             "package.json" => features.has_package_json = true,
                             "angular.json" => features.has_angular_json = true,
                             "composer.json" => features.has_composer_json = true,
+                            "artisan" => {
+                                if entry.depth() == 1 {
+                                    features.has_artisan = true;
+                                }
+                            }
                             "pyproject.toml" => features.has_pyproject_toml = true,
                             "requirements.txt" => features.has_requirements_txt = true,
                             "setup.py" => features.has_setup_py = true,
                             "go.mod" => features.has_go_mod = true,
+                            "pom.xml" => features.has_pom_xml = true,
+                            "CMakeLists.txt" => features.has_cmake_lists = true,
+                            "meson.build" => features.has_meson_build = true,
+                            "Makefile" | "makefile" | "GNUmakefile" => features.has_makefile = true,
+                            "build.gradle" | "build.gradle.kts" | "settings.gradle" | "settings.gradle.kts" => {
+                                features.has_gradle_build = true
+                            }
                             _ => {
                                 if file_name.ends_with(".info.yml") {
                                     features.has_info_yml = true;
-                                    
-                                    // Check if file contains Drupal module info
-                                    if let Ok(content) = std::fs::read_to_string(path) {
-                                        if content.contains("type: module") {
-                                            features.has_drupal_module_file = true;
-                                        }
-                                    }
+                                    info_files.push(path.to_path_buf());
                                 } else if file_name.ends_with(".module") {
                                     features.has_drupal_module_extension = true;
                                 }
@@ -113,18 +228,12 @@ impl ProjectAnalyzer {
                     }
                     
                     // Check for language-specific indicators
-                    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+                    if let Some(ext) = languages::detected_extension(path) {
                         // Check for key language file extensions
-                        match ext {
+                        match ext.as_str() {
                             "php" => {
                                 features.has_php_files = true;
-                                if let Ok(content) = std::fs::read_to_string(path) {
-                                    if content.contains("Drupal\\") || 
-                                       content.contains("function") && content.contains("_hook_") ||
-                                       content.contains("implements") && content.contains("Hook") {
-                                        features.has_drupal_php_code = true;
-                                    }
-                                }
+                                php_files.push(path.to_path_buf());
                             },
                             "rs" => features.has_rust_files = true,
                             "py" => features.has_python_files = true,
@@ -133,23 +242,55 @@ impl ProjectAnalyzer {
                             "jsx" => features.has_jsx_files = true,
                             "tsx" => features.has_tsx_files = true,
                             "go" => features.has_go_files = true,
+                            "java" => features.has_java_files = true,
+                            "kt" => features.has_kotlin_files = true,
+                            "cs" => features.has_cs_files = true,
+                            "csproj" | "sln" => features.has_dotnet_project = true,
+                            "c" => features.has_c_files = true,
+                            "cpp" | "cc" | "cxx" | "c++" => features.has_cpp_files = true,
                             _ => {}
                         }
                         
                         // Add file to files_by_type
                         let entry = files_by_type
                             .entry(ext.to_string())
-                            .or_insert_with(Vec::new);
+                            .or_default();
                             
                         entry.push(path.strip_prefix(project_path)?.to_path_buf());
                     }
                 }
-            }
         }
         
-        // Additional directory-based checks
+        // Content checks read files on all cores and stop at the first match
+        features.has_drupal_module_file = info_files.par_iter().any(|path| {
+            DrupalInfoFile::read(path).is_some_and(|info| info.kind == "module")
+        });
+        features.has_drupal_theme_file = info_files.par_iter().any(|path| {
+            DrupalInfoFile::read(path).is_some_and(|info| info.kind == "theme")
+        });
+        features.has_drupal_php_code = php_files.par_iter().any(|path| {
+            std::fs::read_to_string(path).is_ok_and(|content| {
+                content.contains("Drupal\\") || 
+                content.contains("function") && content.contains("_hook_") ||
+                content.contains("implements") && content.contains("Hook")
+            })
+        });
+        
+        // Additional directory-based checks, also covering directories the
+        // walk skips (Drupal core is usually ignored when installed by Composer)
         features.has_drupal_modules_dir = project_path.join("web/modules").exists() || 
                                           project_path.join("modules").exists();
+        features.has_drupal_core |= project_path.join("core/lib/Drupal.php").exists() ||
+                                    project_path.join("web/core/lib/Drupal.php").exists();
+        features.has_node_modules = project_path.join("node_modules").is_dir();
+        features.has_symfony_layout = project_path.join("bin/console").is_file() ||
+                                      project_path.join("config/bundles.php").is_file();
+        if let Ok(content) = std::fs::read_to_string(project_path.join("composer.json")) {
+            features.has_laravel_dependency = content.contains("\"laravel/framework\"");
+            features.has_symfony_dependency = content.contains("\"symfony/");
+        }
+        features.has_git = project_path.join(".git").exists();
+        features.has_rust_target = project_path.join("target").is_dir();
                                        
         Ok(features)
     }
@@ -158,15 +299,17 @@ impl ProjectAnalyzer {
     fn determine_project_type(&self, project_path: &Path, 
                              features: &ProjectFeatures, 
                              files_by_type: &HashMap<String, Vec<PathBuf>>) -> Result<(ProjectType, Vec<(String, PathBuf)>)> {
-        // Initialize an empty list for modules
-        let mut drupal_modules = Vec::new();
+        // A theme is recognized by the .info.yml at its root
+        if features.has_drupal_theme_file && self.is_drupal_theme(project_path)? {
+            return Ok((ProjectType::DrupalTheme, Vec::new()));
+        }
         
         // Check for Drupal projects first
         let is_drupal_site = features.has_drupal_core || features.has_drupal_modules_dir;
         
         if is_drupal_site || (features.has_info_yml && (features.has_drupal_module_file || features.has_drupal_php_code)) {
             // Find all modules in the project if it's a Drupal project
-            drupal_modules = self.find_all_drupal_modules(project_path)?;
+            let drupal_modules = self.find_all_drupal_modules(project_path)?;
             
             if !drupal_modules.is_empty() {
                 // Determine if the current directory is itself a module
@@ -180,15 +323,39 @@ impl ProjectAnalyzer {
             }
         }
         
+        // PHP frameworks before the JavaScript checks, since their front ends
+        // often ship React or TypeScript. Laravel itself depends on symfony/*.
+        if features.has_artisan || features.has_laravel_dependency {
+            return Ok((ProjectType::Laravel, Vec::new()));
+        } else if features.has_symfony_dependency && features.has_symfony_layout {
+            return Ok((ProjectType::Symfony, Vec::new()));
+        }
+        
         // Check for other project types
         if features.has_cargo_toml {
             return Ok((ProjectType::Rust, Vec::new()));
         } else if features.has_angular_json && features.has_package_json {
             return Ok((ProjectType::Angular, Vec::new()));
         } else if features.has_package_json && (features.has_jsx_files || features.has_tsx_files || 
-                                              (files_by_type.get("js").map_or(false, |files| 
+                                              (files_by_type.get("js").is_some_and(|files| 
                                                 files.iter().any(|p| p.to_string_lossy().contains("react"))))) {
             return Ok((ProjectType::React, Vec::new()));
+        } else if features.has_pom_xml || features.has_gradle_build {
+            // Kotlin when most sources are Kotlin; Gradle's own .kts scripts don't count
+            let count = |ext: &str| files_by_type.get(ext).map_or(0, |files| files.len());
+            if count("kt") > count("java") {
+                return Ok((ProjectType::Kotlin, Vec::new()));
+            }
+            return Ok((ProjectType::Java, Vec::new()));
+        } else if features.has_dotnet_project {
+            return Ok((ProjectType::CSharp, Vec::new()));
+        } else if features.has_cmake_lists || features.has_meson_build ||
+                  (features.has_makefile && (features.has_c_files || features.has_cpp_files)) {
+            // A Makefile alone is common in other projects, so it needs C/C++ sources
+            if features.has_cpp_files {
+                return Ok((ProjectType::Cpp, Vec::new()));
+            }
+            return Ok((ProjectType::C, Vec::new()));
         } else if features.has_pyproject_toml || features.has_requirements_txt || features.has_setup_py {
             return Ok((ProjectType::Python, Vec::new()));
         } else if features.has_go_mod || features.has_go_files {
@@ -203,7 +370,30 @@ impl ProjectAnalyzer {
         Ok((ProjectType::Generic, Vec::new()))
     }
     
-    fn should_ignore_dir(&self, path: &Path) -> bool {
+    /// Hash of the paths, sizes and modification times of the files and
+    /// directories the analysis walks, which changes whenever its result can
+    pub fn fingerprint(&self, project_path: &Path) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for entry in walk_parallel(&Self::walker(project_path)) {
+            entry.path().hash(&mut hasher);
+            if let Ok(metadata) = entry.metadata() {
+                metadata.len().hash(&mut hasher);
+                metadata.modified().ok().hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Walker over the project, skipping build output, dependencies and
+    /// hidden directories
+    fn walker(project_path: &Path) -> WalkBuilder {
+        let mut walker = project_walker(project_path);
+        walker
+            .filter_entry(|e| e.depth() == 0 || !e.file_type().is_some_and(|t| t.is_dir()) || !Self::should_ignore_dir(e.path()));
+        walker
+    }
+
+    fn should_ignore_dir(path: &Path) -> bool {
         let ignore_dirs = [
             ".git", "node_modules", "target", "build", "dist", "venv",
             "__pycache__", ".idea", ".vscode", "vendor", ".next", "out",
@@ -277,6 +467,41 @@ impl ProjectAnalyzer {
         Ok(has_info_yml_at_root && (has_module_file_at_root || has_plugin_dir || has_drupal_dependency))
     }
     
+    /// Determines if a directory is a Drupal theme: an .info.yml at its root
+    /// declaring `type: theme`
+    fn is_drupal_theme(&self, project_path: &Path) -> Result<bool> {
+        Ok(Self::theme_info_file(project_path)?.is_some())
+    }
+    
+    /// The root .info.yml of a theme
+    fn theme_info_file(project_path: &Path) -> Result<Option<PathBuf>> {
+        let pattern = project_path.join("*.info.yml");
+        Ok(glob(pattern.to_str().unwrap_or(""))?
+            .filter_map(|p| p.ok())
+            .find(|path| DrupalInfoFile::read(path).is_some_and(|info| info.kind == "theme")))
+    }
+    
+    /// Finds the themes of a Drupal site, as (name, path)
+    pub fn find_all_drupal_themes(&self, project_path: &Path) -> Result<Vec<(String, PathBuf)>> {
+        let mut themes = Vec::new();
+        for dir in ["web/themes/custom", "themes/custom", "web/themes/contrib", "themes/contrib"] {
+            let entries = match std::fs::read_dir(project_path.join(dir)) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.filter_map(|e| e.ok()) {
+                let path = entry.path();
+                if path.is_dir() && self.is_drupal_theme(&path)? {
+                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                        themes.push((name.to_string(), path));
+                    }
+                }
+            }
+        }
+        themes.sort();
+        Ok(themes)
+    }
+    
     /// Finds all Drupal modules in a project
     pub fn find_all_drupal_modules(&self, project_path: &Path) -> Result<Vec<(String, PathBuf)>> {
         let mut modules = Vec::new();
@@ -345,21 +570,8 @@ impl ProjectAnalyzer {
             .or_else(|| info_yml_files.first())
             .cloned();
         
-        // Extract module name and description from info.yml
-        let mut module_name = String::new();
-        let mut module_description = String::new();
-        
-        if let Some(info_path) = &info_file {
-            if let Ok(content) = std::fs::read_to_string(info_path) {
-                for line in content.lines() {
-                    if line.starts_with("name:") {
-                        module_name = line.trim_start_matches("name:").trim().trim_matches('"').trim_matches('\'').to_string();
-                    } else if line.starts_with("description:") {
-                        module_description = line.trim_start_matches("description:").trim().trim_matches('"').trim_matches('\'').to_string();
-                    }
-                }
-            }
-        }
+        // Read name, description, requirements and dependencies from info.yml
+        let info = info_file.as_deref().and_then(DrupalInfoFile::read).unwrap_or_default();
         
         // Find the module file (.module)
         let module_file_name = if let Some(info_path) = &info_file {
@@ -403,7 +615,7 @@ impl ProjectAnalyzer {
         
         // Check for plugins
         let has_plugins = project_path.join("src/Plugin").exists() || 
-                         module_file.as_ref().map_or(false, |path| {
+                         module_file.as_ref().is_some_and(|path| {
                              if let Ok(content) = std::fs::read_to_string(path) {
                                  content.contains("Plugin") || content.contains("plugin")
                              } else {
@@ -411,17 +623,13 @@ impl ProjectAnalyzer {
                              }
                          });
         
-        // Check for services
-        let has_services = {
-            let services_path = if let Some(name) = module_file_name {
-                project_path.join(format!("{}.services.yml", name))
-            } else {
-                PathBuf::new()
-            };
-            
-            services_path.exists() || files_by_type.get("yml").map_or(false, |yml_files| {
-                yml_files.iter().any(|p| p.to_string_lossy().ends_with(".services.yml"))
-            })
+        // Services and routes declared by the module
+        let (services, routes) = match module_file_name {
+            Some(name) => (
+                read_drupal_services(&project_path.join(format!("{}.services.yml", name))),
+                read_drupal_routes(&project_path.join(format!("{}.routing.yml", name))),
+            ),
+            None => (Vec::new(), Vec::new()),
         };
         
         // Find implemented hooks
@@ -444,12 +652,12 @@ impl ProjectAnalyzer {
         
         // Search for hooks in all PHP files
         if let Some(php_files) = files_by_type.get("php") {
+            // Hook implementations and `@implements hook_...` annotations
+            let hook_regex = Regex::new(r"function\s+([a-zA-Z0-9_]+)_hook_([a-zA-Z0-9_]+)")?;
+            let annotation_regex = Regex::new(r"@(Implements|implements)\s+hook_([a-zA-Z0-9_]+)")?;
             for file_path in php_files {
                 let full_path = project_path.join(file_path);
                 if let Ok(content) = std::fs::read_to_string(&full_path) {
-                    // Regex to find hook implementations
-                    let hook_regex = Regex::new(r"function\s+([a-zA-Z0-9_]+)_hook_([a-zA-Z0-9_]+)")?;
-                    
                     for cap in hook_regex.captures_iter(&content) {
                         if cap.len() >= 3 {
                             let hook_name = format!("hook_{}", &cap[2]);
@@ -459,9 +667,6 @@ impl ProjectAnalyzer {
                         }
                     }
                     
-                    // Look for implements hook annotations
-                    let annotation_regex = Regex::new(r"@(Implements|implements)\s+hook_([a-zA-Z0-9_]+)")?;
-                    
                     for cap in annotation_regex.captures_iter(&content) {
                         if cap.len() >= 3 {
                             let hook_name = format!("hook_{}", &cap[2]);
@@ -475,23 +680,69 @@ impl ProjectAnalyzer {
         }
         
         Ok(Some(DrupalModuleInfo {
-            name: if module_name.is_empty() { 
+            name: if info.name.is_empty() { 
                 module_file_name.unwrap_or("unknown").to_string() 
             } else { 
-                module_name 
+                info.name.clone()
             },
-            description: module_description,
+            description: info.description.clone(),
+            core_version_requirement: info.core_version_requirement(),
+            dependencies: info.dependencies,
             module_file: module_file.map(|p| p.strip_prefix(project_path).unwrap_or(&p).to_path_buf()),
             info_file: info_file.map(|p| p.strip_prefix(project_path).unwrap_or(&p).to_path_buf()),
             config_schemas: config_schemas.iter()
                 .map(|p| p.strip_prefix(project_path).unwrap_or(p).to_path_buf())
                 .collect(),
             has_plugins,
-            has_services,
+            services,
+            routes,
             hooks,
         }))
     }
     
+    /// Gathers the regions, libraries and templates of a Drupal theme
+    fn gather_drupal_theme_info(&self, project_path: &Path, files_by_type: &HashMap<String, Vec<PathBuf>>) -> Result<Option<DrupalThemeInfo>> {
+        let info_file = match Self::theme_info_file(project_path)? {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let machine_name = info_file
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.trim_end_matches(".info.yml").to_string())
+            .unwrap_or_default();
+        let info = DrupalInfoFile::read(&info_file).unwrap_or_default();
+        let regions = info
+            .regions
+            .iter()
+            .filter_map(|(key, label)| Some((yaml_scalar(key)?, yaml_scalar(label).unwrap_or_default())))
+            .collect();
+        
+        // Library names are the top-level keys of <theme>.libraries.yml
+        let libraries = read_yaml_mapping(&project_path.join(format!("{}.libraries.yml", machine_name)))
+            .keys()
+            .filter_map(yaml_scalar)
+            .collect();
+        
+        let mut templates: Vec<PathBuf> = files_by_type
+            .get("twig")
+            .map_or(Vec::new(), |files| files.clone());
+        templates.sort();
+        
+        let theme_file = project_path.join(format!("{}.theme", machine_name));
+        
+        Ok(Some(DrupalThemeInfo {
+            name: if info.name.is_empty() { machine_name.clone() } else { info.name },
+            description: info.description,
+            base_theme: yaml_scalar(&info.base_theme),
+            info_file: info_file.strip_prefix(project_path).unwrap_or(&info_file).to_path_buf(),
+            theme_file: theme_file.exists().then(|| PathBuf::from(format!("{}.theme", machine_name))),
+            regions,
+            libraries,
+            templates,
+        }))
+    }
+    
     /// Gathers information about a Rust project
     fn gather_rust_project_info(&self, project_path: &Path, files_by_type: &HashMap<String, Vec<PathBuf>>) -> Result<Option<RustProjectInfo>> {
         let cargo_toml_path = project_path.join("Cargo.toml");
@@ -499,23 +750,18 @@ impl ProjectAnalyzer {
             return Ok(None);
         }
         
-        let mut package_name = String::new();
-        let mut version = String::new();
-        
-        if let Ok(content) = std::fs::read_to_string(&cargo_toml_path) {
-            // Extract basic information from Cargo.toml
-            for line in content.lines() {
-                if line.trim().starts_with("name") {
-                    package_name = line.split('=').nth(1)
-                        .map(|s| s.trim().trim_matches('"').trim_matches('\'').to_string())
-                        .unwrap_or_default();
-                } else if line.trim().starts_with("version") {
-                    version = line.split('=').nth(1)
-                        .map(|s| s.trim().trim_matches('"').trim_matches('\'').to_string())
-                        .unwrap_or_default();
-                }
-            }
-        }
+        let manifest = CargoManifest::read(&cargo_toml_path).unwrap_or_default();
+        let workspace = manifest.workspace.as_ref().map(|workspace| RustWorkspaceInfo {
+            members: workspace.member_dirs(project_path)
+                .into_iter()
+                .map(|dir| {
+                    let member = CargoManifest::read(&dir.join("Cargo.toml")).unwrap_or_default();
+                    let name = member.package.as_ref().map_or(String::new(), |package| package.name.clone());
+                    let version = member.version(manifest.workspace.as_ref());
+                    (dir.strip_prefix(project_path).unwrap_or(&dir).to_path_buf(), name, version)
+                })
+                .collect(),
+        });
         
         // Count modules and structs
         let mut module_count = 0;
@@ -532,9 +778,17 @@ impl ProjectAnalyzer {
             }
         }
         
+        let mut features: Vec<String> = manifest.features.keys().cloned().collect();
+        features.sort();
+        
         Ok(Some(RustProjectInfo {
-            name: package_name,
-            version,
+            name: manifest.package.as_ref().map_or(String::new(), |package| package.name.clone()),
+            version: manifest.version(manifest.workspace.as_ref()),
+            edition: manifest.package.as_ref().and_then(|package| package.edition.as_ref()).and_then(|edition| edition.as_str()).map(String::from),
+            features,
+            dependencies: cargo_dependencies(&manifest.dependencies),
+            dev_dependencies: cargo_dependencies(&manifest.dev_dependencies),
+            workspace,
             module_count,
             struct_count,
             has_lib: project_path.join("src/lib.rs").exists(),
@@ -549,21 +803,25 @@ impl ProjectAnalyzer {
             return Ok(None);
         }
         
-        let mut project_name = String::new();
-        
-        if let Ok(content) = std::fs::read_to_string(&angular_json_path) {
-            // Try to extract project name from angular.json
-            if let Some(start) = content.find("\"projects\"") {
-                if let Some(project_start) = content[start..].find('{') {
-                    if let Some(name_start) = content[start + project_start + 1..].find('"') {
-                        let name_end = content[start + project_start + 1 + name_start + 1..].find('"').unwrap_or(0);
-                        if name_end > 0 {
-                            project_name = content[start + project_start + 1 + name_start + 1..start + project_start + 1 + name_start + 1 + name_end].to_string();
-                        }
-                    }
-                }
-            }
-        }
+        let workspace = AngularWorkspace::read(&angular_json_path).unwrap_or_default();
+        let package = PackageJson::read(&project_path.join("package.json")).unwrap_or_default();
+        let projects: Vec<AngularProject> = workspace
+            .projects
+            .iter()
+            .map(|(name, project)| AngularProject {
+                name: name.clone(),
+                kind: project.project_type.clone(),
+                root: project.root.clone(),
+                targets: project.architect.keys().cloned().collect(),
+            })
+            .collect();
+        // The default project, else the first application, else the first project
+        let project_name = workspace
+            .default_project
+            .clone()
+            .or_else(|| projects.iter().find(|p| p.kind.as_deref() == Some("application")).map(|p| p.name.clone()))
+            .or_else(|| projects.first().map(|p| p.name.clone()))
+            .unwrap_or_default();
         
         // Count components and services
         let mut component_count = 0;
@@ -588,14 +846,19 @@ impl ProjectAnalyzer {
             }
         }
         
+        let uses_ngrx = package.depends_on("@ngrx/store");
         Ok(Some(AngularProjectInfo {
             name: project_name,
+            projects,
+            scripts: package.scripts,
+            dependencies: package.dependencies.into_iter().collect(),
+            dev_dependencies: package.dev_dependencies.into_iter().collect(),
             component_count,
             service_count,
-            has_routing: files_by_type.get("ts").map_or(false, |files| 
+            has_routing: files_by_type.get("ts").is_some_and(|files| 
                 files.iter().any(|p| p.to_string_lossy().contains("routing") || 
                                     p.to_string_lossy().contains("routes"))),
-            has_ngrx: files_by_type.get("ts").map_or(false, |files| 
+            has_ngrx: uses_ngrx || files_by_type.get("ts").is_some_and(|files| 
                 files.iter().any(|p| p.to_string_lossy().contains("reducer") || 
                                     p.to_string_lossy().contains("action") || 
                                     p.to_string_lossy().contains("effect"))),
@@ -609,28 +872,10 @@ impl ProjectAnalyzer {
             return Ok(None);
         }
         
-        let mut project_name = String::new();
-        let mut has_redux = false;
-        
-        if let Ok(content) = std::fs::read_to_string(&package_json_path) {
-            // Extract project name from package.json
-            if let Some(name_start) = content.find("\"name\"") {
-                if let Some(colon) = content[name_start..].find(':') {
-                    let start_idx = name_start + colon + 1;
-                    if let Some(quote_start) = content[start_idx..].find('"') {
-                        let value_start = start_idx + quote_start + 1;
-                        if let Some(quote_end) = content[value_start..].find('"') {
-                            project_name = content[value_start..value_start + quote_end].to_string();
-                        }
-                    }
-                }
-            }
-            
-            // Check for Redux dependencies
-            has_redux = content.contains("\"redux\"") || 
-                        content.contains("\"@reduxjs/toolkit\"") || 
-                        content.contains("\"react-redux\"");
-        }
+        let package = PackageJson::read(&package_json_path).unwrap_or_default();
+        let has_redux = ["redux", "@reduxjs/toolkit", "react-redux"]
+            .iter()
+            .any(|name| package.depends_on(name));
         
         // Count components
         let mut component_count = 0;
@@ -654,12 +899,17 @@ impl ProjectAnalyzer {
         }
         
         // Determine if Next.js project
-        let is_nextjs = project_path.join("pages").exists() || 
+        let is_nextjs = package.depends_on("next") ||
+                        project_path.join("pages").exists() || 
                         project_path.join("src/pages").exists() || 
                         project_path.join(".next").exists();
         
         Ok(Some(ReactProjectInfo {
-            name: project_name,
+            name: package.name,
+            version: package.version,
+            scripts: package.scripts,
+            dependencies: package.dependencies.into_iter().collect(),
+            dev_dependencies: package.dev_dependencies.into_iter().collect(),
             component_count,
             has_redux,
             is_nextjs,
@@ -749,23 +999,466 @@ impl ProjectAnalyzer {
             has_fastapi,
         }))
     }
+    
+    /// Gathers information about a Maven or Gradle project written in Java or Kotlin
+    fn gather_java_project_info(&self, project_path: &Path, files_by_type: &HashMap<String, Vec<PathBuf>>) -> Result<Option<JavaProjectInfo>> {
+        let pom_path = project_path.join("pom.xml");
+        let gradle_paths: Vec<PathBuf> = ["settings.gradle.kts", "settings.gradle", "build.gradle.kts", "build.gradle"]
+            .iter()
+            .map(|name| project_path.join(name))
+            .filter(|path| path.exists())
+            .collect();
+        if !pom_path.exists() && gradle_paths.is_empty() {
+            return Ok(None);
+        }
+        
+        let mut project_name = String::new();
+        let mut build_tool = "Gradle";
+        let mut build_files = String::new();
+        
+        if let Ok(content) = std::fs::read_to_string(&pom_path) {
+            build_tool = "Maven";
+            // The first artifactId outside the <parent> block names the project
+            let own = match (content.find("<parent>"), content.find("</parent>")) {
+                (Some(start), Some(end)) if start < end => format!("{}{}", &content[..start], &content[end..]),
+                _ => content.clone(),
+            };
+            if let Some(captures) = Regex::new(r"<artifactId>\s*([^<\s]+)\s*</artifactId>")?.captures(&own) {
+                project_name = captures[1].to_string();
+            }
+            build_files.push_str(&content);
+        }
+        let root_project = Regex::new(r#"rootProject\.name\s*=\s*["']([^"']+)["']"#)?;
+        for path in &gradle_paths {
+            if let Ok(content) = std::fs::read_to_string(path) {
+                if project_name.is_empty() {
+                    if let Some(captures) = root_project.captures(&content) {
+                        project_name = captures[1].to_string();
+                    }
+                }
+                build_files.push_str(&content);
+            }
+        }
+        
+        // If project name still not found, use directory name
+        if project_name.is_empty() {
+            if let Some(dir_name) = project_path.file_name().and_then(|n| n.to_str()) {
+                project_name = dir_name.to_string();
+            }
+        }
+        
+        // Count classes, interfaces and Spring stereotypes, reading sources on all cores
+        let class_re = Regex::new(
+            r"(?m)^\s*(?:(?:public|private|protected|internal|abstract|final|static|sealed|open|data|inner|enum|annotation|value)\s+)*class\s+[A-Za-z_]"
+        )?;
+        let interface_re = Regex::new(r"(?m)^\s*(?:(?:public|private|protected|internal|abstract|sealed|static|fun)\s+)*interface\s+[A-Za-z_]")?;
+        let annotation_re = Regex::new(
+            r"@(SpringBootApplication|RestController|Controller|Service|Repository|Component|Configuration|Entity)\b"
+        )?;
+        
+        let java_files = files_by_type.get("java").map_or(&[][..], |files| files.as_slice());
+        let kotlin_files = files_by_type.get("kt").map_or(&[][..], |files| files.as_slice());
+        let per_file: Vec<(usize, usize, Vec<String>)> = java_files
+            .par_iter()
+            .chain(kotlin_files.par_iter())
+            .filter_map(|file_path| std::fs::read_to_string(project_path.join(file_path)).ok())
+            .map(|content| {
+                let annotations = annotation_re.captures_iter(&content).map(|c| c[1].to_string()).collect();
+                (class_re.find_iter(&content).count(), interface_re.find_iter(&content).count(), annotations)
+            })
+            .collect();
+        
+        let mut class_count = 0;
+        let mut interface_count = 0;
+        let mut annotation_counts: HashMap<String, usize> = HashMap::new();
+        for (classes, interfaces, annotations) in per_file {
+            class_count += classes;
+            interface_count += interfaces;
+            for annotation in annotations {
+                *annotation_counts.entry(annotation).or_insert(0) += 1;
+            }
+        }
+        let mut spring_annotations: Vec<(String, usize)> = annotation_counts.into_iter().collect();
+        spring_annotations.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        
+        let is_spring_boot = build_files.contains("spring-boot") ||
+                             spring_annotations.iter().any(|(name, _)| name == "SpringBootApplication");
+        
+        Ok(Some(JavaProjectInfo {
+            name: project_name,
+            build_tool: build_tool.to_string(),
+            java_file_count: java_files.len(),
+            kotlin_file_count: kotlin_files.len(),
+            class_count,
+            interface_count,
+            is_spring_boot,
+            spring_annotations,
+        }))
+    }
+    
+    /// Gathers the solution, projects and class counts of a .NET project written in C#
+    fn gather_dotnet_project_info(&self, project_path: &Path, files_by_type: &HashMap<String, Vec<PathBuf>>) -> Result<Option<DotNetProjectInfo>> {
+        let project_files = files_by_type.get("csproj").map_or(&[][..], |files| files.as_slice());
+        let solution = files_by_type
+            .get("sln")
+            .and_then(|files| files.iter().min_by_key(|path| path.components().count()))
+            .cloned();
+        if project_files.is_empty() && solution.is_none() {
+            return Ok(None);
+        }
+        
+        // Each project's target framework(s) and whether it is a web project
+        let framework_re = Regex::new(r"<TargetFrameworks?>\s*([^<]+?)\s*</TargetFrameworks?>")?;
+        let mut projects = Vec::new();
+        let mut is_aspnet = false;
+        for path in project_files {
+            let content = std::fs::read_to_string(project_path.join(path)).unwrap_or_default();
+            let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_string();
+            let frameworks = framework_re.captures(&content).map_or(String::new(), |c| c[1].to_string());
+            is_aspnet |= content.contains("Microsoft.NET.Sdk.Web") || content.contains("Microsoft.AspNetCore");
+            projects.push((name, frameworks));
+        }
+        projects.sort();
+        
+        // The solution names the project, else the first project file, else the directory
+        let project_name = solution
+            .as_ref()
+            .or_else(|| project_files.iter().min_by_key(|path| path.components().count()))
+            .and_then(|path| path.file_stem())
+            .or_else(|| project_path.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        
+        // Count types and controllers, skipping generated sources under bin/ and obj/
+        let class_re = Regex::new(
+            r"(?m)^\s*(?:\[[^\]]*\]\s*)*(?:(?:public|private|protected|internal|abstract|sealed|static|partial|file)\s+)*(?:class|record|struct)\s+[A-Za-z_]"
+        )?;
+        let interface_re = Regex::new(r"(?m)^\s*(?:(?:public|private|protected|internal|partial)\s+)*interface\s+[A-Za-z_]")?;
+        let controller_re = Regex::new(r"\[ApiController\]|class\s+\w+Controller\s*:\s*(?:Controller|ControllerBase)\b")?;
+        
+        let cs_files: Vec<&PathBuf> = files_by_type
+            .get("cs")
+            .map_or(&[][..], |files| files.as_slice())
+            .iter()
+            .filter(|path| !path.components().any(|c| c.as_os_str() == "bin" || c.as_os_str() == "obj"))
+            .collect();
+        let per_file: Vec<(usize, usize, bool)> = cs_files
+            .par_iter()
+            .filter_map(|file_path| std::fs::read_to_string(project_path.join(file_path)).ok())
+            .map(|content| (
+                class_re.find_iter(&content).count(),
+                interface_re.find_iter(&content).count(),
+                controller_re.is_match(&content),
+            ))
+            .collect();
+        
+        let class_count = per_file.iter().map(|(classes, _, _)| classes).sum();
+        let interface_count = per_file.iter().map(|(_, interfaces, _)| interfaces).sum();
+        let controller_count = per_file.iter().filter(|(_, _, controller)| *controller).count();
+        is_aspnet |= controller_count > 0;
+        
+        Ok(Some(DotNetProjectInfo {
+            name: project_name,
+            solution,
+            projects,
+            cs_file_count: cs_files.len(),
+            class_count,
+            interface_count,
+            controller_count,
+            is_aspnet,
+        }))
+    }
+    
+    /// Gathers the build system, targets and source counts of a C or C++ project
+    fn gather_cpp_project_info(&self, project_path: &Path, files_by_type: &HashMap<String, Vec<PathBuf>>) -> Result<Option<CppProjectInfo>> {
+        let mut project_name = String::new();
+        let mut build_system = String::new();
+        let mut targets: Vec<(String, String)> = Vec::new();
+        
+        // CMake: every CMakeLists.txt in the tree, the root one first
+        let mut cmake_files: Vec<&PathBuf> = files_by_type
+            .get("txt")
+            .map(|files| files.iter().filter(|p| p.file_name().is_some_and(|n| n == "CMakeLists.txt")).collect())
+            .unwrap_or_default();
+        cmake_files.sort_by_key(|p| p.components().count());
+        if !cmake_files.is_empty() {
+            build_system = "CMake".to_string();
+            let project_re = Regex::new(r"(?i)\bproject\s*\(\s*([A-Za-z0-9_.+-]+)")?;
+            let target_re = Regex::new(r"(?i)\badd_(executable|library)\s*\(\s*([^\s)]+)")?;
+            for file in cmake_files {
+                let content = match std::fs::read_to_string(project_path.join(file)) {
+                    Ok(content) => content,
+                    Err(_) => continue,
+                };
+                if project_name.is_empty() {
+                    if let Some(captures) = project_re.captures(&content) {
+                        project_name = captures[1].to_string();
+                    }
+                }
+                for captures in target_re.captures_iter(&content) {
+                    let name = captures[2].replace("${PROJECT_NAME}", &project_name);
+                    targets.push((name, captures[1].to_lowercase()));
+                }
+            }
+        } else if let Ok(content) = std::fs::read_to_string(project_path.join("meson.build")) {
+            build_system = "Meson".to_string();
+            if let Some(captures) = Regex::new(r"\bproject\s*\(\s*'([^']+)'")?.captures(&content) {
+                project_name = captures[1].to_string();
+            }
+            let target_re = Regex::new(r"\b(executable|library|shared_library|static_library|both_libraries)\s*\(\s*'([^']+)'")?;
+            for captures in target_re.captures_iter(&content) {
+                targets.push((captures[2].to_string(), captures[1].replace('_', " ")));
+            }
+        } else {
+            let makefile = ["GNUmakefile", "makefile", "Makefile"]
+                .iter()
+                .map(|name| project_path.join(name))
+                .find(|path| path.exists());
+            if let Some(content) = makefile.and_then(|path| std::fs::read_to_string(path).ok()) {
+                build_system = "Make".to_string();
+                // Rule targets, leaving out special targets, pattern rules and variable assignments
+                let rule_re = Regex::new(r"(?m)^([A-Za-z0-9_][A-Za-z0-9_./-]*(?:\s+[A-Za-z0-9_][A-Za-z0-9_./-]*)*)\s*::?(?:[^=]|$)")?;
+                for captures in rule_re.captures_iter(&content) {
+                    for name in captures[1].split_whitespace() {
+                        targets.push((name.to_string(), "make target".to_string()));
+                    }
+                }
+            }
+        }
+        
+        if build_system.is_empty() {
+            return Ok(None);
+        }
+        let mut seen = std::collections::HashSet::new();
+        targets.retain(|(name, _)| seen.insert(name.clone()));
+        
+        // If project name still not found, use directory name
+        if project_name.is_empty() {
+            if let Some(dir_name) = project_path.file_name().and_then(|n| n.to_str()) {
+                project_name = dir_name.to_string();
+            }
+        }
+        
+        let count = |extensions: &[&str]| -> usize {
+            extensions.iter().map(|ext| files_by_type.get(*ext).map_or(0, |files| files.len())).sum()
+        };
+        
+        Ok(Some(CppProjectInfo {
+            name: project_name,
+            build_system,
+            targets,
+            c_file_count: count(&["c"]),
+            cpp_file_count: count(&["cpp", "cc", "cxx", "c++"]),
+            header_count: count(&["h", "hpp", "hh", "hxx"]),
+        }))
+    }
+    
+    /// Gathers routes, controllers and models of a Laravel or Symfony application
+    fn gather_php_framework_info(&self, project_path: &Path, project_type: &ProjectType,
+                                 files_by_type: &HashMap<String, Vec<PathBuf>>) -> Result<Option<PhpFrameworkInfo>> {
+        let is_laravel = *project_type == ProjectType::Laravel;
+        let composer: serde_json::Value = std::fs::read_to_string(project_path.join("composer.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        
+        // The package name without its vendor, else the directory name
+        let project_name = composer["name"]
+            .as_str()
+            .map(|name| name.rsplit('/').next().unwrap_or(name).to_string())
+            .or_else(|| project_path.file_name().and_then(|n| n.to_str()).map(String::from))
+            .unwrap_or_default();
+        let framework_package = if is_laravel { "laravel/framework" } else { "symfony/framework-bundle" };
+        let framework_version = composer["require"][framework_package].as_str().map(String::from);
+        
+        let php_files = files_by_type.get("php").map_or(&[][..], |files| files.as_slice());
+        let yaml_files = ["yaml", "yml"]
+            .iter()
+            .flat_map(|ext| files_by_type.get(*ext).map_or(&[][..], |files| files.as_slice()));
+        
+        let route_re = if is_laravel {
+            Regex::new(r"Route::(?:get|post|put|patch|delete|options|any|match|redirect|view|resource|apiResource)\s*\(")?
+        } else {
+            Regex::new(r"(?m)#\[Route\(|@Route\(|^\s+path:")?
+        };
+        let model_re = if is_laravel {
+            Regex::new(r"class\s+(\w+)\s+extends\s+(?:\\?Illuminate\\Database\\Eloquent\\)?(?:Model|Authenticatable|Pivot)\b")?
+        } else {
+            Regex::new(r"(?s)(?:#\[ORM\\Entity|@ORM\\Entity).*?\bclass\s+(\w+)")?
+        };
+        let controller_dir = if is_laravel { "app/Http/Controllers" } else { "src/Controller" };
+        
+        // Laravel declares routes in routes/*.php. Symfony declares them in
+        // config/routes* and in #[Route] attributes or @Route annotations on controllers.
+        let mut route_files: Vec<PathBuf> = if is_laravel {
+            php_files.iter().filter(|path| path.starts_with("routes")).cloned().collect()
+        } else {
+            let is_routes_config = |path: &Path| path.starts_with("config/routes") ||
+                (path.parent() == Some(Path::new("config")) && path.file_stem().is_some_and(|stem| stem == "routes"));
+            let attribute_routes = php_files
+                .par_iter()
+                .filter(|path| path.starts_with(controller_dir))
+                .filter(|path| std::fs::read_to_string(project_path.join(path))
+                    .is_ok_and(|content| route_re.is_match(&content)));
+            yaml_files
+                .filter(|path| is_routes_config(path))
+                .cloned()
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .chain(attribute_routes.cloned())
+                .collect()
+        };
+        route_files.sort();
+        let route_count = route_files
+            .par_iter()
+            .filter_map(|path| std::fs::read_to_string(project_path.join(path)).ok())
+            .map(|content| route_re.find_iter(&content).count())
+            .sum();
+        
+        let mut models: Vec<String> = php_files
+            .par_iter()
+            .filter_map(|path| std::fs::read_to_string(project_path.join(path)).ok())
+            .flat_map_iter(|content| model_re.captures_iter(&content).map(|c| c[1].to_string()).collect::<Vec<_>>())
+            .collect();
+        models.sort();
+        models.dedup();
+        
+        let controller_count = php_files
+            .iter()
+            .filter(|path| path.starts_with(controller_dir))
+            .filter(|path| path.file_stem().and_then(|s| s.to_str()).is_some_and(|stem| stem.ends_with("Controller")))
+            .count();
+        
+        Ok(Some(PhpFrameworkInfo {
+            name: project_name,
+            framework: if is_laravel { "Laravel" } else { "Symfony" }.to_string(),
+            framework_version,
+            php_file_count: php_files.len(),
+            route_files,
+            route_count,
+            controller_count,
+            models,
+        }))
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum ProjectType {
     Drupal,
     DrupalModule,
+    DrupalTheme,
     Rust,
     Python,
     JavaScript,
     TypeScript,
     Go,
+    Java,
+    Kotlin,
+    CSharp,
+    C,
+    Cpp,
     PHP,
+    Laravel,
+    Symfony,
     Angular,
     React,
     Generic,
 }
 
+impl ProjectType {
+    /// Every type, in the order listed to users
+    pub const ALL: &'static [ProjectType] = &[
+        ProjectType::Drupal,
+        ProjectType::DrupalModule,
+        ProjectType::DrupalTheme,
+        ProjectType::Rust,
+        ProjectType::Python,
+        ProjectType::JavaScript,
+        ProjectType::TypeScript,
+        ProjectType::Go,
+        ProjectType::Java,
+        ProjectType::Kotlin,
+        ProjectType::CSharp,
+        ProjectType::C,
+        ProjectType::Cpp,
+        ProjectType::PHP,
+        ProjectType::Laravel,
+        ProjectType::Symfony,
+        ProjectType::Angular,
+        ProjectType::React,
+        ProjectType::Generic,
+    ];
+
+    /// Name used in `--project-type`, `[project] type` and CAULK.md
+    pub fn id(&self) -> &'static str {
+        match self {
+            ProjectType::Drupal => "drupal",
+            ProjectType::DrupalModule => "drupal-module",
+            ProjectType::DrupalTheme => "drupal-theme",
+            ProjectType::Rust => "rust",
+            ProjectType::Python => "python",
+            ProjectType::JavaScript => "javascript",
+            ProjectType::TypeScript => "typescript",
+            ProjectType::Go => "go",
+            ProjectType::Java => "java",
+            ProjectType::Kotlin => "kotlin",
+            ProjectType::CSharp => "csharp",
+            ProjectType::C => "c",
+            ProjectType::Cpp => "cpp",
+            ProjectType::PHP => "php",
+            ProjectType::Laravel => "laravel",
+            ProjectType::Symfony => "symfony",
+            ProjectType::Angular => "angular",
+            ProjectType::React => "react",
+            ProjectType::Generic => "generic",
+        }
+    }
+
+    /// Parses a type name case-insensitively, accepting `_` or spaces for
+    /// `-` and a few common aliases (`c#`, `dotnet`, `c++`, `js`, `ts`)
+    pub fn parse(name: &str) -> Result<Self> {
+        let name = name.trim().to_lowercase().replace(['_', ' '], "-");
+        let name = match name.as_str() {
+            "c#" | "dotnet" | ".net" => "csharp",
+            "c++" => "cpp",
+            "js" | "node" => "javascript",
+            "ts" => "typescript",
+            "drupal-site" => "drupal",
+            other => other,
+        };
+        Self::ALL.iter().copied().find(|t| t.id() == name).ok_or_else(|| {
+            let ids: Vec<&str> = Self::ALL.iter().map(|t| t.id()).collect();
+            anyhow::anyhow!("Unknown project type '{}' (expected one of: {})", name, ids.join(", "))
+        })
+    }
+
+    /// Human readable name, e.g. "Drupal module"
+    pub fn description(&self) -> &'static str {
+        match self {
+            ProjectType::Drupal => "Drupal site",
+            ProjectType::DrupalModule => "Drupal module",
+            ProjectType::DrupalTheme => "Drupal theme",
+            ProjectType::Rust => "Rust project",
+            ProjectType::Python => "Python project",
+            ProjectType::JavaScript => "JavaScript project",
+            ProjectType::TypeScript => "TypeScript project",
+            ProjectType::Go => "Go project",
+            ProjectType::Java => "Java project",
+            ProjectType::Kotlin => "Kotlin project",
+            ProjectType::CSharp => ".NET (C#) project",
+            ProjectType::C => "C project",
+            ProjectType::Cpp => "C++ project",
+            ProjectType::PHP => "PHP project",
+            ProjectType::Laravel => "Laravel application",
+            ProjectType::Symfony => "Symfony application",
+            ProjectType::Angular => "Angular application",
+            ProjectType::React => "React application",
+            ProjectType::Generic => "Generic project",
+        }
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ProjectFeatures {
     // Drupal-specific
@@ -773,6 +1466,7 @@ pub struct ProjectFeatures {
     pub has_drupal_modules_dir: bool,
     pub has_info_yml: bool,
     pub has_drupal_module_file: bool,  // .info.yml with "type: module"
+    pub has_drupal_theme_file: bool,  // .info.yml with "type: theme"
     pub has_drupal_module_extension: bool,  // .module file
     pub has_drupal_php_code: bool,
     pub has_drupal_plugin_dir: bool,
@@ -792,79 +1486,432 @@ pub struct ProjectFeatures {
     pub has_jsx_files: bool,
     pub has_tsx_files: bool,
     pub has_go_files: bool,
+    pub has_java_files: bool,
+    pub has_kotlin_files: bool,
+    pub has_cs_files: bool,
+    pub has_c_files: bool,
+    pub has_cpp_files: bool,
     
     // Project definition files
     pub has_cargo_toml: bool,
     pub has_package_json: bool,
     pub has_angular_json: bool,
     pub has_composer_json: bool,
+    pub has_artisan: bool,
+    pub has_laravel_dependency: bool,
+    pub has_symfony_dependency: bool,
+    pub has_symfony_layout: bool,  // bin/console or config/bundles.php
     pub has_pyproject_toml: bool,
     pub has_requirements_txt: bool,
     pub has_setup_py: bool,
     pub has_go_mod: bool,
+    pub has_pom_xml: bool,
+    pub has_gradle_build: bool,
+    pub has_dotnet_project: bool,  // .csproj or .sln
+    pub has_cmake_lists: bool,
+    pub has_makefile: bool,
+    pub has_meson_build: bool,
 }
 
 // Specific project information types
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "info")]
 pub enum SpecificProjectInfo {
     Drupal(Option<DrupalModuleInfo>),
+    DrupalTheme(Option<DrupalThemeInfo>),
     Rust(Option<RustProjectInfo>),
     Angular(Option<AngularProjectInfo>),
     React(Option<ReactProjectInfo>),
     Python(Option<PythonProjectInfo>),
+    Java(Option<JavaProjectInfo>),
+    DotNet(Option<DotNetProjectInfo>),
+    Cpp(Option<CppProjectInfo>),
+    PhpFramework(Option<PhpFrameworkInfo>),
     None,
 }
 
-#[derive(Debug)]
+/// A project of its own inside the analyzed one
+#[derive(Debug, Clone, Serialize)]
+pub struct SubProject {
+    /// Relative to the analyzed project's root
+    pub path: PathBuf,
+    pub project_type: ProjectType,
+}
+
+#[derive(Debug, Clone)]
 pub struct ProjectStructure {
     pub directories: Vec<PathBuf>,
     pub files_by_type: HashMap<String, Vec<PathBuf>>,
+    /// Code, comment and blank lines per language, the most code first
+    pub languages: Vec<LanguageLines>,
     pub project_type: Option<ProjectType>,
     pub specific_info: SpecificProjectInfo,
     pub modules: Vec<(String, PathBuf)>, // List of (module_name, module_path)
+    pub sub_projects: Vec<SubProject>,
+    /// Set by the project type plugin recognizing the project
+    pub plugin: Option<PluginProjectInfo>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DrupalModuleInfo {
     pub name: String,
     pub description: String, 
     pub module_file: Option<PathBuf>,
     pub info_file: Option<PathBuf>,
+    /// `core_version_requirement`, or `core` for Drupal 8 modules
+    pub core_version_requirement: Option<String>,
+    /// Modules this one depends on, e.g. `drupal:node`
+    pub dependencies: Vec<String>,
     pub config_schemas: Vec<PathBuf>,
     pub has_plugins: bool,
-    pub has_services: bool,
+    /// Services from the module's .services.yml
+    pub services: Vec<DrupalService>,
+    /// Routes from the module's .routing.yml
+    pub routes: Vec<DrupalRoute>,
     pub hooks: Vec<String>,
 }
 
-#[derive(Debug)]
+/// A service declared in a .services.yml file
+#[derive(Debug, Clone, Serialize)]
+pub struct DrupalService {
+    pub id: String,
+    /// None when the ID is the class name or the service is an alias
+    pub class: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// A route declared in a .routing.yml file
+#[derive(Debug, Clone, Serialize)]
+pub struct DrupalRoute {
+    pub name: String,
+    pub path: String,
+    /// `_controller`, `_form` or entity handler serving the route
+    pub handler: Option<String>,
+    pub permission: Option<String>,
+}
+
+/// The keys of a Drupal .info.yml file that analysis uses
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DrupalInfoFile {
+    name: String,
+    description: String,
+    #[serde(rename = "type")]
+    kind: String,
+    core_version_requirement: serde_yaml::Value,
+    core: serde_yaml::Value,
+    dependencies: Vec<String>,
+    /// A theme name, or `false` for themes without a base theme
+    #[serde(rename = "base theme")]
+    base_theme: serde_yaml::Value,
+    regions: serde_yaml::Mapping,
+}
+
+impl DrupalInfoFile {
+    /// Parses an .info.yml file; None when it is missing or not valid YAML
+    fn read(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_yaml::from_str(&content).ok()
+    }
+
+    fn core_version_requirement(&self) -> Option<String> {
+        yaml_scalar(&self.core_version_requirement).or_else(|| yaml_scalar(&self.core))
+    }
+}
+
+/// A YAML string or number as text
+fn yaml_scalar(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(text) => Some(text.clone()),
+        serde_yaml::Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+/// Parses a YAML file into a mapping; empty when it is missing or invalid
+fn read_yaml_mapping(path: &Path) -> serde_yaml::Mapping {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_yaml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Services of a .services.yml file, skipping `_defaults`
+fn read_drupal_services(path: &Path) -> Vec<DrupalService> {
+    let mut file = read_yaml_mapping(path);
+    let services = match file.remove("services") {
+        Some(serde_yaml::Value::Mapping(services)) => services,
+        _ => return Vec::new(),
+    };
+    services
+        .into_iter()
+        .filter_map(|(id, definition)| {
+            let id = id.as_str().filter(|id| *id != "_defaults")?.to_string();
+            let tags = definition
+                .get("tags")
+                .and_then(|tags| tags.as_sequence())
+                .map_or(Vec::new(), |tags| {
+                    tags.iter().filter_map(|tag| tag.get("name").and_then(yaml_scalar)).collect()
+                });
+            Some(DrupalService {
+                id,
+                class: definition.get("class").and_then(yaml_scalar),
+                tags,
+            })
+        })
+        .collect()
+}
+
+/// Routes of a .routing.yml file, skipping `route_callbacks`
+fn read_drupal_routes(path: &Path) -> Vec<DrupalRoute> {
+    read_yaml_mapping(path)
+        .into_iter()
+        .filter_map(|(name, definition)| {
+            let path = definition.get("path").and_then(yaml_scalar)?;
+            let defaults = definition.get("defaults");
+            let handler = ["_controller", "_form", "_entity_form", "_entity_list", "_entity_view"]
+                .iter()
+                .find_map(|key| defaults.and_then(|defaults| defaults.get(*key)).and_then(yaml_scalar));
+            Some(DrupalRoute {
+                name: name.as_str()?.to_string(),
+                path,
+                handler,
+                permission: definition
+                    .get("requirements")
+                    .and_then(|requirements| requirements.get("_permission"))
+                    .and_then(yaml_scalar),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DrupalThemeInfo {
+    pub name: String,
+    pub description: String,
+    pub base_theme: Option<String>,
+    pub info_file: PathBuf,
+    /// The .theme file with preprocess functions and theme hooks
+    pub theme_file: Option<PathBuf>,
+    /// Regions as (machine name, label)
+    pub regions: Vec<(String, String)>,
+    /// Library names from the .libraries.yml file
+    pub libraries: Vec<String>,
+    /// Twig templates, relative to the theme root
+    pub templates: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct RustProjectInfo {
+    /// Empty for a virtual workspace manifest
     pub name: String,
     pub version: String,
+    pub edition: Option<String>,
+    /// Feature names from `[features]`
+    pub features: Vec<String>,
+    /// Dependencies as (name, version requirement or source)
+    pub dependencies: Vec<(String, String)>,
+    pub dev_dependencies: Vec<(String, String)>,
+    pub workspace: Option<RustWorkspaceInfo>,
     pub module_count: usize,
     pub struct_count: usize,
     pub has_lib: bool,
     pub has_bin: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
+pub struct RustWorkspaceInfo {
+    /// Members as (directory, package name, version)
+    pub members: Vec<(PathBuf, String, String)>,
+}
+
+/// The parts of a Cargo.toml that analysis uses
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CargoManifest {
+    package: Option<CargoPackage>,
+    workspace: Option<CargoWorkspace>,
+    dependencies: toml::Table,
+    #[serde(rename = "dev-dependencies")]
+    dev_dependencies: toml::Table,
+    features: toml::Table,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CargoPackage {
+    name: String,
+    /// A version string or `{ workspace = true }`
+    version: Option<toml::Value>,
+    edition: Option<toml::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CargoWorkspace {
+    members: Vec<String>,
+    exclude: Vec<String>,
+    /// Values members inherit with `{ workspace = true }`
+    package: toml::Table,
+}
+
+impl CargoManifest {
+    fn read(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        toml::from_str(&content).ok()
+    }
+
+    /// The package version, inherited from `workspace` when the manifest says so
+    fn version(&self, workspace: Option<&CargoWorkspace>) -> String {
+        let version = self.package.as_ref().and_then(|package| package.version.as_ref());
+        match version {
+            Some(toml::Value::String(version)) => version.clone(),
+            Some(toml::Value::Table(_)) => workspace
+                .and_then(|workspace| workspace.package.get("version"))
+                .and_then(|version| version.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+impl CargoWorkspace {
+    /// Member directories, with globs such as `crates/*` expanded
+    fn member_dirs(&self, root: &Path) -> Vec<PathBuf> {
+        let excluded: Vec<PathBuf> = self.exclude.iter().map(|dir| root.join(dir)).collect();
+        let mut dirs: Vec<PathBuf> = self
+            .members
+            .iter()
+            .flat_map(|member| {
+                let pattern = root.join(member);
+                glob(pattern.to_str().unwrap_or(""))
+                    .map(|paths| paths.filter_map(|p| p.ok()).collect::<Vec<_>>())
+                    .unwrap_or_default()
+            })
+            .filter(|dir| dir.join("Cargo.toml").exists() && !excluded.contains(dir))
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+}
+
+/// Dependencies as (name, version requirement), or `path`, `git` or
+/// `workspace` for dependencies without a version
+fn cargo_dependencies(table: &toml::Table) -> Vec<(String, String)> {
+    table
+        .iter()
+        .map(|(name, spec)| {
+            let source = match spec {
+                toml::Value::String(version) => version.clone(),
+                toml::Value::Table(spec) => match spec.get("version").and_then(|v| v.as_str()) {
+                    Some(version) => version.to_string(),
+                    None => ["workspace", "path", "git"]
+                        .iter()
+                        .find(|key| spec.contains_key(**key))
+                        .map_or(String::new(), |key| key.to_string()),
+                },
+                _ => String::new(),
+            };
+            (name.clone(), source)
+        })
+        .collect()
+}
+
+/// The parts of a package.json that analysis uses
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PackageJson {
+    name: String,
+    version: String,
+    /// npm scripts by name, sorted
+    scripts: BTreeMap<String, String>,
+    /// Package names and version ranges, sorted
+    dependencies: BTreeMap<String, String>,
+    #[serde(rename = "devDependencies")]
+    dev_dependencies: BTreeMap<String, String>,
+}
+
+impl PackageJson {
+    fn read(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn depends_on(&self, name: &str) -> bool {
+        self.dependencies.contains_key(name) || self.dev_dependencies.contains_key(name)
+    }
+}
+
+/// The parts of an angular.json that analysis uses
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AngularWorkspace {
+    #[serde(rename = "defaultProject")]
+    default_project: Option<String>,
+    projects: BTreeMap<String, AngularWorkspaceProject>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AngularWorkspaceProject {
+    #[serde(rename = "projectType")]
+    project_type: Option<String>,
+    root: String,
+    /// Builder configuration of each target (`build`, `serve`, `test`, ...);
+    /// newer workspaces call it `targets`
+    #[serde(alias = "targets")]
+    architect: BTreeMap<String, serde_json::Value>,
+}
+
+impl AngularWorkspace {
+    fn read(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+}
+
+/// A project of an Angular workspace
+#[derive(Debug, Clone, Serialize)]
+pub struct AngularProject {
+    pub name: String,
+    /// `application` or `library`
+    pub kind: Option<String>,
+    pub root: String,
+    /// Targets runnable with `ng run <project>:<target>`
+    pub targets: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct AngularProjectInfo {
     pub name: String,
+    pub projects: Vec<AngularProject>,
+    /// npm scripts as (name, command)
+    pub scripts: BTreeMap<String, String>,
+    pub dependencies: Vec<(String, String)>,
+    pub dev_dependencies: Vec<(String, String)>,
     pub component_count: usize,
     pub service_count: usize,
     pub has_routing: bool,
     pub has_ngrx: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ReactProjectInfo {
     pub name: String,
+    pub version: String,
+    /// npm scripts as (name, command)
+    pub scripts: BTreeMap<String, String>,
+    pub dependencies: Vec<(String, String)>,
+    pub dev_dependencies: Vec<(String, String)>,
     pub component_count: usize,
     pub has_redux: bool,
     pub is_nextjs: bool,
     pub has_typescript: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PythonProjectInfo {
     pub name: String,
     pub class_count: usize,
@@ -874,4 +1921,60 @@ pub struct PythonProjectInfo {
     pub has_fastapi: bool,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct JavaProjectInfo {
+    pub name: String,
+    /// "Maven" or "Gradle"
+    pub build_tool: String,
+    pub java_file_count: usize,
+    pub kotlin_file_count: usize,
+    pub class_count: usize,
+    pub interface_count: usize,
+    pub is_spring_boot: bool,
+    /// Spring stereotype annotations and how many classes carry each
+    pub spring_annotations: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DotNetProjectInfo {
+    pub name: String,
+    /// Solution file, relative to the project root
+    pub solution: Option<PathBuf>,
+    /// Projects as (name, target frameworks), e.g. ("Api", "net8.0")
+    pub projects: Vec<(String, String)>,
+    pub cs_file_count: usize,
+    pub class_count: usize,
+    pub interface_count: usize,
+    pub controller_count: usize,
+    pub is_aspnet: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CppProjectInfo {
+    pub name: String,
+    /// "CMake", "Meson" or "Make"
+    pub build_system: String,
+    /// Build targets as (name, kind), e.g. ("server", "executable")
+    pub targets: Vec<(String, String)>,
+    pub c_file_count: usize,
+    pub cpp_file_count: usize,
+    pub header_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PhpFrameworkInfo {
+    pub name: String,
+    /// "Laravel" or "Symfony"
+    pub framework: String,
+    /// Version constraint of the framework package in composer.json
+    pub framework_version: Option<String>,
+    pub php_file_count: usize,
+    /// Files defining routes, relative to the project root
+    pub route_files: Vec<PathBuf>,
+    pub route_count: usize,
+    pub controller_count: usize,
+    /// Eloquent models (Laravel) or Doctrine entities (Symfony)
+    pub models: Vec<String>,
+}
+
 // End of file
@@ -0,0 +1,494 @@
+use anyhow::{anyhow, Result};
+use tree_sitter::{Language, Node, Parser, Tree};
+
+/// Languages parsed with a tree-sitter grammar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grammar {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+    Php,
+}
+
+impl Grammar {
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "rs" => Some(Grammar::Rust),
+            "py" => Some(Grammar::Python),
+            // The JavaScript grammar includes JSX
+            "js" | "jsx" | "mjs" | "cjs" => Some(Grammar::JavaScript),
+            "ts" | "mts" | "cts" => Some(Grammar::TypeScript),
+            "tsx" => Some(Grammar::Tsx),
+            "go" => Some(Grammar::Go),
+            "php" => Some(Grammar::Php),
+            _ => None,
+        }
+    }
+
+    /// Grammar of the file at `path`, from its extension or, for a file
+    /// without one, from its name and shebang line
+    pub fn for_path(path: &std::path::Path) -> Option<Self> {
+        crate::analysis::languages::detected_extension(path).and_then(|ext| Self::from_extension(&ext))
+    }
+
+    fn language(self) -> Language {
+        match self {
+            Grammar::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Grammar::Python => tree_sitter_python::LANGUAGE.into(),
+            Grammar::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Grammar::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Grammar::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Grammar::Go => tree_sitter_go::LANGUAGE.into(),
+            Grammar::Php => tree_sitter_php::LANGUAGE_PHP.into(),
+        }
+    }
+
+    fn is_doc_comment(self, text: &str) -> bool {
+        match self {
+            Grammar::Rust => text.starts_with("///") || text.starts_with("/**"),
+            Grammar::Go => text.starts_with("//") || text.starts_with("/*"),
+            // Python documents with docstrings instead
+            Grammar::Python => false,
+            _ => text.starts_with("/**"),
+        }
+    }
+
+    fn has_jsx(self) -> bool {
+        matches!(self, Grammar::JavaScript | Grammar::Tsx)
+    }
+}
+
+/// Nodes that wrap a definition and carry its doc comment or decorators
+const WRAPPERS: &[&str] = &[
+    "export_statement",
+    "lexical_declaration",
+    "variable_declaration",
+    "type_declaration",
+];
+
+const COMMENTS: &[&str] = &["comment", "line_comment", "block_comment"];
+
+/// Nodes naming a variable, type, field or function
+const IDENTIFIERS: &[&str] = &[
+    "identifier",
+    "type_identifier",
+    "field_identifier",
+    "property_identifier",
+    "shorthand_property_identifier",
+    "name",
+];
+
+/// A definition found in the syntax tree, before any framework-specific
+/// classification
+#[derive(Debug)]
+pub struct Definition {
+    pub name: String,
+    /// function, method, class, struct, enum, trait, interface, impl, module,
+    /// type, constant or macro
+    pub kind: &'static str,
+    pub line: usize,
+    /// Last line of the definition's body
+    pub end_line: usize,
+    /// Enclosing type, impl block, class or function
+    pub parent: Option<String>,
+    /// Doc comment or docstring with the comment markers removed
+    pub doc: Option<String>,
+    /// Attributes, decorators and `@tag` lines of PHP doc comments
+    pub annotations: Vec<String>,
+    /// Superclass, base clause or implemented trait
+    pub extends: Option<String>,
+    /// Whether the body renders JSX
+    pub has_jsx: bool,
+}
+
+impl Definition {
+    /// The doc comment up to its first `@tag`, on one line
+    pub fn description(&self) -> Option<String> {
+        let doc = self.doc.as_ref()?;
+        let description = doc
+            .lines()
+            .map(str::trim)
+            .take_while(|line| !line.starts_with('@'))
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        if description.is_empty() {
+            None
+        } else {
+            Some(description)
+        }
+    }
+}
+
+/// Definitions in a source file plus its namespace or package
+#[derive(Debug, Default)]
+pub struct Outline {
+    pub namespace: Option<String>,
+    pub definitions: Vec<Definition>,
+    /// Every identifier with its line, definition names included
+    pub identifiers: Vec<(String, usize)>,
+}
+
+/// Parses `content` into a syntax tree
+pub fn parse(grammar: Grammar, content: &str) -> Result<Tree> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&grammar.language())
+        .map_err(|e| anyhow!("Failed to load the {:?} grammar: {}", grammar, e))?;
+    parser
+        .parse(content, None)
+        .ok_or_else(|| anyhow!("Failed to parse {:?} source", grammar))
+}
+
+/// `content` with comments and Python docstrings blanked out. Newlines are
+/// kept so every line keeps its number; lines left blank can be skipped.
+pub fn strip_comments(grammar: Grammar, content: &str) -> Result<String> {
+    let tree = parse(grammar, content)?;
+    let mut ranges = Vec::new();
+    collect_comments(grammar, tree.root_node(), &mut ranges);
+
+    let mut bytes = content.as_bytes().to_vec();
+    for range in ranges {
+        for byte in &mut bytes[range] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+    }
+    // Whole characters were replaced, so the text is still valid UTF-8
+    Ok(String::from_utf8(bytes).unwrap_or_else(|_| content.to_string()))
+}
+
+fn collect_comments(grammar: Grammar, node: Node, ranges: &mut Vec<std::ops::Range<usize>>) {
+    let is_docstring = grammar == Grammar::Python
+        && node.kind() == "expression_statement"
+        && node.named_child_count() == 1
+        && first_named_child(node).is_some_and(|child| child.kind() == "string");
+    if node.kind().contains("comment") || is_docstring {
+        ranges.push(node.byte_range());
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_comments(grammar, child, ranges);
+    }
+}
+
+/// Parses `content` and lists its definitions in source order, including
+/// methods and nested functions
+pub fn outline(grammar: Grammar, content: &str) -> Result<Outline> {
+    let tree = parse(grammar, content)?;
+
+    let mut walker = Walker {
+        grammar,
+        source: content.as_bytes(),
+        outline: Outline::default(),
+    };
+    walker.visit(tree.root_node(), None);
+    Ok(walker.outline)
+}
+
+struct Walker<'a> {
+    grammar: Grammar,
+    source: &'a [u8],
+    outline: Outline,
+}
+
+impl<'a> Walker<'a> {
+    /// Visits the children of `node`; `parent` is the innermost enclosing
+    /// definition as (name, kind)
+    fn visit(&mut self, node: Node, parent: Option<(&str, &'static str)>) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            if IDENTIFIERS.contains(&child.kind()) {
+                let name = self.text(child).to_string();
+                self.outline.identifiers.push((name, child.start_position().row + 1));
+            }
+            self.note_namespace(child);
+            match self.definition(child, parent) {
+                Some(definition) => {
+                    let scope = definition.name.clone();
+                    let kind = definition.kind;
+                    self.outline.definitions.push(definition);
+                    self.visit(child, Some((&scope, kind)));
+                }
+                None => self.visit(child, parent),
+            }
+        }
+    }
+
+    fn note_namespace(&mut self, node: Node) {
+        if self.outline.namespace.is_some() {
+            return;
+        }
+        let name = match (self.grammar, node.kind()) {
+            (Grammar::Php, "namespace_definition") => node.child_by_field_name("name"),
+            (Grammar::Go, "package_clause") => first_named_child(node),
+            _ => None,
+        };
+        if let Some(name) = name {
+            self.outline.namespace = Some(self.text(name).to_string());
+        }
+    }
+
+    fn definition(&self, node: Node, parent: Option<(&str, &'static str)>) -> Option<Definition> {
+        let (kind, name, extends) = match self.grammar {
+            Grammar::Rust => self.rust_definition(node)?,
+            Grammar::Python => self.python_definition(node)?,
+            Grammar::JavaScript | Grammar::TypeScript | Grammar::Tsx => self.javascript_definition(node)?,
+            Grammar::Go => self.go_definition(node)?,
+            Grammar::Php => self.php_definition(node)?,
+        };
+
+        // Functions directly inside a type are methods
+        let kind = match (kind, parent) {
+            ("function", Some((_, "impl" | "trait" | "class" | "interface" | "struct"))) => "method",
+            _ => kind,
+        };
+        // Go methods name their type in the receiver instead of being nested
+        let parent = match (self.grammar, node.kind()) {
+            (Grammar::Go, "method_declaration") => self.go_receiver(node),
+            _ => parent.map(|(name, _)| name.to_string()),
+        };
+
+        let (doc, annotations) = self.doc_and_annotations(node);
+        let has_jsx = self.grammar.has_jsx() && matches!(kind, "function" | "method" | "class") && contains_jsx(node);
+        Some(Definition {
+            name,
+            kind,
+            line: node.start_position().row + 1,
+            end_line: node.end_position().row + 1,
+            parent,
+            doc,
+            annotations,
+            extends,
+            has_jsx,
+        })
+    }
+
+    fn rust_definition(&self, node: Node) -> Option<(&'static str, String, Option<String>)> {
+        let kind = match node.kind() {
+            "function_item" | "function_signature_item" => "function",
+            "struct_item" | "union_item" => "struct",
+            "enum_item" => "enum",
+            "trait_item" => "trait",
+            "type_item" => "type",
+            "mod_item" => "module",
+            "macro_definition" => "macro",
+            "const_item" | "static_item" => "constant",
+            "impl_item" => {
+                let name = self.text(node.child_by_field_name("type")?);
+                let name = name.split('<').next().unwrap_or(name).trim().to_string();
+                let extends = node.child_by_field_name("trait").map(|t| self.text(t).to_string());
+                return Some(("impl", name, extends));
+            }
+            _ => return None,
+        };
+        Some((kind, self.field_text(node, "name")?, None))
+    }
+
+    fn python_definition(&self, node: Node) -> Option<(&'static str, String, Option<String>)> {
+        let kind = match node.kind() {
+            "class_definition" => "class",
+            "function_definition" => "function",
+            _ => return None,
+        };
+        let extends = node
+            .child_by_field_name("superclasses")
+            .map(|s| self.text(s).trim_matches(|c| c == '(' || c == ')').to_string())
+            .filter(|s| !s.is_empty());
+        Some((kind, self.field_text(node, "name")?, extends))
+    }
+
+    fn javascript_definition(&self, node: Node) -> Option<(&'static str, String, Option<String>)> {
+        let kind = match node.kind() {
+            "class_declaration" | "abstract_class_declaration" => "class",
+            "function_declaration" | "generator_function_declaration" | "function_signature" => "function",
+            "method_definition" | "method_signature" | "abstract_method_signature" => "method",
+            "interface_declaration" => "interface",
+            "type_alias_declaration" => "type",
+            "enum_declaration" => "enum",
+            "internal_module" | "module" => "module",
+            "variable_declarator" => {
+                // const handler = () => {} and const f = function () {}
+                let value = node.child_by_field_name("value")?;
+                if !matches!(value.kind(), "arrow_function" | "function_expression" | "function") {
+                    return None;
+                }
+                "function"
+            }
+            _ => return None,
+        };
+        let extends = children_of_kind(node, "class_heritage")
+            .first()
+            .map(|h| self.text(*h).trim().to_string());
+        Some((kind, self.field_text(node, "name")?, extends))
+    }
+
+    fn go_definition(&self, node: Node) -> Option<(&'static str, String, Option<String>)> {
+        let kind = match node.kind() {
+            "function_declaration" => "function",
+            "method_declaration" => "method",
+            "type_spec" | "type_alias" => match node.child_by_field_name("type").map(|t| t.kind()) {
+                Some("struct_type") => "struct",
+                Some("interface_type") => "interface",
+                _ => "type",
+            },
+            _ => return None,
+        };
+        Some((kind, self.field_text(node, "name")?, None))
+    }
+
+    fn php_definition(&self, node: Node) -> Option<(&'static str, String, Option<String>)> {
+        let kind = match node.kind() {
+            "class_declaration" => "class",
+            "interface_declaration" => "interface",
+            "trait_declaration" => "trait",
+            "enum_declaration" => "enum",
+            "function_definition" => "function",
+            "method_declaration" => "method",
+            _ => return None,
+        };
+        let extends = children_of_kind(node, "base_clause")
+            .first()
+            .map(|b| self.text(*b).trim_start_matches("extends").trim().to_string());
+        Some((kind, self.field_text(node, "name")?, extends))
+    }
+
+    /// Type name from a Go method receiver such as `(s *Server[T])`
+    fn go_receiver(&self, node: Node) -> Option<String> {
+        let receiver = self.text(node.child_by_field_name("receiver")?);
+        let receiver = receiver.trim_matches(|c| c == '(' || c == ')');
+        let type_name = receiver.split_whitespace().last()?.trim_start_matches('*');
+        Some(type_name.split('[').next().unwrap_or(type_name).to_string())
+    }
+
+    /// Doc comment directly above the definition (or the docstring in
+    /// Python) and its attributes or decorators
+    fn doc_and_annotations(&self, node: Node) -> (Option<String>, Vec<String>) {
+        let mut doc = self.docstring(node);
+        let mut annotations = Vec::new();
+
+        let mut anchor = node;
+        loop {
+            for child in children_of_kind(anchor, "decorator")
+                .into_iter()
+                .chain(children_of_kind(anchor, "attribute_list"))
+            {
+                annotations.push(self.text(child).to_string());
+            }
+
+            let mut attributes = Vec::new();
+            let mut comments = Vec::new();
+            let mut row = anchor.start_position().row;
+            let mut sibling = anchor.prev_sibling();
+            while let Some(prev) = sibling {
+                if matches!(prev.kind(), "attribute_item" | "decorator") {
+                    attributes.push(self.text(prev).to_string());
+                } else if COMMENTS.contains(&prev.kind()) {
+                    let text = self.text(prev).trim();
+                    if !self.grammar.is_doc_comment(text) || last_row(prev) + 1 < row {
+                        break;
+                    }
+                    comments.push(text.to_string());
+                } else {
+                    break;
+                }
+                row = prev.start_position().row;
+                sibling = prev.prev_sibling();
+            }
+            attributes.reverse();
+            annotations.splice(0..0, attributes);
+            if doc.is_none() && !comments.is_empty() {
+                comments.reverse();
+                doc = Some(clean_comment(&comments.join("\n")));
+            }
+
+            match anchor.parent() {
+                Some(parent) if WRAPPERS.contains(&parent.kind()) => anchor = parent,
+                _ => break,
+            }
+        }
+
+        if self.grammar == Grammar::Php {
+            if let Some(doc) = &doc {
+                annotations.extend(doc.lines().map(str::trim).filter(|l| l.starts_with('@')).map(String::from));
+            }
+        }
+        (doc.filter(|d| !d.is_empty()), annotations)
+    }
+
+    fn docstring(&self, node: Node) -> Option<String> {
+        if self.grammar != Grammar::Python {
+            return None;
+        }
+        let first = first_named_child(node.child_by_field_name("body")?)?;
+        if first.kind() != "expression_statement" {
+            return None;
+        }
+        let string = first_named_child(first).filter(|s| s.kind() == "string")?;
+        let text = self.text(string).trim_matches(|c| c == '"' || c == '\'');
+        Some(clean_comment(text))
+    }
+
+    fn field_text(&self, node: Node, field: &str) -> Option<String> {
+        Some(self.text(node.child_by_field_name(field)?).to_string())
+    }
+
+    fn text(&self, node: Node) -> &'a str {
+        node.utf8_text(self.source).unwrap_or("")
+    }
+}
+
+fn first_named_child(node: Node) -> Option<Node> {
+    let mut cursor = node.walk();
+    let child = node.named_children(&mut cursor).next();
+    child
+}
+
+fn children_of_kind<'t>(node: Node<'t>, kind: &str) -> Vec<Node<'t>> {
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor).filter(|c| c.kind() == kind).collect()
+}
+
+/// Last row with content (line comments end at column 0 of the next row)
+fn last_row(node: Node) -> usize {
+    let end = node.end_position();
+    if end.column == 0 && end.row > node.start_position().row {
+        end.row - 1
+    } else {
+        end.row
+    }
+}
+
+fn contains_jsx(node: Node) -> bool {
+    if matches!(node.kind(), "jsx_element" | "jsx_self_closing_element" | "jsx_fragment") {
+        return true;
+    }
+    let mut cursor = node.walk();
+    let found = node.named_children(&mut cursor).any(contains_jsx);
+    found
+}
+
+/// Comment text without `///`, `//`, `/**`, `*/` and leading `*` markers
+fn clean_comment(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let mut line = line.trim().trim_end_matches("*/").trim_end();
+            for prefix in ["///", "//!", "//", "/**", "/*", "*"] {
+                if let Some(rest) = line.strip_prefix(prefix) {
+                    line = rest;
+                    break;
+                }
+            }
+            line.trim()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
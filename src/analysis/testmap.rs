@@ -0,0 +1,142 @@
+use crate::fs::project_files;
+use regex::Regex;
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+
+/// Directories holding tests by convention
+const TEST_DIRS: &[&str] = &["tests", "test", "__tests__", "spec", "specs"];
+/// File name endings of tests: Go, JavaScript/TypeScript, Python, PHPUnit,
+/// JUnit, .NET and RSpec conventions
+const TEST_SUFFIXES: &[&str] = &[
+    "_test.go",
+    ".spec.ts",
+    ".spec.tsx",
+    ".spec.js",
+    ".spec.jsx",
+    ".test.ts",
+    ".test.tsx",
+    ".test.js",
+    ".test.jsx",
+    "_test.py",
+    "Test.php",
+    "Test.java",
+    "Tests.java",
+    "Test.kt",
+    "Tests.cs",
+    "Test.cs",
+    "_spec.rb",
+    "_test.rb",
+];
+/// Words of commands that change code, whose tests then belong in the context
+const EDIT_WORDS: &[&str] = &[
+    "add", "change", "edit", "fix", "implement", "modify", "refactor", "remove", "rename", "replace", "rewrite",
+    "test", "tests", "update",
+];
+
+/// Whether a command changes code, so the tests of the files it touches
+/// should be in the context
+pub fn wants_tests(command: &str) -> bool {
+    command
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| EDIT_WORDS.contains(&word))
+}
+
+/// Whether `path` (relative to the project root) is a test, by its
+/// directory or its name
+pub fn is_test_file(path: &Path) -> bool {
+    let in_test_dir = path
+        .parent()
+        .is_some_and(|dir| dir.components().any(|c| TEST_DIRS.iter().any(|d| c.as_os_str() == *d)));
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let is_python_test = name.starts_with("test_") && name.ends_with(".py");
+    in_test_dir || is_python_test || TEST_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// The test files of a project, for finding the tests of a source file by
+/// the naming conventions of its language
+pub struct TestMap {
+    root: PathBuf,
+    /// Relative to the root
+    tests: Vec<PathBuf>,
+    /// Name ending of PHPUnit tests: the `suffix` in phpunit.xml, `Test.php`
+    /// by default
+    phpunit_suffix: String,
+}
+
+impl TestMap {
+    pub fn build(root: &Path) -> Self {
+        let phpunit_suffix = phpunit_suffix(root).unwrap_or_else(|| "Test.php".to_string());
+        let tests = project_files(root)
+            .into_iter()
+            .filter_map(|path| path.strip_prefix(root).ok().map(Path::to_path_buf))
+            .filter(|path| is_test_file(path) || path.to_string_lossy().ends_with(phpunit_suffix.as_str()))
+            .collect();
+        Self {
+            root: root.to_path_buf(),
+            tests,
+            phpunit_suffix,
+        }
+    }
+
+    /// Tests of `source`, those closest to it in the tree first. Empty when
+    /// `source` is itself a test.
+    pub fn tests_of(&self, source: &Path) -> Vec<PathBuf> {
+        let relative = source.strip_prefix(&self.root).unwrap_or(source);
+        if is_test_file(relative) {
+            return Vec::new();
+        }
+        let names = self.test_names(relative);
+        let mut tests: Vec<&PathBuf> = self
+            .tests
+            .iter()
+            .filter(|test| test.file_name().and_then(|n| n.to_str()).is_some_and(|name| names.iter().any(|n| n == name)))
+            .collect();
+        tests.sort_by_key(|test| (Reverse(shared_components(test, relative)), test.to_path_buf()));
+        tests.into_iter().map(|test| self.root.join(test)).collect()
+    }
+
+    /// File names the tests of `source` have in its language
+    fn test_names(&self, source: &Path) -> Vec<String> {
+        let stem = match source.file_stem().and_then(|s| s.to_str()) {
+            Some(stem) => stem,
+            None => return Vec::new(),
+        };
+        let ext = source.extension().and_then(|e| e.to_str()).unwrap_or("");
+        match ext {
+            "go" => vec![format!("{}_test.go", stem)],
+            "ts" | "tsx" | "js" | "jsx" => ["spec", "test"]
+                .iter()
+                .flat_map(|kind| {
+                    let mut exts = vec![ext, "ts", "js"];
+                    exts.dedup();
+                    exts.into_iter().map(move |e| format!("{}.{}.{}", stem, kind, e))
+                })
+                .collect(),
+            "py" => vec![format!("test_{}.py", stem), format!("{}_test.py", stem)],
+            "php" => vec![format!("{}{}", stem, self.phpunit_suffix)],
+            "java" | "kt" => ["Test", "Tests", "IT"].iter().map(|suffix| format!("{}{}.{}", stem, suffix, ext)).collect(),
+            "cs" => vec![format!("{}Tests.cs", stem), format!("{}Test.cs", stem)],
+            "rb" => vec![format!("{}_spec.rb", stem), format!("{}_test.rb", stem)],
+            // Integration tests in tests/; unit tests live in the file itself
+            "rs" if stem != "mod" && stem != "lib" && stem != "main" => vec![format!("{}.rs", stem)],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// The `suffix` of a test directory in phpunit.xml or phpunit.xml.dist
+fn phpunit_suffix(root: &Path) -> Option<String> {
+    let suffix = Regex::new(r#"<directory[^>]*\bsuffix="([^"]+)""#).expect("valid phpunit suffix pattern");
+    ["phpunit.xml", "phpunit.xml.dist"].iter().find_map(|name| {
+        let content = std::fs::read_to_string(root.join(name)).ok()?;
+        suffix.captures(&content).map(|captures| captures[1].to_string())
+    })
+}
+
+/// Leading directories two paths have in common
+fn shared_components(a: &Path, b: &Path) -> usize {
+    let a_dir = a.parent().unwrap_or(Path::new(""));
+    let b_dir = b.parent().unwrap_or(Path::new(""));
+    a_dir.components().zip(b_dir.components()).take_while(|(x, y)| x == y).count()
+}
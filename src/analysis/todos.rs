@@ -0,0 +1,91 @@
+use crate::fs::project_files;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// Files larger than this are not searched for tasks
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// A `TODO(ai): <instruction>` comment left for the model to implement
+#[derive(Debug, Clone)]
+pub struct TodoTask {
+    pub path: PathBuf,
+    /// 1-based line of the comment when it was found
+    pub line: usize,
+    pub instruction: String,
+    /// The whole line, for finding the comment again after earlier edits
+    /// moved it
+    pub text: String,
+}
+
+fn task_pattern() -> Regex {
+    Regex::new(r"(?i)(?://+|#|/\*+|\*|--|<!--|\{#)\s*TODO\(ai\)\s*:?\s*(.*?)\s*(?:\*/|-->|#\})?\s*$")
+        .expect("valid TODO task pattern")
+}
+
+/// `TODO(ai)` comments in the file at `path` or the files under it, in path
+/// and line order. Comments without an instruction are left out.
+pub fn find_tasks(path: &Path) -> Vec<TodoTask> {
+    let pattern = task_pattern();
+    let mut tasks = Vec::new();
+    for file in project_files(path) {
+        if std::fs::metadata(&file).map_or(true, |metadata| metadata.len() > MAX_FILE_BYTES) {
+            continue;
+        }
+        let content = match std::fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        for (idx, line) in content.lines().enumerate() {
+            let instruction = match pattern.captures(line) {
+                Some(captures) => captures[1].to_string(),
+                None => continue,
+            };
+            if instruction.is_empty() {
+                continue;
+            }
+            tasks.push(TodoTask { path: file.clone(), line: idx + 1, instruction, text: line.to_string() });
+        }
+    }
+    tasks
+}
+
+impl TodoTask {
+    /// 1-based line of the comment in `content`: the line it was found on
+    /// when it is still there, otherwise the nearest line with the same text
+    pub fn find_in(&self, content: &str) -> Option<usize> {
+        let lines: Vec<&str> = content.lines().collect();
+        if lines.get(self.line - 1) == Some(&self.text.as_str()) {
+            return Some(self.line);
+        }
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| **line == self.text)
+            .min_by_key(|(idx, _)| (*idx + 1).abs_diff(self.line))
+            .map(|(idx, _)| idx + 1)
+    }
+
+    /// `content` without the comment: its line is dropped, or only the
+    /// comment when it trails code. None when the comment is gone.
+    pub fn remove_from(&self, content: &str) -> Option<String> {
+        let line = self.find_in(content)?;
+        let captures = task_pattern().captures(&self.text)?;
+        let code = self.text[..captures.get(0).map_or(0, |m| m.start())].trim_end();
+
+        let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let ending = if lines[line - 1].ends_with("\r\n") {
+            "\r\n"
+        } else if lines[line - 1].ends_with('\n') {
+            "\n"
+        } else {
+            ""
+        };
+        let kept = format!("{}{}", code, ending);
+        if code.trim().is_empty() {
+            lines.remove(line - 1);
+        } else {
+            lines[line - 1] = &kept;
+        }
+        Some(lines.concat())
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use crate::analysis::structure::ProjectStructure;
+
+/// Directory tree built from a ProjectStructure, annotated with file counts and sizes
+#[derive(Debug, Default)]
+pub struct FileTree {
+    root: TreeNode,
+}
+
+#[derive(Debug, Default)]
+struct TreeNode {
+    children: BTreeMap<String, TreeNode>,
+    is_dir: bool,
+    file_count: usize,
+    total_size: u64,
+}
+
+impl FileTree {
+    /// Builds the tree from the files recorded in the project structure.
+    /// File sizes are read from disk relative to `project_path`.
+    pub fn from_structure(project_structure: &ProjectStructure, project_path: &Path) -> Self {
+        let mut tree = FileTree::default();
+        tree.root.is_dir = true;
+
+        for files in project_structure.files_by_type.values() {
+            for file in files {
+                let size = std::fs::metadata(project_path.join(file))
+                    .map(|m| m.len())
+                    .unwrap_or(0);
+                tree.insert(file, size);
+            }
+        }
+
+        tree
+    }
+
+    fn insert(&mut self, relative_path: &Path, size: u64) {
+        let components: Vec<String> = relative_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+
+        let mut node = &mut self.root;
+        node.file_count += 1;
+        node.total_size += size;
+
+        for (idx, component) in components.iter().enumerate() {
+            let is_last = idx == components.len() - 1;
+            node = node.children.entry(component.clone()).or_default();
+            node.is_dir = node.is_dir || !is_last;
+            node.file_count += 1;
+            node.total_size += size;
+        }
+    }
+
+    /// Renders the tree like `tree -L <max_depth>`, stopping after `max_lines` entries
+    pub fn render(&self, max_depth: usize, max_lines: usize) -> String {
+        let mut lines = Vec::new();
+        let mut omitted = 0;
+
+        lines.push(format!(
+            "./ ({} files, {})",
+            self.root.file_count,
+            format_size(self.root.total_size)
+        ));
+        Self::render_node(&self.root, "", 1, max_depth, max_lines, &mut lines, &mut omitted);
+
+        let mut output = lines.join("\n");
+        output.push('\n');
+        if omitted > 0 {
+            output.push_str(&format!("... ({} more entries not shown)\n", omitted));
+        }
+        output
+    }
+
+    fn render_node(node: &TreeNode, prefix: &str, depth: usize, max_depth: usize,
+                   max_lines: usize, lines: &mut Vec<String>, omitted: &mut usize) {
+        // Directories first, then files, each alphabetically
+        let mut entries: Vec<(&String, &TreeNode)> = node.children.iter().collect();
+        entries.sort_by_key(|(name, child)| (!child.is_dir, name.to_lowercase()));
+
+        for (idx, (name, child)) in entries.iter().enumerate() {
+            if lines.len() >= max_lines {
+                *omitted += entries.len() - idx;
+                return;
+            }
+
+            let is_last = idx == entries.len() - 1;
+            let connector = if is_last { "└── " } else { "├── " };
+
+            if child.is_dir {
+                lines.push(format!(
+                    "{}{}{}/ ({} files, {})",
+                    prefix, connector, name, child.file_count, format_size(child.total_size)
+                ));
+
+                if depth < max_depth {
+                    let child_prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
+                    Self::render_node(child, &child_prefix, depth + 1, max_depth, max_lines, lines, omitted);
+                }
+            } else {
+                lines.push(format!("{}{}{} ({})", prefix, connector, name, format_size(child.total_size)));
+            }
+        }
+    }
+}
+
+/// Formats a byte count in a compact human readable form
+pub fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
@@ -0,0 +1,292 @@
+use anyhow::Result;
+use log::debug;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::analysis::parser::CodeParser;
+use crate::analysis::syntax::Grammar;
+use crate::fs;
+use crate::llm::vendor::VENDOR_DIRS;
+
+/// Files larger than this are not indexed
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// Lines a signature may span before the body starts
+const MAX_SIGNATURE_LINES: usize = 8;
+
+/// Phrases that ask where a symbol is used rather than what it does
+const USAGE_PHRASES: &[&str] = &[
+    "where is",
+    "where's",
+    "where are",
+    "usages of",
+    "uses of",
+    "references to",
+    "who calls",
+    "what calls",
+    "callers of",
+];
+/// Words that complete a "where is X ..." question
+const USAGE_VERBS: &[&str] = &["used", "called", "referenced", "invoked", "defined"];
+
+/// A symbol occurrence in the project
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub path: PathBuf,
+    pub line: usize,
+    pub kind: Option<String>,
+    /// Enclosing type, impl block, class or function of a definition
+    pub parent: Option<String>,
+}
+
+/// Where a symbol is defined and referenced
+#[derive(Debug, Clone)]
+pub struct SymbolUsage {
+    pub name: String,
+    pub definitions: Vec<Location>,
+    pub references: Vec<Location>,
+}
+
+impl SymbolUsage {
+    pub fn is_known(&self) -> bool {
+        !self.definitions.is_empty() || !self.references.is_empty()
+    }
+}
+
+/// A definition found by a symbol lookup, with its signature
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolDefinition {
+    /// Relative to the project root
+    pub path: PathBuf,
+    pub line: usize,
+    pub kind: String,
+    pub parent: Option<String>,
+    pub signature: String,
+}
+
+/// Definitions and identifier occurrences of one source file
+struct IndexedFile {
+    modified: SystemTime,
+    definitions: Vec<IndexedDefinition>,
+    references: Vec<(String, usize)>,
+}
+
+struct IndexedDefinition {
+    name: String,
+    line: usize,
+    kind: String,
+    parent: Option<String>,
+}
+
+/// Definitions and references of every symbol in the project's source files,
+/// kept up to date by re-parsing only files whose modification time changed
+#[derive(Default)]
+pub struct SymbolIndex {
+    files: HashMap<PathBuf, IndexedFile>,
+}
+
+impl SymbolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Re-indexes changed and new files under `root` and forgets deleted ones
+    pub fn refresh(&mut self, root: &Path) -> Result<()> {
+        let mut seen = BTreeSet::new();
+        let mut changed = Vec::new();
+
+        let mut walker = fs::project_walker(root);
+        walker.filter_entry(|e| !VENDOR_DIRS.contains(&e.file_name().to_string_lossy().as_ref()));
+        for entry in fs::walk_parallel(&walker) {
+            let path = entry.path();
+            if Grammar::for_path(path).is_none() {
+                continue;
+            }
+            let metadata = match entry.metadata() {
+                Ok(metadata) if metadata.is_file() && metadata.len() <= MAX_FILE_BYTES => metadata,
+                _ => continue,
+            };
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+
+            seen.insert(path.to_path_buf());
+            if self.files.get(path).is_none_or(|file| file.modified != modified) {
+                changed.push((path.to_path_buf(), modified));
+            }
+        }
+
+        let parsed: Vec<(PathBuf, IndexedFile)> = changed
+            .into_par_iter()
+            .filter_map(|(path, modified)| {
+                let structure = match CodeParser.analyze_file_structure(&path) {
+                    Ok(structure) => structure,
+                    Err(e) => {
+                        debug!("Failed to index {}: {}", path.display(), e);
+                        return None;
+                    }
+                };
+                let definitions = structure
+                    .elements
+                    .into_iter()
+                    .map(|e| IndexedDefinition { name: e.name, line: e.line, kind: e.kind, parent: e.parent })
+                    .collect();
+                Some((path, IndexedFile { modified, definitions, references: structure.references }))
+            })
+            .collect();
+
+        self.files.retain(|path, _| seen.contains(path));
+        debug!("Symbol index: {} files re-parsed, {} indexed", parsed.len(), self.files.len() + parsed.len());
+        self.files.extend(parsed);
+        Ok(())
+    }
+
+    pub fn usage(&self, name: &str) -> SymbolUsage {
+        SymbolUsage {
+            name: name.to_string(),
+            definitions: self.definitions(name),
+            references: self.references(name),
+        }
+    }
+
+    /// Where `name` is defined, sorted by path and line. impl blocks share
+    /// the type's name and are left out.
+    pub fn definitions(&self, name: &str) -> Vec<Location> {
+        let mut locations: Vec<Location> = self
+            .files
+            .iter()
+            .flat_map(|(path, file)| {
+                file.definitions
+                    .iter()
+                    .filter(|d| d.name == name && d.kind != "impl")
+                    .map(move |d| Location {
+                        path: path.clone(),
+                        line: d.line,
+                        kind: Some(d.kind.clone()),
+                        parent: d.parent.clone(),
+                    })
+            })
+            .collect();
+        sort_locations(&mut locations);
+        locations
+    }
+
+    /// Definitions of `query` under `root` with their signatures, after
+    /// refreshing the index. A query like `Type::method` or `Class.method`
+    /// only matches definitions inside that parent.
+    pub fn lookup(&mut self, root: &Path, query: &str) -> Result<Vec<SymbolDefinition>> {
+        self.refresh(root)?;
+        let (parent, name) = match query.rsplit_once("::").or_else(|| query.rsplit_once('.')) {
+            Some((parent, name)) => (Some(parent.rsplit([':', '.']).next().unwrap_or(parent)), name),
+            None => (None, query),
+        };
+        let definitions = self
+            .definitions(name)
+            .into_iter()
+            .filter(|location| parent.is_none_or(|parent| location.parent.as_deref() == Some(parent)))
+            .map(|location| {
+                let content = std::fs::read_to_string(&location.path).unwrap_or_default();
+                SymbolDefinition {
+                    path: location.path.strip_prefix(root).unwrap_or(&location.path).to_path_buf(),
+                    line: location.line,
+                    kind: location.kind.unwrap_or_else(|| "definition".to_string()),
+                    parent: location.parent,
+                    signature: signature(&content, location.line),
+                }
+            })
+            .collect();
+        Ok(definitions)
+    }
+
+    /// Where `name` is referenced, excluding the lines it is defined on
+    pub fn references(&self, name: &str) -> Vec<Location> {
+        let mut locations: Vec<Location> = self
+            .files
+            .iter()
+            .flat_map(|(path, file)| {
+                let mut lines: Vec<usize> = file
+                    .references
+                    .iter()
+                    .filter(|(n, line)| n == name && !file.definitions.iter().any(|d| d.name == name && d.line == *line))
+                    .map(|(_, line)| *line)
+                    .collect();
+                lines.dedup();
+                lines.into_iter().map(move |line| Location { path: path.clone(), line, kind: None, parent: None })
+            })
+            .collect();
+        sort_locations(&mut locations);
+        locations
+    }
+}
+
+fn sort_locations(locations: &mut [Location]) {
+    locations.sort_by(|a, b| a.path.cmp(&b.path).then(a.line.cmp(&b.line)));
+}
+
+/// Whether the command asks where something is used, defined or called,
+/// e.g. "where is `ContextManager` used" or "who calls parse_config"
+pub fn is_usage_query(command: &str) -> bool {
+    let lower = command.to_lowercase();
+    USAGE_PHRASES.iter().any(|phrase| {
+        lower.contains(phrase) && (!phrase.starts_with("where") || USAGE_VERBS.iter().any(|v| lower.contains(v)))
+    })
+}
+
+/// Definitions as `path:line (kind in parent)` with the signature indented
+/// below, for the terminal and the model alike
+pub fn format_definitions(definitions: &[SymbolDefinition]) -> String {
+    let mut text = String::new();
+    for definition in definitions {
+        let kind = match &definition.parent {
+            Some(parent) => format!("{} in {}", definition.kind, parent),
+            None => definition.kind.clone(),
+        };
+        text.push_str(&format!("{}:{} ({})\n", definition.path.display(), definition.line, kind));
+        if !definition.signature.is_empty() {
+            text.push_str(&format!("    {}\n", definition.signature));
+        }
+    }
+    text
+}
+
+/// The declaration starting at a 1-based line, up to where its body opens,
+/// joined onto one line: `fn parse(input: &str) -> Result<Ast>` or
+/// `def parse(self, text):`
+pub fn signature(content: &str, line: usize) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    // Open parentheses and brackets; the declaration continues while any are
+    let mut depth = 0i32;
+    for text in content.lines().skip(line.saturating_sub(1)).take(MAX_SIGNATURE_LINES) {
+        let mut text = text.trim();
+        for (idx, c) in text.char_indices() {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' => depth -= 1,
+                '{' if depth <= 0 => {
+                    text = text[..idx].trim_end();
+                    break;
+                }
+                _ => {}
+            }
+        }
+        parts.push(text);
+        if depth <= 0 {
+            break;
+        }
+    }
+    // Parameters listed one per line lose their line breaks
+    parts
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("( ", "(")
+        .replace(", )", ")")
+        .replace(" )", ")")
+}
+
+/// The trimmed text of a 1-based line
+pub fn line_text(content: &str, line: usize) -> &str {
+    content.lines().nth(line.saturating_sub(1)).unwrap_or("").trim()
+}
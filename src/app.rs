@@ -1,100 +1,1301 @@
-use crate::commands::executor::CommandExecutor;
-use crate::config::Config;
+use crate::analysis::commands as project_commands;
+use crate::analysis::report::{self, Report, ReportFormat};
+use crate::analysis::todos;
+use crate::analysis::xref::{self, SymbolIndex};
+use crate::commands::executor::{CommandExecutor, ExecutionReport};
+use crate::commands::middleware;
+use crate::commands::policy::ActionPolicy;
+use crate::config::{Config, LlmOverrides};
+use crate::events::{self, Event, EventBus};
+use crate::fs::edit::{FileEditor, IfExists};
+use crate::fs::retention;
+use crate::fs::storage::Storage;
+use crate::fs::undo::UndoLog;
+use crate::git::commands::GitCommands;
+use crate::git::GitSupport;
+use crate::llm::cache::ResponseCache;
 use crate::llm::client::LlmClient;
-use crate::llm::context::ContextManager;
-use crate::ui::prompt::Prompt;
-use anyhow::{Context, Result};
+use crate::llm::codeblocks;
+use crate::llm::compare::{self, BranchComparison};
+use crate::llm::completion::{self, Cursor};
+use crate::llm::context::{ContextManager, ModuleTarget};
+use crate::llm::prompt::{self, PromptContext};
+use crate::llm::scaffold;
+use crate::llm::summarize::{self, Summarizer};
+use crate::lsp::LspManager;
+use crate::memory::ProjectMemory;
+use crate::session::{SessionEntry, SessionStore};
+use crate::ui::display;
+use crate::ui::interface::{TerminalUi, UserInterface};
+use crate::ui::interrupt::{SoftInterrupt, PAUSE_KEY};
+use crate::ui::notify::Notifier;
+use crate::ui::slash::{SlashCommandRegistry, SlashOutcome};
+use anyhow::{anyhow, Context, Result};
 use colored::Colorize;
+use log::debug;
+use serde_json::json;
+use std::future::Future;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Rounds of earlier file versions and symbol lookups sent back to the
+/// model for one command
+const MAX_LOOKUPS: usize = 3;
+/// Lines above and below a TODO(ai) comment sent with it
+const TODO_CONTEXT_LINES: usize = 40;
+/// Bytes of each prompt message and of the reply shown by `/debug last`
+const DEBUG_PREVIEW_BYTES: usize = 2000;
 
 pub struct App {
     config: Config,
     llm_client: LlmClient,
     context_manager: ContextManager,
     command_executor: CommandExecutor,
-    prompt: Prompt,
+    notifier: Notifier,
+    ui: Arc<dyn UserInterface>,
+    slash_commands: SlashCommandRegistry,
+    events: EventBus,
+    /// Commands and answers of this and earlier sessions
+    sessions: SessionStore,
+    /// Writer task for `events.log_file`, awaited on shutdown
+    event_log: Option<tokio::task::JoinHandle<()>>,
+    /// Ctrl-\ presses asking a run to pause between steps
+    interrupt: SoftInterrupt,
+    /// Context sent with the last request, reused as is by `/also`
+    last_context: Mutex<Option<PromptContext>>,
+}
+
+/// What the user chose when a run paused between steps
+enum Resume {
+    AsPlanned,
+    /// Go on with this correction added to the conversation
+    Amended(String),
+    Stop,
 }
 
 impl App {
+    /// App reading from and writing to the terminal
     pub fn new(config: Config) -> Result<Self> {
-        let llm_client = LlmClient::new(&config)?;
-        let context_manager = ContextManager::new();
-        let command_executor = CommandExecutor::new();
-        let prompt = Prompt::new();
+        Self::with_ui(config, Arc::new(TerminalUi::new()))
+    }
+
+    /// App driven through the given input/output, e.g. a TUI, a server or a test harness
+    pub fn with_ui(config: Config, ui: Arc<dyn UserInterface>) -> Result<Self> {
+        let cwd = std::env::current_dir()?;
+        if let Err(e) = retention::enforce(&config, &cwd) {
+            ui.print(&format!("{} Could not apply the retention limits: {:#}", "!".yellow(), e));
+        }
+        let events = EventBus::new();
+        let event_log = match config.events.log_file.as_deref().map(|file| events::log_path(&cwd, file)) {
+            Some(Ok(path)) => Some(events::spawn_event_log(&events, &path)?),
+            Some(Err(e)) => {
+                ui.print(&format!("{} Not logging events: {:#}", "!".yellow(), e));
+                None
+            }
+            None => None,
+        };
+
+        let llm_client = LlmClient::new(&config, ui.clone())?;
+        let git = GitSupport::detect(&cwd, config.git.enable_git_features);
+        if let Some(notice) = git.notice() {
+            ui.print(&format!("{} {}", "!".yellow(), notice));
+        }
+        let lsp = LspManager::new(&config.lsp, &cwd, ui.clone()).map(Arc::new);
+        let symbols = Arc::new(Mutex::new(SymbolIndex::new()));
+        let storage = Storage::new(&config.privacy)?;
+        let context_manager = ContextManager::new(&config, lsp.clone(), git.clone(), symbols.clone(), storage, ui.clone());
+        let command_executor = CommandExecutor::new(&config, lsp, git, symbols, ui.clone(), events.clone())?;
+        let notifier = Notifier::new(&config.notifications);
+        let sessions = SessionStore::new(&cwd, storage);
+        let interrupt = SoftInterrupt::listen(ui.clone());
 
         Ok(Self {
             config,
             llm_client,
             context_manager,
             command_executor,
-            prompt,
+            notifier,
+            ui,
+            slash_commands: SlashCommandRegistry::new(),
+            events,
+            sessions,
+            event_log,
+            interrupt,
+            last_context: Mutex::new(None),
         })
     }
 
+    /// Ends the session, waiting until the event log has written every event
+    pub async fn shutdown(mut self) {
+        let event_log = self.event_log.take();
+        // Dropping the app drops every sender, which ends the log task
+        drop(self);
+        if let Some(handle) = event_log {
+            let _ = handle.await;
+        }
+    }
+
     pub async fn run(&mut self) -> Result<()> {
-        println!("{}", "Welcome to CodeAssist!".bright_green().bold());
-        println!("Type your natural language commands or 'exit' to quit");
+        self.ui.print(&"Welcome to CodeAssist!".bright_green().bold().to_string());
+        self.ui.print("Type your natural language commands, /help for commands, or 'exit' to quit");
+        self.warn_if_unreachable();
 
-        loop {
-            let input = self.prompt.get_input()?;
+        // Commands the model ran that failed since the last one that worked
+        let mut failed_attempts = 0;
+        while let Some(input) = self.ui.read_line().await? {
             let input_trimmed = input.trim();
 
             if input_trimmed.to_lowercase() == "exit" {
                 break;
             }
+            if input_trimmed.is_empty() {
+                continue;
+            }
+
+            // Handle slash commands
+            if input_trimmed.starts_with('/') {
+                let outcome = match self.slash_commands.resolve(input_trimmed) {
+                    Ok((handler, args)) => handler.run(self, &args).await,
+                    Err(e) => Err(e),
+                };
+
+                match outcome {
+                    Ok(SlashOutcome::Exit) => break,
+                    Ok(SlashOutcome::Continue) => {}
+                    Err(e) => self.ui.error(&format!("{} {}", "Error:".bright_red().bold(), e)),
+                }
+                continue;
+            }
+
+            let result = match self.execute_command(&input).await {
+                Ok(report) => match self.note_command_attempts(&report, failed_attempts).await {
+                    Ok(failed) => {
+                        failed_attempts = failed;
+                        self.offer_code_blocks(&report).await
+                    }
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                self.ui.error(&format!("{} {}", "Error:".bright_red().bold(), e));
+            }
+        }
+
+        self.ui.print("Goodbye!");
+        Ok(())
+    }
+
+    /// Checks in the background that the model can be reached, so a missing
+    /// server or API key shows up before the first command is typed. When it
+    /// can't, prints what to check and what works without a model.
+    fn warn_if_unreachable(&self) {
+        let probe = self.llm_client.probe();
+        let ui = self.ui.clone();
+        let config = self.config.clone();
+        tokio::spawn(async move {
+            let reason = match probe.await {
+                Some(reason) => reason,
+                None => return,
+            };
+            let mut banner = format!("{} No LLM reachable at {}: {}", "!".yellow(), config.llm.api_url, reason);
+            for hint in reason.hints(&config) {
+                banner.push_str(&format!("\n  - {}", hint));
+            }
+            banner.push_str(
+                "\n  Without a model, /search, /memory and /init still work here, as do the `analyze`, `search`, \
+                 `find-symbol`, `mv`, `imports fix` and `report --no-summary` subcommands",
+            );
+            ui.print(&banner);
+        });
+    }
+
+    /// Prints the slash commands followed by the settings in effect for this session
+    pub fn print_help(&self) {
+        self.ui.print(&self.slash_commands.help_text());
+
+        let llm = &self.config.llm;
+        let middleware = &self.config.middleware;
+        self.ui.print(&"Session settings".bold().to_string());
+        self.ui.print(&format!("  model        {}", llm.model));
+        if !llm.fallbacks.is_empty() {
+            let fallbacks: Vec<&str> = llm.fallbacks.iter().map(|f| f.model.as_str()).collect();
+            self.ui.print(&format!("  fallbacks    {}", fallbacks.join(", ")));
+        }
+        self.ui.print(&format!("  temperature  {}", llm.temperature));
+        self.ui.print(&format!("  max_tokens   {}", llm.max_tokens));
+        self.ui.print(&format!("  cache        {}", if self.config.cache.enabled { "on" } else { "off" }));
+        self.ui.print(&format!(
+            "  protected    {}",
+            if middleware.protected_paths.is_empty() { "none".to_string() } else { middleware.protected_paths.join(", ") }
+        ));
+        self.ui.print(&format!("  auto_stage   {}", if middleware.auto_stage { "on" } else { "off" }));
+        self.ui.print(&format!("  dry_run      {}", if self.command_executor.dry_run() { "on" } else { "off" }));
+    }
+
+    /// Turns dry-run mode on or off: the actions the model proposes are
+    /// printed (diffs, commands, git operations) instead of run
+    pub fn set_dry_run(&self, enabled: bool) {
+        self.command_executor.set_dry_run(enabled);
+    }
+
+    /// Handles `/dryrun [on|off]`, toggling without an argument
+    pub fn toggle_dry_run(&self, value: Option<&str>) -> Result<()> {
+        let enabled = match value {
+            None => !self.command_executor.dry_run(),
+            Some("on") => true,
+            Some("off") => false,
+            Some(other) => return Err(anyhow!("Unknown /dryrun value '{}' (expected on or off)", other)),
+        };
+        self.set_dry_run(enabled);
+        if enabled {
+            self.ui.print(&format!("{} Dry run on: proposed actions are shown, not applied", "✓".bright_green()));
+        } else {
+            self.ui.print(&format!("{} Dry run off: actions are applied again", "✓".bright_green()));
+        }
+        Ok(())
+    }
+
+    /// Handles `/cache [on|off|clear]`: turns the response cache on or off
+    /// for the rest of the session (toggling without an argument), or deletes
+    /// the cached responses
+    pub fn set_cache(&mut self, value: Option<&str>) -> Result<()> {
+        let enabled = match value {
+            None => !self.config.cache.enabled,
+            Some("on") => true,
+            Some("off") => false,
+            Some("clear") => {
+                let removed = ResponseCache::clear()?;
+                self.ui.print(&format!("{} Deleted {} cached responses", "✓".bright_green(), removed));
+                return Ok(());
+            }
+            Some(other) => return Err(anyhow!("Unknown /cache value '{}' (expected on, off or clear)", other)),
+        };
+        // The client keeps its session state, such as the exchange `/debug last` shows
+        self.llm_client.set_cache(enabled)?;
+        self.config.cache.enabled = enabled;
+        if enabled {
+            self.ui.print(&format!("{} Cache on: replies to repeated requests are reused", "✓".bright_green()));
+        } else {
+            self.ui.print(&format!("{} Cache off: every request goes to the model", "✓".bright_green()));
+        }
+        Ok(())
+    }
+
+    /// Handles `/debug last`: the request sent for the previous turn, the raw
+    /// reply, the reply after post-processing and the action parsed from it
+    pub fn show_last_turn(&self) {
+        let exchange = match self.llm_client.last_exchange() {
+            Some(exchange) => exchange,
+            None => {
+                self.ui.print("Nothing was sent to the model in this session yet");
+                return;
+            }
+        };
+        self.ui.print(&format!(
+            "{} {} (model {}{})",
+            "Request".bold(),
+            exchange.url,
+            exchange.model,
+            if exchange.structured { ", with response_format" } else { "" }
+        ));
+        self.ui.print(&format!("{} ({} bytes)", "System message".bold(), exchange.system.len()));
+        self.ui.print(&debug_preview(&exchange.system));
+        self.ui.print(&format!("{} ({} bytes)", "User message".bold(), exchange.user.len()));
+        self.ui.print(&debug_preview(&exchange.user));
+        let status = match exchange.status {
+            Some(status) => format!("HTTP {}", status),
+            None if exchange.response.is_empty() => "no reply".to_string(),
+            None => "cached".to_string(),
+        };
+        self.ui.print(&format!("{} ({}, {} bytes)", "Raw response".bold(), status, exchange.response.len()));
+        self.ui.print(&debug_preview(&exchange.response));
+
+        // Answers to lookups, summaries and completions aren't actions
+        let reply = match self.command_executor.last_reply() {
+            Some(reply) => reply,
+            None => return,
+        };
+        self.ui.print(&"Cleaned response".bold().to_string());
+        self.ui.print(&debug_preview(&reply.cleaned));
+        match reply.action {
+            Ok(action) => {
+                self.ui.print(&"Parsed action".bold().to_string());
+                self.ui.print(&debug_preview(&serde_json::to_string_pretty(&action).unwrap_or_default()));
+            }
+            Err(e) => self.ui.print(&format!("{} not JSON: {}", "Parsed action".bold(), e)),
+        }
+    }
+
+    /// Handles `undo` and `/undo`: restores the files of the last applied
+    /// action, or of action `id`, as they were before it
+    pub fn undo(&self, id: Option<u64>, force: bool) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let log = UndoLog::new(&cwd, Storage::new(&self.config.privacy)?);
+        let entry = log.undo(&cwd, id, force)?;
+        for file in &entry.files {
+            let verb = if file.before.is_some() { "Restored" } else { "Deleted" };
+            self.ui.print(&format!("{} {} {}", "✓".bright_green(), verb, file.path.display()));
+        }
+        self.ui.print(&format!("Undid action {} ({})", entry.id, entry.action));
+        Ok(())
+    }
+
+    /// Handles `undo --list` and `/undo list`: the actions that can be undone,
+    /// newest first
+    pub fn list_undo(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let entries = UndoLog::new(&cwd, Storage::new(&self.config.privacy)?).entries()?;
+        if entries.is_empty() {
+            self.ui.print("No actions to undo");
+            return Ok(());
+        }
+        let rows: Vec<Vec<String>> = entries
+            .iter()
+            .rev()
+            .map(|entry| {
+                let time = chrono::DateTime::parse_from_rfc3339(&entry.time)
+                    .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|_| entry.time.clone());
+                let files: Vec<String> = entry.files.iter().map(|file| file.path.display().to_string()).collect();
+                vec![entry.id.to_string(), time, entry.action.clone(), files.join(", ")]
+            })
+            .collect();
+        self.ui.print(display::format_table(&["ID", "TIME", "ACTION", "FILES"], &rows).trim_end());
+        Ok(())
+    }
+
+    /// Creates a CAULK.md template in the current directory
+    pub fn init_caulk_file(&self) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        ProjectMemory::new().init_caulk_file(&cwd, self.ui.as_ref())
+    }
+
+    /// Handles `/memory [refresh]`: the CAULK.md files in the prompt, and
+    /// those in subdirectories that only apply when working there
+    pub fn show_memory_files(&self, refresh: bool) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let memory = self.context_manager.project_memory.load()?;
+        let discovered = if refresh {
+            ProjectMemory::discover_subdirectory_files(&cwd, true)
+        } else {
+            memory.discovered_files().to_vec()
+        };
+
+        if memory.get_loaded_files().is_empty() {
+            self.ui.print("No CAULK.md is loaded (/init creates one here)");
+        } else {
+            self.ui.print(&"Loaded".bold().to_string());
+            for path in memory.get_loaded_files() {
+                self.ui.print(&format!("  {}", path.display()));
+            }
+        }
+        if !discovered.is_empty() {
+            self.ui.print(&"In subdirectories (used when code-assist runs there)".bold().to_string());
+            for path in &discovered {
+                self.ui.print(&format!("  {}", path.strip_prefix(&cwd).unwrap_or(path).display()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles `/set [model|temperature|max_tokens <value>]` for the rest of the session.
+    /// Without arguments the current values are printed.
+    pub fn set_llm_option(&mut self, key: Option<&str>, value: Option<&str>) -> Result<()> {
+        let (key, value) = match (key, value) {
+            (None, _) => {
+                self.ui.print(&format!("model = {}", self.config.llm.model));
+                self.ui.print(&format!("temperature = {}", self.config.llm.temperature));
+                self.ui.print(&format!("max_tokens = {}", self.config.llm.max_tokens));
+                return Ok(());
+            }
+            (Some(key), Some(value)) => (key, value),
+            (Some(key), None) => return Err(anyhow!("Missing value for '{}'", key)),
+        };
+
+        let mut overrides = LlmOverrides::default();
+        match key {
+            "model" => overrides.model = Some(value.to_string()),
+            "temperature" => {
+                overrides.temperature = Some(value.parse().with_context(|| format!("Invalid temperature: {}", value))?)
+            }
+            "max_tokens" | "max-tokens" => {
+                overrides.max_tokens = Some(value.parse().with_context(|| format!("Invalid max_tokens: {}", value))?)
+            }
+            _ => return Err(anyhow!("Unknown setting '{}' (expected model, temperature or max_tokens)", key)),
+        }
+
+        self.config.llm.apply_overrides(&overrides);
+        self.llm_client = LlmClient::new(&self.config, self.ui.clone())?;
+        self.ui.print(&format!("{} {} set to {}", "✓".bright_green(), key, value));
+        Ok(())
+    }
+
+    /// Warns early when the configured model is unknown to the provider
+    pub async fn check_model(&self) {
+        self.llm_client.warn_if_unknown_model().await;
+    }
+
+    pub async fn execute_command(&self, command: &str) -> Result<ExecutionReport> {
+        // A pause asked for at the prompt is not meant for this run
+        self.interrupt.take();
+        self.execute_with_policy(command, &ActionPolicy::Any, String::new(), false).await
+    }
+
+    /// Answers a question without ever editing files or running commands.
+    /// With `summarize` the whole codebase is summarized first (map-reduce).
+    pub async fn ask(&self, question: &str, summarize: bool) -> Result<()> {
+        self.execute_with_policy(question, &ActionPolicy::AnswerOnly, String::new(), summarize).await?;
+        Ok(())
+    }
+
+    /// Applies an instruction as an edit to the given files only, then prints the diff
+    pub async fn edit(&self, instruction: &str, files: &[PathBuf]) -> Result<()> {
+        // The model needs the full content of the files it is allowed to edit
+        let mut extra_context = String::new();
+        for file in files {
+            let content = std::fs::read_to_string(file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            extra_context.push_str(&format!("\nFile to edit: {}\n", file.display()));
+            for (idx, line) in content.lines().enumerate() {
+                extra_context.push_str(&format!("{:>5} | {}\n", idx + 1, line));
+            }
+        }
+
+        let policy = ActionPolicy::EditOnly(files.to_vec());
+        let report = self.execute_with_policy(instruction, &policy, extra_context, false).await?;
+
+        if report.file_changes.is_empty() {
+            self.ui.print(&"No changes were made".yellow().to_string());
+        }
+        for change in &report.file_changes {
+            self.ui.print(&format!(
+                "\n{}",
+                display::format_diff(
+                    &change.path.display().to_string(),
+                    change.before.as_deref().unwrap_or(""),
+                    change.after.as_deref().unwrap_or("")
+                )
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Creates a new project in `dir` from a description: the model proposes
+    /// the files, their tree is shown and, once confirmed (or with `yes`),
+    /// they are written and committed to a new git repository
+    pub async fn new_project(&self, description: &str, dir: &Path, yes: bool) -> Result<()> {
+        self.ui.print(&"Planning the project...".bright_blue().to_string());
+        let scaffold = match cancellable(scaffold::propose(&self.llm_client, description)).await {
+            Some(scaffold) => scaffold?,
+            None => {
+                self.ui.print(&format!("\n{}", "Request cancelled".yellow()));
+                return Ok(());
+            }
+        };
+
+        let conflicts = scaffold.conflicts(dir);
+        if !conflicts.is_empty() {
+            let paths: Vec<String> = conflicts.iter().map(|path| path.display().to_string()).collect();
+            return Err(anyhow!("{} already has {}; choose an empty directory with --dir", dir.display(), paths.join(", ")));
+        }
+        if !scaffold.language.is_empty() {
+            self.ui.print(&format!("\n{} {}", "Language:".bold(), scaffold.language));
+        }
+        if !scaffold.summary.is_empty() {
+            self.ui.print(&display::wrap_text(&scaffold.summary));
+        }
+        self.ui.print(&format!("\n{}/\n{}", dir.display(), scaffold.tree().trim_end()));
+
+        if !yes {
+            self.ui.print(&format!("Create these {} files? [y/N]", scaffold.files.len()));
+            let input = match self.ui.read_line().await? {
+                Some(input) => input.trim().to_lowercase(),
+                None => String::new(),
+            };
+            if input != "y" && input != "yes" {
+                self.ui.print("Nothing was created");
+                return Ok(());
+            }
+        }
+
+        for file in &scaffold.files {
+            FileEditor::create_file(&dir.join(&file.path), &file.content, IfExists::Fail)?;
+        }
+        self.ui.print(&format!("{} Created {} files in {}", "✓".bright_green(), scaffold.files.len(), dir.display()));
+
+        if !self.config.git.enable_git_features {
+            return Ok(());
+        }
+        if git2::Repository::discover(dir).is_ok() {
+            self.ui.print(&format!("{} {} is already in a git repository; the files were not committed", "!".yellow(), dir.display()));
+            return Ok(());
+        }
+        GitCommands::init(dir)?;
+        let committed = GitCommands::add(dir, &["."]).and_then(|_| GitCommands::commit(dir, "Initial commit"));
+        match committed {
+            Ok(_) => self.ui.print(&format!("{} Initialized a git repository with an initial commit", "✓".bright_green())),
+            Err(e) => self.ui.print(&format!("{} Initialized a git repository, but the initial commit failed: {:#}", "!".yellow(), e)),
+        }
+        Ok(())
+    }
+
+    /// Implements the `TODO(ai)` comments in `path` (a file or directory) one
+    /// at a time: each goes to the model with the code around it as an edit
+    /// of its file, and the TODO is removed once the edit is made. The diff is
+    /// shown and, unless `yes`, the edit is kept only when confirmed.
+    pub async fn implement_todos(&self, path: &Path, yes: bool) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let tasks = todos::find_tasks(&cwd.join(path));
+        if tasks.is_empty() {
+            self.ui.print(&format!("No TODO(ai) comments found in {}", path.display()));
+            return Ok(());
+        }
+        self.ui.print(&format!(
+            "Found {} TODO(ai) comment{} {}",
+            tasks.len(),
+            if tasks.len() == 1 { "" } else { "s" },
+            format!("({} pauses between them)", PAUSE_KEY).dimmed()
+        ));
+
+        self.interrupt.take();
+        let mut implemented = 0;
+        // Corrections given at pauses, sent with every later TODO
+        let mut corrections = String::new();
+        for (idx, task) in tasks.iter().enumerate() {
+            if idx > 0 {
+                match self.pause_point().await? {
+                    Resume::AsPlanned => {}
+                    Resume::Amended(correction) => corrections.push_str(&correction_note(&correction)),
+                    Resume::Stop => break,
+                }
+            }
+            let relative = task.path.strip_prefix(&cwd).unwrap_or(&task.path).to_path_buf();
+            // Earlier edits may have moved the comment or removed it
+            let before = std::fs::read_to_string(&task.path)
+                .with_context(|| format!("Failed to read {}", relative.display()))?;
+            let line = match task.find_in(&before) {
+                Some(line) => line,
+                None => {
+                    self.ui.print(&format!("{} {}:{} is gone, skipped", "!".yellow(), relative.display(), task.line));
+                    continue;
+                }
+            };
+            self.ui.print(&format!("\n{} {}:{} {}", "▶".bright_blue(), relative.display(), line, task.instruction));
+
+            let lines: Vec<&str> = before.lines().collect();
+            let start = line.saturating_sub(TODO_CONTEXT_LINES + 1);
+            let end = (line + TODO_CONTEXT_LINES).min(lines.len());
+            let mut extra_context = format!("\nFile to edit: {} (lines {}-{} of {})\n", relative.display(), start + 1, end, lines.len());
+            for (idx, text) in lines[start..end].iter().enumerate() {
+                extra_context.push_str(&format!("{:>5} | {}\n", start + idx + 1, text));
+            }
+            extra_context.push_str(&corrections);
+            let instruction = format!(
+                "Implement the TODO comment at {}:{}: {}. Change only what it asks for.",
+                relative.display(),
+                line,
+                task.instruction
+            );
+
+            let policy = ActionPolicy::EditOnly(vec![relative.clone()]);
+            // The diff is shown below and kept only when confirmed (or with `yes`)
+            self.command_executor.approve_edits_to(vec![task.path.clone()]);
+            let report = self.execute_with_policy(&instruction, &policy, extra_context, false).await;
+            self.command_executor.approve_edits_to(Vec::new());
+            let report = report?;
+            if report.file_changes.is_empty() {
+                self.ui.print(&format!("{} No changes were made; the TODO is kept", "!".yellow()));
+                continue;
+            }
+
+            let edited = std::fs::read_to_string(&task.path)?;
+            let after = task.remove_from(&edited).unwrap_or(edited);
+            FileEditor::write_file(&task.path, &after)?;
+            self.ui.print(&format!("\n{}", display::format_diff(&relative.display().to_string(), &before, &after)));
+
+            if !yes {
+                self.ui.print("Keep this change? [y/N]");
+                let input = match self.ui.read_line().await? {
+                    Some(input) => input.trim().to_lowercase(),
+                    None => String::new(),
+                };
+                if input != "y" && input != "yes" {
+                    FileEditor::write_file(&task.path, &before)?;
+                    self.ui.print(&format!("{} Reverted {}", "✓".bright_green(), relative.display()));
+                    continue;
+                }
+            }
+            implemented += 1;
+            self.ui.print(&format!("{} Implemented the TODO at {}:{}", "✓".bright_green(), relative.display(), line));
+        }
+
+        self.ui.print(&format!("\n{} of {} TODO(ai) comment{} implemented", implemented, tasks.len(), if tasks.len() == 1 { "" } else { "s" }));
+        Ok(())
+    }
+
+    /// Code to insert at the cursor, completed with the context of the
+    /// surrounding lines. With `apply` it is also inserted into the file.
+    /// None when the request was cancelled.
+    pub async fn complete(&self, cursor: &Cursor, apply: bool) -> Result<Option<String>> {
+        let content = std::fs::read_to_string(&cursor.path)
+            .with_context(|| format!("Failed to read {}", cursor.path.display()))?;
+        let offset = cursor.offset(&content)?;
+        if let Some(line) = middleware::conflict_marker_line(&content).filter(|_| apply) {
+            return Err(anyhow!("{} has an unresolved merge conflict at line {}; resolve it first", cursor.path.display(), line));
+        }
+
+        let prompt_context = self.gather_context(&completion::query_text(&content, offset), false).await?;
+        let request = completion::complete(&self.llm_client, &prompt_context, &cursor.path, &content, offset);
+        let insertion = match cancellable(request).await {
+            Some(insertion) => insertion?,
+            None => return Ok(None),
+        };
+
+        if apply {
+            if insertion.is_empty() {
+                self.ui.print(&"No completion was suggested".yellow().to_string());
+            } else {
+                FileEditor::write_file(&cursor.path, &completion::insert(&content, offset, &insertion))?;
+                self.ui.print(&format!("{} Inserted completion at {}:{}", "✓".bright_green(), cursor.path.display(), cursor.line));
+            }
+        }
+        Ok(Some(insertion))
+    }
+
+    /// Summarizes each file changed on `head` since it forked from `base`,
+    /// then lets the user pick files to explain in detail and ask for a
+    /// reviewer checklist. With `checklist` the checklist is printed right away.
+    pub async fn compare(&self, base: &str, head: &str, checklist: bool) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let comparison = BranchComparison::new(&self.llm_client, &self.config.summarize, &cwd, base, head)?;
+        if comparison.files.is_empty() {
+            self.ui.print(&format!("No changes on {} since it forked from {}", head, base));
+            return Ok(());
+        }
+
+        self.ui.print(&format!(
+            "Comparing {}...{}: {} commit(s), {} file(s) changed",
+            base,
+            head,
+            comparison.commits.len(),
+            comparison.files.len()
+        ).bright_blue().to_string());
+        let summaries = match cancellable(comparison.summarize_files()).await {
+            Some(summaries) => summaries,
+            None => {
+                self.ui.print(&format!("\n{}", "Request cancelled".yellow()));
+                return Ok(());
+            }
+        };
+        for (idx, (file, summary)) in comparison.files.iter().zip(&summaries).enumerate() {
+            self.ui.print(&format!("{:>3}. {}", idx + 1, compare::describe(file).bold()));
+            self.ui.print(&format!("     {}", summary));
+        }
 
-            // Handle special commands
-            if input_trimmed == "/init" {
-                let cwd = std::env::current_dir()?;
-                let memory = crate::memory::ProjectMemory::new();
-                memory.init_caulk_file(&cwd)?;
+        if checklist {
+            self.print_compare_reply(cancellable(comparison.checklist(&summaries)).await);
+        }
+
+        loop {
+            self.ui.print(&format!(
+                "\nFile number to explain (1-{}), 'c' for a reviewer checklist, or Enter to finish",
+                comparison.files.len()
+            ));
+            let input = match self.ui.read_line().await? {
+                Some(input) => input.trim().to_string(),
+                None => break,
+            };
+            if input.is_empty() || input == "q" || input == "exit" {
+                break;
+            }
+            if input == "c" {
+                self.print_compare_reply(cancellable(comparison.checklist(&summaries)).await);
                 continue;
             }
+            match input.parse::<usize>().ok().and_then(|n| comparison.files.get(n.wrapping_sub(1))) {
+                Some(file) => {
+                    self.ui.print(&format!("\n{}", compare::describe(file).bold()));
+                    self.print_compare_reply(cancellable(comparison.explain_file(file)).await);
+                }
+                None => self.ui.error(&format!("{} '{}' is not a file number", "Error:".bright_red().bold(), input)),
+            }
+        }
 
-            if let Err(e) = self.execute_command(&input).await {
-                eprintln!("{} {}", "Error:".bright_red().bold(), e);
+        Ok(())
+    }
+
+    /// Prints an explanation or checklist, or why there is none
+    fn print_compare_reply(&self, reply: Option<Result<String>>) {
+        match reply {
+            Some(Ok(text)) => self.ui.print(&format!("\n{}", text.trim())),
+            Some(Err(e)) => self.ui.error(&format!("{} {:#}", "Error:".bright_red().bold(), e)),
+            None => self.ui.print(&format!("\n{}", "Request cancelled".yellow())),
+        }
+    }
+
+    /// Writes a report of the project in the current directory to `output`
+    /// (default `code-assist-report.<format>`). With `summary` the model
+    /// writes an executive summary from the gathered facts.
+    pub async fn report(&self, format: ReportFormat, output: Option<PathBuf>, summary: bool) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        self.ui.print(&"Analyzing project...".bright_blue().to_string());
+        let mut report = Report::gather(&cwd)?;
+
+        if summary {
+            let facts = report.summary_facts()?;
+            match cancellable(self.llm_client.complete(report::TASK, report::SUMMARY_SYSTEM_PROMPT, &facts)).await {
+                Some(Ok(text)) => report.summary = Some(text.trim().to_string()),
+                Some(Err(e)) => self.ui.error(&format!("{} No executive summary: {:#}", "!".yellow(), e)),
+                None => {
+                    self.ui.print(&format!("\n{}", "Request cancelled".yellow()));
+                    return Ok(());
+                }
             }
         }
 
-        println!("Goodbye!");
+        let output = output.unwrap_or_else(|| PathBuf::from(format!("code-assist-report.{}", format.extension())));
+        std::fs::write(&output, report.render(format)?)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+        self.ui.print(&format!("{} Wrote report to {}", "✓".bright_green(), output.display()));
         Ok(())
     }
 
-    pub async fn execute_command(&self, command: &str) -> Result<()> {
-        println!("{}", "Analyzing request...".bright_blue());
+    /// Prints what the analyzer detects in the current directory: project
+    /// type, files per extension, modules, project commands and framework
+    /// details
+    pub fn analyze(&self, format: AnalysisFormat) -> Result<()> {
+        let cwd = std::env::current_dir()?;
+        let forced_type = self.context_manager.project_type_override();
+        let structure = self.context_manager.project_structure_as(&cwd, forced_type.map(|(project_type, _)| project_type))?;
+        let mut project_type = structure.project_type.as_ref().map(|t| t.description()).unwrap_or("Unknown").to_string();
+        if let Some(plugin) = &structure.plugin {
+            project_type = format!("{} ({})", plugin.name, project_type);
+        }
+        if let Some((_, source)) = forced_type {
+            project_type.push_str(&format!(" (set in {})", source));
+        }
+        let mut file_counts: Vec<(&String, usize)> = structure
+            .files_by_type
+            .iter()
+            .map(|(ext, files)| (ext, files.len()))
+            .collect();
+        file_counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        let modules: Vec<(&String, &Path)> = structure
+            .modules
+            .iter()
+            .map(|(name, path)| (name, path.strip_prefix(&cwd).unwrap_or(path)))
+            .collect();
+        let details = self.context_manager.project_details(&structure, &cwd)?;
+        let commands = project_commands::detect_commands(&cwd);
+
+        let output = match format {
+            AnalysisFormat::Json => serde_json::to_string_pretty(&json!({
+                "root": cwd,
+                "project_type": structure.project_type.as_ref().map(|t| t.description()),
+                "kind": structure.project_type,
+                "directories": structure.directories.len(),
+                "files_by_type": file_counts.iter().map(|(ext, count)| (ext.to_string(), *count)).collect::<BTreeMap<_, _>>(),
+                "languages": structure.languages,
+                "modules": modules.iter().map(|(name, path)| json!({ "name": name, "path": path })).collect::<Vec<_>>(),
+                "sub_projects": structure.sub_projects,
+                "commands": commands,
+                "details": structure.specific_info,
+                "plugin": structure.plugin,
+            }))?,
+            AnalysisFormat::Markdown => {
+                let mut text = format!("# Project analysis: {}\n\n", cwd.display());
+                text.push_str(&format!("- **Project type:** {}\n", project_type));
+                text.push_str(&format!("- **Directories:** {}\n\n", structure.directories.len()));
+                text.push_str("## Files by type\n\n| Extension | Files |\n| --- | ---: |\n");
+                for (ext, count) in &file_counts {
+                    text.push_str(&format!("| {} | {} |\n", ext, count));
+                }
+                if !structure.languages.is_empty() {
+                    text.push_str("\n## Languages\n\n| Language | Files | Code | Comments | Blanks |\n| --- | ---: | ---: | ---: | ---: |\n");
+                    for language in &structure.languages {
+                        text.push_str(&format!(
+                            "| {} | {} | {} | {} | {} |\n",
+                            language.name, language.files, language.code, language.comments, language.blanks
+                        ));
+                    }
+                }
+                if !modules.is_empty() {
+                    text.push_str(&format!("\n## Modules ({})\n\n", modules.len()));
+                    for (name, path) in &modules {
+                        text.push_str(&format!("- `{}` in `{}`\n", name, path.display()));
+                    }
+                }
+                if !structure.sub_projects.is_empty() {
+                    text.push_str(&format!("\n## Sub-projects ({})\n\n", structure.sub_projects.len()));
+                    for sub_project in &structure.sub_projects {
+                        text.push_str(&format!("- `{}/`: {}\n", sub_project.path.display(), sub_project.project_type.description()));
+                    }
+                }
+                if !commands.is_empty() {
+                    text.push_str(&format!("\n## Commands ({})\n\n", commands.len()));
+                    for command in &commands {
+                        text.push_str(&format!("- `{}`: {}\n", command.command, command.description));
+                    }
+                }
+                if !details.trim().is_empty() {
+                    text.push_str(&format!("\n## Details\n\n```text\n{}\n```\n", details.trim()));
+                }
+                text
+            }
+            AnalysisFormat::Text => {
+                let mut text = format!("{} {}\n", "Project type:".bold(), project_type);
+                text.push_str(&format!("{} {}\n\n", "Directories:".bold(), structure.directories.len()));
+                let rows: Vec<Vec<String>> = file_counts
+                    .iter()
+                    .map(|(ext, count)| vec![ext.to_string(), count.to_string()])
+                    .collect();
+                text.push_str(&display::format_table(&["EXTENSION", "FILES"], &rows));
+                if !structure.languages.is_empty() {
+                    let rows: Vec<Vec<String>> = structure
+                        .languages
+                        .iter()
+                        .map(|language| {
+                            vec![
+                                language.name.clone(),
+                                language.files.to_string(),
+                                language.code.to_string(),
+                                language.comments.to_string(),
+                                language.blanks.to_string(),
+                            ]
+                        })
+                        .collect();
+                    text.push('\n');
+                    text.push_str(&display::format_table(&["LANGUAGE", "FILES", "CODE", "COMMENTS", "BLANKS"], &rows));
+                }
+                if !modules.is_empty() {
+                    text.push_str(&format!("\n{}\n", format!("Modules ({}):", modules.len()).bold()));
+                    for (name, path) in &modules {
+                        text.push_str(&format!("  {} ({})\n", name, path.display()));
+                    }
+                }
+                if !structure.sub_projects.is_empty() {
+                    text.push_str(&format!("\n{}\n", format!("Sub-projects ({}):", structure.sub_projects.len()).bold()));
+                    for sub_project in &structure.sub_projects {
+                        text.push_str(&format!("  {}/ ({})\n", sub_project.path.display(), sub_project.project_type.description()));
+                    }
+                }
+                if !commands.is_empty() {
+                    text.push_str(&format!("\n{}\n", format!("Commands ({}):", commands.len()).bold()));
+                    for command in &commands {
+                        text.push_str(&format!("  {}: {}\n", command.command, command.description));
+                    }
+                }
+                if !details.trim().is_empty() {
+                    text.push_str(&format!("\n{}\n{}\n", "Details:".bold(), details.trim()));
+                }
+                text
+            }
+        };
+        self.ui.print(output.trim_end());
+        Ok(())
+    }
+
+    async fn execute_with_policy(&self, command: &str, policy: &ActionPolicy, extra_context: String, summarize: bool) -> Result<ExecutionReport> {
+        // Questions about where a symbol is used are answered from the index
+        if policy.allowed_actions().contains(&"answer_question") {
+            if let Some(answer) = self.context_manager.answer_usage_query(command)? {
+                self.ui.print(&answer);
+                let report = ExecutionReport {
+                    action_type: Some("answer_question".to_string()),
+                    executed: true,
+                    answer: Some(answer),
+                    ..Default::default()
+                };
+                self.record_session(command, &report);
+                return Ok(report);
+            }
+        }
+
+        self.ui.print(&"Analyzing request...".bright_blue().to_string());
         
         // Gather context from the codebase
-        let context = self.gather_context(command)?;
-        
-        // Send to LLM for interpretation
-        let llm_response = self.llm_client.process_command(command, &context).await
-            .context("Failed to process command with LLM")?;
-        
-        // Execute the interpreted command
-        self.command_executor.execute(&llm_response).await?;
+        let mut prompt_context = self.gather_context(command, true).await?;
+        prompt_context.context.push_str(&extra_context);
+
+        // Overview questions get per-file summaries of the whole codebase
+        if summarize || (self.config.summarize.enabled && summarize::wants_overview(command)) {
+            let summarizer = Summarizer::new(&self.llm_client, &self.config.summarize, &self.context_manager.summaries, self.ui.as_ref());
+            match cancellable(summarizer.summarize_codebase(&std::env::current_dir()?, command)).await {
+                Some(Ok(summary)) => prompt_context.context.push_str(&format!("\n{}", summary)),
+                Some(Err(e)) => self.ui.print(&format!("{} Codebase summary skipped: {:#}", "!".yellow(), e)),
+                None => {
+                    self.ui.print(&format!("\n{}", "Request cancelled".yellow()));
+                    return Ok(ExecutionReport::default());
+                }
+            }
+        }
         
+        self.events.emit(Event::ContextGathered {
+            command: command.to_string(),
+            context_bytes: prompt_context.context.len(),
+            project_type: prompt_context.project_type.clone(),
+        });
+        if let Ok(mut last) = self.last_context.lock() {
+            *last = Some(prompt_context.clone());
+        }
+
+        self.run_with_context(command, prompt_context, policy).await
+    }
+
+    /// Handles `/also <question>`: asks a follow-up with the context of the
+    /// previous request, without scanning or searching the project again
+    pub async fn ask_also(&self, question: &str) -> Result<()> {
+        let mut prompt_context = match self.last_context.lock().ok().and_then(|last| last.clone()) {
+            Some(context) => context,
+            None => {
+                self.ui.print(&format!("{} No earlier request to reuse the context of; ask without /also first", "!".yellow()));
+                return Ok(());
+            }
+        };
+        self.ui.print(&format!(
+            "{} {}",
+            "Analyzing request...".bright_blue(),
+            format!("(reusing the previous context, {} bytes)", prompt_context.context.len()).dimmed()
+        ));
+        prompt_context.command = question.to_string();
+        self.interrupt.take();
+        let report = self.run_with_context(question, prompt_context, &ActionPolicy::Any).await?;
+        self.offer_code_blocks(&report).await
+    }
+
+    /// Sends the request with `prompt_context` to the model and runs its
+    /// reply, going back to it with the files and symbols it looks up
+    async fn run_with_context(&self, command: &str, mut prompt_context: PromptContext, policy: &ActionPolicy) -> Result<ExecutionReport> {
+        let mut lookups = 0;
+        loop {
+            // Send to LLM for interpretation (Ctrl-C aborts the request)
+            self.events.emit(Event::LlmRequested {
+                model: self.config.llm.targets(policy.task())[0].model.clone(),
+                task: policy.task().map(String::from),
+            });
+            let started = std::time::Instant::now();
+            let llm_response = match cancellable(self.llm_client.process_command(&prompt_context, policy)).await {
+                Some(response) => response.context("Failed to process command with LLM")?,
+                None => {
+                    self.ui.print(&format!("\n{}", "Request cancelled".yellow()));
+                    return Ok(ExecutionReport::default());
+                }
+            };
+            self.notifier.finished(started, "LLM response received");
+            if let Some(reasoning) = &llm_response.reasoning {
+                debug!("Model reasoning:\n{}", reasoning);
+            }
+            
+            // Execute the interpreted command
+            let mut report = self.command_executor.execute(&llm_response.content, policy).await?;
+            report.usage = llm_response.usage;
+            if self.command_executor.last_reply().is_some_and(|reply| reply.action.is_ok()) {
+                self.llm_client.cache_reply();
+            }
+
+            // Earlier versions of files and symbol definitions the model asked
+            // for go back to it
+            if !report.revisions.is_empty() || !report.symbol_lookups.is_empty() {
+                if lookups < MAX_LOOKUPS {
+                    lookups += 1;
+                    for revision in &report.revisions {
+                        prompt_context.context.push_str(&format!(
+                            "\nFile {} at revision {} (read at your request):\n```\n{}\n```\n",
+                            revision.path.display(),
+                            revision.revision,
+                            revision.content.trim_end()
+                        ));
+                    }
+                    for lookup in &report.symbol_lookups {
+                        let definitions = if lookup.definitions.is_empty() {
+                            "No definition found\n".to_string()
+                        } else {
+                            xref::format_definitions(&lookup.definitions)
+                        };
+                        prompt_context.context.push_str(&format!(
+                            "\nDefinitions of `{}` (looked up at your request):\n{}",
+                            lookup.name, definitions
+                        ));
+                    }
+                    if lookups == 1 {
+                        self.ui.print(&format!("({} pauses before the next step to add a correction)", PAUSE_KEY).dimmed().to_string());
+                    }
+                    match self.pause_point().await? {
+                        Resume::AsPlanned => {}
+                        Resume::Amended(correction) => prompt_context.context.push_str(&correction_note(&correction)),
+                        Resume::Stop => {
+                            self.record_session(command, &report);
+                            return Ok(report);
+                        }
+                    }
+                    continue;
+                }
+                self.ui.print(&format!("{} Stopped after {} rounds of file and symbol lookups", "!".yellow(), MAX_LOOKUPS));
+            }
+            self.record_session(command, &report);
+            
+            return Ok(report);
+        }
+    }
+
+    /// Between two steps of a run: when a pause was asked for, asks for a
+    /// correction to add to the conversation before the run goes on
+    async fn pause_point(&self) -> Result<Resume> {
+        if !self.interrupt.take() {
+            return Ok(Resume::AsPlanned);
+        }
+        self.ui.print(&format!(
+            "{} Paused. Type a correction for the rest of this run, Enter to go on as planned, or 'stop' to end it",
+            "▶".bright_blue()
+        ));
+        let input = match self.ui.read_line().await? {
+            Some(input) => input.trim().to_string(),
+            None => return Ok(Resume::Stop),
+        };
+        Ok(match input.to_lowercase().as_str() {
+            "" => Resume::AsPlanned,
+            "stop" => {
+                self.ui.print(&format!("{} Stopped at your request", "!".yellow()));
+                Resume::Stop
+            }
+            _ => Resume::Amended(input),
+        })
+    }
+
+    fn record_session(&self, command: &str, report: &ExecutionReport) {
+        if self.config.sessions.record {
+            self.sessions.record(SessionEntry::new(command, report));
+        }
+    }
+
+    /// Counts the commands of `report` that failed and, when one worked after
+    /// earlier attempts failed, offers to record it in the project's CAULK.md
+    /// so later sessions get it right the first time. Returns the failed
+    /// attempts since the last command that worked.
+    async fn note_command_attempts(&self, report: &ExecutionReport, mut failed: usize) -> Result<usize> {
+        for run in &report.commands {
+            if run.exit_code != Some(0) {
+                failed += 1;
+                continue;
+            }
+            if failed > 0 {
+                self.offer_to_record_command(&run.command, failed).await?;
+            }
+            failed = 0;
+        }
+        Ok(failed)
+    }
+
+    async fn offer_to_record_command(&self, command: &str, failed: usize) -> Result<()> {
+        let memory = self.context_manager.project_memory.load()?;
+        if memory.knows_command(command) {
+            return Ok(());
+        }
+        self.ui.print(&format!(
+            "`{}` worked after {} failed attempt{}. Record it under Frequently Used Commands in CAULK.md? [y/N]",
+            command,
+            failed,
+            if failed == 1 { "" } else { "s" }
+        ));
+        let input = match self.ui.read_line().await? {
+            Some(input) => input.trim().to_lowercase(),
+            None => return Ok(()),
+        };
+        if input == "y" || input == "yes" {
+            let path = memory.record_command(&std::env::current_dir()?, command)?;
+            self.ui.print(&format!("{} Recorded in {}", "✓".bright_green(), path.display()));
+        }
+        Ok(())
+    }
+
+    /// Offers to apply each fenced code block of an answer that shows an
+    /// existing file as an edit to that file, showing the diff first. Accepted
+    /// edits go through the policy and middleware like the model's own.
+    async fn offer_code_blocks(&self, report: &ExecutionReport) -> Result<()> {
+        let answer = match &report.answer {
+            Some(answer) => answer,
+            None => return Ok(()),
+        };
+        let cwd = std::env::current_dir()?;
+        for block in codeblocks::file_blocks(answer, &cwd) {
+            let current = match std::fs::read_to_string(cwd.join(&block.path)) {
+                Ok(current) => current,
+                Err(_) => continue,
+            };
+            let updated = match block.apply_to(&current) {
+                Some(updated) => updated,
+                None => continue,
+            };
+
+            self.ui.print(&format!("\n{}", display::format_diff(&block.path.display().to_string(), &current, &updated)));
+            self.ui.print(&format!("Apply as edit to {}? [y/N]", block.path.display()));
+            let input = match self.ui.read_line().await? {
+                Some(input) => input.trim().to_lowercase(),
+                None => break,
+            };
+            if input != "y" && input != "yes" {
+                continue;
+            }
+            let action = json!({
+                "action": "edit_file",
+                "details": { "file_path": block.path, "content": updated }
+            });
+            let policy = ActionPolicy::EditOnly(vec![block.path.clone()]);
+            let edit = self.command_executor.execute_action(action, &policy).await?;
+            if edit.executed {
+                self.record_session(&format!("apply code block to {}", block.path.display()), &edit);
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles `/search <text>`: lists matching commands of this and earlier
+    /// sessions, then shows a chosen answer in full or runs its command again
+    pub async fn search_sessions(&self, query: &str) -> Result<()> {
+        let matches = self.sessions.search(query)?;
+        if matches.is_empty() {
+            self.ui.print(&format!("No past commands or answers match '{}'", query));
+            return Ok(());
+        }
+
+        for (idx, entry) in matches.iter().enumerate() {
+            let outcome = entry.outcome();
+            let first_line = outcome.lines().find(|line| !line.trim().is_empty()).unwrap_or("");
+            self.ui.print(&format!("{:>3}. {} {}", idx + 1, entry.short_time().dimmed(), entry.command.bold()));
+            self.ui.print(&format!("     {}", display::truncate_to_width(first_line.trim(), 100)));
+        }
+
+        loop {
+            self.ui.print(&format!(
+                "\nNumber to show (1-{}), 'r <number>' to run that command again, or Enter to finish",
+                matches.len()
+            ));
+            let input = match self.ui.read_line().await? {
+                Some(input) => input.trim().to_string(),
+                None => break,
+            };
+            if input.is_empty() || input == "q" || input == "exit" {
+                break;
+            }
+
+            let (rerun, number) = match input.strip_prefix('r') {
+                Some(number) => (true, number.trim()),
+                None => (false, input.as_str()),
+            };
+            let entry = match number.parse::<usize>().ok().and_then(|n| matches.get(n.wrapping_sub(1))) {
+                Some(entry) => entry,
+                None => {
+                    self.ui.error(&format!("{} '{}' is not a result number", "Error:".bright_red().bold(), input));
+                    continue;
+                }
+            };
+
+            if rerun {
+                self.ui.print(&format!("{} {}", ">>".bright_green().bold(), entry.command));
+                self.execute_command(&entry.command).await?;
+                break;
+            }
+            self.ui.print(&format!("\n{} {}", entry.short_time().dimmed(), entry.command.bold()));
+            self.ui.print(&display::wrap_text(&entry.outcome()));
+        }
+
         Ok(())
     }
     
-    // New method to gather context with project memory
-    fn gather_context(&self, command: &str) -> Result<String> {
+    /// Asks which of the site's Drupal modules a command is about; None
+    /// keeps all of them
+    async fn pick_module(&self, modules: &[(String, PathBuf)]) -> Result<Option<String>> {
+        let cwd = std::env::current_dir()?;
+        self.ui.print(&format!("This Drupal site has {} modules:", modules.len()));
+        for (idx, (name, path)) in modules.iter().enumerate() {
+            self.ui.print(&format!("{:>3}. {} ({})", idx + 1, name.bold(), path.strip_prefix(&cwd).unwrap_or(path).display()));
+        }
+        loop {
+            self.ui.print(&format!("Module to work on (1-{}, Enter for all; --module skips this):", modules.len()));
+            let input = match self.ui.read_line().await? {
+                Some(input) => input.trim().to_string(),
+                None => return Ok(None),
+            };
+            if input.is_empty() {
+                return Ok(None);
+            }
+            let chosen = input
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| modules.get(number.wrapping_sub(1)))
+                .or_else(|| modules.iter().find(|(name, _)| *name == input));
+            match chosen {
+                Some((name, _)) => return Ok(Some(name.clone())),
+                None => self.ui.print(&format!("{} No module '{}'", "!".yellow(), input)),
+            }
+        }
+    }
+
+    // New method to gather context with project memory; `interactive` lets
+    // the LLM summarize relevant files not summarized before and asks which
+    // Drupal module is meant when the command doesn't say
+    async fn gather_context(&self, command: &str, interactive: bool) -> Result<PromptContext> {
         // Load project memory (returns a new instance without modifying self)
         let loaded_memory = self.context_manager.project_memory.load()?;
         
-        // Start building context
-        let mut context = String::new();
-        
-        // Add project memory if available
-        let memory = loaded_memory.get_memory();
-        if !memory.is_empty() {
-            context.push_str("# Project Memory\n");
-            context.push_str(memory);
-            context.push_str("\n\n");
-        }
+        let module = match self.context_manager.module_target(command)? {
+            ModuleTarget::Module(name) => Some(name),
+            ModuleTarget::Ambiguous(modules) if interactive => self.pick_module(&modules).await?,
+            _ => None,
+        };
         
         // Get the regular code context
-        let code_context = self.context_manager.gather_context(command)?;
-        context.push_str(&code_context);
+        let llm = if interactive { Some(&self.llm_client) } else { None };
+        let code_context = self.context_manager.gather_context(command, llm, module.as_deref()).await?;
         
-        Ok(context)
+        Ok(PromptContext {
+            command: command.to_string(),
+            project_type: code_context.project_type,
+            memory: loaded_memory.get_memory().to_string(),
+            memory_system_prompt: loaded_memory.system_prompt(),
+            conventions: loaded_memory
+                .conventions()
+                .or_else(|| code_context.kind.and_then(prompt::conventions).map(str::to_string))
+                .filter(|conventions| !conventions.is_empty()),
+            context: code_context.text,
+        })
+    }
+}
+
+/// Output format of `code-assist analyze`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnalysisFormat {
+    Text,
+    Markdown,
+    Json,
+}
+
+/// Runs a future until it completes or the user presses Ctrl-C.
+/// Returns None when cancelled; dropping the future aborts any in-flight request.
+async fn cancellable<F: Future>(future: F) -> Option<F::Output> {
+    tokio::select! {
+        output = future => Some(output),
+        _ = tokio::signal::ctrl_c() => None,
+    }
+}
+
+/// A correction given at a pause, as added to the conversation
+fn correction_note(correction: &str) -> String {
+    format!("\nCorrection from the user, given while you were working; follow it from now on: {}\n", correction)
+}
+
+/// `text` indented for `/debug last`, cut after DEBUG_PREVIEW_BYTES
+fn debug_preview(text: &str) -> String {
+    let mut end = text.len().min(DEBUG_PREVIEW_BYTES);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut preview: String = text[..end].lines().map(|line| format!("  {}\n", line)).collect();
+    if end < text.len() {
+        preview.push_str(&format!("  {}\n", format!("... ({} more bytes)", text.len() - end).dimmed()));
     }
+    preview.trim_end().to_string()
 }
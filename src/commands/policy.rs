@@ -0,0 +1,171 @@
+use crate::commands::middleware::action_file_path;
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::path::{Component, Path, PathBuf};
+
+/// Restricts which actions a single request is allowed to produce
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionPolicy {
+    /// Any supported action may be executed
+    Any,
+    /// Only answer_question; nothing is edited or executed
+    AnswerOnly,
+    /// Only edit_file, restricted to the listed files
+    EditOnly(Vec<PathBuf>),
+}
+
+impl ActionPolicy {
+    /// Actions the model may respond with under this policy
+    pub fn allowed_actions(&self) -> Vec<&'static str> {
+        match self {
+            ActionPolicy::Any => vec!["edit_file", "multi_edit", "create_file", "delete_file", "rename_file", "answer_question", "execute_command", "git_operation", "read_file_at_revision", "find_symbol"],
+            ActionPolicy::AnswerOnly => vec!["answer_question"],
+            ActionPolicy::EditOnly(_) => vec!["edit_file"],
+        }
+    }
+
+    /// The single action this policy allows, used for per-task model routing
+    pub fn task(&self) -> Option<&'static str> {
+        match self.allowed_actions().as_slice() {
+            [action] => Some(action),
+            _ => None,
+        }
+    }
+
+    /// Extra instructions appended to the system prompt
+    pub fn prompt_instructions(&self) -> Option<String> {
+        match self {
+            ActionPolicy::Any => None,
+            ActionPolicy::AnswerOnly => Some(
+                "You are in read-only mode: you must respond with the answer_question action only. \
+                Never propose file edits, shell commands, or git operations."
+                    .to_string(),
+            ),
+            ActionPolicy::EditOnly(files) => Some(format!(
+                "You must respond with a single edit_file action targeting one of these files: {}. \
+                Do not touch any other file, answer questions, run commands, or perform git operations.",
+                files.iter().map(|f| f.display().to_string()).collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+
+    /// OpenAI-style `response_format` constraining replies to the action
+    /// envelope. Answers must list the context they are drawn from.
+    pub fn response_format(&self) -> Value {
+        let actions = self.allowed_actions();
+        let others: Vec<&str> = actions.iter().copied().filter(|a| *a != "answer_question").collect();
+        let mut variants = Vec::new();
+        if actions.contains(&"answer_question") {
+            variants.push(json!({
+                "type": "object",
+                "properties": {
+                    "action": { "type": "string", "enum": ["answer_question"] },
+                    "details": answer_details_schema()
+                },
+                "required": ["action", "details"]
+            }));
+        }
+        if !others.is_empty() {
+            variants.push(json!({
+                "type": "object",
+                "properties": {
+                    "action": { "type": "string", "enum": others },
+                    "details": { "type": "object" }
+                },
+                "required": ["action", "details"]
+            }));
+        }
+        let schema = if variants.len() == 1 {
+            variants.remove(0)
+        } else {
+            json!({ "type": "object", "anyOf": variants })
+        };
+
+        json!({
+            "type": "json_schema",
+            "json_schema": {
+                "name": "action",
+                "schema": schema
+            }
+        })
+    }
+
+    /// Fails if the parsed action is not permitted by this policy
+    pub fn check(&self, action: &Value) -> Result<()> {
+        if *self == ActionPolicy::Any {
+            // Unknown actions are reported by the executor itself
+            return Ok(());
+        }
+
+        let action_type = action.get("action").and_then(|a| a.as_str()).unwrap_or("");
+
+        if !self.allowed_actions().contains(&action_type) {
+            return Err(anyhow!(
+                "Model proposed '{}' but only {} is allowed for this request; nothing was executed",
+                action_type,
+                self.allowed_actions().join(", ")
+            ));
+        }
+
+        if let ActionPolicy::EditOnly(files) = self {
+            let target = action_file_path(action)
+                .ok_or_else(|| anyhow!("edit_file action has no file path; nothing was executed"))?;
+            let target = resolve_path(&target);
+
+            if !files.iter().any(|f| resolve_path(f) == target) {
+                return Err(anyhow!(
+                    "Model proposed an edit to {} which is not one of the allowed files; nothing was executed",
+                    target.display()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Details of answer_question: the answer and the files and line ranges of
+/// the context it is based on
+fn answer_details_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "answer": { "type": "string" },
+            "sources": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string" },
+                        "start_line": { "type": "integer" },
+                        "end_line": { "type": "integer" }
+                    },
+                    "required": ["path", "start_line", "end_line"]
+                }
+            }
+        },
+        "required": ["answer", "sources"]
+    })
+}
+
+/// Makes a path absolute against the working directory and removes `.`/`..`
+/// segments without touching the filesystem (the file may not exist yet)
+fn resolve_path(path: &Path) -> PathBuf {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().unwrap_or_default().join(path)
+    };
+
+    let mut resolved = PathBuf::new();
+    for component in absolute.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
@@ -0,0 +1,163 @@
+use crate::config::{PostProcessConfig, PostProcessStep};
+use crate::llm::reasoning;
+use crate::ui::interface::UserInterface;
+use crate::wasm::WasmPlugin;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// A single transformation applied to an LLM response before it is executed.
+///
+/// Text processors run on the raw response string, value processors run on the
+/// parsed action JSON. Both kinds are ordered by the configured pipeline.
+pub enum PostProcessor {
+    StripThinking,
+    StripFences,
+    RegexRewrites(Vec<(Regex, String)>),
+    MapActionAliases(HashMap<String, String>),
+    NormalizePaths,
+    /// A `[[plugins.wasm]]` post-processor: called with `{"hook":
+    /// "post_process", "action": ...}`, it prints the action to run instead,
+    /// or nothing to keep it
+    Wasm(WasmPlugin),
+}
+
+impl PostProcessor {
+    fn apply_text(&self, text: String) -> String {
+        match self {
+            PostProcessor::StripThinking => reasoning::split_reasoning(&text).1,
+            PostProcessor::StripFences => {
+                let code_block_pattern = r"```(?:json)?\s*\n([\s\S]*?)\n```";
+                if let Ok(regex) = Regex::new(code_block_pattern) {
+                    if let Some(captures) = regex.captures(&text) {
+                        if let Some(json_match) = captures.get(1) {
+                            return json_match.as_str().trim().to_string();
+                        }
+                    }
+                }
+                text
+            }
+            PostProcessor::RegexRewrites(rewrites) => {
+                let mut result = text;
+                for (regex, replacement) in rewrites {
+                    result = regex.replace_all(&result, replacement.as_str()).into_owned();
+                }
+                result
+            }
+            _ => text,
+        }
+    }
+
+    fn apply_value(&self, action: &mut Value) -> Result<()> {
+        match self {
+            PostProcessor::MapActionAliases(aliases) => {
+                let mapped = action
+                    .get("action")
+                    .and_then(|a| a.as_str())
+                    .and_then(|a| aliases.get(a))
+                    .cloned();
+
+                if let Some(new_name) = mapped {
+                    action["action"] = Value::String(new_name);
+                }
+            }
+            PostProcessor::NormalizePaths => {
+                if let Some(details) = action.get_mut("details") {
+                    for key in ["file_path", "file", "path"] {
+                        if let Some(Value::String(path)) = details.get_mut(key) {
+                            *path = normalize_path(path);
+                        }
+                    }
+                }
+            }
+            PostProcessor::Wasm(plugin) => {
+                let output = plugin.call(&json!({ "hook": "post_process", "action": action })).and_then(|output| {
+                    let output = output.trim();
+                    if output.is_empty() {
+                        return Ok(None);
+                    }
+                    serde_json::from_str(output)
+                        .map(Some)
+                        .with_context(|| format!("Plugin '{}' returned invalid JSON", plugin.name()))
+                });
+                if let Some(updated) = output? {
+                    *action = updated;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Cleans up a path proposed by the model: stray quotes, Windows separators
+/// and leading `./` segments are removed.
+fn normalize_path(path: &str) -> String {
+    let mut normalized = path.trim().trim_matches('`').trim_matches('"').trim_matches('\'').to_string();
+
+    if !cfg!(target_os = "windows") {
+        normalized = normalized.replace('\\', "/");
+    }
+
+    while let Some(stripped) = normalized.strip_prefix("./") {
+        normalized = stripped.to_string();
+    }
+
+    normalized
+}
+
+/// Ordered pipeline of post-processors built from `[post_process]` in the config
+pub struct PostProcessPipeline {
+    processors: Vec<PostProcessor>,
+}
+
+impl PostProcessPipeline {
+    /// The configured steps, then the WebAssembly post-processors
+    pub fn from_config(config: &PostProcessConfig, plugins: Vec<WasmPlugin>) -> Result<Self> {
+        let mut processors = Vec::new();
+
+        for step in &config.steps {
+            let processor = match step {
+                PostProcessStep::StripThinking => PostProcessor::StripThinking,
+                PostProcessStep::StripFences => PostProcessor::StripFences,
+                PostProcessStep::MapActionAliases => {
+                    PostProcessor::MapActionAliases(config.action_aliases.clone())
+                }
+                PostProcessStep::NormalizePaths => PostProcessor::NormalizePaths,
+                PostProcessStep::RegexRewrites => {
+                    let mut rewrites = Vec::new();
+                    for rewrite in &config.rewrites {
+                        let regex = Regex::new(&rewrite.pattern).with_context(|| {
+                            format!("Invalid post-process rewrite pattern: {}", rewrite.pattern)
+                        })?;
+                        rewrites.push((regex, rewrite.replacement.clone()));
+                    }
+                    PostProcessor::RegexRewrites(rewrites)
+                }
+            };
+            processors.push(processor);
+        }
+        processors.extend(plugins.into_iter().map(PostProcessor::Wasm));
+
+        Ok(Self { processors })
+    }
+
+    /// Runs all text-level processors over the raw response
+    pub fn process_text(&self, response: &str) -> String {
+        self.processors
+            .iter()
+            .fold(response.to_string(), |text, processor| processor.apply_text(text))
+    }
+
+    /// Runs all value-level processors over the parsed action. A failing
+    /// processor is reported and leaves the action as it was.
+    pub fn process_value(&self, action: &mut Value, ui: &dyn UserInterface) {
+        for processor in &self.processors {
+            if let Err(e) = processor.apply_value(action) {
+                ui.error(&format!("{} {:#}", "!".yellow(), e));
+            }
+        }
+    }
+}
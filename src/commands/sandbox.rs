@@ -0,0 +1,209 @@
+use crate::config::Config;
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+
+/// How long the output of a stopped command is still read, in case
+/// something it started left its own process group and holds the pipes
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Output of a shell command run in the sandbox
+pub struct SandboxOutput {
+    /// None when the command was stopped or killed by a signal
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub timed_out: bool,
+    /// Stopped with Ctrl-C
+    pub cancelled: bool,
+}
+
+/// Runs the shell commands of `execute_command` inside the project, without
+/// secrets in their environment, stopped after a time limit and, on Unix,
+/// under the configured resource limits
+pub struct CommandSandbox {
+    root: PathBuf,
+    timeout: Option<Duration>,
+    /// Upper-cased parts of variable names that mark them as secrets
+    scrub_env: Vec<String>,
+    keep_env: Vec<String>,
+    /// Configured API keys, removed from the environment whatever the
+    /// variable holding them is called
+    secrets: Vec<String>,
+    /// `ulimit` flags with their values
+    limits: Vec<(&'static str, u64)>,
+}
+
+impl CommandSandbox {
+    pub fn new(config: &Config, root: &Path) -> Self {
+        let safety = &config.safety;
+        let mut secrets = vec![config.llm.api_key.clone(), config.embeddings.api_key.clone()];
+        secrets.extend(config.llm.fallbacks.iter().filter_map(|fallback| fallback.api_key.clone()));
+        secrets.retain(|secret| !secret.is_empty());
+
+        let mut limits = Vec::new();
+        if let Some(mb) = safety.max_memory_mb {
+            limits.push(("-v", mb * 1024));
+        }
+        if let Some(secs) = safety.max_cpu_secs {
+            limits.push(("-t", secs));
+        }
+        if let Some(mb) = safety.max_file_size_mb {
+            limits.push(("-f", mb * 1024));
+        }
+
+        Self {
+            root: root.to_path_buf(),
+            timeout: Some(safety.command_timeout_secs).filter(|secs| *secs > 0).map(Duration::from_secs),
+            scrub_env: safety.scrub_env.iter().map(|name| name.to_uppercase()).collect(),
+            keep_env: safety.keep_env.clone(),
+            secrets,
+            limits,
+        }
+    }
+
+    /// Directory a command runs in: `working_dir` relative to the project
+    /// root, or the root itself. Directories outside the project, also
+    /// through symlinks, are refused.
+    pub fn working_dir(&self, working_dir: Option<&str>) -> Result<PathBuf> {
+        let root = self.root.canonicalize().with_context(|| format!("Failed to resolve {}", self.root.display()))?;
+        let dir = match working_dir {
+            Some(dir) => root.join(dir),
+            None => return Ok(root),
+        };
+        let resolved = dir.canonicalize().with_context(|| format!("No directory {}", dir.display()))?;
+        if !resolved.starts_with(&root) {
+            return Err(anyhow!("{} is outside the project", dir.display()));
+        }
+        if !resolved.is_dir() {
+            return Err(anyhow!("{} is not a directory", dir.display()));
+        }
+        Ok(resolved)
+    }
+
+    /// Names of the environment variables withheld from commands
+    pub fn scrubbed_vars(&self) -> Vec<String> {
+        let mut names: Vec<String> = std::env::vars_os()
+            .filter_map(|(name, value)| {
+                let name = name.into_string().ok()?;
+                if self.keep_env.contains(&name) {
+                    return None;
+                }
+                let upper = name.to_uppercase();
+                let secret_name = self.scrub_env.iter().any(|part| upper.contains(part.as_str()));
+                let secret_value = value.to_str().is_some_and(|value| self.secrets.iter().any(|secret| secret == value));
+                (secret_name || secret_value).then_some(name)
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Runs `command` with the shell in `dir`, stopping it and everything it
+    /// started once the timeout passes or on Ctrl-C. The output printed until
+    /// then is kept.
+    pub async fn run(&self, command: &str, dir: &Path) -> Result<SandboxOutput> {
+        let mut process = if cfg!(target_os = "windows") {
+            let mut process = Command::new("cmd");
+            process.arg("/C").arg(command);
+            process
+        } else {
+            let mut process = Command::new("bash");
+            if self.limits.is_empty() {
+                process.arg("-c").arg(command);
+            } else {
+                // Without -H or -S, ulimit sets the hard limit too, so the command can't raise it
+                let flags: Vec<String> = self.limits.iter().map(|(flag, value)| format!("{} {}", flag, value)).collect();
+                process.arg("-c").arg(format!("ulimit {} || exit 125\n{}", flags.join(" "), command));
+            }
+            process
+        };
+        for name in self.scrubbed_vars() {
+            process.env_remove(name);
+        }
+        process.current_dir(dir).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).kill_on_drop(true);
+        // A group of its own, so what the command started can be stopped with it
+        #[cfg(unix)]
+        process.process_group(0);
+
+        let mut child = process.spawn().context("Failed to execute command")?;
+        let pid = child.id();
+        // Read while the command runs, so what it printed survives stopping it
+        let (stdout, stderr) = (Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(Vec::new())));
+        let mut readers = [
+            tokio::spawn(read_into(child.stdout.take(), stdout.clone())),
+            tokio::spawn(read_into(child.stderr.take(), stderr.clone())),
+        ];
+        let finished = async {
+            let status = child.wait().await;
+            // Output still in the pipes once the shell exits
+            for reader in readers.iter_mut() {
+                let _ = reader.await;
+            }
+            status
+        };
+        let deadline = async {
+            match self.timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        // In a process group of its own the command doesn't get the terminal's SIGINT
+        let (exit_code, timed_out, cancelled) = tokio::select! {
+            status = finished => (status.context("Failed to execute command")?.code(), false, false),
+            _ = deadline => (None, true, false),
+            _ = tokio::signal::ctrl_c() => (None, false, true),
+        };
+        if timed_out || cancelled {
+            kill_group(pid);
+            let _ = child.kill().await;
+            let drained = async {
+                while !readers.iter().all(|reader| reader.is_finished()) {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            };
+            let _ = tokio::time::timeout(DRAIN_TIMEOUT, drained).await;
+        }
+
+        let text = |buffer: &Mutex<Vec<u8>>| buffer.lock().map(|bytes| String::from_utf8_lossy(&bytes).to_string()).unwrap_or_default();
+        Ok(SandboxOutput { exit_code, stdout: text(&stdout), stderr: text(&stderr), timed_out, cancelled })
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+}
+
+/// Appends what comes out of `pipe` to `buffer` until it closes
+async fn read_into(pipe: Option<impl AsyncRead + Unpin>, buffer: Arc<Mutex<Vec<u8>>>) {
+    let mut pipe = match pipe {
+        Some(pipe) => pipe,
+        None => return,
+    };
+    let mut chunk = [0; 8192];
+    loop {
+        match pipe.read(&mut chunk).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => match buffer.lock() {
+                Ok(mut buffer) => buffer.extend_from_slice(&chunk[..read]),
+                Err(_) => return,
+            },
+        }
+    }
+}
+
+/// Kills a stopped command and everything it started
+#[cfg(unix)]
+fn kill_group(pid: Option<u32>) {
+    if let Some(pid) = pid {
+        let _ = std::process::Command::new("kill").args(["-KILL", "--"]).arg(format!("-{}", pid)).status();
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(_pid: Option<u32>) {}
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use anyhow::{Result, Context};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -9,15 +10,211 @@ pub struct Config {
     pub llm: LlmConfig,
     pub editor: EditorConfig,
     pub git: GitConfig,
+    #[serde(default)]
+    pub post_process: PostProcessConfig,
+    #[serde(default)]
+    pub middleware: MiddlewareConfig,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub embeddings: EmbeddingsConfig,
+    #[serde(default)]
+    pub notifications: NotificationConfig,
+    #[serde(default)]
+    pub lsp: LspConfig,
+    #[serde(default)]
+    pub summarize: SummarizeConfig,
+    #[serde(default)]
+    pub events: EventsConfig,
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub sessions: SessionsConfig,
+    #[serde(default)]
+    pub context: ContextConfig,
+    #[serde(default)]
+    pub scan: ScanConfig,
+    #[serde(default)]
+    pub safety: SafetyConfig,
+    #[serde(default)]
+    pub diff: DiffConfig,
+    #[serde(default)]
+    pub project: ProjectConfig,
+    #[serde(default)]
+    pub plugins: PluginsConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LlmConfig {
+    #[serde(default)]
+    pub provider: LlmProvider,
     pub api_url: String,
     pub api_key: String,
     pub model: String,
     pub temperature: f32,
     pub max_tokens: usize,
+    /// File whose contents are appended to the built-in system prompt
+    #[serde(default)]
+    pub system_prompt_path: Option<PathBuf>,
+    /// Models tried in order when the primary model errors or times out
+    #[serde(default)]
+    pub fallbacks: Vec<FallbackModel>,
+    /// Seconds to wait for a chat completion before giving up on a model
+    #[serde(default = "default_request_timeout_secs")]
+    pub request_timeout_secs: u64,
+    /// Send the action envelope as a `response_format` JSON schema when the provider accepts it
+    #[serde(default = "default_structured_output")]
+    pub structured_output: bool,
+    /// Model to use per task (`answer_question`, `edit_file`, ..., `summarize`) when a request is limited to that task
+    #[serde(default)]
+    pub routes: HashMap<String, String>,
+    /// Whether model reasoning (`<think>` blocks, `reasoning` fields) is shown
+    #[serde(default)]
+    pub reasoning: ReasoningDisplay,
+    /// Write every prompt and raw response to `.caulk/logs/` (also `--debug-llm`)
+    #[serde(default)]
+    pub debug_log: bool,
+    /// Largest request body sent to the provider; longer context is trimmed.
+    /// Defaults to a limit per provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_request_bytes: Option<usize>,
+    /// Replies of the `mock` provider, one per request
+    #[cfg(feature = "test-support")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mock_responses: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningDisplay {
+    /// Strip reasoning before the reply is parsed; it is logged at debug level
+    #[default]
+    Hidden,
+    /// Stream replies and print reasoning dimmed while the model thinks
+    Live,
+}
+
+fn default_structured_output() -> bool {
+    true
+}
+
+fn default_request_timeout_secs() -> u64 {
+    300
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FallbackModel {
+    pub model: String,
+    /// Provider kind; defaults to the primary `provider`
+    #[serde(default)]
+    pub provider: Option<LlmProvider>,
+    /// Provider endpoint; defaults to the primary `api_url`
+    #[serde(default)]
+    pub api_url: Option<String>,
+    /// Provider key; defaults to the primary `api_key`
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Request size limit; defaults to the primary `max_request_bytes`
+    #[serde(default)]
+    pub max_request_bytes: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmProvider {
+    /// Any OpenAI-compatible chat completions endpoint (OpenAI, Ollama, llama.cpp, ...)
+    #[default]
+    #[serde(alias = "openai")]
+    OpenAi,
+    /// OpenRouter; models are named `vendor/model` and requests carry attribution headers
+    #[serde(alias = "openrouter")]
+    OpenRouter,
+    /// Replies with `mock_responses` in order instead of calling a model
+    #[cfg(feature = "test-support")]
+    Mock,
+}
+
+impl LlmProvider {
+    /// Request body size sent when `max_request_bytes` is not set, about
+    /// 120k tokens for OpenAI-style models and 200k tokens on OpenRouter
+    pub fn default_max_request_bytes(&self) -> usize {
+        match self {
+            LlmProvider::OpenAi => 480_000,
+            LlmProvider::OpenRouter => 800_000,
+            #[cfg(feature = "test-support")]
+            LlmProvider::Mock => usize::MAX,
+        }
+    }
+}
+
+/// A model together with the provider it is requested from
+#[derive(Debug, Clone)]
+pub struct ModelTarget {
+    pub model: String,
+    pub provider: LlmProvider,
+    pub api_url: String,
+    pub api_key: String,
+    pub max_request_bytes: usize,
+}
+
+/// Per-request replacements for LlmConfig values (CLI flags and `/set`)
+#[derive(Debug, Clone, Default)]
+pub struct LlmOverrides {
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<usize>,
+}
+
+impl LlmConfig {
+    /// Replaces the values that are set in `overrides`
+    pub fn apply_overrides(&mut self, overrides: &LlmOverrides) {
+        if let Some(model) = &overrides.model {
+            self.model = model.clone();
+            // An explicitly chosen model wins over per-task routing
+            self.routes.clear();
+        }
+        if let Some(temperature) = overrides.temperature {
+            self.temperature = temperature;
+        }
+        if let Some(max_tokens) = overrides.max_tokens {
+            self.max_tokens = max_tokens;
+        }
+    }
+
+    /// The primary model followed by the configured fallbacks. When `task` (the
+    /// single action a request may produce) has a route, the routed model
+    /// replaces the primary one.
+    pub fn targets(&self, task: Option<&str>) -> Vec<ModelTarget> {
+        let primary = task
+            .and_then(|task| self.routes.get(task))
+            .unwrap_or(&self.model);
+
+        let mut targets = vec![ModelTarget {
+            model: primary.clone(),
+            provider: self.provider,
+            api_url: self.api_url.clone(),
+            api_key: self.api_key.clone(),
+            max_request_bytes: self.max_request_bytes.unwrap_or_else(|| self.provider.default_max_request_bytes()),
+        }];
+
+        for fallback in &self.fallbacks {
+            let provider = fallback.provider.unwrap_or(self.provider);
+            targets.push(ModelTarget {
+                model: fallback.model.clone(),
+                provider,
+                api_url: fallback.api_url.clone().unwrap_or_else(|| self.api_url.clone()),
+                api_key: fallback.api_key.clone().unwrap_or_else(|| self.api_key.clone()),
+                max_request_bytes: fallback
+                    .max_request_bytes
+                    .or(self.max_request_bytes)
+                    .unwrap_or_else(|| provider.default_max_request_bytes()),
+            });
+        }
+
+        targets
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -27,25 +224,557 @@ pub struct EditorConfig {
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GitConfig {
+    /// Git status in context, git actions and auto-staging. Without the git
+    /// binary these use the built-in libgit2 support instead.
     pub enable_git_features: bool,
+    /// Access token for HTTPS remotes, tried before the git credential helper
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PostProcessConfig {
+    /// Ordered list of post-processing steps applied to every LLM response
+    pub steps: Vec<PostProcessStep>,
+    /// Deprecated or model-specific action names mapped to supported ones
+    pub action_aliases: HashMap<String, String>,
+    /// Custom regex rewrites applied to the raw response text
+    pub rewrites: Vec<RegexRewrite>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostProcessStep {
+    StripThinking,
+    StripFences,
+    RegexRewrites,
+    MapActionAliases,
+    NormalizePaths,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RegexRewrite {
+    pub pattern: String,
+    pub replacement: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct EmbeddingsConfig {
+    /// Rank relevant files by semantic similarity to the command
+    pub enabled: bool,
+    pub provider: EmbeddingProvider,
+    pub api_url: String,
+    pub api_key: String,
+    pub model: String,
+    /// Share of a file's score taken from embedding similarity, the rest
+    /// from keyword relevance (0.0 to 1.0)
+    pub semantic_weight: f32,
+}
+
+fn default_semantic_weight() -> f32 {
+    0.6
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingProvider {
+    /// OpenAI-compatible `POST {api_url}/embeddings`
+    OpenAi,
+    /// Ollama `POST {api_url}/embed`
+    Ollama,
+}
+
+impl Default for EmbeddingsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: EmbeddingProvider::Ollama,
+            api_url: "http://localhost:11434/api".to_string(),
+            api_key: "".to_string(),
+            model: "nomic-embed-text".to_string(),
+            semantic_weight: default_semantic_weight(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Reuse the replies to identical requests (same endpoint, model, prompts,
+    /// temperature, max_tokens and response format)
+    pub enabled: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SessionsConfig {
+    /// Save every command and its answer to `.caulk/sessions/` for `/search`
+    pub record: bool,
+}
+
+impl Default for SessionsConfig {
+    fn default() -> Self {
+        Self { record: true }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// How to signal that a slow LLM response or command has finished
+    pub mode: NotificationMode,
+    /// Only notify when the task took at least this many seconds
+    pub min_duration_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationMode {
+    Off,
+    /// Ring the terminal bell
+    Bell,
+    /// Show a desktop notification (falls back to the bell)
+    Desktop,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            mode: NotificationMode::Off,
+            min_duration_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ContextConfig {
+    /// Strip comments, docstrings and blank lines from included code to fit
+    /// more of it in the prompt (also `--dense-context`)
+    pub dense: bool,
+    /// Files changed in this many of the last commits rank higher, as do
+    /// files with uncommitted changes; 0 turns the boost off
+    pub recent_commits: usize,
+    /// Most relevant files whose code is included; the imports and tests of
+    /// these files are added too
+    pub max_files: usize,
+    /// Bytes of code included from one file, filled with its best matching
+    /// definitions
+    pub max_bytes_per_file: usize,
+    /// Estimated tokens of the whole context; files past it are left out and
+    /// the rest is cut. 0 leaves it to `llm.max_request_bytes`.
+    pub max_total_tokens: usize,
+    /// Include trimmed README, CONTRIBUTING and docs overview pages for
+    /// architecture-level questions
+    pub include_docs: bool,
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            dense: false,
+            recent_commits: 10,
+            max_files: 3,
+            max_bytes_per_file: 8000,
+            max_total_tokens: 0,
+            include_docs: true,
+        }
+    }
+}
+
+/// How project files are walked, by the analysis, the code search, the
+/// symbol index, memory discovery and every other scan of the project
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ScanConfig {
+    /// Directory levels walked below the project root
+    pub max_depth: usize,
+    /// Files walked at most; 0 for no limit
+    pub max_files: usize,
+    /// Walk into symlinked directories and read symlinked files
+    pub follow_symlinks: bool,
+    /// Paths to skip in .gitignore syntax, on top of .gitignore and
+    /// `.caulkignore`
+    pub ignore: Vec<String>,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 20,
+            max_files: 100_000,
+            follow_symlinks: false,
+            ignore: Vec::new(),
+        }
+    }
+}
+
+/// How the diffs of edits are computed and shown
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DiffConfig {
+    pub algorithm: DiffAlgorithm,
+    /// Highlight the changed words within a changed line
+    pub word_level: bool,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self { algorithm: DiffAlgorithm::Myers, word_level: true }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffAlgorithm {
+    #[default]
+    Myers,
+    /// Matches unique lines first, which keeps moved blocks and braces of
+    /// rewritten functions from lining up with the wrong code
+    Patience,
+    Lcs,
+}
+
+/// Which actions run without asking first, and which shell commands never run
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SafetyConfig {
+    /// Actions applied without showing them for approval: `edit_file`
+    /// (which covers `create_file` and `multi_edit`) and `execute_command`
+    pub auto_approve: Vec<String>,
+    /// Regular expressions of shell commands that are refused, even when
+    /// they match `allow_commands`
+    pub deny_commands: Vec<String>,
+    /// Regular expressions of the only shell commands that may run; any
+    /// command may when empty
+    pub allow_commands: Vec<String>,
+    /// Seconds a shell command may run before it is stopped; 0 for no limit
+    pub command_timeout_secs: u64,
+    /// Environment variables whose names contain one of these (ignoring
+    /// case) are removed from the environment of shell commands
+    pub scrub_env: Vec<String>,
+    /// Variables passed to shell commands even though `scrub_env` matches them
+    pub keep_env: Vec<String>,
+    /// Address space of a shell command in MB (Unix)
+    pub max_memory_mb: Option<u64>,
+    /// CPU time of a shell command in seconds (Unix)
+    pub max_cpu_secs: Option<u64>,
+    /// Size of a file a shell command writes in MB (Unix)
+    pub max_file_size_mb: Option<u64>,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self {
+            auto_approve: Vec::new(),
+            deny_commands: vec![
+                // rm -rf, rm -fr, rm -Rf, rm -r -f, rm --recursive --force
+                r"\brm\s+(?:-[A-Za-z]*(?:[rR][A-Za-z]*f|f[A-Za-z]*[rR])|(?:-[rR]|--recursive)\s+(?:-f|--force)|(?:-f|--force)\s+(?:-[rR]|--recursive))".to_string(),
+                // A downloaded script piped into a shell
+                r"\b(?:curl|wget)\b[^|]*\|\s*(?:sudo\s+)?(?:ba|z|da|k)?sh\b".to_string(),
+                // --force, --force-with-lease, -f
+                r"\bgit\s+(?:.*\s)?push\b.*\s(?:--force|-f)\b".to_string(),
+            ],
+            allow_commands: Vec::new(),
+            command_timeout_secs: 300,
+            scrub_env: ["KEY", "TOKEN", "SECRET", "PASSWORD", "PASSWD", "CREDENTIAL"].iter().map(|s| s.to_string()).collect(),
+            keep_env: Vec::new(),
+            max_memory_mb: None,
+            max_cpu_secs: None,
+            max_file_size_mb: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ProjectConfig {
+    /// Project type used instead of the detected one (`php`, `drupal-module`,
+    /// `react`, ...; also `--project-type` or `project.type:` in CAULK.md)
+    #[serde(default, rename = "type")]
+    pub project_type: Option<String>,
+    /// Drupal module the context is scoped to in a site with several (also `--module`)
+    #[serde(default)]
+    pub module: Option<String>,
+    /// Script plugins recognizing in-house project types
+    #[serde(default)]
+    pub plugins: Vec<ProjectPluginConfig>,
+}
+
+/// A project type recognized by marker files and/or a detection command,
+/// whose info command's output is added to the context
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectPluginConfig {
+    /// Name of the project type, e.g. "Acme PHP framework"
+    pub name: String,
+    /// Paths relative to the project root; the project matches when any exists
+    #[serde(default)]
+    pub markers: Vec<String>,
+    /// Command run in the project root that exits 0 when the project matches
+    #[serde(default)]
+    pub detect: Option<String>,
+    /// Command run in the project root whose output describes the project
+    #[serde(default)]
+    pub info: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PluginsConfig {
+    /// WebAssembly plugins, run sandboxed (needs the `wasm-plugins` feature)
+    #[serde(default)]
+    pub wasm: Vec<WasmPluginConfig>,
+}
+
+/// A WASI module that reads a JSON request on stdin and answers on stdout
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WasmPluginConfig {
+    pub name: String,
+    /// The .wasm module, relative to the project root
+    pub path: PathBuf,
+    /// What the plugin is called for
+    #[serde(default = "default_wasm_hooks")]
+    pub hooks: Vec<WasmHook>,
+    /// Project directories the plugin may read, mounted under /project
+    #[serde(default = "default_wasm_read")]
+    pub read: Vec<String>,
+    /// Project directories the plugin may also write to
+    #[serde(default)]
+    pub write: Vec<String>,
+}
+
+fn default_wasm_hooks() -> Vec<WasmHook> {
+    vec![WasmHook::Analyze]
+}
+
+fn default_wasm_read() -> Vec<String> {
+    vec![".".to_string()]
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WasmHook {
+    /// Detects and describes projects, like `[[project.plugins]]`
+    Analyze,
+    /// Rewrites each action the model returns
+    PostProcess,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EventsConfig {
+    /// Append every action event as a JSON line to this file (relative to the
+    /// project root, inside `.caulk/`)
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PrivacyConfig {
+    /// Encrypt the response cache, embeddings index and LLM debug logs at
+    /// rest, with a key kept in the OS keyring (or `CODE_ASSIST_STORAGE_KEY`)
+    #[serde(default)]
+    pub encrypt_storage: bool,
+}
+
+/// How long local data is kept, enforced on startup. Nothing is deleted
+/// unless a limit is set.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// Cached LLM responses
+    pub cache: RetentionLimits,
+    /// LLM transcripts in `.caulk/logs/` and recorded sessions in `.caulk/sessions/`
+    pub sessions: RetentionLimits,
+    /// The events log (`[events] log_file`)
+    pub audit_log: RetentionLimits,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RetentionLimits {
+    /// Delete entries older than this many days
+    pub max_age_days: Option<u64>,
+    /// Delete the oldest entries until the rest fit in this many megabytes
+    pub max_size_mb: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SummarizeConfig {
+    /// Summarize the codebase file by file for overview questions ("explain the architecture")
+    pub enabled: bool,
+    /// Most source files summarized per question, shallowest first
+    pub max_files: usize,
+    /// Summaries requested from the LLM at the same time
+    pub concurrency: usize,
+    /// Bytes of each file sent to be summarized
+    pub max_file_bytes: usize,
+    /// Summaries are merged until they fit in this many bytes
+    pub max_summary_bytes: usize,
+    /// Summarize the relevant files of each request (cached in `.caulk/summaries/`)
+    /// and show the summaries in the context instead of previews of long files
+    pub file_summaries: bool,
+}
+
+impl Default for SummarizeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_files: 60,
+            concurrency: 4,
+            max_file_bytes: 6000,
+            max_summary_bytes: 12000,
+            file_summaries: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LspConfig {
+    /// Use language servers for definitions, hover types and edit diagnostics
+    pub enabled: bool,
+    /// Server command per language (`rust`, `python`, `php`, ...)
+    pub servers: HashMap<String, String>,
+    /// Seconds to wait for a language server response
+    pub timeout_secs: u64,
+}
+
+impl Default for LspConfig {
+    fn default() -> Self {
+        let servers = [
+            ("rust", "rust-analyzer"),
+            ("python", "pyright-langserver --stdio"),
+            ("php", "intelephense --stdio"),
+            ("typescript", "typescript-language-server --stdio"),
+            ("javascript", "typescript-language-server --stdio"),
+            ("go", "gopls"),
+        ];
+        Self {
+            enabled: false,
+            servers: servers.iter().map(|(l, c)| (l.to_string(), c.to_string())).collect(),
+            timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MiddlewareConfig {
+    /// Glob patterns (relative to the project root) that actions may not
+    /// modify. A project config adds to the user's patterns.
+    #[serde(default)]
+    pub protected_paths: Vec<String>,
+    /// Stage files in git after they have been edited
+    #[serde(default)]
+    pub auto_stage: bool,
+    /// Add missing and remove unused imports in edited Rust, Python and
+    /// JavaScript/TypeScript files, then sort them
+    #[serde(default)]
+    pub organize_imports: bool,
+    /// External commands run before/after each action
+    #[serde(default)]
+    pub hooks: Vec<HookConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HookConfig {
+    pub stage: HookStage,
+    pub command: String,
+    /// Restrict the hook to these action types (all actions when empty)
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStage {
+    Pre,
+    Post,
+}
+
+impl Default for PostProcessConfig {
+    fn default() -> Self {
+        let action_aliases = [
+            ("modify_file", "edit_file"),
+            ("update_file", "edit_file"),
+            ("write_file", "edit_file"),
+            ("new_file", "create_file"),
+            ("remove_file", "delete_file"),
+            ("move_file", "rename_file"),
+            ("run_command", "execute_command"),
+            ("shell", "execute_command"),
+            ("answer", "answer_question"),
+            ("git", "git_operation"),
+        ]
+        .iter()
+        .map(|(from, to)| (from.to_string(), to.to_string()))
+        .collect();
+
+        Self {
+            steps: vec![
+                PostProcessStep::StripThinking,
+                PostProcessStep::RegexRewrites,
+                PostProcessStep::StripFences,
+                PostProcessStep::MapActionAliases,
+                PostProcessStep::NormalizePaths,
+            ],
+            action_aliases,
+            rewrites: Vec::new(),
+        }
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             llm: LlmConfig {
+                provider: LlmProvider::OpenAi,
                 api_url: "http://localhost:11434/api".to_string(),
                 api_key: "".to_string(),
                 model: "codellama".to_string(),
                 temperature: 0.7,
                 max_tokens: 2048,
+                system_prompt_path: None,
+                fallbacks: Vec::new(),
+                request_timeout_secs: default_request_timeout_secs(),
+                structured_output: default_structured_output(),
+                routes: HashMap::new(),
+                reasoning: ReasoningDisplay::default(),
+                debug_log: false,
+                max_request_bytes: None,
+                #[cfg(feature = "test-support")]
+                mock_responses: Vec::new(),
             },
             editor: EditorConfig {
                 default_editor: "vim".to_string(),
             },
             git: GitConfig {
                 enable_git_features: true,
+                token: None,
             },
+            post_process: PostProcessConfig::default(),
+            middleware: MiddlewareConfig::default(),
+            cache: CacheConfig::default(),
+            embeddings: EmbeddingsConfig::default(),
+            notifications: NotificationConfig::default(),
+            lsp: LspConfig::default(),
+            summarize: SummarizeConfig::default(),
+            events: EventsConfig::default(),
+            privacy: PrivacyConfig::default(),
+            retention: RetentionConfig::default(),
+            sessions: SessionsConfig::default(),
+            context: ContextConfig::default(),
+            scan: ScanConfig::default(),
+            safety: SafetyConfig::default(),
+            diff: DiffConfig::default(),
+            project: ProjectConfig::default(),
+            plugins: PluginsConfig::default(),
         }
     }
 }
@@ -74,6 +803,116 @@ pub fn load_or_create_config(config_path: &Path) -> Result<Config> {
     Ok(config)
 }
 
+/// Keys a project's `.caulk/config.toml` can't set, since a checked-out
+/// repository could use them to run its own commands or to send the user's
+/// API key and code to a host of its choosing
+const USER_ONLY_KEYS: &[&str] = &[
+    "llm.api_url",
+    "llm.fallbacks",
+    "llm.system_prompt_path",
+    "embeddings.api_url",
+    "middleware.hooks",
+    "project.plugins",
+    "lsp.servers",
+    "events.log_file",
+    "retention",
+];
+
+/// Merges a project-level `.caulk/config.toml` (if any) over the global config.
+/// Only the keys present in the project file are overridden, except
+/// `USER_ONLY_KEYS`, which are ignored.
+pub fn apply_project_config(config: Config, project_path: &Path) -> Result<Config> {
+    let project_config_path = project_path.join(".caulk").join("config.toml");
+    if !project_config_path.exists() {
+        return Ok(config);
+    }
+
+    let contents = fs::read_to_string(&project_config_path)
+        .with_context(|| format!("Failed to read {}", project_config_path.display()))?;
+    let mut overrides: toml::Value = toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse {}", project_config_path.display()))?;
+    let ignored: Vec<&str> = USER_ONLY_KEYS.iter().copied().filter(|key| remove_key(&mut overrides, key)).collect();
+    if !ignored.is_empty() {
+        log::warn!(
+            "Ignored {} in {}: only the user config can set them",
+            ignored.join(", "),
+            project_config_path.display()
+        );
+    }
+
+    let mut merged = toml::Value::try_from(&config)?;
+    merge_toml(&mut merged, overrides);
+
+    let mut project: Config = merged.try_into().context("Invalid project configuration")?;
+    // A checked-out repository must not approve its own edits and commands
+    project.safety.auto_approve.retain(|action| config.safety.auto_approve.contains(action));
+    // nor lift the command rules or the sandbox: it may restrict more but not less
+    let (user, safety) = (&config.safety, &mut project.safety);
+    for pattern in &user.deny_commands {
+        if !safety.deny_commands.contains(pattern) {
+            safety.deny_commands.push(pattern.clone());
+        }
+    }
+    if !user.allow_commands.is_empty() {
+        safety.allow_commands = user.allow_commands.clone();
+    }
+    for name in &user.scrub_env {
+        if !safety.scrub_env.contains(name) {
+            safety.scrub_env.push(name.clone());
+        }
+    }
+    safety.keep_env.retain(|name| user.keep_env.contains(name));
+    let timeout = |secs: u64| Some(secs).filter(|secs| *secs > 0);
+    safety.command_timeout_secs = stricter(timeout(user.command_timeout_secs), timeout(safety.command_timeout_secs)).unwrap_or(0);
+    safety.max_memory_mb = stricter(user.max_memory_mb, safety.max_memory_mb);
+    safety.max_cpu_secs = stricter(user.max_cpu_secs, safety.max_cpu_secs);
+    safety.max_file_size_mb = stricter(user.max_file_size_mb, safety.max_file_size_mb);
+    // and may protect more paths, but not unprotect the user's
+    for pattern in &config.middleware.protected_paths {
+        if !project.middleware.protected_paths.contains(pattern) {
+            project.middleware.protected_paths.push(pattern.clone());
+        }
+    }
+    Ok(project)
+}
+
+/// Removes the dotted `key` (`llm.api_url`) from a TOML table; returns
+/// whether it was there
+fn remove_key(value: &mut toml::Value, key: &str) -> bool {
+    let (table, name) = match key.rsplit_once('.') {
+        Some((parent, name)) => (parent.split('.').try_fold(value, |value, part| value.get_mut(part)), name),
+        None => (Some(value), key),
+    };
+    match table.and_then(|table| table.as_table_mut()) {
+        Some(table) => table.remove(name).is_some(),
+        None => false,
+    }
+}
+
+/// The lower of two limits, where None is no limit
+fn stricter(user: Option<u64>, project: Option<u64>) -> Option<u64> {
+    match (user, project) {
+        (Some(user), Some(project)) => Some(user.min(project)),
+        (user, project) => user.or(project),
+    }
+}
+
+fn merge_toml(base: &mut toml::Value, overrides: toml::Value) {
+    match (base, overrides) {
+        (toml::Value::Table(base_table), toml::Value::Table(override_table)) => {
+            for (key, value) in override_table {
+                match base_table.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, value),
+                    None => {
+                        base_table.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
 pub fn update_config(
     config_path: &Path,
     api_url: &Option<String>,
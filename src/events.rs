@@ -0,0 +1,128 @@
+use anyhow::{anyhow, Context, Result};
+use log::warn;
+use serde::Serialize;
+use serde_json::json;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+/// Events buffered per subscriber before a slow one starts missing events
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Something that happened while a command was handled
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    ContextGathered {
+        command: String,
+        context_bytes: usize,
+        project_type: Option<String>,
+    },
+    LlmRequested {
+        model: String,
+        task: Option<String>,
+    },
+    ActionProposed {
+        action: String,
+        target: Option<PathBuf>,
+    },
+    ActionApplied {
+        action: String,
+        target: Option<PathBuf>,
+    },
+    ActionFailed {
+        action: String,
+        target: Option<PathBuf>,
+        error: String,
+    },
+}
+
+/// Broadcasts [`Event`]s to every subscriber (TUI, session and audit logs,
+/// plugins) so they don't have to hook into the executor itself
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Receives every event emitted from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// Sends an event; having no subscribers is fine
+    pub fn emit(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Appends every event as a timestamped JSON line to `path`. The task ends
+/// once all senders of the bus are dropped.
+/// Where `[events] log_file` is written for the project at `project_root`.
+/// Paths outside the project's `.caulk/` directory, also through symlinks,
+/// are refused.
+pub fn log_path(project_root: &Path, log_file: &Path) -> Result<PathBuf> {
+    let caulk = project_root.join(".caulk");
+    let mut path = PathBuf::new();
+    for component in project_root.join(log_file).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                path.pop();
+            }
+            other => path.push(other.as_os_str()),
+        }
+    }
+    let outside = || anyhow!("events.log_file {} is outside {}", log_file.display(), caulk.display());
+    if !path.starts_with(&caulk) || path == caulk {
+        return Err(outside());
+    }
+    // What exists of the path may be a symlink leading out of .caulk
+    if let Ok(caulk) = caulk.canonicalize() {
+        let existing = path.ancestors().find(|ancestor| ancestor.symlink_metadata().is_ok()).and_then(|ancestor| ancestor.canonicalize().ok());
+        if !existing.is_some_and(|existing| existing.starts_with(&caulk)) {
+            return Err(outside());
+        }
+    }
+    Ok(path)
+}
+
+pub fn spawn_event_log(bus: &EventBus, path: &Path) -> Result<JoinHandle<()>> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open event log: {}", path.display()))?;
+
+    let mut receiver = bus.subscribe();
+    Ok(tokio::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!("Event log missed {} events", missed);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            };
+
+            let mut line = json!({ "time": chrono::Local::now().to_rfc3339() });
+            if let (Some(line), Ok(serde_json::Value::Object(fields))) = (line.as_object_mut(), serde_json::to_value(&event)) {
+                line.extend(fields);
+            }
+            if let Err(e) = writeln!(file, "{}", line) {
+                warn!("Failed to write event log: {}", e);
+            }
+        }
+    }))
+}
@@ -0,0 +1,310 @@
+use crate::fs::project_files;
+use std::path::{Path, PathBuf};
+
+/// Extensions of documentation pages
+const DOC_EXTENSIONS: &[&str] = &["md", "mdx", "rst"];
+/// Directories holding documentation when no generator config says otherwise
+const DOC_DIRS: &[&str] = &["docs", "doc", "documentation"];
+/// Pages larger than this are not indexed
+const MAX_PAGE_BYTES: u64 = 512 * 1024;
+/// How much a keyword in a section title outweighs one in its text
+const TITLE_MATCH_WEIGHT: usize = 5;
+
+/// Pages describing a project as a whole, in the order they are included
+const OVERVIEW_PAGES: &[&str] = &[
+    "README.md",
+    "README.rst",
+    "README",
+    "ARCHITECTURE.md",
+    "CONTRIBUTING.md",
+    "docs/ARCHITECTURE.md",
+    "docs/architecture.md",
+    "docs/overview.md",
+    "docs/README.md",
+    "docs/index.md",
+    "doc/README.md",
+    "doc/index.md",
+];
+/// Lines of a code block kept in a trimmed page
+const MAX_TRIMMED_CODE_LINES: usize = 6;
+
+/// Phrases of questions answered from the documentation rather than the code
+const DOCS_PHRASES: &[&str] = &[
+    "how do i",
+    "how can i",
+    "how to",
+    "how should i",
+    "where do i",
+    "documentation",
+    "docs",
+    "deploy",
+    "install",
+    "getting started",
+    "set up",
+    "setup",
+    "configure",
+    "upgrade",
+    "contribute",
+];
+
+/// Whether a command asks something the project's documentation answers
+pub fn wants_docs(command: &str) -> bool {
+    let command = command.to_lowercase();
+    DOCS_PHRASES.iter().any(|phrase| command.contains(phrase))
+}
+
+/// A heading of a documentation page and the text under it
+#[derive(Debug)]
+pub struct DocSection {
+    pub path: PathBuf,
+    /// First line, 1-based
+    pub line: usize,
+    pub title: String,
+    pub text: String,
+}
+
+/// The documentation of a project (a `docs/` directory, an MkDocs or Sphinx
+/// site, and the top-level README), split into sections by heading. It is
+/// kept apart from the code so documentation questions can be answered from
+/// it first.
+pub struct DocsIndex {
+    /// MkDocs or Sphinx, when a generator config was found
+    pub generator: Option<&'static str>,
+    pub sections: Vec<DocSection>,
+}
+
+impl DocsIndex {
+    /// Indexes the documentation under `root`; None when the project has none
+    pub fn build(root: &Path) -> Option<Self> {
+        let (generator, dirs) = detect(root);
+        let mut pages: Vec<PathBuf> = dirs
+            .iter()
+            .flat_map(|dir| project_files(dir))
+            .filter(|path| is_doc_page(path))
+            .collect();
+        pages.extend(
+            ["README.md", "README.rst"]
+                .iter()
+                .map(|name| root.join(name))
+                .filter(|path| path.exists()),
+        );
+        pages.sort();
+        pages.dedup();
+        if dirs.is_empty() && pages.is_empty() {
+            return None;
+        }
+
+        let sections = pages
+            .iter()
+            .filter(|path| std::fs::metadata(path).is_ok_and(|m| m.len() <= MAX_PAGE_BYTES))
+            .filter_map(|path| Some((path, std::fs::read_to_string(path).ok()?)))
+            .flat_map(|(path, content)| split_sections(path, &content))
+            .collect();
+        Some(Self { generator, sections })
+    }
+
+    /// Whether `path` is one of the indexed pages
+    pub fn contains(&self, path: &Path) -> bool {
+        self.sections.iter().any(|section| section.path == path)
+    }
+
+    /// Number of indexed pages
+    pub fn page_count(&self) -> usize {
+        let mut pages: Vec<&PathBuf> = self.sections.iter().map(|section| &section.path).collect();
+        pages.dedup();
+        pages.len()
+    }
+
+    /// Sections mentioning the keywords, best first
+    pub fn search(&self, keywords: &[String], limit: usize) -> Vec<&DocSection> {
+        let mut scored: Vec<(usize, &DocSection)> = self
+            .sections
+            .iter()
+            .map(|section| {
+                let title = section.title.to_lowercase();
+                let text = section.text.to_lowercase();
+                let score: usize = keywords
+                    .iter()
+                    .map(|k| title.matches(k.as_str()).count() * TITLE_MATCH_WEIGHT + text.matches(k.as_str()).count())
+                    .sum();
+                (score, section)
+            })
+            .filter(|(score, _)| *score > 0)
+            .collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.path.cmp(&b.1.path)).then(a.1.line.cmp(&b.1.line)));
+        scored.into_iter().take(limit).map(|(_, section)| section).collect()
+    }
+}
+
+/// The documentation generator and the directories holding the pages
+fn detect(root: &Path) -> (Option<&'static str>, Vec<PathBuf>) {
+    // MkDocs names its page directory in mkdocs.yml (`docs_dir`, default docs/)
+    for config in ["mkdocs.yml", "mkdocs.yaml"] {
+        let path = root.join(config);
+        if !path.exists() {
+            continue;
+        }
+        let docs_dir = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_yaml::from_str::<serde_yaml::Value>(&content).ok())
+            .and_then(|config| config.get("docs_dir").and_then(|dir| dir.as_str()).map(String::from))
+            .unwrap_or_else(|| "docs".to_string());
+        let dir = root.join(docs_dir);
+        return (Some("MkDocs"), if dir.is_dir() { vec![dir] } else { Vec::new() });
+    }
+
+    // Sphinx keeps conf.py in its source directory
+    for dir in ["docs/source", "doc/source", "docs", "doc", ""] {
+        let dir = if dir.is_empty() { root.to_path_buf() } else { root.join(dir) };
+        // A conf.py at the root is only Sphinx's when an index page sits next to it
+        if dir.join("conf.py").exists() && (dir != root || dir.join("index.rst").exists()) {
+            return (Some("Sphinx"), vec![dir]);
+        }
+    }
+
+    let dirs = DOC_DIRS.iter().map(|dir| root.join(dir)).filter(|dir| dir.is_dir()).collect();
+    (None, dirs)
+}
+
+fn is_doc_page(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| DOC_EXTENSIONS.contains(&ext))
+}
+
+/// Splits a page at its headings: `#` lines in Markdown, and lines
+/// underlined with `=`, `-`, `~`, `^` or `*` in reStructuredText
+fn split_sections(path: &Path, content: &str) -> Vec<DocSection> {
+    let lines: Vec<&str> = content.lines().collect();
+    let is_rst = path.extension().is_some_and(|ext| ext == "rst");
+    let mut headings = Vec::new();
+    let mut in_fence = false;
+    for (idx, line) in lines.iter().enumerate() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence {
+            continue;
+        }
+        if !is_rst {
+            if let Some(title) = line.strip_prefix('#').map(|rest| rest.trim_start_matches('#')) {
+                if title.starts_with(' ') {
+                    headings.push((idx, title.trim().to_string()));
+                }
+            }
+        } else if let Some(next) = lines.get(idx + 1) {
+            let underline = next.trim_end();
+            let is_underline = underline.len() >= line.trim().len()
+                && !line.trim().is_empty()
+                && underline.chars().next().is_some_and(|c| "=-~^*".contains(c))
+                && underline.chars().all(|c| underline.starts_with(c));
+            if is_underline {
+                headings.push((idx, line.trim().to_string()));
+            }
+        }
+    }
+
+    // Text before the first heading belongs to a section named after the file
+    if headings.first().is_none_or(|(idx, _)| *idx > 0) {
+        let name = path.file_stem().and_then(|n| n.to_str()).unwrap_or("").to_string();
+        headings.insert(0, (0, name));
+    }
+
+    headings
+        .iter()
+        .enumerate()
+        .map(|(i, (start, title))| {
+            let end = headings.get(i + 1).map_or(lines.len(), |(next, _)| *next);
+            DocSection {
+                path: path.to_path_buf(),
+                line: start + 1,
+                title: title.clone(),
+                text: lines[*start..end].join("\n"),
+            }
+        })
+        .filter(|section| !section.text.trim().is_empty())
+        .collect()
+}
+
+/// The README, CONTRIBUTING, architecture and docs overview pages of the
+/// project at `root` that exist. File names are matched case-insensitively.
+pub fn overview_pages(root: &Path) -> Vec<PathBuf> {
+    let mut pages: Vec<PathBuf> = Vec::new();
+    for name in OVERVIEW_PAGES {
+        let wanted = root.join(name);
+        let dir = wanted.parent().unwrap_or(root);
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        let found = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).find(|path| {
+            path.is_file()
+                && path.file_name().map(|n| n.to_string_lossy().to_lowercase())
+                    == wanted.file_name().map(|n| n.to_string_lossy().to_lowercase())
+        });
+        if let Some(path) = found {
+            if !pages.contains(&path) {
+                pages.push(path);
+            }
+        }
+    }
+    pages
+}
+
+/// The lines of a page worth reading for an overview, with their 1-based
+/// numbers: badges, images, HTML comments and tags, and runs of blank lines
+/// are left out, and long code blocks are cut short
+pub fn trimmed_lines(content: &str) -> Vec<(usize, &str)> {
+    let mut lines = Vec::new();
+    let mut in_comment = false;
+    // Lines of the current code block, None outside of one
+    let mut code_lines: Option<usize> = None;
+    let mut blank = true;
+    for (idx, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            code_lines = match code_lines {
+                Some(_) => None,
+                None => Some(0),
+            };
+            lines.push((idx + 1, line));
+            blank = false;
+            continue;
+        }
+        if let Some(count) = code_lines.as_mut() {
+            *count += 1;
+            if *count <= MAX_TRIMMED_CODE_LINES {
+                lines.push((idx + 1, line));
+            } else if *count == MAX_TRIMMED_CODE_LINES + 1 {
+                lines.push((idx + 1, "..."));
+            }
+            continue;
+        }
+        if in_comment || trimmed.starts_with("<!--") {
+            in_comment = !trimmed.contains("-->");
+            continue;
+        }
+        // Badges, images and raw HTML such as centered logos
+        let is_markup = trimmed.starts_with("[![")
+            || trimmed.starts_with("![")
+            || trimmed.starts_with(".. image::")
+            || trimmed.starts_with(".. |")
+            || (trimmed.starts_with('<') && trimmed.ends_with('>'));
+        if is_markup {
+            continue;
+        }
+        if trimmed.is_empty() {
+            if !blank {
+                lines.push((idx + 1, ""));
+            }
+            blank = true;
+            continue;
+        }
+        lines.push((idx + 1, line));
+        blank = false;
+    }
+    while lines.last().is_some_and(|(_, line)| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
@@ -1,6 +1,7 @@
 use anyhow::{Result, Context};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::io::Write;
+use std::path::Path;
 
 pub struct FileEditor;
 
@@ -21,47 +22,54 @@ impl FileEditor {
             .with_context(|| format!("Failed to write file: {}", path.display()))
     }
     
-    pub fn find_and_replace(
-        path: &Path,
-        pattern: &str,
-        replacement: &str,
-        use_regex: bool
-    ) -> Result<(String, usize)> {
-        let content = Self::read_file(path)?;
-        
-        if use_regex {
-            let regex = regex::Regex::new(pattern)
-                .with_context(|| format!("Invalid regex pattern: {}", pattern))?;
-            
-            let new_content = regex.replace_all(&content, replacement)
-                .into_owned();
-            let count = regex.find_iter(&content).count();
-            
-            Ok((new_content, count))
-        } else {
-            let count = content.matches(pattern).count();
-            let new_content = content.replace(pattern, replacement);
-            
-            Ok((new_content, count))
+    /// Writes a new file, creating its directory. What happens when `path`
+    /// already exists depends on `if_exists`; returns false when it was left
+    /// as it was.
+    pub fn create_file(path: &Path, content: &str, if_exists: IfExists) -> Result<bool> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        if if_exists == IfExists::Overwrite {
+            Self::write_file(path, content)?;
+            return Ok(true);
+        }
+        // create_new fails on an existing file without a race between checking and writing
+        let file = fs::OpenOptions::new().write(true).create_new(true).open(path);
+        match file {
+            Ok(mut file) => {
+                file.write_all(content.as_bytes())
+                    .with_context(|| format!("Failed to write file: {}", path.display()))?;
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => match if_exists {
+                IfExists::Skip => Ok(false),
+                _ => Err(anyhow::anyhow!("{} already exists", path.display())),
+            },
+            Err(e) => Err(e).with_context(|| format!("Failed to create file: {}", path.display())),
         }
     }
-    
+
     pub fn apply_edit(path: &Path, edit: &FileEdit) -> Result<()> {
         let content = Self::read_file(path)?;
-        
-        let new_content = match edit {
+        let new_content = Self::edited(&content, edit)?;
+        Self::write_file(path, &new_content)
+    }
+
+    /// `content` with `edit` applied, without touching any file
+    pub fn edited(content: &str, edit: &FileEdit) -> Result<String> {
+        match edit {
             FileEdit::Replace { start_line, end_line, new_text } => {
-                Self::replace_lines(&content, *start_line, *end_line, new_text)
+                Self::replace_lines(content, *start_line, *end_line, new_text)
             },
             FileEdit::Insert { line, text } => {
-                Self::insert_at_line(&content, *line, text)
+                Self::insert_at_line(content, *line, text)
             },
             FileEdit::Delete { start_line, end_line } => {
-                Self::delete_lines(&content, *start_line, *end_line)
+                Self::delete_lines(content, *start_line, *end_line)
             },
-        }?;
-        
-        Self::write_file(path, &new_content)
+        }
     }
     
     fn replace_lines(content: &str, start_line: usize, end_line: usize, new_text: &str) -> Result<String> {
@@ -151,6 +159,29 @@ impl FileEditor {
     }
 }
 
+/// What creating a file does when it already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IfExists {
+    /// Fail the action
+    #[default]
+    Fail,
+    /// Leave the existing file as it is
+    Skip,
+    /// Replace its content
+    Overwrite,
+}
+
+impl IfExists {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "fail" | "error" => Ok(Self::Fail),
+            "skip" | "keep" => Ok(Self::Skip),
+            "overwrite" | "replace" => Ok(Self::Overwrite),
+            _ => Err(anyhow::anyhow!("Unknown if_exists '{}' (expected fail, skip or overwrite)", value)),
+        }
+    }
+}
+
 pub enum FileEdit {
     Replace {
         start_line: usize,
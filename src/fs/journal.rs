@@ -0,0 +1,114 @@
+use crate::fs::storage::Storage;
+use anyhow::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Entries kept in the journal; the oldest are dropped past this
+const MAX_ENTRIES: usize = 1000;
+/// Hex digits of the action hash used as its id
+const ID_LEN: usize = 12;
+
+/// A file change an action made
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Hash of the action's type and details, the same for a repeated action
+    pub id: String,
+    /// RFC 3339 time the change was made
+    pub time: String,
+    pub action: String,
+    pub path: PathBuf,
+    /// SHA-256 of the file before and after the change; None when it didn't
+    /// exist
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// File changes made by actions, in `.caulk/journal.jsonl`. An action whose
+/// id is journaled and whose file is still as it left it is a repeat (a
+/// retried request, a duplicated agent step) and is not applied again.
+pub struct EditJournal {
+    path: PathBuf,
+    storage: Storage,
+    entries: Mutex<Vec<JournalEntry>>,
+}
+
+impl EditJournal {
+    pub fn new(project_root: &Path, storage: Storage) -> Self {
+        let path = Self::path(project_root);
+        let entries = match storage.read(&path) {
+            Ok(content) => content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect(),
+            Err(_) => Vec::new(),
+        };
+        Self { path, storage, entries: Mutex::new(entries) }
+    }
+
+    /// File the journal of the project at `project_root` is saved in
+    pub fn path(project_root: &Path) -> PathBuf {
+        project_root.join(".caulk").join("journal.jsonl")
+    }
+
+    /// Id of an action: a hash of its type and details, so proposing the
+    /// same change again yields the same id
+    pub fn action_id(action: &Value) -> String {
+        let canonical = json!({ "action": action.get("action"), "details": action.get("details") });
+        let digest = format!("{:x}", Sha256::digest(canonical.to_string().as_bytes()));
+        digest[..ID_LEN].to_string()
+    }
+
+    /// The journaled change of action `id` to `path` when the file is still
+    /// as that change left it (`current` is its content, None when missing)
+    pub fn applied(&self, id: &str, path: &Path, current: Option<&str>) -> Option<JournalEntry> {
+        let current = current.map(content_hash);
+        let entries = self.entries.lock().ok()?;
+        entries
+            .iter()
+            .rev()
+            .find(|entry| entry.id == id && entry.path == path)
+            .filter(|entry| entry.after == current)
+            .cloned()
+    }
+
+    /// Journals a change of `path` by action `id` from `before` to `after`
+    pub fn record(&self, id: &str, action: &str, path: &Path, before: Option<&str>, after: Option<&str>) {
+        let entry = JournalEntry {
+            id: id.to_string(),
+            time: chrono::Local::now().to_rfc3339(),
+            action: action.to_string(),
+            path: path.to_path_buf(),
+            before: before.map(content_hash),
+            after: after.map(content_hash),
+        };
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        entries.push(entry);
+        let excess = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..excess);
+        if let Err(e) = self.save(&entries) {
+            warn!("Failed to save the edit journal {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Rewrites the whole file so it can be encrypted
+    fn save(&self, entries: &[JournalEntry]) -> Result<()> {
+        let content: String = entries
+            .iter()
+            .filter_map(|entry| serde_json::to_string(entry).ok())
+            .map(|line| format!("{}\n", line))
+            .collect();
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        self.storage.write(&self.path, &content)
+    }
+}
+
+/// SHA-256 of file content, hex-encoded
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
@@ -1,2 +1,97 @@
+pub mod docs;
 pub mod edit;
+pub mod journal;
+pub mod relocate;
+pub mod retention;
 pub mod search;
+pub mod storage;
+pub mod undo;
+
+use crate::config::ScanConfig;
+use ignore::overrides::OverrideBuilder;
+use ignore::{DirEntry, WalkBuilder, WalkState};
+use log::warn;
+use once_cell::sync::OnceCell;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Per-directory file in .gitignore syntax listing paths to leave out of
+/// project analysis and context
+pub const IGNORE_FILE: &str = ".caulkignore";
+
+/// `[scan]` settings every walker of the project uses
+static SCAN: OnceCell<ScanConfig> = OnceCell::new();
+
+/// Applies the `[scan]` settings to every walk of the project. Walks before
+/// this, or without it, use the defaults.
+pub fn init_scan(config: &ScanConfig) {
+    let _ = SCAN.set(config.clone());
+}
+
+/// The `[scan]` settings in effect
+pub fn scan_config() -> &'static ScanConfig {
+    SCAN.get_or_init(ScanConfig::default)
+}
+
+/// Walker over `root` that skips hidden files and paths matched by
+/// .gitignore, `.caulkignore` or `[scan] ignore`, down to `[scan] max_depth`
+pub fn project_walker(root: &Path) -> WalkBuilder {
+    let scan = scan_config();
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .add_custom_ignore_filename(IGNORE_FILE)
+        .max_depth(Some(scan.max_depth))
+        .follow_links(scan.follow_symlinks);
+    if !scan.ignore.is_empty() {
+        // Override globs select files; a leading `!` makes them skip files instead
+        let mut overrides = OverrideBuilder::new(root);
+        for pattern in &scan.ignore {
+            if let Err(e) = overrides.add(&format!("!{}", pattern)) {
+                warn!("Invalid [scan] ignore pattern '{}': {}", pattern, e);
+            }
+        }
+        match overrides.build() {
+            Ok(overrides) => {
+                builder.overrides(overrides);
+            }
+            Err(e) => warn!("Invalid [scan] ignore patterns: {}", e),
+        }
+    }
+    builder
+}
+
+/// Every entry the walker yields, walking directories on all cores, sorted
+/// by path so results don't depend on thread scheduling. The walk stops
+/// after `[scan] max_files` files.
+pub fn walk_parallel(builder: &WalkBuilder) -> Vec<DirEntry> {
+    let max_files = match scan_config().max_files {
+        0 => usize::MAX,
+        max_files => max_files,
+    };
+    let files = AtomicUsize::new(0);
+    let entries = Mutex::new(Vec::new());
+    builder.build_parallel().run(|| {
+        Box::new(|entry| {
+            if let Ok(entry) = entry {
+                if entry.file_type().is_some_and(|t| t.is_file()) && files.fetch_add(1, Ordering::Relaxed) >= max_files {
+                    return WalkState::Quit;
+                }
+                entries.lock().unwrap().push(entry);
+            }
+            WalkState::Continue
+        })
+    });
+    let mut entries = entries.into_inner().unwrap();
+    entries.sort_by(|a, b| a.path().cmp(b.path()));
+    entries
+}
+
+/// Files under `root` that `project_walker` doesn't skip, found in parallel
+pub fn project_files(root: &Path) -> Vec<PathBuf> {
+    walk_parallel(&project_walker(root))
+        .into_iter()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .map(DirEntry::into_path)
+        .collect()
+}
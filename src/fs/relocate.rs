@@ -0,0 +1,1376 @@
+use anyhow::{anyhow, Context, Result};
+use ignore::WalkBuilder;
+use log::debug;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use tree_sitter::Node;
+
+use crate::analysis::imports::{crate_src_dir, relative_path};
+use crate::analysis::syntax::{self, Grammar};
+use crate::llm::vendor::VENDOR_DIRS;
+
+/// Extensions a JavaScript or TypeScript import may leave out, in resolution order
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs", "mts", "cts"];
+/// Files whose `#include "..."` lines are followed
+const C_EXTENSIONS: &[&str] = &["c", "h", "cc", "cpp", "cxx", "hpp", "hh", "hxx"];
+/// Files whose `include`/`require` paths are followed
+const PHP_EXTENSIONS: &[&str] = &["php", "inc", "module", "install", "theme"];
+/// Functions whose string argument is a module specifier
+const JS_LOADERS: &[&str] = &["require", "import", "require.resolve", "jest.mock", "vi.mock"];
+/// Files larger than this are neither parsed nor rewritten
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// What `move_file` moved and rewrote, with paths relative to the project root
+#[derive(Debug, Default)]
+pub struct MoveReport {
+    /// (from, to) pairs, the requested file first. Rust modules take their
+    /// submodule directory along.
+    pub moved: Vec<(PathBuf, PathBuf)>,
+    /// Files whose references were updated, at their new location
+    pub updated: Vec<PathBuf>,
+    /// Content of each updated file before its references were rewritten
+    pub originals: HashMap<PathBuf, String>,
+    /// References that need a manual fix
+    pub warnings: Vec<String>,
+}
+
+/// Moves `from` to `to` (into `to` when it is a directory) and rewrites what
+/// refers to it across the project: Rust `mod` declarations and `crate::`/
+/// `super::` paths, Python imports and dotted module strings, relative
+/// JavaScript/TypeScript specifiers and PHP/C includes. The moved file's own
+/// relative references are rewritten for its new location.
+pub fn move_file(root: &Path, from: &Path, to: &Path) -> Result<MoveReport> {
+    let root = normalize(&root.canonicalize().unwrap_or_else(|_| root.to_path_buf()));
+    let from = normalize(&root.join(from));
+    let mut to = normalize(&root.join(to));
+
+    if !from.is_file() {
+        return Err(anyhow!("{} is not a file", from.display()));
+    }
+    if to.is_dir() {
+        to = to.join(from.file_name().unwrap_or_default());
+    }
+    if to.exists() {
+        return Err(anyhow!("{} already exists", to.display()));
+    }
+    if !from.starts_with(&root) || !to.starts_with(&root) {
+        return Err(anyhow!("Both paths must be inside the project ({})", root.display()));
+    }
+
+    let is_rust = from.extension().is_some_and(|e| e == "rs");
+    if is_rust && crate_src_dir(&from).is_some() && crate_src_dir(&from) != crate_src_dir(&to) {
+        return Err(anyhow!("Rust modules can only be moved within the src directory of their crate"));
+    }
+
+    let mut moves = Moves { pairs: vec![(from.clone(), to.clone())] };
+    let is_module_root = matches!(from.file_stem().and_then(|s| s.to_str()), Some("mod" | "lib" | "main"));
+    if is_rust && !is_module_root {
+        let submodules = from.with_extension("");
+        if submodules.is_dir() {
+            let target = to.with_extension("");
+            if target.starts_with(&submodules) {
+                return Err(anyhow!("Cannot move a module into its own submodule directory"));
+            }
+            if target.exists() {
+                return Err(anyhow!("{} already exists", target.display()));
+            }
+            moves.pairs.push((submodules, target));
+        }
+    }
+
+    let project = Project::scan(&root)?;
+    let mut report = MoveReport::default();
+    let edits = project.edits(&moves, &mut report.warnings);
+
+    for (from, to) in &moves.pairs {
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        if let Err(e) = std::fs::rename(from, to) {
+            if !from.is_file() {
+                return Err(e).with_context(|| format!("Failed to move {} to {}", from.display(), to.display()));
+            }
+            // Across file systems
+            std::fs::copy(from, to).with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+            std::fs::remove_file(from).with_context(|| format!("Failed to remove {}", from.display()))?;
+        }
+        report.moved.push((relative_to(&root, from), relative_to(&root, to)));
+    }
+
+    let mut edited: Vec<(&PathBuf, &Vec<Edit>)> = edits.iter().filter(|(_, e)| !e.is_empty()).collect();
+    edited.sort_by(|a, b| a.0.cmp(b.0));
+    for (path, file_edits) in edited {
+        let content = match project.files.get(path) {
+            Some(file) => &file.content,
+            None => continue,
+        };
+        let updated = apply_edits(content, file_edits, path, &mut report.warnings);
+        if &updated == content {
+            continue;
+        }
+        let new_path = moves.new_path(path);
+        std::fs::write(&new_path, updated).with_context(|| format!("Failed to write {}", new_path.display()))?;
+        report.originals.insert(relative_to(&root, &new_path), content.clone());
+        report.updated.push(relative_to(&root, &new_path));
+    }
+
+    Ok(report)
+}
+
+/// Project files each source file refers to through imports, `mod`
+/// declarations and includes, as paths relative to `root`. A package
+/// imported as a whole appears as its directory.
+pub fn dependency_graph(root: &Path) -> Result<BTreeMap<PathBuf, BTreeSet<PathBuf>>> {
+    let root = normalize(&root.canonicalize().unwrap_or_else(|_| root.to_path_buf()));
+    let project = Project::scan(&root)?;
+
+    let mut graph = BTreeMap::new();
+    for (path, file) in &project.files {
+        let targets: BTreeSet<PathBuf> = file
+            .references
+            .iter()
+            .filter(|reference| &reference.target != path)
+            .map(|reference| relative_to(&root, &reference.target))
+            .collect();
+        graph.insert(relative_to(&root, path), targets);
+    }
+    Ok(graph)
+}
+
+/// Project files each of `sources` refers to directly, like
+/// `dependency_graph` but without parsing the rest of the project. Paths
+/// are relative to `root`; packages imported as a whole are left out.
+pub fn direct_imports(root: &Path, sources: &[PathBuf]) -> Result<BTreeMap<PathBuf, BTreeSet<PathBuf>>> {
+    let root = normalize(&root.canonicalize().unwrap_or_else(|_| root.to_path_buf()));
+    let sources: HashSet<PathBuf> = sources
+        .iter()
+        .map(|source| normalize(&root.join(source).canonicalize().unwrap_or_else(|_| root.join(source))))
+        .collect();
+    let project = Project::scan_only(&root, Some(&sources))?;
+
+    let mut imports = BTreeMap::new();
+    for (path, file) in &project.files {
+        let targets: BTreeSet<PathBuf> = file
+            .references
+            .iter()
+            .filter(|reference| &reference.target != path && reference.target.is_file())
+            .map(|reference| relative_to(&root, &reference.target))
+            .collect();
+        imports.insert(relative_to(&root, path), targets);
+    }
+    Ok(imports)
+}
+
+/// Where files end up, for both the moved files and the rest
+struct Moves {
+    pairs: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Moves {
+    fn new_path(&self, path: &Path) -> PathBuf {
+        for (from, to) in &self.pairs {
+            if let Ok(rest) = path.strip_prefix(from) {
+                return if rest.as_os_str().is_empty() { to.clone() } else { to.join(rest) };
+            }
+        }
+        path.to_path_buf()
+    }
+
+    fn is_moved(&self, path: &Path) -> bool {
+        self.pairs.iter().any(|(from, _)| path.starts_with(from))
+    }
+}
+
+/// A replacement in one file; insertions have an empty range
+struct Edit {
+    range: Range<usize>,
+    text: String,
+}
+
+/// A path written in a source file that resolves to another project file or
+/// package directory: one edge of the project's dependency graph
+struct Reference {
+    /// Bytes of the path text
+    range: Range<usize>,
+    target: PathBuf,
+    kind: ReferenceKind,
+}
+
+enum ReferenceKind {
+    /// `crate::a::b`, `my_crate::a::b`, `super::b` or a leading `b` naming a Rust module
+    RustPath(RustStart),
+    /// `mod b;` in the parent module. `item` covers its lines and attributes,
+    /// `name` is where the name sits within `declaration`.
+    RustModDecl { item: Range<usize>, declaration: String, name: Range<usize> },
+    /// The `b` of `b` or `b::{X, Y}` in `use crate::a::{b, c};`, where
+    /// `parent` is the module file of the list's prefix
+    RustUseItem {
+        item: Range<usize>,
+        statement: Range<usize>,
+        visibility: String,
+        only_item: bool,
+        start: RustStart,
+        parent: Option<PathBuf>,
+    },
+    /// `a.b` in an import statement, a module attribute chain or a string
+    PythonModule { relative: bool },
+    /// `b` in `from a import b`; `module` is the package it is imported from
+    PythonFromItem { item: Range<usize>, statement: Range<usize>, only_item: bool, alias: Option<String>, module: PathBuf },
+    /// A name bound to a module by an import without an alias, where it is used
+    BoundName,
+    /// A relative JavaScript/TypeScript specifier
+    Specifier { extension: Option<String>, index: bool },
+    /// A PHP include or C `#include` path
+    Include { from_root: bool, leading_slash: bool },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum RustStart {
+    Crate,
+    /// The package name, from integration tests, examples and binaries
+    Named,
+    /// `self::` or `super::`
+    Relative,
+    /// A name brought into scope by `use`
+    Bound,
+    /// A child module named from its parent without a prefix
+    Child,
+}
+
+struct SourceFile {
+    content: String,
+    references: Vec<Reference>,
+    /// Where a new `mod` declaration goes in a Rust module file
+    mod_insert_at: Option<usize>,
+}
+
+/// The project's source files and the references between them
+struct Project {
+    root: PathBuf,
+    files: HashMap<PathBuf, SourceFile>,
+}
+
+impl Project {
+    fn scan(root: &Path) -> Result<Self> {
+        Self::scan_only(root, None)
+    }
+
+    /// Like `scan`, but only reads the references of `sources` when given;
+    /// the rest of the project is only listed to resolve them
+    fn scan_only(root: &Path, sources: Option<&HashSet<PathBuf>>) -> Result<Self> {
+        let paths: Vec<PathBuf> = WalkBuilder::new(root)
+            .filter_entry(|e| !VENDOR_DIRS.contains(&e.file_name().to_string_lossy().as_ref()))
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.metadata().is_ok_and(|m| m.is_file() && m.len() <= MAX_FILE_BYTES))
+            .map(|e| normalize(e.path()))
+            .filter(|p| reference_language(p).is_some())
+            .collect();
+
+        let rust_crates = RustCrates::new(&paths);
+        let python_modules: HashMap<String, PathBuf> = paths
+            .iter()
+            .filter(|p| p.extension().is_some_and(|e| e == "py"))
+            .filter_map(|p| python_module(root, p).map(|m| (m, p.clone())))
+            .collect();
+        let known: HashSet<&PathBuf> = paths.iter().collect();
+
+        let mut files = HashMap::new();
+        for path in paths.iter().filter(|p| sources.is_none_or(|sources| sources.contains(*p))) {
+            let content = match std::fs::read_to_string(path) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            let mut file = SourceFile { content, references: Vec::new(), mod_insert_at: None };
+            let result = match reference_language(path) {
+                Some(Language::Rust) => rust_references(path, &mut file, &rust_crates),
+                Some(Language::Python) => python_references(root, path, &mut file, &python_modules),
+                Some(Language::Js(grammar)) => js_references(path, grammar, &mut file, &known),
+                Some(Language::Include) => {
+                    include_references(root, path, &mut file, &known);
+                    Ok(())
+                }
+                None => Ok(()),
+            };
+            if let Err(e) = result {
+                debug!("Skipping references in {}: {}", path.display(), e);
+            }
+            files.insert(path.clone(), file);
+        }
+
+        Ok(Self { root: root.to_path_buf(), files })
+    }
+
+    /// Edits per file (by current path) that keep every reference to or from
+    /// a moved file pointing at the same target
+    fn edits(&self, moves: &Moves, warnings: &mut Vec<String>) -> HashMap<PathBuf, Vec<Edit>> {
+        let mut edits: HashMap<PathBuf, Vec<Edit>> = HashMap::new();
+        // The module file of each Rust module path after the move, by current path
+        let mut new_modules: HashMap<(PathBuf, Vec<String>), PathBuf> = HashMap::new();
+        for path in self.files.keys().filter(|p| p.extension().is_some_and(|e| e == "rs")) {
+            let new_path = moves.new_path(path);
+            let key = match crate_src_dir(&new_path).and_then(|src| Some((src.clone(), rust_module(&src, &new_path)?))) {
+                Some(key) => key,
+                None => continue,
+            };
+            // lib.rs rather than main.rs declares the crate's modules
+            if !new_modules.contains_key(&key) || path.ends_with("lib.rs") {
+                new_modules.insert(key, path.clone());
+            }
+        }
+
+        for (path, file) in &self.files {
+            let source_moved = moves.is_moved(path);
+            let new_source = moves.new_path(path);
+            for reference in &file.references {
+                if !source_moved && !moves.is_moved(&reference.target) {
+                    continue;
+                }
+                let new_target = moves.new_path(&reference.target);
+                let original = &file.content[reference.range.clone()];
+                let mut push = |file: &Path, range: Range<usize>, text: String| {
+                    edits.entry(file.to_path_buf()).or_default().push(Edit { range, text });
+                };
+
+                match &reference.kind {
+                    ReferenceKind::RustPath(start) => {
+                        match render_rust_path(*start, &new_source, &new_target) {
+                            Some(text) => push(path, reference.range.clone(), text),
+                            None => warnings.push(format!(
+                                "{}: `{}` could not be updated",
+                                relative_to(&self.root, path).display(),
+                                original
+                            )),
+                        }
+                    }
+                    ReferenceKind::RustModDecl { item, declaration, name } => {
+                        let stem = file_module_name(&new_target);
+                        let new_parent = crate_src_dir(&new_target).and_then(|src| {
+                            let mut module = rust_module(&src, &new_target)?;
+                            module.pop();
+                            new_modules.get(&(src, module)).cloned()
+                        });
+                        if module_parent_of(&new_source, &new_target) {
+                            push(path, reference.range.clone(), stem);
+                            continue;
+                        }
+                        match new_parent.and_then(|parent| Some((self.files.get(&parent)?, parent))) {
+                            Some((parent_file, parent)) => {
+                                push(path, item.clone(), String::new());
+                                let mut declaration = declaration.clone();
+                                declaration.replace_range(name.clone(), &stem);
+                                let at = parent_file.mod_insert_at.unwrap_or(0);
+                                let separator = if parent_file.content[..at].ends_with('\n') || at == 0 { "" } else { "\n" };
+                                push(&parent, at..at, format!("{}{}\n", separator, declaration.trim_end()));
+                            }
+                            None => {
+                                push(path, item.clone(), String::new());
+                                warnings.push(format!(
+                                    "Declare `mod {};` in the parent module of {}",
+                                    stem,
+                                    relative_to(&self.root, &new_target).display()
+                                ));
+                            }
+                        }
+                    }
+                    ReferenceKind::RustUseItem { item, statement, visibility, only_item, start, parent } => {
+                        let stem = file_module_name(&new_target);
+                        let same_parent = parent.as_ref().is_some_and(|parent| {
+                            module_parent_of(&moves.new_path(parent), &new_target)
+                        });
+                        if same_parent {
+                            push(path, reference.range.clone(), stem);
+                            continue;
+                        }
+                        let start = if *start == RustStart::Named { RustStart::Named } else { RustStart::Crate };
+                        let absolute = match render_rust_path(start, &new_source, &new_target) {
+                            Some(absolute) => absolute,
+                            None => {
+                                warnings.push(format!("{}: `{}` could not be updated", relative_to(&self.root, path).display(), original));
+                                continue;
+                            }
+                        };
+                        let rest = &file.content[reference.range.end..item.end];
+                        let statement_text = format!("{}use {}{};", visibility, absolute, rest);
+                        if *only_item {
+                            push(path, statement.clone(), statement_text);
+                        } else {
+                            push(path, list_item_removal(&file.content, item.clone()), String::new());
+                            let indent = line_indent(&file.content, statement.start);
+                            push(path, statement.end..statement.end, format!("\n{}{}", indent, statement_text));
+                        }
+                    }
+                    ReferenceKind::PythonModule { relative } => {
+                        match render_python_module(&self.root, *relative, &new_source, &new_target) {
+                            Some(text) => push(path, reference.range.clone(), text),
+                            None => warnings.push(format!("{}: `{}` could not be updated", relative_to(&self.root, path).display(), original)),
+                        }
+                    }
+                    ReferenceKind::PythonFromItem { item, statement, only_item, alias, module } => {
+                        let target_module = match python_module(&self.root, &new_target) {
+                            Some(module) => module,
+                            None => continue,
+                        };
+                        let (package, stem) = match target_module.rsplit_once('.') {
+                            Some((package, stem)) => (package.to_string(), stem.to_string()),
+                            None => (String::new(), target_module.clone()),
+                        };
+                        let new_module = python_module(&self.root, &moves.new_path(module)).unwrap_or_default();
+                        if new_module == package {
+                            push(path, reference.range.clone(), stem);
+                            continue;
+                        }
+                        let alias = alias.as_ref().map(|a| format!(" as {}", a)).unwrap_or_default();
+                        let statement_text = if package.is_empty() {
+                            format!("import {}{}", stem, alias)
+                        } else {
+                            format!("from {} import {}{}", package, stem, alias)
+                        };
+                        if *only_item {
+                            push(path, statement.clone(), statement_text);
+                        } else {
+                            push(path, list_item_removal(&file.content, item.clone()), String::new());
+                            let indent = line_indent(&file.content, statement.start);
+                            push(path, statement.end..statement.end, format!("\n{}{}", indent, statement_text));
+                        }
+                    }
+                    ReferenceKind::BoundName => push(path, reference.range.clone(), file_module_name(&new_target)),
+                    ReferenceKind::Specifier { extension, index } => {
+                        let from_dir = new_source.parent().unwrap_or(&self.root);
+                        push(path, reference.range.clone(), render_specifier(from_dir, &new_target, extension.as_deref(), *index));
+                    }
+                    ReferenceKind::Include { from_root, leading_slash } => {
+                        let base = if *from_root { self.root.as_path() } else { new_source.parent().unwrap_or(&self.root) };
+                        let mut text = relative_path(base, &new_target).to_string_lossy().replace('\\', "/");
+                        if *leading_slash {
+                            text.insert(0, '/');
+                        }
+                        push(path, reference.range.clone(), text);
+                    }
+                }
+            }
+        }
+
+        edits
+    }
+}
+
+/// Whether `module` is the parent module of the module file `child`
+fn module_parent_of(module: &Path, child: &Path) -> bool {
+    let src = match crate_src_dir(child) {
+        Some(src) if crate_src_dir(module).as_ref() == Some(&src) => src,
+        _ => return false,
+    };
+    match (rust_module(&src, module), rust_module(&src, child)) {
+        (Some(parent), Some(mut child)) => {
+            child.pop();
+            parent == child
+        }
+        _ => false,
+    }
+}
+
+enum Language {
+    Rust,
+    Python,
+    Js(Grammar),
+    Include,
+}
+
+fn reference_language(path: &Path) -> Option<Language> {
+    let extension = path.extension()?.to_str()?;
+    match Grammar::from_extension(extension) {
+        Some(Grammar::Rust) => Some(Language::Rust),
+        Some(Grammar::Python) => Some(Language::Python),
+        Some(grammar @ (Grammar::JavaScript | Grammar::TypeScript | Grammar::Tsx)) => Some(Language::Js(grammar)),
+        _ if PHP_EXTENSIONS.contains(&extension) || C_EXTENSIONS.contains(&extension) => Some(Language::Include),
+        _ => None,
+    }
+}
+
+/// Module files of each Cargo package, by `src` directory and module path
+struct RustCrates {
+    modules: HashMap<PathBuf, HashMap<Vec<String>, PathBuf>>,
+}
+
+impl RustCrates {
+    fn new(paths: &[PathBuf]) -> Self {
+        let mut modules: HashMap<PathBuf, HashMap<Vec<String>, PathBuf>> = HashMap::new();
+        for path in paths.iter().filter(|p| p.extension().is_some_and(|e| e == "rs")) {
+            if let Some(src) = crate_src_dir(path) {
+                if let Some(module) = rust_module(&src, path) {
+                    modules.entry(src).or_default().insert(module, path.clone());
+                }
+            }
+        }
+        Self { modules }
+    }
+}
+
+/// The module path of a file in a crate's `src`, e.g. `["a", "b"]` for
+/// `src/a/b.rs` or `src/a/b/mod.rs`. Binaries under `src/bin` are crates of their own.
+fn rust_module(src: &Path, path: &Path) -> Option<Vec<String>> {
+    let relative = path.strip_prefix(src).ok()?;
+    if relative.starts_with("bin") {
+        return None;
+    }
+    let mut segments: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    if matches!(segments.last().map(String::as_str), Some("mod" | "lib" | "main")) {
+        segments.pop();
+    }
+    Some(segments)
+}
+
+/// The name a file's module is declared under
+fn file_module_name(path: &Path) -> String {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    if matches!(stem.as_str(), "mod" | "__init__" | "index") {
+        path.parent().and_then(|p| p.file_name()).map(|n| n.to_string_lossy().to_string()).unwrap_or(stem)
+    } else {
+        stem
+    }
+}
+
+fn package_name(src: &Path) -> Option<String> {
+    let manifest = std::fs::read_to_string(src.parent()?.join("Cargo.toml")).ok()?;
+    let manifest: toml::Value = toml::from_str(&manifest).ok()?;
+    Some(manifest.get("package")?.get("name")?.as_str()?.replace('-', "_"))
+}
+
+/// The package directory of a file outside `src` (tests, examples, benches)
+fn package_src_dir(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|dir| dir.join("Cargo.toml").exists())
+        .map(|dir| dir.join("src"))
+        .filter(|src| src.is_dir())
+}
+
+fn rust_references(path: &Path, file: &mut SourceFile, crates: &RustCrates) -> Result<()> {
+    let (src, file_module) = match crate_src_dir(path) {
+        Some(src) => {
+            let module = rust_module(&src, path);
+            (src, module)
+        }
+        None => match package_src_dir(path) {
+            Some(src) => (src, None),
+            None => return Ok(()),
+        },
+    };
+    let modules = match crates.modules.get(&src) {
+        Some(modules) => modules,
+        None => return Ok(()),
+    };
+    let tree = syntax::parse(Grammar::Rust, &file.content)?;
+    let mut walker = RustWalker {
+        source: &file.content,
+        file_module,
+        crate_name: package_name(&src),
+        modules,
+        bindings: HashMap::new(),
+        references: Vec::new(),
+    };
+
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let top_level: Vec<Node> = root.named_children(&mut cursor).collect();
+    let mut last_mod = None;
+    let mut last_use = None;
+    let mut header_end = 0;
+    for (i, node) in top_level.iter().enumerate() {
+        match node.kind() {
+            "mod_item" if node.child_by_field_name("body").is_none() => {
+                walker.mod_declaration(*node, &top_level[..i]);
+                last_mod = Some(line_end(&file.content, node.end_byte()));
+            }
+            "use_declaration" => last_use = Some(line_end(&file.content, node.end_byte())),
+            "inner_attribute_item" | "line_comment" | "block_comment" if last_use.is_none() && last_mod.is_none() => {
+                let text = &file.content[node.byte_range()];
+                if text.starts_with("#!") || text.starts_with("//!") || text.starts_with("/*!") {
+                    header_end = line_end(&file.content, node.end_byte());
+                }
+            }
+            _ => {}
+        }
+    }
+    file.mod_insert_at = Some(last_mod.or(last_use).unwrap_or(header_end));
+
+    // Bindings from `use` first, so paths before the `use` resolve too
+    let mut uses = Vec::new();
+    collect_kind(root, "use_declaration", &mut uses);
+    for node in &uses {
+        walker.use_declaration(*node);
+    }
+    walker.visit(root);
+    file.references = walker.references;
+    Ok(())
+}
+
+struct RustWalker<'a> {
+    source: &'a str,
+    /// None for files outside the crate's module tree
+    file_module: Option<Vec<String>>,
+    crate_name: Option<String>,
+    modules: &'a HashMap<Vec<String>, PathBuf>,
+    /// Names bound to modules by `use` without an alias
+    bindings: HashMap<String, Vec<String>>,
+    references: Vec<Reference>,
+}
+
+impl<'a> RustWalker<'a> {
+    fn text(&self, node: Node) -> &'a str {
+        &self.source[node.byte_range()]
+    }
+
+    fn segments(&self, node: Node) -> Option<Vec<String>> {
+        let text: String = self.text(node).chars().filter(|c| !c.is_whitespace()).collect();
+        let segments: Vec<String> = text.split("::").map(String::from).collect();
+        if segments.iter().all(|s| !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_')) {
+            Some(segments)
+        } else {
+            None
+        }
+    }
+
+    /// The absolute module path a path starting with `crate`, `self`, `super`
+    /// or the package name points at
+    fn resolve(&self, segments: &[String]) -> Option<(Vec<String>, RustStart)> {
+        let first = segments.first()?.as_str();
+        match first {
+            "crate" => {
+                self.file_module.as_ref()?;
+                Some((segments[1..].to_vec(), RustStart::Crate))
+            }
+            "self" | "super" => {
+                let mut module = self.file_module.clone()?;
+                let mut rest = &segments[1..];
+                if first == "super" {
+                    module.pop()?;
+                    while rest.first().map(String::as_str) == Some("super") {
+                        module.pop()?;
+                        rest = &rest[1..];
+                    }
+                }
+                module.extend(rest.iter().cloned());
+                Some((module, RustStart::Relative))
+            }
+            _ if Some(first) == self.crate_name.as_deref() => Some((segments[1..].to_vec(), RustStart::Named)),
+            _ => None,
+        }
+    }
+
+    /// The module a leading bare name refers to: a `use` binding or a child module
+    fn resolve_bare(&self, name: &str) -> Option<(Vec<String>, RustStart)> {
+        if let Some(module) = self.bindings.get(name) {
+            return Some((module.clone(), RustStart::Bound));
+        }
+        let mut module = self.file_module.clone()?;
+        module.push(name.to_string());
+        if self.modules.contains_key(&module) {
+            Some((module, RustStart::Child))
+        } else {
+            None
+        }
+    }
+
+    /// Records a reference for the longest prefix of the path at `node` that
+    /// names a module, or its leading name when that is a bare module name.
+    /// Returns the module the whole path names, if it is one.
+    fn path(&mut self, node: Node) -> Option<Vec<String>> {
+        let segments = self.segments(node)?;
+        let first = segments[0].as_str();
+        let prefixed = matches!(first, "crate" | "self" | "super") || Some(first) == self.crate_name.as_deref();
+        if !prefixed {
+            let (module, start) = self.resolve_bare(first)?;
+            self.push(leading_segment(node).byte_range(), &module, start);
+            let mut full = module;
+            full.extend(segments[1..].iter().cloned());
+            return Some(full).filter(|m| self.modules.contains_key(m));
+        }
+
+        let mut current = node;
+        loop {
+            if let Some(segments) = self.segments(current) {
+                if let Some((module, start)) = self.resolve(&segments) {
+                    if self.modules.contains_key(&module) {
+                        self.push(current.byte_range(), &module, start);
+                        return Some(module).filter(|_| current == node);
+                    }
+                }
+            }
+            current = current.child_by_field_name("path")?;
+        }
+    }
+
+    fn push(&mut self, range: Range<usize>, module: &[String], start: RustStart) {
+        if let Some(target) = self.modules.get(module) {
+            self.references.push(Reference { range, target: target.clone(), kind: ReferenceKind::RustPath(start) });
+        }
+    }
+
+    fn mod_declaration(&mut self, node: Node, preceding: &[Node]) {
+        let name = match node.child_by_field_name("name") {
+            Some(name) => name,
+            None => return,
+        };
+        let mut module = match self.file_module.clone() {
+            Some(module) => module,
+            None => return,
+        };
+        module.push(self.text(name).to_string());
+        let target = match self.modules.get(&module) {
+            Some(target) => target.clone(),
+            None => return,
+        };
+
+        let mut start = node.start_byte();
+        for attribute in preceding.iter().rev().take_while(|n| n.kind() == "attribute_item") {
+            start = attribute.start_byte();
+        }
+        let start = line_start(self.source, start);
+        let mut end = line_end(self.source, node.end_byte());
+        let declaration = self.source[start..end].to_string();
+        // Don't leave a blank line at the top of the file or a double one
+        if (start == 0 || self.source[..start].ends_with("\n\n")) && self.source[end..].starts_with('\n') {
+            end += 1;
+        }
+        let name_range = (name.start_byte() - start)..(name.end_byte() - start);
+        self.references.push(Reference {
+            range: name.byte_range(),
+            target,
+            kind: ReferenceKind::RustModDecl { item: start..end, declaration, name: name_range },
+        });
+    }
+
+    fn use_declaration(&mut self, node: Node) {
+        let argument = match node.child_by_field_name("argument") {
+            Some(argument) => argument,
+            None => return,
+        };
+        let visibility = children_of_kind(node, "visibility_modifier")
+            .map(|v| format!("{} ", self.text(v)))
+            .unwrap_or_default();
+        let statement = node.byte_range();
+        self.use_tree(argument, None, &UseStatement { range: statement, visibility });
+    }
+
+    /// Walks a use tree. `prefix` is the module and start of the enclosing
+    /// `{...}` list, if inside one.
+    fn use_tree(&mut self, node: Node, prefix: Option<(Vec<String>, RustStart)>, statement: &UseStatement) {
+        match node.kind() {
+            "scoped_use_list" => {
+                let list = match node.child_by_field_name("list") {
+                    Some(list) => list,
+                    None => return,
+                };
+                let inner = match (node.child_by_field_name("path"), &prefix) {
+                    (Some(path), None) => {
+                        let segments = match self.segments(path) {
+                            Some(segments) => segments,
+                            None => return,
+                        };
+                        self.path(path);
+                        match self.resolve(&segments) {
+                            Some(resolved) => resolved,
+                            None => return,
+                        }
+                    }
+                    (Some(path), Some((module, start))) => {
+                        self.list_item(node, path, module, *start, statement);
+                        let mut module = module.clone();
+                        module.extend(self.segments(path).unwrap_or_default());
+                        (module, *start)
+                    }
+                    (None, _) => return,
+                };
+                let mut cursor = list.walk();
+                let items: Vec<Node> = list.named_children(&mut cursor).collect();
+                for item in items {
+                    self.use_tree(item, Some(inner.clone()), statement);
+                }
+            }
+            "use_as_clause" => {
+                if let Some(path) = node.child_by_field_name("path") {
+                    match &prefix {
+                        Some((module, start)) => {
+                            self.list_item(node, path, module, *start, statement);
+                        }
+                        None => {
+                            self.path(path);
+                        }
+                    }
+                }
+            }
+            "use_wildcard" => {
+                let path = node.named_child(0);
+                match (path, &prefix) {
+                    (Some(path), Some((module, start))) => {
+                        self.list_item(node, path, module, *start, statement);
+                    }
+                    (Some(path), None) => {
+                        self.path(path);
+                    }
+                    _ => {}
+                }
+            }
+            "scoped_identifier" | "identifier" => match &prefix {
+                Some((module, start)) => {
+                    let bound = self.list_item(node, node, module, *start, statement);
+                    if let Some(bound) = bound {
+                        self.bindings.insert(bound.last().cloned().unwrap_or_default(), bound);
+                    }
+                }
+                None => {
+                    if let Some(module) = self.path(node) {
+                        if let Some(name) = module.last() {
+                            self.bindings.insert(name.clone(), module.clone());
+                        }
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Records a reference for an item of a `use` list whose first segment is
+    /// a module under `prefix`. Returns the module the whole item names.
+    fn list_item(&mut self, item: Node, path: Node, prefix: &[String], start: RustStart, statement: &UseStatement) -> Option<Vec<String>> {
+        let leading = leading_segment(path);
+        let mut module = prefix.to_vec();
+        module.push(self.text(leading).to_string());
+        let target = self.modules.get(&module)?.clone();
+
+        let list = item.parent()?;
+        // Only a top-level list can be replaced by a whole statement
+        let only_item = list.named_child_count() == 1
+            && list.parent().and_then(|p| p.parent()).is_some_and(|d| d.kind() == "use_declaration");
+        self.references.push(Reference {
+            range: leading.byte_range(),
+            target,
+            kind: ReferenceKind::RustUseItem {
+                item: item.byte_range(),
+                statement: statement.range.clone(),
+                visibility: statement.visibility.clone(),
+                only_item,
+                start,
+                parent: self.modules.get(prefix).cloned(),
+            },
+        });
+
+        let mut full = prefix.to_vec();
+        full.extend(self.segments(path)?);
+        Some(full).filter(|m| self.modules.contains_key(m))
+    }
+
+    /// Paths outside `use` and `mod` items
+    fn visit(&mut self, node: Node) {
+        match node.kind() {
+            "use_declaration" | "mod_item" if node.child_by_field_name("body").is_none() => return,
+            // Paths with generic arguments inside are walked into instead
+            "scoped_identifier" | "scoped_type_identifier" if self.segments(node).is_some() => {
+                self.path(node);
+                return;
+            }
+            _ => {}
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            self.visit(child);
+        }
+    }
+}
+
+struct UseStatement {
+    range: Range<usize>,
+    visibility: String,
+}
+
+/// The first segment of a path: `crate` in `crate::a::b`
+fn leading_segment(node: Node) -> Node {
+    let mut current = node;
+    while let Some(path) = current.child_by_field_name("path") {
+        current = path;
+    }
+    current
+}
+
+fn collect_kind<'t>(node: Node<'t>, kind: &str, found: &mut Vec<Node<'t>>) {
+    if node.kind() == kind {
+        found.push(node);
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_kind(child, kind, found);
+    }
+}
+
+fn children_of_kind<'t>(node: Node<'t>, kind: &str) -> Option<Node<'t>> {
+    let mut cursor = node.walk();
+    let found = node.named_children(&mut cursor).find(|c| c.kind() == kind);
+    found
+}
+
+/// How a file in the crate writes the path to the module file `target`
+fn render_rust_path(start: RustStart, source: &Path, target: &Path) -> Option<String> {
+    let src = crate_src_dir(target)?;
+    let module = rust_module(&src, target)?;
+    let source_module = crate_src_dir(source).filter(|s| s == &src).and_then(|s| rust_module(&s, source));
+    let absolute = |first: String| {
+        std::iter::once(first).chain(module.iter().cloned()).collect::<Vec<_>>().join("::")
+    };
+
+    match start {
+        RustStart::Crate => {
+            source_module.as_ref()?;
+            Some(absolute("crate".to_string()))
+        }
+        RustStart::Named => Some(absolute(package_name(&src)?)),
+        RustStart::Relative => {
+            let source_module = source_module?;
+            let common = source_module.iter().zip(&module).take_while(|(a, b)| a == b).count();
+            let ups = source_module.len() - common;
+            let rest = &module[common..];
+            let relative = match ups {
+                0 => std::iter::once("self".to_string()).chain(rest.iter().cloned()).collect::<Vec<_>>(),
+                1 => std::iter::once("super".to_string()).chain(rest.iter().cloned()).collect::<Vec<_>>(),
+                _ => return Some(absolute("crate".to_string())),
+            };
+            Some(relative.join("::"))
+        }
+        RustStart::Bound => module.last().cloned(),
+        RustStart::Child => {
+            let source_module = source_module?;
+            if module.len() == source_module.len() + 1 && module.starts_with(&source_module) {
+                module.last().cloned()
+            } else {
+                Some(absolute("crate".to_string()))
+            }
+        }
+    }
+}
+
+/// The dotted module of a Python file or package directory, treating `src/`
+/// as a source root when it isn't a package itself
+fn python_module(root: &Path, path: &Path) -> Option<String> {
+    let mut relative = path.strip_prefix(root).ok()?.to_path_buf();
+    if path.extension().is_some_and(|e| e == "py") {
+        relative = relative.with_extension("");
+    }
+    if let Ok(stripped) = relative.strip_prefix("src") {
+        if !root.join("src/__init__.py").exists() {
+            relative = stripped.to_path_buf();
+        }
+    }
+    let mut segments: Vec<String> =
+        relative.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+    if segments.last().map(String::as_str) == Some("__init__") {
+        segments.pop();
+    }
+    Some(segments.join(".")).filter(|m| !m.is_empty())
+}
+
+fn python_references(root: &Path, path: &Path, file: &mut SourceFile, modules: &HashMap<String, PathBuf>) -> Result<()> {
+    let tree = syntax::parse(Grammar::Python, &file.content)?;
+    let source = file.content.as_str();
+    let text = |node: Node| &source[node.byte_range()];
+    let module = python_module(root, path).unwrap_or_default();
+    let mut package: Vec<&str> = module.split('.').filter(|s| !s.is_empty()).collect();
+    if path.file_stem().is_none_or(|s| s != "__init__") {
+        package.pop();
+    }
+    // Modules and packages, including directories without an __init__.py
+    let target_of = |dotted: &str| -> Option<PathBuf> {
+        if let Some(file) = modules.get(dotted) {
+            return Some(file.clone());
+        }
+        let relative: PathBuf = dotted.split('.').collect();
+        [root.join(&relative), root.join("src").join(&relative)]
+            .into_iter()
+            .find(|dir| dir.is_dir() && python_module(root, dir).as_deref() == Some(dotted))
+    };
+
+    let mut imports = Vec::new();
+    collect_kind(tree.root_node(), "import_statement", &mut imports);
+    collect_kind(tree.root_node(), "import_from_statement", &mut imports);
+    let mut imported_modules: HashSet<String> = HashSet::new();
+    let mut bound: HashMap<String, PathBuf> = HashMap::new();
+
+    for statement in imports {
+        let mut cursor = statement.walk();
+        let names: Vec<Node> = statement.children_by_field_name("name", &mut cursor).collect();
+        if statement.kind() == "import_statement" {
+            for name in names {
+                let (dotted, aliased) = match name.kind() {
+                    "aliased_import" => match name.child_by_field_name("name") {
+                        Some(dotted) => (dotted, true),
+                        None => continue,
+                    },
+                    _ => (name, false),
+                };
+                if let Some((range, target)) = longest_module_prefix(dotted, source, &target_of) {
+                    if !aliased {
+                        imported_modules.insert(source[range.clone()].to_string());
+                    }
+                    file.references.push(Reference { range, target, kind: ReferenceKind::PythonModule { relative: false } });
+                }
+            }
+            continue;
+        }
+
+        let module_name = match statement.child_by_field_name("module_name") {
+            Some(module_name) => module_name,
+            None => continue,
+        };
+        let (absolute, relative) = if module_name.kind() == "relative_import" {
+            let written = text(module_name);
+            let dots = written.chars().take_while(|&c| c == '.').count();
+            if dots > package.len() + 1 {
+                continue;
+            }
+            let mut segments: Vec<&str> = package[..package.len() + 1 - dots].to_vec();
+            segments.extend(written[dots..].split('.').filter(|s| !s.is_empty()));
+            (segments.join("."), true)
+        } else {
+            (text(module_name).to_string(), false)
+        };
+        let module_target = if relative {
+            target_of(&absolute).or_else(|| (absolute.is_empty()).then(|| root.to_path_buf()))
+        } else {
+            None
+        };
+        if relative {
+            if let Some(target) = &module_target {
+                file.references.push(Reference {
+                    range: module_name.byte_range(),
+                    target: target.clone(),
+                    kind: ReferenceKind::PythonModule { relative: true },
+                });
+            }
+        } else if let Some((range, target)) = longest_module_prefix(module_name, source, &target_of) {
+            file.references.push(Reference { range, target, kind: ReferenceKind::PythonModule { relative: false } });
+        }
+
+        let only_item = names.len() == 1;
+        for name in names {
+            let (dotted, alias) = match name.kind() {
+                "aliased_import" => match (name.child_by_field_name("name"), name.child_by_field_name("alias")) {
+                    (Some(dotted), Some(alias)) => (dotted, Some(text(alias).to_string())),
+                    _ => continue,
+                },
+                _ => (name, None),
+            };
+            let item_module = if absolute.is_empty() { text(dotted).to_string() } else { format!("{}.{}", absolute, text(dotted)) };
+            let target = match modules.get(&item_module) {
+                Some(target) => target.clone(),
+                None => continue,
+            };
+            let package_dir = module_target.clone().or_else(|| target_of(&absolute)).unwrap_or_else(|| root.to_path_buf());
+            if alias.is_none() {
+                bound.insert(text(dotted).to_string(), target.clone());
+            }
+            file.references.push(Reference {
+                range: dotted.byte_range(),
+                target,
+                kind: ReferenceKind::PythonFromItem {
+                    item: name.byte_range(),
+                    statement: statement.byte_range(),
+                    only_item,
+                    alias,
+                    module: package_dir,
+                },
+            });
+        }
+    }
+
+    let dotted_string = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*(\.[A-Za-z_][A-Za-z0-9_]*)+").expect("valid dotted name pattern");
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        match node.kind() {
+            "import_statement" | "import_from_statement" => continue,
+            "attribute" => {
+                let written: String = text(node).chars().filter(|c| !c.is_whitespace()).collect();
+                if imported_modules.contains(&written) {
+                    if let Some(target) = modules.get(&written) {
+                        file.references.push(Reference {
+                            range: node.byte_range(),
+                            target: target.clone(),
+                            kind: ReferenceKind::PythonModule { relative: false },
+                        });
+                        continue;
+                    }
+                }
+                if let Some(object) = node.child_by_field_name("object") {
+                    if object.kind() == "identifier" {
+                        if let Some(target) = bound.get(text(object)) {
+                            file.references.push(Reference { range: object.byte_range(), target: target.clone(), kind: ReferenceKind::BoundName });
+                        }
+                    }
+                }
+            }
+            "string_content" => {
+                // "pkg.module.func" in mock.patch, settings and entry points
+                if let Some(found) = dotted_string.find(text(node)) {
+                    let written = found.as_str();
+                    let mut end = written.len();
+                    loop {
+                        let prefix = &written[..end];
+                        if prefix.contains('.') {
+                            if let Some(target) = modules.get(prefix) {
+                                let start = node.start_byte();
+                                file.references.push(Reference {
+                                    range: start..start + end,
+                                    target: target.clone(),
+                                    kind: ReferenceKind::PythonModule { relative: false },
+                                });
+                                break;
+                            }
+                        }
+                        match prefix.rfind('.') {
+                            Some(dot) => end = dot,
+                            None => break,
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    Ok(())
+}
+
+/// The longest leading part of a dotted name that names a module
+fn longest_module_prefix(
+    dotted: Node,
+    source: &str,
+    target_of: &dyn Fn(&str) -> Option<PathBuf>,
+) -> Option<(Range<usize>, PathBuf)> {
+    let mut cursor = dotted.walk();
+    let parts: Vec<Node> = dotted.named_children(&mut cursor).collect();
+    (1..=parts.len()).rev().find_map(|count| {
+        let range = dotted.start_byte()..parts[count - 1].end_byte();
+        let written: String = source[range.clone()].chars().filter(|c| !c.is_whitespace()).collect();
+        target_of(&written).filter(|t| t.is_file()).map(|target| (range, target))
+    })
+}
+
+fn render_python_module(root: &Path, relative: bool, source: &Path, target: &Path) -> Option<String> {
+    let module = python_module(root, target).unwrap_or_default();
+    if relative {
+        let source_module = python_module(root, source).unwrap_or_default();
+        let package = if source.file_stem().is_some_and(|s| s == "__init__") {
+            source_module
+        } else {
+            source_module.rsplit_once('.').map(|(p, _)| p.to_string()).unwrap_or_default()
+        };
+        if module == package {
+            return Some(".".to_string());
+        }
+        if let Some(rest) = module.strip_prefix(&format!("{}.", package)).filter(|_| !package.is_empty()) {
+            return Some(format!(".{}", rest));
+        }
+    }
+    Some(module).filter(|m| !m.is_empty())
+}
+
+fn js_references(path: &Path, grammar: Grammar, file: &mut SourceFile, known: &HashSet<&PathBuf>) -> Result<()> {
+    let tree = syntax::parse(grammar, &file.content)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let specifier = match node.kind() {
+            "import_statement" | "export_statement" => node.child_by_field_name("source"),
+            "call_expression" => {
+                let function = node.child_by_field_name("function");
+                let is_loader = function.is_some_and(|f| JS_LOADERS.contains(&&file.content[f.byte_range()]));
+                node.child_by_field_name("arguments")
+                    .and_then(|args| args.named_child(0))
+                    .filter(|arg| is_loader && arg.kind() == "string")
+            }
+            _ => None,
+        };
+        if let Some(fragment) = specifier.and_then(|s| children_of_kind(s, "string_fragment")) {
+            let written = &file.content[fragment.byte_range()];
+            if written.starts_with("./") || written.starts_with("../") || written == "." || written == ".." {
+                if let Some((target, extension, index)) = resolve_specifier(dir, written, known) {
+                    file.references.push(Reference {
+                        range: fragment.byte_range(),
+                        target,
+                        kind: ReferenceKind::Specifier { extension, index },
+                    });
+                }
+            }
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+    }
+    Ok(())
+}
+
+/// The file a relative specifier loads, with the extension it was written
+/// with and whether it names a directory's index file
+fn resolve_specifier(dir: &Path, written: &str, known: &HashSet<&PathBuf>) -> Option<(PathBuf, Option<String>, bool)> {
+    let base = normalize(&dir.join(written));
+    let exists = |p: &PathBuf| known.contains(p) || p.is_file();
+    let written_extension = Path::new(written).extension().map(|e| e.to_string_lossy().to_string());
+
+    if exists(&base) {
+        return Some((base, written_extension, false));
+    }
+    for extension in JS_EXTENSIONS {
+        let mut candidate = base.clone().into_os_string();
+        candidate.push(format!(".{}", extension));
+        let candidate = PathBuf::from(candidate);
+        if exists(&candidate) {
+            return Some((candidate, None, false));
+        }
+    }
+    // `./a.js` written for `a.ts` under ESM resolution
+    if let Some(extension) = written_extension.as_deref().filter(|e| matches!(*e, "js" | "jsx" | "mjs" | "cjs")) {
+        for replacement in ["ts", "tsx", "mts", "cts"] {
+            let candidate = base.with_extension(replacement);
+            if exists(&candidate) {
+                return Some((candidate, Some(extension.to_string()), false));
+            }
+        }
+    }
+    JS_EXTENSIONS
+        .iter()
+        .map(|extension| base.join(format!("index.{}", extension)))
+        .find(|candidate| exists(candidate))
+        .map(|candidate| (candidate, None, true))
+}
+
+fn render_specifier(from_dir: &Path, target: &Path, extension: Option<&str>, index: bool) -> String {
+    let is_index = target.file_stem().is_some_and(|s| s == "index");
+    let target = if index && is_index {
+        target.parent().unwrap_or(target).to_path_buf()
+    } else {
+        match extension {
+            Some(extension) => target.with_extension(extension),
+            None => target.with_extension(""),
+        }
+    };
+    let specifier = relative_path(from_dir, &target).to_string_lossy().replace('\\', "/");
+    if specifier.is_empty() {
+        ".".to_string()
+    } else if specifier.starts_with("..") {
+        specifier
+    } else {
+        format!("./{}", specifier)
+    }
+}
+
+fn include_references(root: &Path, path: &Path, file: &mut SourceFile, known: &HashSet<&PathBuf>) {
+    let is_c = path.extension().and_then(|e| e.to_str()).is_some_and(|e| C_EXTENSIONS.contains(&e));
+    let pattern = if is_c {
+        Regex::new(r#"(?m)^\s*#\s*include\s*"([^"]+)""#)
+    } else {
+        Regex::new(r#"(?:include|require)(?:_once)?\s*\(?\s*(__DIR__\s*\.\s*)?['"]([^'"]+)['"]"#)
+    }
+    .expect("valid include pattern");
+    let dir = path.parent().unwrap_or(root);
+    let exists = |p: &PathBuf| known.contains(p) || p.is_file();
+
+    for captures in pattern.captures_iter(&file.content) {
+        let written = match captures.get(if is_c { 1 } else { 2 }) {
+            Some(written) => written,
+            None => continue,
+        };
+        let leading_slash = !is_c && captures.get(1).is_some() && written.as_str().starts_with('/');
+        let relative = written.as_str().trim_start_matches('/');
+        let from_dir = normalize(&dir.join(relative));
+        let from_root = normalize(&root.join(relative));
+        let (target, from_root) = if exists(&from_dir) {
+            (from_dir, false)
+        } else if !leading_slash && exists(&from_root) {
+            (from_root, true)
+        } else {
+            continue;
+        };
+        file.references.push(Reference {
+            range: written.range(),
+            target,
+            kind: ReferenceKind::Include { from_root, leading_slash },
+        });
+    }
+}
+
+/// Applies non-overlapping edits; an edit overlapping an earlier one is dropped with a warning
+fn apply_edits(content: &str, edits: &[Edit], path: &Path, warnings: &mut Vec<String>) -> String {
+    let mut sorted: Vec<&Edit> = edits.iter().collect();
+    sorted.sort_by_key(|e| (e.range.start, e.range.end));
+    let mut result = String::with_capacity(content.len());
+    let mut position = 0;
+    for edit in sorted {
+        if edit.range.start < position {
+            warnings.push(format!("{}: overlapping updates near byte {}", path.display(), edit.range.start));
+            continue;
+        }
+        result.push_str(&content[position..edit.range.start]);
+        result.push_str(&edit.text);
+        position = edit.range.end;
+    }
+    result.push_str(&content[position..]);
+    result
+}
+
+/// An item's range widened to its separating comma, so removing it leaves a valid list
+fn list_item_removal(content: &str, item: Range<usize>) -> Range<usize> {
+    let after = &content[item.end..];
+    let trimmed = after.trim_start();
+    if trimmed.starts_with(',') {
+        let comma = item.end + (after.len() - trimmed.len());
+        let rest = &content[comma + 1..];
+        let spaces = rest.len() - rest.trim_start().len();
+        return item.start..comma + 1 + spaces;
+    }
+    let before = &content[..item.start];
+    match before.trim_end().strip_suffix(',') {
+        Some(kept) => kept.len()..item.end,
+        None => item,
+    }
+}
+
+fn line_start(content: &str, offset: usize) -> usize {
+    content[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// The offset just past the newline ending the line at `offset`
+fn line_end(content: &str, offset: usize) -> usize {
+    content[offset..].find('\n').map_or(content.len(), |i| offset + i + 1)
+}
+
+fn line_indent(content: &str, offset: usize) -> &str {
+    let start = line_start(content, offset);
+    let line = &content[start..offset];
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Resolves `.` and `..` without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+fn relative_to(root: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(root).map(Path::to_path_buf).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,206 @@
+use crate::config::{Config, RetentionLimits};
+use crate::events;
+use crate::llm::cache::ResponseCache;
+use crate::llm::debug_log::DebugLog;
+use crate::llm::summarize::SummaryCache;
+use crate::session::SessionStore;
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Kinds of data the app keeps on disk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataKind {
+    /// Cached LLM responses and file summaries in `.caulk/summaries/`
+    Cache,
+    /// LLM transcripts in `.caulk/logs/` and recorded sessions in `.caulk/sessions/`
+    Sessions,
+    /// Embeddings index of the project
+    Index,
+    /// Events log (`[events] log_file`)
+    AuditLog,
+}
+
+impl DataKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DataKind::Cache => "response cache",
+            DataKind::Sessions => "session transcripts",
+            DataKind::Index => "embeddings index",
+            DataKind::AuditLog => "audit log",
+        }
+    }
+}
+
+/// Files and bytes removed from one location
+#[derive(Debug)]
+pub struct Removed {
+    pub kind: DataKind,
+    pub path: PathBuf,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Where each kind of data lives for the project at `project_root`. An
+/// events log outside the project's `.caulk/` is left out.
+pub fn locations(config: &Config, project_root: &Path) -> Vec<(DataKind, PathBuf)> {
+    let mut locations = vec![
+        (DataKind::Cache, ResponseCache::default_dir()),
+        (DataKind::Cache, SummaryCache::dir(project_root)),
+        (DataKind::Sessions, DebugLog::dir(project_root)),
+        (DataKind::Sessions, SessionStore::dir(project_root)),
+        (DataKind::Index, project_root.join(crate::fs::search::VECTOR_STORE_PATH)),
+    ];
+    if let Some(log_file) = &config.events.log_file {
+        match events::log_path(project_root, log_file) {
+            Ok(path) => locations.push((DataKind::AuditLog, path)),
+            Err(e) => warn!("Skipping the audit log: {:#}", e),
+        }
+    }
+    locations
+}
+
+/// Applies the `[retention]` limits: deletes cache entries and transcripts
+/// older than `max_age_days`, then the oldest ones until the rest fit in
+/// `max_size_mb`. The audit log is trimmed line by line the same way.
+pub fn enforce(config: &Config, project_root: &Path) -> Result<Vec<Removed>> {
+    let mut removed = Vec::new();
+    for (kind, path) in locations(config, project_root) {
+        let limits = match kind {
+            DataKind::Cache => &config.retention.cache,
+            DataKind::Sessions => &config.retention.sessions,
+            DataKind::AuditLog => &config.retention.audit_log,
+            DataKind::Index => continue,
+        };
+        if limits.max_age_days.is_none() && limits.max_size_mb.is_none() {
+            continue;
+        }
+        let (files, bytes) = if kind == DataKind::AuditLog {
+            trim_log(&path, limits)?
+        } else {
+            prune_dir(&path, limits)?
+        };
+        if files > 0 || bytes > 0 {
+            debug!("Retention removed {} entries ({} bytes) from {}", files, bytes, path.display());
+            removed.push(Removed { kind, path, files, bytes });
+        }
+    }
+    Ok(removed)
+}
+
+/// Deletes everything stored for the given kinds of data
+pub fn purge(config: &Config, project_root: &Path, kinds: &[DataKind]) -> Result<Vec<Removed>> {
+    let mut removed = Vec::new();
+    for (kind, path) in locations(config, project_root) {
+        if !kinds.contains(&kind) || !path.exists() {
+            continue;
+        }
+        let (files, bytes) = if path.is_dir() {
+            let entries = files_in(&path)?;
+            let bytes = entries.iter().map(|(_, _, size)| size).sum();
+            fs::remove_dir_all(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            (entries.len(), bytes)
+        } else {
+            let bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            (1, bytes)
+        };
+        removed.push(Removed { kind, path, files, bytes });
+    }
+    Ok(removed)
+}
+
+/// Files directly in `dir` with their modification time and size, oldest first
+fn files_in(dir: &Path) -> Result<Vec<(PathBuf, SystemTime, u64)>> {
+    let mut files = Vec::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        let metadata = match entry.metadata() {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => continue,
+        };
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        files.push((entry.path(), modified, metadata.len()));
+    }
+    // Ties are broken by name so the same files go first on every run
+    files.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    Ok(files)
+}
+
+/// Removes files past the age limit, then the oldest until under the size limit
+fn prune_dir(dir: &Path, limits: &RetentionLimits) -> Result<(usize, u64)> {
+    let files = files_in(dir)?;
+    let cutoff = max_age_cutoff(limits);
+    let mut total: u64 = files.iter().map(|(_, _, size)| size).sum();
+    let max_bytes = limits.max_size_mb.map(|mb| mb * 1024 * 1024);
+
+    let (mut count, mut bytes) = (0, 0);
+    for (path, modified, size) in files {
+        let expired = cutoff.is_some_and(|cutoff| modified < cutoff);
+        let over_size = max_bytes.is_some_and(|max| total > max);
+        if !expired && !over_size {
+            continue;
+        }
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        total -= size;
+        count += 1;
+        bytes += size;
+    }
+    Ok((count, bytes))
+}
+
+/// Drops audit log lines past the age limit, then the oldest lines until the
+/// log fits the size limit
+fn trim_log(path: &Path, limits: &RetentionLimits) -> Result<(usize, u64)> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((0, 0)),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    let cutoff = limits
+        .max_age_days
+        .map(|days| chrono::Local::now() - chrono::Duration::days(days as i64));
+    let max_bytes = limits.max_size_mb.map(|mb| (mb * 1024 * 1024) as usize);
+
+    let mut lines: Vec<&str> = content
+        .lines()
+        .filter(|line| {
+            let time = serde_json::from_str::<serde_json::Value>(line).ok().and_then(|event| {
+                event["time"].as_str().and_then(|time| chrono::DateTime::parse_from_rfc3339(time).ok())
+            });
+            match (cutoff, time) {
+                (Some(cutoff), Some(time)) => time >= cutoff,
+                _ => true,
+            }
+        })
+        .collect();
+    if let Some(max_bytes) = max_bytes {
+        let mut total: usize = lines.iter().map(|line| line.len() + 1).sum();
+        let mut skip = 0;
+        while total > max_bytes && skip < lines.len() {
+            total -= lines[skip].len() + 1;
+            skip += 1;
+        }
+        lines.drain(..skip);
+    }
+
+    let dropped = content.lines().count() - lines.len();
+    if dropped == 0 {
+        return Ok((0, 0));
+    }
+    let kept: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    fs::write(path, &kept).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok((dropped, content.len().saturating_sub(kept.len()) as u64))
+}
+
+fn max_age_cutoff(limits: &RetentionLimits) -> Option<SystemTime> {
+    limits
+        .max_age_days
+        .and_then(|days| SystemTime::now().checked_sub(Duration::from_secs(days * 24 * 60 * 60)))
+}
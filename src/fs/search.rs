@@ -1,93 +1,138 @@
-use anyhow::Result;
-use ignore::Walk;
+use crate::analysis::syntax::{self, Grammar};
+use crate::analysis::xref::SymbolUsage;
+use crate::fs::project_files;
+use crate::fs::storage::Storage;
+use crate::llm::embeddings::{cosine_similarity, EmbeddingClient};
+use anyhow::{Context, Result};
+use log::debug;
+use rayon::prelude::*;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-pub struct CodeSearch;
+/// Where the vector store is kept, relative to the project root
+pub const VECTOR_STORE_PATH: &str = ".caulk/vectors.json";
+/// Most lines in one chunk; files without parseable definitions are cut into
+/// chunks of this size and longer definitions are split
+const CHUNK_LINES: usize = 60;
+/// Chunks shorter than this are merged into the next one
+const MIN_CHUNK_LINES: usize = 5;
+/// Characters of each chunk sent to the embeddings endpoint
+const CHUNK_CHARS: usize = 2000;
+/// Chunks sent per embeddings request
+const EMBED_BATCH: usize = 64;
+/// Files kept in the vector store, so a huge checkout doesn't mean thousands of requests
+const MAX_STORED_FILES: usize = 2000;
+
+pub struct CodeSearch {
+    storage: Storage,
+}
 
 impl CodeSearch {
-    pub fn new() -> Self {
-        Self
+    pub fn new(storage: Storage) -> Self {
+        Self { storage }
     }
     
-    pub fn find_relevant_files(&self, base_path: &Path, keywords: &[String]) -> Result<Vec<PathBuf>> {
-        let mut relevant_files = Vec::new();
-        
+    /// Files containing the keywords with their keyword relevance, most relevant first
+    pub fn find_scored_files(&self, base_path: &Path, keywords: &[String]) -> Result<Vec<(PathBuf, usize)>> {
         if keywords.is_empty() {
-            return Ok(relevant_files);
+            return Ok(Vec::new());
         }
         
-        // Use a map to store path and relevance for sorting
-        let mut path_relevance: Vec<(PathBuf, usize)> = Vec::new();
-        
-        for entry in Walk::new(base_path) {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                
-                // Skip non-files
-                if !path.is_file() {
-                    continue;
-                }
-                
-                // Skip binary files and large files
-                if self.is_binary_or_large_file(path)? {
-                    continue;
-                }
-                
-                // Read file content
-                if let Ok(content) = std::fs::read_to_string(path) {
-                    // Check if any keyword matches
-                    let relevance = self.calculate_relevance(&content, keywords);
-                    
-                    if relevance > 0 {
-                        path_relevance.push((path.to_owned(), relevance));
-                    }
-                }
-            }
-        }
+        // Score files on all cores; unreadable and binary files score nothing
+        let mut path_relevance: Vec<(PathBuf, usize)> = project_files(base_path)
+            .into_par_iter()
+            .filter(|path| !self.is_binary_or_large_file(path).unwrap_or(true))
+            .filter_map(|path| {
+                let content = std::fs::read_to_string(&path).ok()?;
+                let relevance = self.calculate_relevance(&content, keywords);
+                (relevance > 0).then_some((path, relevance))
+            })
+            .collect();
         
         // Sort by relevance (most relevant first)
-        path_relevance.sort_by(|a, b| b.1.cmp(&a.1));
+        path_relevance.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
         
-        // Extract sorted paths
-        relevant_files = path_relevance.into_iter().map(|(path, _)| path).collect();
+        Ok(path_relevance)
+    }
+    
+    /// Ranks files by a blend of keyword relevance and the embedding
+    /// similarity between the query and each file's closest chunk, after
+    /// bringing the project's vector store up to date. `semantic_weight` is
+    /// the share of the score taken from similarity.
+    pub async fn rank_semantically(&self, base_path: &Path, query: &str, scored: Vec<(PathBuf, usize)>,
+                                   embedder: &EmbeddingClient, semantic_weight: f32) -> Result<Vec<PathBuf>> {
+        let mut store = VectorStore::load(base_path, embedder.model(), &self.storage);
+        let update = store.update(base_path, &self.searchable_files(base_path), embedder).await;
+        store.save(base_path, &self.storage)?;
+        update?;
         
-        Ok(relevant_files)
+        let query_embedding = embedder.embed(&[query.to_string()]).await?.remove(0);
+        let weight = semantic_weight.clamp(0.0, 1.0);
+        let max_relevance = scored.iter().map(|(_, relevance)| *relevance).max().unwrap_or(0).max(1) as f32;
+        
+        let mut scores: HashMap<PathBuf, f32> = scored.into_iter()
+            .map(|(path, relevance)| (path, (1.0 - weight) * relevance as f32 / max_relevance))
+            .collect();
+        for (relative, file) in &store.files {
+            let similarity = file.chunks.iter()
+                .map(|chunk| cosine_similarity(&query_embedding, &chunk.embedding))
+                .fold(0.0, f32::max);
+            *scores.entry(base_path.join(relative)).or_insert(0.0) += weight * similarity;
+        }
+        
+        let mut ranked: Vec<(PathBuf, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
+        Ok(ranked.into_iter().map(|(path, _)| path).collect())
+    }
+    
+    /// Text files under `base_path` that relevance search looks at
+    fn searchable_files(&self, base_path: &Path) -> Vec<PathBuf> {
+        project_files(base_path)
+            .into_par_iter()
+            .filter(|path| !self.is_binary_or_large_file(path).unwrap_or(true))
+            .collect()
+    }
+    
+    /// Moves the files defining `symbols` to the front, followed by the files
+    /// calling them, so the definition and its call sites make it into context
+    pub fn with_symbol_files(&self, candidates: Vec<PathBuf>, symbols: &[SymbolUsage]) -> Vec<PathBuf> {
+        let definitions = symbols.iter().flat_map(|s| &s.definitions);
+        let references = symbols.iter().flat_map(|s| &s.references);
+        let mut files: Vec<PathBuf> = definitions.chain(references).map(|l| l.path.clone()).collect();
+        files.extend(candidates);
+
+        let mut seen = std::collections::HashSet::new();
+        files.retain(|path| seen.insert(path.clone()));
+        files
     }
     
     pub fn search_in_files(&self, base_path: &Path, pattern: &str) -> Result<Vec<SearchResult>> {
-        let mut results = Vec::new();
         let regex = Regex::new(pattern)?;
         
-        for entry in Walk::new(base_path) {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                
-                // Skip non-files
-                if !path.is_file() {
-                    continue;
-                }
-                
-                // Skip binary files and large files
-                if self.is_binary_or_large_file(path)? {
-                    continue;
-                }
-                
-                // Read file content
-                if let Ok(content) = std::fs::read_to_string(path) {
-                    // Find all matches
-                    for (line_idx, line) in content.lines().enumerate() {
-                        if regex.is_match(line) {
-                            results.push(SearchResult {
-                                file_path: path.to_path_buf(),
-                                line_number: line_idx + 1,
-                                line_content: line.to_string(),
-                            });
-                        }
-                    }
-                }
-            }
-        }
+        // Files are searched on all cores and come back in path order
+        let results = project_files(base_path)
+            .into_par_iter()
+            .filter(|path| !self.is_binary_or_large_file(path).unwrap_or(true))
+            .flat_map_iter(|path| {
+                let content = std::fs::read_to_string(&path).unwrap_or_default();
+                content
+                    .lines()
+                    .enumerate()
+                    .filter_map(|(line_idx, line)| {
+                        let found = regex.find(line)?;
+                        Some(SearchResult {
+                            file_path: path.clone(),
+                            line_number: line_idx + 1,
+                            column: found.start() + 1,
+                            line_content: line.to_string(),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
         
         Ok(results)
     }
@@ -220,7 +265,7 @@ impl CodeSearch {
             // Penalize mismatches between search language and file language
             if let SearchLanguage::Drupal = lang {
                 if lang_signatures.is_javascript && !content_lower.contains("drupal") {
-                    score = score / 2;
+                    score /= 2;
                 }
             }
         }
@@ -229,6 +274,7 @@ impl CodeSearch {
     }
     
     /// Detects language signatures from file content
+    #[allow(clippy::field_reassign_with_default)]
     fn detect_language_signatures(&self, content: &str) -> LanguageSignatures {
         let mut signatures = LanguageSignatures::default();
         
@@ -308,6 +354,7 @@ impl CodeSearch {
     }
     
     /// Gets a language-specific boost factor for a keyword
+    #[allow(clippy::if_same_then_else)]
     fn get_language_boost(&self, keyword: &str, signatures: &LanguageSignatures) -> usize {
         if signatures.is_rust && (keyword == "rust" || keyword.contains("fn ") || keyword.contains("struct ") || keyword.contains("impl ")) {
             3
@@ -429,6 +476,7 @@ struct LanguageSignatures {
     is_drupal_template: bool,
 }
 
+#[allow(clippy::upper_case_acronyms)]
 enum SearchLanguage {
     Rust,
     Python,
@@ -439,9 +487,194 @@ enum SearchLanguage {
     Generic,
 }
 
-#[derive(Debug)]
+/// A line matching a search pattern
+#[derive(Debug, Serialize)]
 pub struct SearchResult {
     pub file_path: PathBuf,
     pub line_number: usize,
+    /// 1-based byte column of the first match
+    pub column: usize,
     pub line_content: String,
 }
+
+/// Output format of `code-assist search`
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SearchFormat {
+    /// `path:line: text`
+    Text,
+    /// An array of results
+    Json,
+    /// `path:line:column:text`, as read by Vim's quickfix list and Emacs'
+    /// grep mode
+    Quickfix,
+}
+
+/// Search results with paths relative to `root`, in `format`
+pub fn format_results(results: Vec<SearchResult>, root: &Path, format: SearchFormat) -> Result<String> {
+    let results: Vec<SearchResult> = results
+        .into_iter()
+        .map(|result| SearchResult {
+            file_path: result.file_path.strip_prefix(root).map(Path::to_path_buf).unwrap_or(result.file_path),
+            ..result
+        })
+        .collect();
+    let text = match format {
+        SearchFormat::Json => format!("{}\n", serde_json::to_string_pretty(&results)?),
+        SearchFormat::Text => results
+            .iter()
+            .map(|result| format!("{}:{}: {}\n", result.file_path.display(), result.line_number, result.line_content.trim()))
+            .collect(),
+        SearchFormat::Quickfix => results
+            .iter()
+            .map(|result| {
+                format!("{}:{}:{}:{}\n", result.file_path.display(), result.line_number, result.column, result.line_content)
+            })
+            .collect(),
+    };
+    Ok(text)
+}
+
+/// Embeddings of file chunks, persisted under `.caulk/` and re-embedded only
+/// for files whose content changed
+#[derive(Serialize, Deserialize, Default)]
+struct VectorStore {
+    model: String,
+    /// Keyed by path relative to the project root
+    files: HashMap<String, StoredFile>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredFile {
+    hash: String,
+    chunks: Vec<StoredChunk>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredChunk {
+    start_line: usize,
+    end_line: usize,
+    embedding: Vec<f32>,
+}
+
+/// A file waiting to be embedded
+struct PendingFile {
+    relative: String,
+    hash: String,
+    chunks: Vec<(usize, usize, String)>,
+}
+
+impl VectorStore {
+    /// Loads the project's store, starting over when it was built with another model
+    fn load(root: &Path, model: &str, storage: &Storage) -> Self {
+        let stored = storage.read(&root.join(VECTOR_STORE_PATH))
+            .ok()
+            .and_then(|content| serde_json::from_str::<VectorStore>(&content).ok());
+        match stored {
+            Some(store) if store.model == model => store,
+            _ => Self { model: model.to_string(), files: HashMap::new() },
+        }
+    }
+
+    fn save(&self, root: &Path, storage: &Storage) -> Result<()> {
+        let path = root.join(VECTOR_STORE_PATH);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        storage.write(&path, &serde_json::to_string(self)?)
+    }
+
+    /// Embeds the chunks of new and changed files and forgets deleted ones.
+    /// Files embedded before a failed request are kept.
+    async fn update(&mut self, root: &Path, files: &[PathBuf], embedder: &EmbeddingClient) -> Result<()> {
+        let mut current = std::collections::HashSet::new();
+        let mut pending = Vec::new();
+
+        for path in files.iter().take(MAX_STORED_FILES) {
+            let content = match std::fs::read_to_string(path) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            let relative = path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+            let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+            current.insert(relative.clone());
+
+            if self.files.get(&relative).is_some_and(|file| file.hash == hash) {
+                continue;
+            }
+            let chunks = chunk_file(path, &relative, &content);
+            if !chunks.is_empty() {
+                pending.push(PendingFile { relative, hash, chunks });
+            }
+        }
+        self.files.retain(|relative, _| current.contains(relative));
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+        debug!("Vector store: embedding {} new or changed files", pending.len());
+
+        let mut batch: Vec<PendingFile> = Vec::new();
+        for file in pending {
+            batch.push(file);
+            if batch.iter().map(|f| f.chunks.len()).sum::<usize>() >= EMBED_BATCH {
+                self.embed_batch(std::mem::take(&mut batch), embedder).await?;
+            }
+        }
+        self.embed_batch(batch, embedder).await
+    }
+
+    async fn embed_batch(&mut self, batch: Vec<PendingFile>, embedder: &EmbeddingClient) -> Result<()> {
+        let inputs: Vec<String> = batch.iter().flat_map(|f| f.chunks.iter().map(|(_, _, text)| text.clone())).collect();
+        let mut embeddings = embedder.embed(&inputs).await?.into_iter();
+
+        for file in batch {
+            let chunks = file.chunks.into_iter()
+                .zip(embeddings.by_ref())
+                .map(|((start_line, end_line, _), embedding)| StoredChunk { start_line, end_line, embedding })
+                .collect();
+            self.files.insert(file.relative, StoredFile { hash: file.hash, chunks });
+        }
+        Ok(())
+    }
+}
+
+/// Splits a file into (start line, end line, text) chunks, one per function,
+/// method or type where the grammar is known and fixed runs of lines otherwise.
+/// Each chunk's text starts with the file path so it carries that context.
+fn chunk_file(path: &Path, relative: &str, content: &str) -> Vec<(usize, usize, String)> {
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.iter().all(|line| line.trim().is_empty()) {
+        return Vec::new();
+    }
+
+    let mut starts: Vec<usize> = vec![1];
+    let grammar = Grammar::for_path(path);
+    if let Some(outline) = grammar.and_then(|g| syntax::outline(g, content).ok()) {
+        starts.extend(outline.definitions.iter().map(|d| d.line));
+    }
+    starts.sort_unstable();
+    starts.dedup();
+
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map_or(lines.len(), |next| next - 1);
+        if end < start {
+            continue;
+        }
+        // Leading comments, imports and one-line definitions join the next chunk
+        match ranges.last_mut() {
+            Some(last) if last.1 - last.0 + 1 < MIN_CHUNK_LINES => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .flat_map(|(start, end)| (start..=end).step_by(CHUNK_LINES).map(move |s| (s, (s + CHUNK_LINES - 1).min(end))))
+        .map(|(start, end)| {
+            let text = format!("{}\n{}", relative, lines[start - 1..end].join("\n"));
+            (start, end, text.chars().take(CHUNK_CHARS).collect())
+        })
+        .collect()
+}
@@ -0,0 +1,120 @@
+use crate::config::PrivacyConfig;
+use anyhow::{anyhow, Context, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use log::debug;
+use once_cell::sync::OnceCell;
+use std::fs;
+use std::path::Path;
+
+/// Keyring entry holding the storage key
+const KEYRING_SERVICE: &str = "code-assist";
+const KEYRING_USER: &str = "storage-key";
+/// Hex-encoded key used instead of the keyring, e.g. on headless machines
+const KEY_ENV_VAR: &str = "CODE_ASSIST_STORAGE_KEY";
+/// Start of every encrypted file, followed by the nonce and the ciphertext
+const MAGIC: &[u8] = b"caulk-enc-v1\0";
+const NONCE_LEN: usize = 12;
+
+/// The key is read from the keyring once per process
+static STORAGE_KEY: OnceCell<Key> = OnceCell::new();
+
+/// Reads and writes the app's own state files (response cache, embeddings
+/// index, LLM debug logs). With `[privacy] encrypt_storage` they are
+/// encrypted with ChaCha20-Poly1305 under a key kept in the OS keyring.
+/// Plain files written before encryption was turned on stay readable and
+/// are encrypted the next time they are written.
+#[derive(Clone, Copy)]
+pub struct Storage {
+    key: Option<&'static Key>,
+}
+
+impl Storage {
+    pub fn new(config: &PrivacyConfig) -> Result<Self> {
+        if !config.encrypt_storage {
+            return Ok(Self::plain());
+        }
+        let key = STORAGE_KEY.get_or_try_init(load_or_create_key)?;
+        Ok(Self { key: Some(key) })
+    }
+
+    /// Storage that writes plain files
+    pub fn plain() -> Self {
+        Self { key: None }
+    }
+
+    pub fn read(&self, path: &Path) -> Result<String> {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let plain = match bytes.strip_prefix(MAGIC) {
+            Some(sealed) => self.decrypt(sealed).with_context(|| format!("Failed to decrypt {}", path.display()))?,
+            None => bytes,
+        };
+        String::from_utf8(plain).with_context(|| format!("{} is not valid UTF-8", path.display()))
+    }
+
+    pub fn write(&self, path: &Path, content: &str) -> Result<()> {
+        let bytes = match self.key {
+            Some(key) => {
+                let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+                let ciphertext = ChaCha20Poly1305::new(key)
+                    .encrypt(&nonce, content.as_bytes())
+                    .map_err(|_| anyhow!("Failed to encrypt {}", path.display()))?;
+                [MAGIC, nonce.as_slice(), &ciphertext].concat()
+            }
+            None => content.as_bytes().to_vec(),
+        };
+        fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>> {
+        let key = self.key
+            .ok_or_else(|| anyhow!("the file is encrypted; turn on encrypt_storage under [privacy] to read it"))?;
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow!("the encrypted file is truncated"));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        ChaCha20Poly1305::new(key)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("wrong key or corrupted file"))
+    }
+}
+
+/// The key from `CODE_ASSIST_STORAGE_KEY` or the OS keyring, generating and
+/// storing a new one on first use
+fn load_or_create_key() -> Result<Key> {
+    if let Ok(hex) = std::env::var(KEY_ENV_VAR) {
+        return decode_key(hex.trim()).with_context(|| format!("{} must be 64 hex characters", KEY_ENV_VAR));
+    }
+
+    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+        .context("Failed to open the OS keyring for the storage key")?;
+    match entry.get_password() {
+        Ok(hex) => decode_key(&hex).context("The storage key in the OS keyring is invalid"),
+        Err(keyring::Error::NoEntry) => {
+            debug!("Generating a new storage key in the OS keyring");
+            let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+            entry.set_password(&encode_key(&key))
+                .context("Failed to save the storage key in the OS keyring")?;
+            Ok(key)
+        }
+        Err(e) => Err(anyhow!(
+            "Failed to read the storage key from the OS keyring ({}); set {} to a 64-character hex key instead",
+            e,
+            KEY_ENV_VAR
+        )),
+    }
+}
+
+fn encode_key(key: &Key) -> String {
+    key.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_key(hex: &str) -> Result<Key> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(anyhow!("expected 64 hex characters"));
+    }
+    let bytes = (0..32)
+        .map(|i| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16))
+        .collect::<std::result::Result<Vec<u8>, _>>()?;
+    Ok(*Key::from_slice(&bytes))
+}
@@ -0,0 +1,132 @@
+use crate::fs::edit::FileEditor;
+use crate::fs::journal::content_hash;
+use crate::fs::storage::Storage;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Actions kept for undo; the oldest are dropped past this
+const MAX_ACTIONS: usize = 100;
+
+/// A file an action changed, with what is needed to change it back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoFile {
+    pub path: PathBuf,
+    /// Content before the action; None when the action created the file
+    pub before: Option<String>,
+    /// SHA-256 of the content the action left; None when it deleted the file
+    pub after: Option<String>,
+}
+
+/// One applied action in the undo log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndoEntry {
+    pub id: u64,
+    /// RFC 3339 time the action was applied
+    pub time: String,
+    pub action: String,
+    pub files: Vec<UndoFile>,
+}
+
+/// Applied actions with the pre-images of the files they changed, one file
+/// per action in `.caulk/undo/`, so any of them can be reverted
+pub struct UndoLog {
+    dir: PathBuf,
+    storage: Storage,
+}
+
+impl UndoLog {
+    pub fn new(project_root: &Path, storage: Storage) -> Self {
+        Self { dir: Self::dir(project_root), storage }
+    }
+
+    /// Directory the undo log of the project at `project_root` is saved in
+    pub fn dir(project_root: &Path) -> PathBuf {
+        project_root.join(".caulk").join("undo")
+    }
+
+    /// Records an action that changed `files` (path, content before and
+    /// after, None when missing); returns its id
+    pub fn record<'a>(&self, action: &str, files: impl Iterator<Item = (&'a Path, Option<&'a str>, Option<&'a str>)>) -> Result<u64> {
+        let entries = self.entries()?;
+        let id = entries.last().map_or(1, |entry| entry.id + 1);
+        let entry = UndoEntry {
+            id,
+            time: chrono::Local::now().to_rfc3339(),
+            action: action.to_string(),
+            files: files
+                .map(|(path, before, after)| UndoFile {
+                    path: path.to_path_buf(),
+                    before: before.map(String::from),
+                    after: after.map(content_hash),
+                })
+                .collect(),
+        };
+        std::fs::create_dir_all(&self.dir).with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        self.storage.write(&self.entry_path(id), &serde_json::to_string(&entry)?)?;
+
+        let excess = (entries.len() + 1).saturating_sub(MAX_ACTIONS);
+        for old in entries.iter().take(excess) {
+            let _ = std::fs::remove_file(self.entry_path(old.id));
+        }
+        Ok(id)
+    }
+
+    /// Recorded actions, oldest first
+    pub fn entries(&self) -> Result<Vec<UndoEntry>> {
+        let dir = match std::fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.dir.display())),
+        };
+        let mut entries: Vec<UndoEntry> = dir
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| serde_json::from_str(&self.storage.read(&path).ok()?).ok())
+            .collect();
+        entries.sort_by_key(|entry| entry.id);
+        Ok(entries)
+    }
+
+    /// Restores the files of action `id` (the last one when None) under
+    /// `project_root` as they were before it and drops it from the log. A
+    /// file changed since the action is only overwritten with `force`.
+    pub fn undo(&self, project_root: &Path, id: Option<u64>, force: bool) -> Result<UndoEntry> {
+        let entries = self.entries()?;
+        let entry = match id {
+            Some(id) => entries.into_iter().find(|entry| entry.id == id).ok_or_else(|| anyhow!("No action {} to undo", id))?,
+            None => entries.into_iter().last().ok_or_else(|| anyhow!("No action to undo"))?,
+        };
+
+        // Check every file first so an action is undone completely or not at all
+        if !force {
+            for file in &entry.files {
+                let current = std::fs::read_to_string(project_root.join(&file.path)).ok();
+                if current.as_deref().map(content_hash) != file.after {
+                    return Err(anyhow!(
+                        "{} changed after action {}; undo with --force to overwrite it",
+                        file.path.display(),
+                        entry.id
+                    ));
+                }
+            }
+        }
+        for file in &entry.files {
+            let path = project_root.join(&file.path);
+            match &file.before {
+                Some(content) => FileEditor::write_file(&path, content)?,
+                None if path.exists() => {
+                    std::fs::remove_file(&path).with_context(|| format!("Failed to delete {}", path.display()))?
+                }
+                None => {}
+            }
+        }
+        std::fs::remove_file(self.entry_path(entry.id))
+            .with_context(|| format!("Failed to remove action {} from the undo log", entry.id))?;
+        Ok(entry)
+    }
+
+    fn entry_path(&self, id: u64) -> PathBuf {
+        self.dir.join(format!("{:06}.json", id))
+    }
+}
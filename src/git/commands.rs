@@ -1,34 +1,81 @@
 use anyhow::{Result, Context};
-use std::path::Path;
+use git2::{Repository, Status, StatusOptions};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use super::binary_available;
+
+/// Git operations. Status always goes through libgit2; `add` and `commit`
+/// use the git binary when installed (so hooks and config apply) and fall
+/// back to libgit2 otherwise.
 pub struct GitCommands;
 
 impl GitCommands {
+    /// Branch and changed files in `git status --short --branch` format
     pub fn status(repo_path: &Path) -> Result<String> {
-        let output = Command::new("git")
-            .current_dir(repo_path)
-            .args(&["status"])
-            .output()
-            .context("Failed to execute git status")?;
-        
-        if output.status.success() {
-            Ok(String::from_utf8_lossy(&output.stdout).to_string())
-        } else {
-            Err(anyhow::anyhow!(
-                "Git status failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ))
+        let repo = Repository::discover(repo_path)
+            .context("Failed to open git repository")?;
+
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(true);
+        let statuses = repo.statuses(Some(&mut options))
+            .context("Failed to read git status")?;
+
+        let mut output = String::new();
+        if let Ok(head) = repo.head() {
+            if let Some(branch) = head.shorthand() {
+                output.push_str(&format!("## {}\n", branch));
+            }
         }
+        for entry in statuses.iter() {
+            let path = entry.path().unwrap_or("");
+            output.push_str(&format!("{} {}\n", status_code(entry.status()), path));
+        }
+        if statuses.is_empty() {
+            output.push_str("nothing to commit, working tree clean\n");
+        }
+        Ok(output)
+    }
+
+    /// Files with uncommitted changes, untracked ones included, relative to
+    /// `repo_path` (files outside it are left out)
+    pub fn changed_files(repo_path: &Path) -> Result<Vec<PathBuf>> {
+        let repo = Repository::discover(repo_path)
+            .context("Failed to open git repository")?;
+        let workdir = repo.workdir()
+            .ok_or_else(|| anyhow::anyhow!("Repository has no working directory"))?
+            .canonicalize()?;
+        let prefix = repo_path.canonicalize()?.strip_prefix(&workdir).map(Path::to_path_buf).unwrap_or_default();
+
+        let mut options = StatusOptions::new();
+        options.include_untracked(true).recurse_untracked_dirs(true);
+        let statuses = repo.statuses(Some(&mut options))
+            .context("Failed to read git status")?;
+        Ok(statuses
+            .iter()
+            .filter(|entry| !entry.status().contains(Status::IGNORED))
+            .filter_map(|entry| entry.path().and_then(|path| Path::new(path).strip_prefix(&prefix).ok().map(Path::to_path_buf)))
+            .collect())
     }
-    
+
+    /// Creates a repository at `repo_path`
+    pub fn init(repo_path: &Path) -> Result<()> {
+        Repository::init(repo_path)
+            .with_context(|| format!("Failed to create a git repository in {}", repo_path.display()))?;
+        Ok(())
+    }
+
     pub fn commit(repo_path: &Path, message: &str) -> Result<String> {
+        if !binary_available() {
+            return Self::commit_with_library(repo_path, message);
+        }
+
         let output = Command::new("git")
             .current_dir(repo_path)
-            .args(&["commit", "-m", message])
+            .args(["commit", "-m", message])
             .output()
             .context("Failed to execute git commit")?;
-        
+
         if output.status.success() {
             Ok(String::from_utf8_lossy(&output.stdout).to_string())
         } else {
@@ -38,17 +85,21 @@ impl GitCommands {
             ))
         }
     }
-    
+
     pub fn add(repo_path: &Path, files: &[&str]) -> Result<String> {
+        if !binary_available() {
+            return Self::add_with_library(repo_path, files);
+        }
+
         let mut args = vec!["add"];
         args.extend(files);
-        
+
         let output = Command::new("git")
             .current_dir(repo_path)
             .args(&args)
             .output()
             .context("Failed to execute git add")?;
-        
+
         if output.status.success() {
             Ok(String::from_utf8_lossy(&output.stdout).to_string())
         } else {
@@ -58,6 +109,121 @@ impl GitCommands {
             ))
         }
     }
-    
+
+    /// Whether the index has `path`, or files under it when it is a directory
+    pub fn is_tracked(repo_path: &Path, path: &Path) -> bool {
+        let repo = match Repository::discover(repo_path) {
+            Ok(repo) => repo,
+            Err(_) => return false,
+        };
+        let (workdir, index) = match (repo.workdir(), repo.index()) {
+            (Some(workdir), Ok(index)) => (workdir.to_path_buf(), index),
+            _ => return false,
+        };
+        let relative = match relative_to_workdir(&workdir, &repo_path.join(path)) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        index.iter().any(|entry| Path::new(&*String::from_utf8_lossy(&entry.path)).starts_with(&relative))
+    }
+
+    fn add_with_library(repo_path: &Path, files: &[&str]) -> Result<String> {
+        let repo = Repository::discover(repo_path)
+            .context("Failed to open git repository")?;
+        let workdir = repo.workdir()
+            .ok_or_else(|| anyhow::anyhow!("Git add failed: repository has no working directory"))?;
+        let mut index = repo.index().context("Failed to read git index")?;
+
+        for file in files {
+            let relative = relative_to_workdir(workdir, &repo_path.join(file))?;
+            if workdir.join(&relative).exists() {
+                index.add_path(&relative)
+            } else {
+                index.remove_path(&relative)
+            }
+            .with_context(|| format!("Git add failed for {}", file))?;
+        }
+        index.write().context("Failed to write git index")?;
+        Ok(String::new())
+    }
+
+    fn commit_with_library(repo_path: &Path, message: &str) -> Result<String> {
+        let repo = Repository::discover(repo_path)
+            .context("Failed to open git repository")?;
+        let signature = repo.signature()
+            .context("Git commit failed: set user.name and user.email in your git config")?;
+
+        let mut index = repo.index().context("Failed to read git index")?;
+        let tree = repo.find_tree(index.write_tree().context("Failed to write git tree")?)?;
+        let parent = match repo.head() {
+            Ok(head) => Some(head.peel_to_commit().context("Failed to read HEAD commit")?),
+            Err(_) => None,
+        };
+        if parent.as_ref().is_some_and(|parent| parent.tree_id() == tree.id()) {
+            return Err(anyhow::anyhow!("Git commit failed: nothing to commit"));
+        }
+
+        let parents: Vec<_> = parent.iter().collect();
+        let id = repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)
+            .context("Git commit failed")?;
+        let branch = repo.head().ok()
+            .and_then(|head| head.shorthand().map(String::from))
+            .unwrap_or_else(|| "HEAD".to_string());
+        let short_id = id.to_string();
+        Ok(format!("[{} {}] {}\n", branch, &short_id[..7], message.lines().next().unwrap_or("")))
+    }
+
     // Add more git commands as needed...
 }
+
+/// Two-letter index/worktree code as printed by `git status --short`
+fn status_code(status: Status) -> String {
+    if status.is_conflicted() {
+        return "UU".to_string();
+    }
+    if status.is_wt_new() {
+        return "??".to_string();
+    }
+    let index = if status.is_index_new() {
+        'A'
+    } else if status.is_index_modified() {
+        'M'
+    } else if status.is_index_deleted() {
+        'D'
+    } else if status.is_index_renamed() {
+        'R'
+    } else if status.is_index_typechange() {
+        'T'
+    } else {
+        ' '
+    };
+    let worktree = if status.is_wt_modified() {
+        'M'
+    } else if status.is_wt_deleted() {
+        'D'
+    } else if status.is_wt_renamed() {
+        'R'
+    } else if status.is_wt_typechange() {
+        'T'
+    } else {
+        ' '
+    };
+    format!("{}{}", index, worktree)
+}
+
+/// `path` relative to the repository's working directory, as the index
+/// expects (symlinked temp dirs make a plain strip_prefix unreliable)
+fn relative_to_workdir(workdir: &Path, path: &Path) -> Result<PathBuf> {
+    let workdir = workdir.canonicalize().unwrap_or_else(|_| workdir.to_path_buf());
+    let path = match path.canonicalize() {
+        Ok(path) => path,
+        // Deleted files can't be canonicalized, but their directory can
+        Err(_) => match (path.parent().and_then(|p| p.canonicalize().ok()), path.file_name()) {
+            (Some(parent), Some(name)) => parent.join(name),
+            _ => path.to_path_buf(),
+        },
+    };
+    path.strip_prefix(&workdir)
+        .map(Path::to_path_buf)
+        .map_err(|_| anyhow::anyhow!("{} is outside the repository", path.display()))
+}
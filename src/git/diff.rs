@@ -1,57 +1,81 @@
-use git2::{Repository, Diff, Error as Git2Error};
+use git2::{Repository, DiffFindOptions, Delta, Patch};
 use anyhow::{Result, Context};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub struct GitDiff;
 
+/// One file's changes between two revisions
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    /// Previous path of a renamed or copied file
+    pub old_path: Option<PathBuf>,
+    /// `git diff --name-status` letter: A, M, D, R, C or T
+    pub status: char,
+    pub additions: usize,
+    pub deletions: usize,
+    pub patch: String,
+}
+
 impl GitDiff {
-    pub fn get_working_diff(repo_path: &Path) -> Result<String> {
-        let repo = Repository::open(repo_path)
+    /// Changes made on `head` since it forked from `base`, like
+    /// `git diff base...head`, one entry per file with renames detected
+    pub fn range_diff(repo_path: &Path, base: &str, head: &str) -> Result<Vec<FileDiff>> {
+        let repo = Repository::discover(repo_path)
             .context("Failed to open git repository")?;
-        
-        let diff = repo.diff_index_to_workdir(None, None)
-            .context("Failed to get diff between index and working directory")?;
-        
-        let mut diff_output = String::new();
-        diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
-            let content = std::str::from_utf8(line.content()).unwrap_or("");
-            diff_output.push_str(content);
-            true
-        })?;
-        
-        Ok(diff_output)
-    }
-    
-    pub fn resolve_merge_conflict(
-        repo_path: &Path,
-        file_path: &Path,
-        resolution: &str
-    ) -> Result<()> {
-        use std::fs;
-        
-        // Write the resolution to the file
-        fs::write(file_path, resolution)
-            .context("Failed to write resolution to file")?;
-        
-        // Mark as resolved with git add
-        let relative_path = file_path.strip_prefix(repo_path)
-            .unwrap_or(file_path)
-            .to_str()
-            .ok_or_else(|| anyhow::anyhow!("Failed to convert path to string"))?;
-        
-        let output = std::process::Command::new("git")
-            .current_dir(repo_path)
-            .args(&["add", relative_path])
-            .output()
-            .context("Failed to execute git add")?;
-        
-        if !output.status.success() {
-            return Err(anyhow::anyhow!(
-                "Failed to mark file as resolved: {}",
-                String::from_utf8_lossy(&output.stderr)
-            ));
+        let base_commit = resolve_commit(&repo, base)?;
+        let head_commit = resolve_commit(&repo, head)?;
+        let fork_point = repo.merge_base(base_commit.id(), head_commit.id())
+            .with_context(|| format!("{} and {} have no common ancestor", base, head))?;
+
+        let old_tree = repo.find_commit(fork_point)?.tree()?;
+        let new_tree = head_commit.tree()?;
+        let mut diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)
+            .context("Failed to diff the revisions")?;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true).copies(true)))?;
+
+        let mut files = Vec::new();
+        for idx in 0..diff.deltas().len() {
+            let mut patch = match Patch::from_diff(&diff, idx)? {
+                Some(patch) => patch,
+                None => continue,
+            };
+            let delta = patch.delta();
+            let status = match delta.status() {
+                Delta::Added => 'A',
+                Delta::Deleted => 'D',
+                Delta::Renamed => 'R',
+                Delta::Copied => 'C',
+                Delta::Typechange => 'T',
+                _ => 'M',
+            };
+            let new_path = delta.new_file().path().map(Path::to_path_buf);
+            let old_path = delta.old_file().path().map(Path::to_path_buf);
+            let path = match new_path.clone().or_else(|| old_path.clone()) {
+                Some(path) => path,
+                None => continue,
+            };
+            let old_path = old_path.filter(|old| matches!(status, 'R' | 'C') && Some(old) != new_path.as_ref());
+
+            let (_, additions, deletions) = patch.line_stats()?;
+            let text = patch.to_buf()?;
+            files.push(FileDiff {
+                path,
+                old_path,
+                status,
+                additions,
+                deletions,
+                patch: String::from_utf8_lossy(&text).to_string(),
+            });
         }
-        
-        Ok(())
+
+        Ok(files)
     }
 }
+
+/// The commit a branch, tag or other revision points to
+pub fn resolve_commit<'r>(repo: &'r Repository, revision: &str) -> Result<git2::Commit<'r>> {
+    repo.revparse_single(revision)
+        .and_then(|object| object.peel_to_commit())
+        .with_context(|| format!("Unknown revision '{}'", revision))
+}
@@ -1,86 +1,97 @@
-use git2::{Repository, Commit, Error as Git2Error};
+use git2::Repository;
 use anyhow::{Result, Context, anyhow};
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::diff::resolve_commit;
 
 pub struct GitHistory;
 
 impl GitHistory {
-    pub fn get_commit_history(repo_path: &Path, max_count: usize) -> Result<Vec<CommitInfo>> {
-        let repo = Repository::open(repo_path)
+    /// How many of the last `max_commits` commits on HEAD touched each file,
+    /// with paths relative to `repo_path` (files outside it are left out)
+    pub fn change_counts(repo_path: &Path, max_commits: usize) -> Result<HashMap<PathBuf, usize>> {
+        let repo = Repository::discover(repo_path)
             .context("Failed to open git repository")?;
-        
+        let workdir = repo.workdir()
+            .ok_or_else(|| anyhow!("Repository has no working directory"))?
+            .canonicalize()?;
+        let prefix = repo_path.canonicalize()?.strip_prefix(&workdir).map(Path::to_path_buf).unwrap_or_default();
+
         let mut revwalk = repo.revwalk()
             .context("Failed to create revision walker")?;
-        
         revwalk.push_head()
             .context("Failed to push HEAD to revision walker")?;
-        
-        let mut commits = Vec::new();
-        
-        for (i, oid_result) in revwalk.enumerate() {
-            if i >= max_count {
-                break;
-            }
-            
-            let oid = oid_result?;
-            let commit = repo.find_commit(oid)?;
-            
-            let commit_info = CommitInfo {
-                id: commit.id().to_string(),
-                author: commit.author().name().unwrap_or("Unknown").to_string(),
-                email: commit.author().email().unwrap_or("").to_string(),
-                time: commit.time().seconds(),
-                message: commit.message().unwrap_or("").to_string(),
+
+        let mut counts = HashMap::new();
+        for oid_result in revwalk.take(max_commits) {
+            let commit = repo.find_commit(oid_result?)?;
+            let parent_tree = match commit.parents().next() {
+                Some(parent) => Some(parent.tree()?),
+                None => None,
             };
-            
-            commits.push(commit_info);
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            for delta in diff.deltas() {
+                if let Some(path) = delta.new_file().path().and_then(|p| p.strip_prefix(&prefix).ok()) {
+                    *counts.entry(path.to_path_buf()).or_insert(0) += 1;
+                }
+            }
         }
-        
-        Ok(commits)
+
+        Ok(counts)
     }
-    
-    pub fn search_commits(repo_path: &Path, query: &str) -> Result<Vec<CommitInfo>> {
-        let repo = Repository::open(repo_path)
+
+    /// Commits reachable from `head` but not from `base`, newest first
+    pub fn commits_in_range(repo_path: &Path, base: &str, head: &str) -> Result<Vec<CommitInfo>> {
+        let repo = Repository::discover(repo_path)
             .context("Failed to open git repository")?;
-        
+        let base_commit = resolve_commit(&repo, base)?;
+        let head_commit = resolve_commit(&repo, head)?;
+
         let mut revwalk = repo.revwalk()
             .context("Failed to create revision walker")?;
-        
-        revwalk.push_head()
-            .context("Failed to push HEAD to revision walker")?;
-        
-        let query_lower = query.to_lowercase();
-        let mut matching_commits = Vec::new();
-        
+        revwalk.push(head_commit.id())?;
+        revwalk.hide(base_commit.id())?;
+
+        let mut commits = Vec::new();
         for oid_result in revwalk {
-            let oid = oid_result?;
-            let commit = repo.find_commit(oid)?;
-            
-            let message = commit.message().unwrap_or("").to_lowercase();
-            let author = commit.author().name().unwrap_or("").to_lowercase();
-            
-            if message.contains(&query_lower) || author.contains(&query_lower) {
-                let commit_info = CommitInfo {
-                    id: commit.id().to_string(),
-                    author: commit.author().name().unwrap_or("Unknown").to_string(),
-                    email: commit.author().email().unwrap_or("").to_string(),
-                    time: commit.time().seconds(),
-                    message: commit.message().unwrap_or("").to_string(),
-                };
-                
-                matching_commits.push(commit_info);
-            }
+            let commit = repo.find_commit(oid_result?)?;
+            commits.push(CommitInfo {
+                id: commit.id().to_string(),
+                message: commit.message().unwrap_or("").to_string(),
+            });
+        }
+
+        Ok(commits)
+    }
+
+    /// Contents of `path` (relative to `repo_path`) as of `revision`: a
+    /// commit, branch, tag or an expression like `HEAD~3`
+    pub fn file_at_revision(repo_path: &Path, revision: &str, path: &Path) -> Result<String> {
+        let repo = Repository::discover(repo_path)
+            .context("Failed to open git repository")?;
+        let workdir = repo.workdir()
+            .ok_or_else(|| anyhow!("Repository has no working directory"))?
+            .canonicalize()?;
+        let prefix = repo_path.canonicalize()?.strip_prefix(&workdir).map(Path::to_path_buf).unwrap_or_default();
+        let path = path.strip_prefix(repo_path).unwrap_or(path);
+
+        let commit = resolve_commit(&repo, revision)?;
+        let entry = commit.tree()?
+            .get_path(&prefix.join(path))
+            .with_context(|| format!("{} does not exist at {}", path.display(), revision))?;
+        let blob = entry.to_object(&repo)?
+            .into_blob()
+            .map_err(|_| anyhow!("{} is a directory at {}", path.display(), revision))?;
+        if blob.is_binary() {
+            return Err(anyhow!("{} is a binary file", path.display()));
         }
-        
-        Ok(matching_commits)
+        Ok(String::from_utf8_lossy(blob.content()).into_owned())
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct CommitInfo {
     pub id: String,
-    pub author: String,
-    pub email: String,
-    pub time: i64,
     pub message: String,
 }
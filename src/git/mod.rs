@@ -1,3 +1,67 @@
 pub mod commands;
 pub mod history;
 pub mod diff;
+pub mod remote;
+
+use git2::Repository;
+use once_cell::sync::OnceCell;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// How git features are provided in a project
+#[derive(Debug, Clone, PartialEq)]
+pub enum GitSupport {
+    /// The git binary is installed
+    Binary,
+    /// No git binary; the built-in libgit2 implementation is used
+    Library,
+    /// Git features are off, with the reason
+    Disabled(String),
+}
+
+impl GitSupport {
+    /// Checks the config, the git binary and the repository at `project_root`
+    pub fn detect(project_root: &Path, enabled: bool) -> Self {
+        if !enabled {
+            return GitSupport::Disabled("disabled by git.enable_git_features".to_string());
+        }
+        if Repository::discover(project_root).is_err() {
+            return GitSupport::Disabled("not a git repository".to_string());
+        }
+        if binary_available() {
+            GitSupport::Binary
+        } else {
+            GitSupport::Library
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self, GitSupport::Disabled(_))
+    }
+
+    /// Message for the user when git works differently than they may expect.
+    /// A project that simply isn't a repository gets no notice.
+    pub fn notice(&self) -> Option<String> {
+        match self {
+            GitSupport::Library => Some("git not found; using built-in git support (commit hooks will not run)".to_string()),
+            GitSupport::Disabled(reason) if !binary_available() && reason == "not a git repository" => {
+                Some("git not found and no repository to open; git features are disabled".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Whether the `git` binary can be run, checked once per process
+pub fn binary_available() -> bool {
+    static AVAILABLE: OnceCell<bool> = OnceCell::new();
+    *AVAILABLE.get_or_init(|| {
+        Command::new("git")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false)
+    })
+}
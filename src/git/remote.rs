@@ -0,0 +1,213 @@
+use anyhow::{anyhow, Context, Result};
+use git2::{
+    Cred, CredentialType, ErrorClass, ErrorCode, FetchOptions, PushOptions, RemoteCallbacks, Repository,
+};
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+
+use crate::config::GitConfig;
+
+/// Push and pull through libgit2, authenticating the way the git binary
+/// would: ssh-agent and default SSH keys for SSH remotes, then a token from
+/// the config or the configured credential helper for HTTPS remotes
+pub struct GitRemote {
+    token: Option<String>,
+}
+
+impl GitRemote {
+    pub fn new(config: &GitConfig) -> Self {
+        Self {
+            token: config.token.clone(),
+        }
+    }
+
+    /// Fetches `remote` and fast-forwards the current branch to its upstream
+    pub fn pull(&self, repo_path: &Path, remote: &str) -> Result<String> {
+        let repo = Repository::discover(repo_path).context("Failed to open git repository")?;
+        let branch = current_branch(&repo)?;
+
+        let attempts = Attempts::default();
+        {
+            let mut git_remote = repo.find_remote(remote).with_context(|| format!("No remote named {}", remote))?;
+            let mut options = FetchOptions::new();
+            options.remote_callbacks(self.callbacks(&repo, &attempts));
+            git_remote
+                .fetch(&[&branch], Some(&mut options), None)
+                .map_err(|e| remote_error("pull from", remote, e, &attempts))?;
+        }
+
+        let fetch_head = repo.find_reference("FETCH_HEAD").context("Nothing was fetched")?;
+        let fetched = repo.reference_to_annotated_commit(&fetch_head)?;
+        let (analysis, _) = repo.merge_analysis(&[&fetched])?;
+
+        if analysis.is_up_to_date() {
+            return Ok("Already up to date".to_string());
+        }
+        if !analysis.is_fast_forward() {
+            return Err(anyhow!(
+                "Cannot fast-forward {} to {}/{}; merge or rebase the branches first",
+                branch,
+                remote,
+                branch
+            ));
+        }
+
+        // Update the working tree first so local changes in the way abort the pull
+        let target = repo.find_object(fetched.id(), None)?;
+        repo.checkout_tree(&target, Some(git2::build::CheckoutBuilder::default().safe()))
+            .context("Failed to update the working tree; commit or stash local changes first")?;
+        let refname = format!("refs/heads/{}", branch);
+        let mut reference = repo.find_reference(&refname)?;
+        reference.set_target(fetched.id(), &format!("pull: fast-forward to {}/{}", remote, branch))?;
+
+        Ok(format!("Fast-forwarded {} to {}", branch, &fetched.id().to_string()[..7]))
+    }
+
+    /// Pushes the current branch to the branch of the same name on `remote`
+    pub fn push(&self, repo_path: &Path, remote: &str) -> Result<String> {
+        let repo = Repository::discover(repo_path).context("Failed to open git repository")?;
+        let branch = current_branch(&repo)?;
+        let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+
+        let attempts = Attempts::default();
+        let rejected = RefCell::new(None);
+        {
+            let mut callbacks = self.callbacks(&repo, &attempts);
+            callbacks.push_update_reference(|refname, status| {
+                if let Some(status) = status {
+                    *rejected.borrow_mut() = Some(format!("{}: {}", refname, status));
+                }
+                Ok(())
+            });
+
+            let mut git_remote = repo.find_remote(remote).with_context(|| format!("No remote named {}", remote))?;
+            let mut options = PushOptions::new();
+            options.remote_callbacks(callbacks);
+            git_remote
+                .push(&[&refspec], Some(&mut options))
+                .map_err(|e| remote_error("push to", remote, e, &attempts))?;
+        }
+
+        if let Some(reason) = rejected.into_inner() {
+            return Err(anyhow!("{} rejected the push of {}", remote, reason));
+        }
+        Ok(format!("Pushed {} to {}", branch, remote))
+    }
+
+    /// Credential callbacks that try each method once, recording what was
+    /// tried so an authentication failure can say so
+    fn callbacks<'a>(&'a self, repo: &Repository, attempts: &'a Attempts) -> RemoteCallbacks<'a> {
+        let git_config = repo.config().ok();
+        let mut callbacks = RemoteCallbacks::new();
+
+        callbacks.credentials(move |url, username_from_url, allowed| {
+            let username = username_from_url.unwrap_or("git");
+
+            if allowed.contains(CredentialType::USERNAME) {
+                return Cred::username(username);
+            }
+
+            if allowed.contains(CredentialType::SSH_KEY) {
+                if attempts.try_method("ssh-agent") {
+                    return Cred::ssh_key_from_agent(username);
+                }
+                for key in default_ssh_keys() {
+                    if attempts.try_method(&key.display().to_string()) {
+                        return Cred::ssh_key(username, None, &key, None);
+                    }
+                }
+            }
+
+            if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                if let Some(token) = &self.token {
+                    if attempts.try_method("token from git.token") {
+                        return Cred::userpass_plaintext(username_from_url.unwrap_or("x-access-token"), token);
+                    }
+                }
+                if let Some(git_config) = &git_config {
+                    // Fails when no helper is configured or it has nothing stored
+                    if attempts.try_method("credential helper") {
+                        if let Ok(cred) = Cred::credential_helper(git_config, url, username_from_url) {
+                            return Ok(cred);
+                        }
+                    }
+                }
+            }
+
+            if allowed.contains(CredentialType::DEFAULT) && attempts.try_method("default credentials") {
+                return Cred::default();
+            }
+
+            Err(git2::Error::new(ErrorCode::Auth, ErrorClass::Callback, "no more credentials to try"))
+        });
+
+        callbacks
+    }
+}
+
+/// Credential methods already offered to the remote
+#[derive(Default)]
+struct Attempts {
+    tried: RefCell<Vec<String>>,
+}
+
+impl Attempts {
+    /// Records `method` and returns whether it hadn't been tried yet
+    fn try_method(&self, method: &str) -> bool {
+        let mut tried = self.tried.borrow_mut();
+        if tried.iter().any(|m| m == method) {
+            return false;
+        }
+        tried.push(method.to_string());
+        true
+    }
+}
+
+/// Tells authentication failures apart from network failures
+fn remote_error(operation: &str, remote: &str, error: git2::Error, attempts: &Attempts) -> anyhow::Error {
+    let tried = attempts.tried.borrow();
+    let is_auth = error.code() == ErrorCode::Auth
+        || (error.class() == ErrorClass::Http && error.message().contains("401"))
+        || (error.class() == ErrorClass::Ssh && error.message().contains("authenticat"));
+
+    if is_auth {
+        let tried = if tried.is_empty() {
+            "no credentials were requested".to_string()
+        } else {
+            format!("tried {}", tried.join(", "))
+        };
+        anyhow!(
+            "Authentication failed to {} {} ({}). Load a key into ssh-agent, configure a git credential helper, or set git.token",
+            operation,
+            remote,
+            tried
+        )
+    } else if matches!(error.class(), ErrorClass::Net | ErrorClass::Http | ErrorClass::Ssh | ErrorClass::Os) {
+        anyhow!("Network error trying to {} {}: {}", operation, remote, error.message())
+    } else {
+        anyhow!("Failed to {} {}: {}", operation, remote, error.message())
+    }
+}
+
+fn current_branch(repo: &Repository) -> Result<String> {
+    let head = repo.head().context("The repository has no commits yet")?;
+    if !head.is_branch() {
+        return Err(anyhow!("HEAD is detached; check out a branch first"));
+    }
+    head.shorthand()
+        .map(String::from)
+        .ok_or_else(|| anyhow!("The current branch name is not valid UTF-8"))
+}
+
+/// Private keys ssh would try when no agent is running
+fn default_ssh_keys() -> Vec<PathBuf> {
+    let ssh_dir = match dirs::home_dir() {
+        Some(home) => home.join(".ssh"),
+        None => return Vec::new(),
+    };
+    ["id_ed25519", "id_ecdsa", "id_rsa"]
+        .iter()
+        .map(|name| ssh_dir.join(name))
+        .filter(|path| path.exists())
+        .collect()
+}
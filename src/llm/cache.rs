@@ -0,0 +1,87 @@
+use crate::fs::storage::Storage;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+
+/// On-disk cache of LLM responses keyed by a hash of the full request
+pub struct ResponseCache {
+    dir: PathBuf,
+    storage: Storage,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    model: String,
+    created_at: String,
+    response: String,
+}
+
+impl ResponseCache {
+    pub fn new(storage: Storage) -> Self {
+        Self { dir: Self::default_dir(), storage }
+    }
+
+    /// Directory holding one file per cached response
+    pub fn default_dir() -> PathBuf {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("code-assist/responses")
+    }
+
+    /// Builds the cache key for a request to `api_url` from the request
+    /// body, so the model, prompts, temperature, max_tokens and
+    /// response_format all tell requests apart
+    pub fn key(api_url: &str, request: &str) -> String {
+        let mut hasher = Sha256::new();
+        for part in [api_url, request] {
+            hasher.update(part.as_bytes());
+            // Separator so ("ab", "c") and ("a", "bc") hash differently
+            hasher.update([0u8]);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let content = self.storage.read(&self.entry_path(key)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+        Some(entry.response)
+    }
+
+    pub fn put(&self, key: &str, model: &str, response: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create cache directory: {}", self.dir.display()))?;
+
+        let entry = CacheEntry {
+            model: model.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            response: response.to_string(),
+        };
+
+        self.storage.write(&self.entry_path(key), &serde_json::to_string(&entry)?)
+            .with_context(|| format!("Failed to write cache entry {}", key))
+    }
+
+    /// Deletes every cached response; returns how many there were
+    pub fn clear() -> Result<usize> {
+        let dir = Self::default_dir();
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+        };
+        let mut removed = 0;
+        for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+            if path.extension().is_some_and(|ext| ext == "json") {
+                fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
@@ -1,8 +1,40 @@
-use crate::config::Config;
+use crate::analysis::tree::format_size;
+use crate::commands::policy::ActionPolicy;
+use crate::config::{Config, LlmProvider, ModelTarget, ReasoningDisplay};
+use crate::fs::storage::Storage;
+use crate::llm::cache::ResponseCache;
+use crate::llm::debug_log::DebugLog;
+use crate::llm::prompt::{PromptBuilder, PromptContext};
+use crate::llm::reasoning::{self, LiveReasoning};
+use crate::ui::interface::UserInterface;
 use anyhow::{anyhow, Context, Result};
-use log::debug;
+use colored::Colorize;
+use log::{debug, warn};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const MODEL_LIST_TIMEOUT: Duration = Duration::from_secs(5);
+const OPENROUTER_REFERER: &str = "https://github.com/helder/code-assist";
+const OPENROUTER_TITLE: &str = "code-assist";
+/// Rough bytes per token, used to spot prompts a provider cut without saying
+/// so and to budget the context
+pub const BYTES_PER_TOKEN: usize = 4;
+/// Prompts shorter than this many estimated tokens are not checked for silent cuts
+const MIN_TRUNCATION_CHECK_TOKENS: usize = 4000;
+/// Error texts of providers rejecting a prompt longer than the model accepts
+const OVERSIZED_ERROR_HINTS: &[&str] = &[
+    "context length",
+    "context_length",
+    "context window",
+    "maximum context",
+    "too long",
+    "too large",
+    "too many tokens",
+];
 
 #[derive(Debug, Serialize)]
 struct ChatMessage {
@@ -16,100 +48,804 @@ struct ChatRequest {
     messages: Vec<ChatMessage>,
     temperature: f32,
     max_tokens: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ChatResponse {
     choices: Vec<ChatChoice>,
+    #[serde(default)]
+    usage: Option<TokenUsage>,
+}
+
+/// Token accounting reported by the provider
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+/// Content of an LLM reply plus its token usage (None for cached replies)
+#[derive(Debug, Clone)]
+pub struct LlmResponse {
+    pub content: String,
+    /// Model reasoning, kept out of `content` so it never reaches action parsing
+    pub reasoning: Option<String>,
+    pub usage: Option<TokenUsage>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ChatChoice {
     message: ChatChoiceMessage,
+    /// `length` when the reply was cut at `max_tokens`
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct ChatChoiceMessage {
-    content: String,
+    /// Null for some reasoning models that ran out of tokens while thinking
+    #[serde(default)]
+    content: Option<String>,
+    /// Separate reasoning as sent by OpenRouter (`reasoning`) and DeepSeek (`reasoning_content`)
+    #[serde(default, alias = "reasoning_content")]
+    reasoning: Option<String>,
+}
+
+/// The last request sent to the model and its raw reply, for `/debug last`
+#[derive(Debug, Clone)]
+pub struct LlmExchange {
+    pub url: String,
+    pub model: String,
+    pub system: String,
+    pub user: String,
+    /// Whether the reply was constrained by a `response_format` schema
+    pub structured: bool,
+    /// HTTP status of the reply; None for replies from the response cache
+    pub status: Option<u16>,
+    /// Response body as received, or the cached content
+    pub response: String,
 }
 
 pub struct LlmClient {
     client: Client,
     config: Config,
+    /// Where warnings about requests and replies go
+    ui: Arc<dyn UserInterface>,
+    /// None while the cache is off; `/cache` turns it on and off
+    cache: Mutex<Option<ResponseCache>>,
+    prompts: PromptBuilder,
+    /// Contents of `llm.system_prompt_path`, if configured
+    custom_system_prompt: Option<String>,
+    /// Payload dumps for `--debug-llm`
+    debug_log: Option<DebugLog>,
+    /// Provider URLs that rejected `response_format`; they get plain requests
+    no_structured_output: Mutex<HashSet<String>>,
+    /// The last request and reply of this session
+    last_exchange: Mutex<Option<LlmExchange>>,
+    /// Cache key, model and content of the last reply in the action
+    /// envelope, cached by `cache_reply` once it parsed into an action
+    uncached_reply: Mutex<Option<(String, String, String)>>,
+    /// Replies already taken from `llm.mock_responses`
+    #[cfg(feature = "test-support")]
+    mock_replies_used: std::sync::atomic::AtomicUsize,
 }
 
 impl LlmClient {
-    pub fn new(config: &Config) -> Result<Self> {
+    pub fn new(config: &Config, ui: Arc<dyn UserInterface>) -> Result<Self> {
         let client = Client::new();
+        let storage = Storage::new(&config.privacy)?;
+        let cache = if config.cache.enabled {
+            Some(ResponseCache::new(storage))
+        } else {
+            None
+        };
+
+        let custom_system_prompt = match &config.llm.system_prompt_path {
+            Some(path) => Some(std::fs::read_to_string(path).with_context(|| {
+                format!("Failed to read system prompt file: {}", path.display())
+            })?),
+            None => None,
+        };
+
+        for target in config.llm.targets(None) {
+            if target.provider == LlmProvider::OpenRouter && !target.model.contains('/') {
+                ui.print(&format!(
+                    "{} OpenRouter model names include the vendor, e.g. 'openai/{}'",
+                    "!".yellow(),
+                    target.model
+                ));
+            }
+        }
 
         Ok(Self {
             client,
             config: config.clone(),
+            ui,
+            cache: Mutex::new(cache),
+            prompts: PromptBuilder::new()?,
+            custom_system_prompt,
+            debug_log: config.llm.debug_log.then(|| DebugLog::new(&std::env::current_dir().unwrap_or_default(), storage)),
+            no_structured_output: Mutex::new(HashSet::new()),
+            last_exchange: Mutex::new(None),
+            uncached_reply: Mutex::new(None),
+            #[cfg(feature = "test-support")]
+            mock_replies_used: std::sync::atomic::AtomicUsize::new(0),
         })
     }
 
-    pub async fn process_command(&self, command: &str, context: &str) -> Result<String> {
-        let system_message = format!(
-            "You are CodeAssist, an AI coding assistant that helps users with their codebase. \
-            You analyze the context and the user's command, and respond with specific actions to take. \
-            Respond in JSON format with the following structure: \
-            {{\"action\": \"<action_type>\", \"details\": {{...action specific details...}}}}. \
-            Possible actions: edit_file, answer_question, execute_command, git_operation."
-        );
+    pub async fn process_command(&self, prompt_context: &PromptContext, policy: &ActionPolicy) -> Result<LlmResponse> {
+        // Ecosystem conventions first, so the user's own instructions can refine them
+        let mut custom_prompts: Vec<String> = prompt_context.conventions.iter().cloned().collect();
+        custom_prompts.extend(self.custom_system_prompt.iter().cloned());
+        custom_prompts.extend(prompt_context.memory_system_prompt.clone());
 
-        let user_message = format!(
-            "Command: {}\n\nContext from codebase:\n{}",
-            command, context
-        );
+        let system_message = self.prompts.build_system_prompt(policy, &custom_prompts, prompt_context)?;
+        let user_message = self.prompts.build_user_prompt(prompt_context)?;
 
-        let request = ChatRequest {
-            model: self.config.llm.model.clone(),
+        let targets = self.config.llm.targets(policy.task());
+        self.chat(&targets, &system_message, &user_message, Some(policy.response_format()), true)
+            .await
+    }
+
+    /// Plain completion outside the action envelope, for internal tasks such
+    /// as summarization. The model can be routed per `task`; replies are cached.
+    pub async fn complete(&self, task: &str, system_message: &str, user_message: &str) -> Result<String> {
+        let targets = self.config.llm.targets(Some(task));
+        let response = self.chat(&targets, system_message, user_message, None, false).await?;
+        Ok(response.content)
+    }
+
+    /// Sends a chat exchange to the first target that answers, using the cache
+    /// and stripping reasoning from the content. `interactive` exchanges may
+    /// stream reasoning to the terminal and announce cache hits.
+    async fn chat(
+        &self,
+        targets: &[ModelTarget],
+        system_message: &str,
+        user_message: &str,
+        response_format: Option<serde_json::Value>,
+        interactive: bool,
+    ) -> Result<LlmResponse> {
+        let structured = response_format.clone().filter(|_| self.config.llm.structured_output);
+        let request = self.build_request(&targets[0], system_message, user_message, structured, false);
+        let cache_key = ResponseCache::key(&targets[0].api_url, &serde_json::to_string(&request)?);
+        let cached = self.cache.lock().ok().and_then(|cache| cache.as_ref().and_then(|cache| cache.get(&cache_key)));
+        if let Some(cached) = cached {
+            if interactive {
+                self.ui.print(&"(using cached response)".dimmed().to_string());
+            }
+            self.set_last_exchange(LlmExchange {
+                url: "(response cache)".to_string(),
+                model: targets[0].model.clone(),
+                system: system_message.to_string(),
+                user: user_message.to_string(),
+                structured: false,
+                status: None,
+                response: cached.clone(),
+            });
+            return Ok(LlmResponse {
+                content: cached,
+                reasoning: None,
+                usage: None,
+            });
+        }
+
+        let mut last_error = None;
+        let mut chat_response = None;
+
+        for (idx, target) in targets.iter().enumerate() {
+            let stream = interactive && self.config.llm.reasoning == ReasoningDisplay::Live;
+            match self.send_chat(target, system_message, user_message, response_format.clone(), stream).await {
+                Ok(response) => {
+                    chat_response = Some((response, &target.model));
+                    break;
+                }
+                Err(e) => {
+                    if let Some(next) = targets.get(idx + 1) {
+                        self.ui.print(&format!(
+                            "{} Model '{}' failed ({}); falling back to '{}'",
+                            "!".yellow(),
+                            target.model,
+                            e.root_cause(),
+                            next.model
+                        ));
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        let (reply, answered_by) = match chat_response {
+            Some(response) => response,
+            None => return Err(last_error.unwrap_or_else(|| anyhow!("No LLM model configured"))),
+        };
+
+        // Reasoning is never cached; only the content is replayed
+        let (inline_reasoning, content) = reasoning::split_reasoning(&reply.content);
+        let reasoning = match (reply.reasoning, inline_reasoning) {
+            (Some(field), Some(inline)) => Some(format!("{}\n{}", field, inline)),
+            (field, inline) => field.or(inline),
+        };
+
+        // A reply that doesn't parse into an action must not be replayed
+        if response_format.is_some() {
+            if let Ok(mut uncached) = self.uncached_reply.lock() {
+                *uncached = Some((cache_key, answered_by.clone(), content.clone()));
+            }
+        } else {
+            self.cache_put(&cache_key, answered_by, &content);
+        }
+
+        Ok(LlmResponse {
+            content,
+            reasoning,
+            usage: reply.usage,
+        })
+    }
+
+    /// Caches the last reply of `process_command`, once it parsed into an action
+    pub fn cache_reply(&self) {
+        let uncached = self.uncached_reply.lock().ok().and_then(|mut uncached| uncached.take());
+        if let Some((key, model, content)) = uncached {
+            self.cache_put(&key, &model, &content);
+        }
+    }
+
+    /// Turns the response cache on or off for the rest of the session
+    pub fn set_cache(&self, enabled: bool) -> Result<()> {
+        let cache = if enabled {
+            Some(ResponseCache::new(Storage::new(&self.config.privacy)?))
+        } else {
+            None
+        };
+        if let Ok(mut current) = self.cache.lock() {
+            *current = cache;
+        }
+        Ok(())
+    }
+
+    fn cache_put(&self, key: &str, model: &str, content: &str) {
+        let cache = match self.cache.lock() {
+            Ok(cache) => cache,
+            Err(_) => return,
+        };
+        if let Some(cache) = cache.as_ref() {
+            if let Err(e) = cache.put(key, model, content) {
+                warn!("Failed to cache LLM response: {}", e);
+            }
+        }
+    }
+
+    /// Sends one chat completion request to a single model/provider.
+    ///
+    /// When structured output is enabled the action envelope is sent as a
+    /// `response_format` JSON schema. Providers that reject it with 400/422 are
+    /// retried without it and remembered for the rest of the session.
+    /// Streamed replies show reasoning as it arrives. The returned content may
+    /// still hold inline reasoning.
+    async fn send_chat(
+        &self,
+        target: &ModelTarget,
+        system_message: &str,
+        user_message: &str,
+        response_format: Option<serde_json::Value>,
+        stream: bool,
+    ) -> Result<LlmResponse> {
+        #[cfg(feature = "test-support")]
+        if target.provider == LlmProvider::Mock {
+            return self.mock_reply();
+        }
+
+        let mut response_format = response_format
+            .filter(|_| self.config.llm.structured_output && !self.rejects_structured_output(&target.api_url));
+        let mut retried_without_schema = false;
+        let user_message = self.fit_request(target, system_message, user_message, &response_format)?;
+
+        loop {
+            let request = self.build_request(target, system_message, &user_message, response_format.clone(), stream);
+            let request_bytes = serde_json::to_vec(&request).map_or(0, |body| body.len());
+
+            debug!("Sending request to LLM: {:?}", request);
+
+            let url = format!("{}/chat/completions", target.api_url);
+            let debug_id = self.debug_log.as_ref().map(|log| {
+                let id = log.request(&url, &request);
+                self.ui.print(&format!("LLM request {} (payloads in {})", id, log.payload_dir().display()).dimmed().to_string());
+                id
+            });
+            self.set_last_exchange(LlmExchange {
+                url: url.clone(),
+                model: target.model.clone(),
+                system: system_message.to_string(),
+                user: user_message.clone(),
+                structured: response_format.is_some(),
+                status: None,
+                response: String::new(),
+            });
+            let mut builder = self
+                .client
+                .post(&url)
+                .header("Content-Type", "application/json")
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", target.api_key),
+                )
+                .timeout(Duration::from_secs(self.config.llm.request_timeout_secs));
+
+            if target.provider == LlmProvider::OpenRouter {
+                // OpenRouter attributes requests to an app through these optional headers
+                builder = builder
+                    .header("HTTP-Referer", OPENROUTER_REFERER)
+                    .header("X-Title", OPENROUTER_TITLE);
+            }
+
+            let response = builder
+                .json(&request)
+                .send()
+                .await
+                .context("Failed to send request to LLM API")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let text = response.text().await?;
+                self.log_response(debug_id.as_deref(), status.as_u16(), &text);
+
+                if is_oversized_error(status.as_u16(), &text) {
+                    return Err(anyhow!(
+                        "LLM API error: {} - the {} request is too large for model '{}'; set max_request_bytes under [llm] below that to trim the context ({})",
+                        status,
+                        format_size(request_bytes as u64),
+                        target.model,
+                        text
+                    ));
+                }
+                if response_format.is_some() && matches!(status.as_u16(), 400 | 422) {
+                    debug!("{} rejected response_format ({}); retrying without it", target.api_url, text);
+                    response_format = None;
+                    retried_without_schema = true;
+                    continue;
+                }
+                return Err(anyhow!("LLM API error: {} - {}", status, text));
+            }
+
+            // Only a successful plain retry proves the schema was the problem
+            if retried_without_schema {
+                if let Ok(mut rejected) = self.no_structured_output.lock() {
+                    rejected.insert(target.api_url.clone());
+                }
+            }
+
+            if stream {
+                let mut raw = Vec::new();
+                let result = self.read_stream(response, &mut raw).await;
+                self.log_response(debug_id.as_deref(), 200, &String::from_utf8_lossy(&raw));
+                let (reply, finish_reason) = result?;
+                warn_if_truncated(self.ui.as_ref(), target, request_bytes, &reply, finish_reason.as_deref(), self.config.llm.max_tokens);
+                return Ok(reply);
+            }
+
+            let status = response.status().as_u16();
+            let text = response.text().await.context("Failed to read LLM API response")?;
+            self.log_response(debug_id.as_deref(), status, &text);
+            let chat_response: ChatResponse = serde_json::from_str(&text)
+                .context("Failed to parse LLM API response")?;
+
+            let choice = match chat_response.choices.into_iter().next() {
+                Some(choice) => choice,
+                None => return Err(anyhow!("LLM returned empty response")),
+            };
+
+            let reply = LlmResponse {
+                content: choice.message.content.unwrap_or_default(),
+                reasoning: choice.message.reasoning.filter(|r| !r.trim().is_empty()),
+                usage: chat_response.usage,
+            };
+            warn_if_truncated(self.ui.as_ref(), target, request_bytes, &reply, choice.finish_reason.as_deref(), self.config.llm.max_tokens);
+            return Ok(reply);
+        }
+    }
+
+    fn build_request(
+        &self,
+        target: &ModelTarget,
+        system_message: &str,
+        user_message: &str,
+        response_format: Option<serde_json::Value>,
+        stream: bool,
+    ) -> ChatRequest {
+        ChatRequest {
+            model: target.model.clone(),
             messages: vec![
                 ChatMessage {
                     role: "system".to_string(),
-                    content: system_message,
+                    content: system_message.to_string(),
                 },
                 ChatMessage {
                     role: "user".to_string(),
-                    content: user_message,
+                    content: user_message.to_string(),
                 },
             ],
             temperature: self.config.llm.temperature,
             max_tokens: self.config.llm.max_tokens,
+            response_format,
+            stream,
+            stream_options: stream.then(|| serde_json::json!({ "include_usage": true })),
+        }
+    }
+
+    /// The user message cut from the end so the request fits the target's
+    /// `max_request_bytes`, with a notice telling the model context is missing.
+    /// The command comes first in the user message, so only context is lost.
+    fn fit_request(
+        &self,
+        target: &ModelTarget,
+        system_message: &str,
+        user_message: &str,
+        response_format: &Option<serde_json::Value>,
+    ) -> Result<String> {
+        let request = self.build_request(target, system_message, user_message, response_format.clone(), false);
+        let size = serde_json::to_vec(&request).map_or(0, |body| body.len());
+        if size <= target.max_request_bytes {
+            return Ok(user_message.to_string());
+        }
+
+        let notice = format!(
+            "\n\n[Context cut here: the request was {}, over the {} limit for this model. \
+             Files or sections may be missing; say so if the answer depends on them.]",
+            format_size(size as u64),
+            format_size(target.max_request_bytes as u64)
+        );
+        // Escaping only makes the JSON longer, so cutting this many raw bytes is enough
+        let excess = size - target.max_request_bytes + serde_json::to_string(&notice).map_or(notice.len(), |n| n.len());
+        let mut keep = match user_message.len().checked_sub(excess) {
+            Some(keep) if keep > 0 => keep,
+            _ => {
+                return Err(anyhow!(
+                    "The prompt is over the {} request limit for model '{}' even without context; raise max_request_bytes under [llm]",
+                    format_size(target.max_request_bytes as u64),
+                    target.model
+                ))
+            }
         };
+        while !user_message.is_char_boundary(keep) {
+            keep -= 1;
+        }
 
-        debug!("Sending request to LLM: {:?}", request);
+        self.ui.print(&format!(
+            "{} The request to '{}' is {}, over its {} limit; {} of context were left out (max_request_bytes under [llm])",
+            "!".yellow(),
+            target.model,
+            format_size(size as u64),
+            format_size(target.max_request_bytes as u64),
+            format_size((user_message.len() - keep) as u64)
+        ));
+        Ok(format!("{}{}", &user_message[..keep], notice))
+    }
+
+    /// Next scripted reply of the mock provider
+    #[cfg(feature = "test-support")]
+    fn mock_reply(&self) -> Result<LlmResponse> {
+        let index = self.mock_replies_used.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let content = self.config.llm.mock_responses.get(index).cloned().ok_or_else(|| {
+            anyhow!(
+                "The mock provider has no reply left ({} scripted)",
+                self.config.llm.mock_responses.len()
+            )
+        })?;
+        Ok(LlmResponse {
+            content,
+            reasoning: None,
+            usage: None,
+        })
+    }
 
-        let url = format!("{}/chat/completions", self.config.llm.api_url);
+    /// Saves the raw reply to the debug log and keeps it for `/debug last`
+    fn log_response(&self, debug_id: Option<&str>, status: u16, body: &str) {
+        if let (Some(log), Some(id)) = (&self.debug_log, debug_id) {
+            log.response(id, status, body);
+        }
+        if let Ok(mut last) = self.last_exchange.lock() {
+            if let Some(exchange) = last.as_mut() {
+                exchange.status = Some(status);
+                exchange.response = body.to_string();
+            }
+        }
+    }
+
+    fn set_last_exchange(&self, exchange: LlmExchange) {
+        if let Ok(mut last) = self.last_exchange.lock() {
+            *last = Some(exchange);
+        }
+    }
+
+    /// The last request sent to the model in this session and its reply
+    pub fn last_exchange(&self) -> Option<LlmExchange> {
+        self.last_exchange.lock().ok()?.clone()
+    }
+
+    /// Collects a server-sent-events reply, printing reasoning dimmed as it
+    /// arrives. The raw stream is copied to `raw`. Returns the reply and its
+    /// finish reason.
+    async fn read_stream(&self, mut response: reqwest::Response, raw: &mut Vec<u8>) -> Result<(LlmResponse, Option<String>)> {
+        let mut buffer = Vec::new();
+        let mut content = String::new();
+        let mut reasoning_text = String::new();
+        let mut usage = None;
+        let mut finish_reason = None;
+        let mut live = LiveReasoning::default();
+
+        'chunks: while let Some(chunk) = response.chunk().await.context("Failed to read LLM API stream")? {
+            raw.extend_from_slice(&chunk);
+            buffer.extend_from_slice(&chunk);
+
+            while let Some(newline) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=newline).collect();
+                let line = String::from_utf8_lossy(&line);
+                let data = match line.trim().strip_prefix("data:") {
+                    Some(data) => data.trim(),
+                    None => continue,
+                };
+                if data == "[DONE]" {
+                    break 'chunks;
+                }
+
+                let event: serde_json::Value = serde_json::from_str(data)
+                    .with_context(|| format!("Failed to parse LLM API stream event: {}", data))?;
+                if let Some(error) = event.get("error") {
+                    return Err(anyhow!("LLM API error: {}", error));
+                }
+                if let Some(event_usage) = event.get("usage").filter(|u| !u.is_null()) {
+                    usage = serde_json::from_value(event_usage.clone()).ok();
+                }
+
+                if let Some(reason) = event["choices"][0]["finish_reason"].as_str() {
+                    finish_reason = Some(reason.to_string());
+                }
+                let delta = &event["choices"][0]["delta"];
+                if let Some(text) = delta["reasoning"].as_str().or_else(|| delta["reasoning_content"].as_str()) {
+                    reasoning_text.push_str(text);
+                }
+                if let Some(text) = delta["content"].as_str() {
+                    content.push_str(text);
+                }
+                live.update(self.ui.as_ref(), &reasoning_text, &content);
+            }
+        }
+        live.finish(self.ui.as_ref());
+
+        if content.is_empty() && reasoning_text.is_empty() {
+            return Err(anyhow!("LLM returned empty response"));
+        }
+
+        let reply = LlmResponse {
+            content,
+            reasoning: Some(reasoning_text).filter(|r| !r.trim().is_empty()),
+            usage,
+        };
+        Ok((reply, finish_reason))
+    }
+
+    fn rejects_structured_output(&self, api_url: &str) -> bool {
+        self.no_structured_output
+            .lock()
+            .map(|rejected| rejected.contains(api_url))
+            .unwrap_or(false)
+    }
+
+    /// Lists the models offered by the provider, trying the OpenAI-style
+    /// `/models` endpoint first and Ollama's `/tags` second
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let openai_url = format!("{}/models", self.config.llm.api_url);
+        match self.fetch_model_list(&openai_url, "data", "id").await {
+            Ok(models) => Ok(models),
+            Err(e) => {
+                debug!("Listing models via {} failed: {}", openai_url, e);
+                let ollama_url = format!("{}/tags", self.config.llm.api_url);
+                self.fetch_model_list(&ollama_url, "models", "name")
+                    .await
+                    .context("Failed to list models from the LLM provider")
+            }
+        }
+    }
+
+    async fn fetch_model_list(&self, url: &str, list_key: &str, name_key: &str) -> Result<Vec<String>> {
         let response = self
             .client
-            .post(&url)
-            .header("Content-Type", "application/json")
+            .get(url)
             .header(
                 "Authorization",
                 format!("Bearer {}", self.config.llm.api_key),
             )
-            .json(&request)
+            .timeout(MODEL_LIST_TIMEOUT)
             .send()
-            .await
-            .context("Failed to send request to LLM API")?;
+            .await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await?;
-            return Err(anyhow!("LLM API error: {} - {}", status, text));
+            return Err(anyhow!("{} returned {}", url, response.status()));
         }
 
-        let chat_response: ChatResponse = response
-            .json()
-            .await
-            .context("Failed to parse LLM API response")?;
+        let body: serde_json::Value = response.json().await?;
+        let list = body
+            .get(list_key)
+            .and_then(|l| l.as_array())
+            .ok_or_else(|| anyhow!("{} did not return a '{}' list", url, list_key))?;
 
-        if chat_response.choices.is_empty() {
-            return Err(anyhow!("LLM returned empty response"));
+        Ok(list
+            .iter()
+            .filter_map(|m| m.get(name_key).and_then(|n| n.as_str()))
+            .map(|n| n.to_string())
+            .collect())
+    }
+
+    /// Checks that the configured endpoint answers, without sending a prompt.
+    /// The future doesn't borrow the client, so it can run in the background.
+    pub fn probe(&self) -> impl Future<Output = Option<Unreachable>> + Send + 'static {
+        let client = self.client.clone();
+        let url = format!("{}/models", self.config.llm.api_url);
+        let api_key = self.config.llm.api_key.clone();
+        #[cfg(feature = "test-support")]
+        let mock = self.config.llm.provider == LlmProvider::Mock;
+        #[cfg(not(feature = "test-support"))]
+        let mock = false;
+
+        async move {
+            if mock {
+                return None;
+            }
+            let response = client
+                .get(&url)
+                .header("Authorization", format!("Bearer {}", api_key))
+                .timeout(MODEL_LIST_TIMEOUT)
+                .send()
+                .await;
+            match response {
+                Ok(response) if matches!(response.status().as_u16(), 401 | 403) => {
+                    Some(Unreachable::Unauthorized(response.status().as_u16()))
+                }
+                // Any other answer, even a 404, means a server is listening
+                Ok(_) => None,
+                Err(e) if e.is_timeout() => Some(Unreachable::TimedOut),
+                Err(e) => {
+                    let mut message = e.to_string();
+                    let mut source = std::error::Error::source(&e);
+                    while let Some(cause) = source {
+                        message.push_str(&format!(": {}", cause));
+                        source = cause.source();
+                    }
+                    let lower = message.to_lowercase();
+                    Some(if lower.contains("connection refused") {
+                        Unreachable::Refused
+                    } else if lower.contains("dns error") || lower.contains("failed to lookup") {
+                        Unreachable::UnknownHost
+                    } else {
+                        Unreachable::Failed(message)
+                    })
+                }
+            }
+        }
+    }
+
+    /// Prints a warning when the configured model is not offered by the provider.
+    /// Stays quiet if the model list cannot be fetched.
+    pub async fn warn_if_unknown_model(&self) {
+        let models = match self.list_models().await {
+            Ok(models) => models,
+            Err(e) => {
+                debug!("Skipping model validation: {}", e);
+                return;
+            }
+        };
+
+        if !models.is_empty() && !model_is_listed(&self.config.llm.model, &models) {
+            self.ui.print(&format!(
+                "{} Model '{}' is not offered by {} (run `code-assist config --list-models`)",
+                "!".yellow(),
+                self.config.llm.model,
+                self.config.llm.api_url
+            ));
+        }
+    }
+}
+
+/// Why the configured LLM endpoint could not be reached
+#[derive(Debug)]
+pub enum Unreachable {
+    /// Nothing listens at the host and port
+    Refused,
+    /// The host name doesn't resolve
+    UnknownHost,
+    TimedOut,
+    /// The endpoint answered with this status, rejecting the API key
+    Unauthorized(u16),
+    Failed(String),
+}
+
+impl Unreachable {
+    /// What to check or run to reach the model, for the banner shown at startup
+    pub fn hints(&self, config: &Config) -> Vec<String> {
+        let mut hints = match self {
+            Self::Refused => vec![
+                "Is Ollama running? Start it with `ollama serve`".to_string(),
+                "Is the port right? Ollama listens on 11434, LM Studio on 1234 and llama.cpp's server on 8080".to_string(),
+            ],
+            Self::UnknownHost => vec![format!("Is the host name in {} right?", config.llm.api_url)],
+            Self::TimedOut => vec!["Is the server still starting, or behind a firewall or VPN?".to_string()],
+            Self::Unauthorized(_) if config.llm.api_key.is_empty() => {
+                vec!["This endpoint needs an API key: set it with `code-assist config --api-key <key>`".to_string()]
+            }
+            Self::Unauthorized(_) => {
+                vec!["The API key was rejected: set another with `code-assist config --api-key <key>`".to_string()]
+            }
+            Self::Failed(_) => Vec::new(),
+        };
+        hints.push("Use another endpoint with `code-assist config --api-url <url>`".to_string());
+        hints
+    }
+}
+
+impl std::fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Refused => write!(f, "connection refused"),
+            Self::UnknownHost => write!(f, "unknown host"),
+            Self::TimedOut => write!(f, "no answer within {} seconds", MODEL_LIST_TIMEOUT.as_secs()),
+            Self::Unauthorized(status) => write!(f, "the API key was refused ({})", status),
+            Self::Failed(message) => write!(f, "{}", message),
         }
+    }
+}
+
+/// Whether an error response says the prompt is longer than the model accepts
+fn is_oversized_error(status: u16, body: &str) -> bool {
+    if status == 413 {
+        return true;
+    }
+    let body = body.to_lowercase();
+    matches!(status, 400 | 422) && OVERSIZED_ERROR_HINTS.iter().any(|hint| body.contains(hint))
+}
+
+/// Warns when the reply was cut at `max_tokens`, or when the provider counted
+/// far fewer prompt tokens than were sent, as servers like Ollama do when
+/// they silently drop the start of a prompt longer than their context window
+fn warn_if_truncated(ui: &dyn UserInterface, target: &ModelTarget, request_bytes: usize, reply: &LlmResponse, finish_reason: Option<&str>, max_tokens: usize) {
+    if finish_reason == Some("length") {
+        ui.print(&format!(
+            "{} The reply from '{}' was cut at {} tokens; raise max_tokens under [llm] if it is incomplete",
+            "!".yellow(),
+            target.model,
+            max_tokens
+        ));
+    }
 
-        Ok(chat_response.choices[0].message.content.clone())
+    let estimated_tokens = request_bytes / BYTES_PER_TOKEN;
+    let counted_tokens = match &reply.usage {
+        Some(usage) if usage.prompt_tokens > 0 => usage.prompt_tokens as usize,
+        _ => return,
+    };
+    if estimated_tokens >= MIN_TRUNCATION_CHECK_TOKENS && counted_tokens * 3 < estimated_tokens {
+        ui.print(&format!(
+            "{} '{}' read {} prompt tokens of about {} sent; the provider probably cut the prompt to its context window \
+             (raise it on the server, e.g. Ollama's num_ctx, or lower max_request_bytes under [llm])",
+            "!".yellow(),
+            target.model,
+            counted_tokens,
+            estimated_tokens
+        ));
     }
 }
 
+/// Ollama lists models with an explicit tag (`codellama:latest`), while the
+/// config usually names them without one
+pub fn model_is_listed(model: &str, models: &[String]) -> bool {
+    models.iter().any(|m| m == model || m.strip_suffix(":latest") == Some(model))
+}
@@ -0,0 +1,122 @@
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// Lines above a fenced block searched for the path of the file it shows
+const PATH_LOOKBACK_LINES: usize = 3;
+/// How much longer than the block the replaced span of a file may be before
+/// the match is considered a coincidence
+const MAX_SPAN_SLACK: usize = 10;
+
+/// A fenced code block of an answer that shows a version of an existing file
+#[derive(Debug)]
+pub struct FileBlock {
+    pub path: PathBuf,
+    pub code: String,
+}
+
+impl FileBlock {
+    /// The file with the block applied: the lines between the block's first
+    /// and last line are replaced when both are found in the file, and the
+    /// whole file is replaced when the block is about as long as the file.
+    /// None when the block can't be placed or changes nothing.
+    pub fn apply_to(&self, current: &str) -> Option<String> {
+        let block: Vec<&str> = self.code.lines().collect();
+        let lines: Vec<&str> = current.lines().collect();
+        if self.code.trim() == current.trim() {
+            return None;
+        }
+
+        let first = block.iter().position(|line| !line.trim().is_empty())?;
+        let last = block.iter().rposition(|line| !line.trim().is_empty())?;
+        // The span of the file between lines equal to the block's first and
+        // last line whose length is closest to the block's
+        let block_span = last - first + 1;
+        let anchored = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.trim() == block[first].trim())
+            .flat_map(|(start, _)| {
+                (start..lines.len().min(start + block_span + MAX_SPAN_SLACK))
+                    .filter(|&end| lines[end].trim() == block[last].trim())
+                    .map(move |end| (start, end))
+            })
+            .min_by_key(|(start, end)| (end - start + 1).abs_diff(block_span));
+
+        let mut updated: Vec<&str> = match anchored {
+            Some((start, end)) => {
+                let mut updated = lines[..start].to_vec();
+                updated.extend(&block[first..=last]);
+                updated.extend(&lines[end + 1..]);
+                updated
+            }
+            None if block.len() * 2 >= lines.len() => block.clone(),
+            None => return None,
+        };
+        if current.ends_with('\n') || current.is_empty() {
+            updated.push("");
+        }
+        let updated = updated.join("\n");
+        if updated == current {
+            None
+        } else {
+            Some(updated)
+        }
+    }
+}
+
+/// Fenced code blocks of `answer` that name an existing file under `root`:
+/// in the fence's info string (```rust src/app.rs), in a comment on the
+/// block's first line, or on one of the lines just above the block
+pub fn file_blocks(answer: &str, root: &Path) -> Vec<FileBlock> {
+    // Path-like tokens: `src/app.rs`, `./lib/util.py`, `Cargo.toml`
+    let paths = Regex::new(r"[\w./-]*\w\.\w+").expect("valid path pattern");
+    let lines: Vec<&str> = answer.lines().collect();
+    let mut blocks = Vec::new();
+    let mut idx = 0;
+    while idx < lines.len() {
+        let fence = lines[idx].trim_start();
+        if !fence.starts_with("```") {
+            idx += 1;
+            continue;
+        }
+        let end = match (idx + 1..lines.len()).find(|&i| lines[i].trim_start().starts_with("```")) {
+            Some(end) => end,
+            None => break,
+        };
+        let mut code: Vec<&str> = lines[idx + 1..end].to_vec();
+
+        let mut path = existing_path(&paths, fence.trim_start_matches('`'), root);
+        if path.is_none() {
+            // A comment like `// src/app.rs` naming the file isn't part of it
+            if let Some(named) = code.first().filter(|line| is_comment(line)).and_then(|line| existing_path(&paths, line, root)) {
+                path = Some(named);
+                code.remove(0);
+            }
+        }
+        if path.is_none() {
+            path = lines[idx.saturating_sub(PATH_LOOKBACK_LINES)..idx]
+                .iter()
+                .rev()
+                .find_map(|line| existing_path(&paths, line, root));
+        }
+        if let Some(path) = path {
+            blocks.push(FileBlock { path, code: code.join("\n") });
+        }
+        idx = end + 1;
+    }
+    blocks
+}
+
+/// The last path-like token of `text` that is an existing file under `root`
+fn existing_path(paths: &Regex, text: &str, root: &Path) -> Option<PathBuf> {
+    paths
+        .find_iter(text)
+        .map(|token| PathBuf::from(token.as_str().trim_start_matches("./")))
+        .filter(|path| root.join(path).is_file())
+        .last()
+}
+
+fn is_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    ["//", "#", "--", "/*", "<!--", ";"].iter().any(|marker| line.starts_with(marker))
+}
@@ -0,0 +1,129 @@
+use crate::config::SummarizeConfig;
+use crate::git::diff::{FileDiff, GitDiff};
+use crate::git::history::{CommitInfo, GitHistory};
+use crate::llm::client::LlmClient;
+use crate::llm::vendor;
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use std::path::Path;
+
+/// Task name used to route branch comparisons to a model (`[llm.routes]`)
+const TASK: &str = "compare";
+/// Bytes of one file's patch sent when explaining that file
+const MAX_EXPLAIN_PATCH_BYTES: usize = 24000;
+/// Commit messages listed with each request
+const MAX_COMMITS: usize = 30;
+
+const FILE_SYSTEM_PROMPT: &str = "You summarize one file's changes in a branch for a code reviewer. \
+Reply with a single plain-text sentence saying what changed and why it matters. No preamble.";
+
+const EXPLAIN_SYSTEM_PROMPT: &str = "You explain one file's changes in a branch to a code reviewer. \
+Walk through each hunk: what it changes, why it was likely changed given the commit messages, and anything \
+that looks risky, incomplete or inconsistent. Reply in plain text.";
+
+const CHECKLIST_SYSTEM_PROMPT: &str = "You write a checklist for reviewing a branch. Given the commits and \
+a summary of each changed file, list the concrete things a reviewer should verify: behaviour changes, edge cases, \
+error handling, tests, migrations, docs and compatibility. Reply with a markdown checklist (`- [ ] ...`), \
+grouped by area, and nothing else.";
+
+/// Changes on a branch since it forked from its base, summarized per file
+/// with an LLM and explained file by file on request
+pub struct BranchComparison<'a> {
+    client: &'a LlmClient,
+    config: &'a SummarizeConfig,
+    pub base: String,
+    pub head: String,
+    pub commits: Vec<CommitInfo>,
+    pub files: Vec<FileDiff>,
+}
+
+impl<'a> BranchComparison<'a> {
+    pub fn new(client: &'a LlmClient, config: &'a SummarizeConfig, repo_path: &Path, base: &str, head: &str) -> Result<Self> {
+        Ok(Self {
+            client,
+            config,
+            base: base.to_string(),
+            head: head.to_string(),
+            commits: GitHistory::commits_in_range(repo_path, base, head)?,
+            files: GitDiff::range_diff(repo_path, base, head)?,
+        })
+    }
+
+    /// One sentence per changed file, in the order of `files`. Files that
+    /// could not be summarized get the error instead.
+    pub async fn summarize_files(&self) -> Vec<String> {
+        stream::iter(&self.files)
+            .map(|file| async move {
+                let user_message = format!(
+                    "{}\nFile: {}\n{}",
+                    self.commit_log(),
+                    describe(file),
+                    vendor::truncate(&file.patch, self.config.max_file_bytes)
+                );
+                match self.client.complete(TASK, FILE_SYSTEM_PROMPT, &user_message).await {
+                    Ok(summary) => summary.trim().replace('\n', " "),
+                    Err(e) => format!("(no summary: {})", e),
+                }
+            })
+            .buffered(self.config.concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Detailed explanation of the changes to `file`
+    pub async fn explain_file(&self, file: &FileDiff) -> Result<String> {
+        let user_message = format!(
+            "{}\nFile: {}\n{}",
+            self.commit_log(),
+            describe(file),
+            vendor::truncate(&file.patch, MAX_EXPLAIN_PATCH_BYTES)
+        );
+        self.client.complete(TASK, EXPLAIN_SYSTEM_PROMPT, &user_message).await
+    }
+
+    /// Reviewer checklist for the whole branch from the per-file summaries
+    pub async fn checklist(&self, summaries: &[String]) -> Result<String> {
+        let files: Vec<String> = self
+            .files
+            .iter()
+            .zip(summaries)
+            .map(|(file, summary)| format!("- {}: {}", describe(file), summary))
+            .collect();
+        let user_message = format!(
+            "{}\nChanged files:\n{}",
+            self.commit_log(),
+            vendor::truncate(&files.join("\n"), self.config.max_summary_bytes)
+        );
+        self.client.complete(TASK, CHECKLIST_SYSTEM_PROMPT, &user_message).await
+    }
+
+    /// Subject lines of the branch's most recent commits, oldest first
+    fn commit_log(&self) -> String {
+        let mut log = format!("Branch {} compared with {}. Commits:\n", self.head, self.base);
+        if self.commits.len() > MAX_COMMITS {
+            log.push_str(&format!("- ({} older commits not shown)\n", self.commits.len() - MAX_COMMITS));
+        }
+        for commit in self.commits.iter().take(MAX_COMMITS).rev() {
+            let subject = commit.message.lines().next().unwrap_or("");
+            log.push_str(&format!("- {} {}\n", &commit.id[..7.min(commit.id.len())], subject));
+        }
+        log
+    }
+}
+
+/// `path (status, +a -d)`, with the previous path of renamed files
+pub fn describe(file: &FileDiff) -> String {
+    let status = match file.status {
+        'A' => "added",
+        'D' => "deleted",
+        'R' => "renamed",
+        'C' => "copied",
+        'T' => "type changed",
+        _ => "modified",
+    };
+    let path = match &file.old_path {
+        Some(old) => format!("{} → {}", old.display(), file.path.display()),
+        None => file.path.display().to_string(),
+    };
+    format!("{} ({}, +{} -{})", path, status, file.additions, file.deletions)
+}
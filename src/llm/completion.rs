@@ -0,0 +1,160 @@
+use crate::llm::client::LlmClient;
+use crate::llm::prompt::PromptContext;
+use anyhow::{anyhow, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Task name used to route completions to a fast model (`[llm.routes]`)
+const TASK: &str = "complete";
+/// Lines of the file sent before and after the cursor
+const PREFIX_LINES: usize = 200;
+const SUFFIX_LINES: usize = 60;
+/// Lines around the cursor whose identifiers pick the project context
+const QUERY_LINES: usize = 8;
+const CURSOR_MARKER: &str = "<CURSOR>";
+
+const SYSTEM_PROMPT: &str = "You are a code completion engine. The user message holds project context followed by \
+a file with a <CURSOR> marker. Reply with only the code to insert at the marker: no explanation, no markdown fences, \
+and none of the code already before or after the marker. The text is inserted exactly at the marker, so start with a \
+newline to continue on a new line. Match the file's indentation and style. Reply with nothing when no code belongs there.";
+
+/// A position given as `<file>:<line>[:<column>]`, both 1-based. Without a
+/// column the cursor is at the end of the line.
+#[derive(Debug, Clone)]
+pub struct Cursor {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+impl Cursor {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = || anyhow!("Expected <file>:<line>[:<column>], got '{}'", spec);
+        let (rest, last) = spec.rsplit_once(':').ok_or_else(invalid)?;
+        let last: usize = last.parse().map_err(|_| invalid())?;
+        let (path, line, column) = match rest.rsplit_once(':') {
+            Some((path, line)) if !path.is_empty() && line.parse::<usize>().is_ok() => {
+                (path, line.parse().map_err(|_| invalid())?, Some(last))
+            }
+            _ => (rest, last, None),
+        };
+        if path.is_empty() || line == 0 || column == Some(0) {
+            return Err(invalid());
+        }
+        Ok(Self { path: PathBuf::from(path), line, column })
+    }
+
+    /// Byte offset of the cursor in `content`. The line after the last one is
+    /// allowed, to complete at the end of the file.
+    pub fn offset(&self, content: &str) -> Result<usize> {
+        let mut start = 0;
+        for (idx, line) in content.split_inclusive('\n').enumerate() {
+            if idx + 1 == self.line {
+                let text = line.trim_end_matches('\n').trim_end_matches('\r');
+                return match self.column {
+                    None => Ok(start + text.len()),
+                    Some(column) => text
+                        .char_indices()
+                        .map(|(i, _)| i)
+                        .chain(std::iter::once(text.len()))
+                        .nth(column - 1)
+                        .map(|i| start + i)
+                        .ok_or_else(|| anyhow!("Line {} has no column {}", self.line, column)),
+                };
+            }
+            start += line.len();
+        }
+
+        let line_count = content.split_inclusive('\n').count();
+        let at_end = self.line == line_count + 1 && (content.is_empty() || content.ends_with('\n'));
+        if at_end && self.column.is_none_or(|column| column == 1) {
+            return Ok(content.len());
+        }
+        Err(anyhow!("{} has {} line(s)", self.path.display(), line_count))
+    }
+}
+
+/// The lines around `offset`, used as the query that picks relevant files
+/// and symbol definitions for the completion
+pub fn query_text(content: &str, offset: usize) -> String {
+    let before: Vec<&str> = content[..offset].lines().rev().take(QUERY_LINES).collect();
+    let after = content[offset..].lines().take(QUERY_LINES / 2);
+    before.into_iter().rev().chain(after).collect::<Vec<_>>().join("\n")
+}
+
+/// Asks the model for the code to insert at `offset` in `path`, with the
+/// gathered project context. Returns the text to insert, empty when the
+/// model has nothing to add.
+pub async fn complete(
+    client: &LlmClient,
+    prompt_context: &PromptContext,
+    path: &Path,
+    content: &str,
+    offset: usize,
+) -> Result<String> {
+    let (prefix, suffix) = content.split_at(offset);
+    let prefix_start = line_start_before(prefix, PREFIX_LINES);
+    let suffix_end = line_end_after(suffix, SUFFIX_LINES);
+
+    let mut user_message = String::new();
+    if !prompt_context.memory.trim().is_empty() {
+        user_message.push_str(&format!("Project memory:\n{}\n\n", prompt_context.memory.trim()));
+    }
+    user_message.push_str(&format!("Project context:\n{}\n", prompt_context.context));
+    user_message.push_str(&format!(
+        "File to complete: {}\n{}{}{}",
+        path.display(),
+        &prefix[prefix_start..],
+        CURSOR_MARKER,
+        &suffix[..suffix_end]
+    ));
+
+    let reply = client
+        .complete(TASK, SYSTEM_PROMPT, &user_message)
+        .await
+        .context("Failed to get a completion from the LLM")?;
+    Ok(clean_insertion(&reply, suffix))
+}
+
+/// `content` with `text` inserted at byte `offset`
+pub fn insert(content: &str, offset: usize, text: &str) -> String {
+    format!("{}{}{}", &content[..offset], text, &content[offset..])
+}
+
+/// Removes markdown fences and an echoed cursor marker from the reply. When
+/// the cursor is at the end of a line, trailing newlines are dropped so the
+/// insertion doesn't leave a blank line behind.
+fn clean_insertion(reply: &str, suffix: &str) -> String {
+    let mut text = reply;
+    if text.trim_start().starts_with("```") {
+        let body = text.trim_start();
+        let body = body.split_once('\n').map_or("", |(_, rest)| rest);
+        text = match body.rfind("```") {
+            Some(end) => body[..end].trim_end_matches([' ', '\t']),
+            None => body,
+        };
+        // The fence's own line break isn't part of the insertion
+        text = text.strip_suffix('\n').unwrap_or(text);
+    }
+
+    let text = text.replace(CURSOR_MARKER, "");
+    if suffix.starts_with('\n') || suffix.starts_with("\r\n") {
+        text.trim_end().to_string()
+    } else {
+        text
+    }
+}
+
+/// Byte index where the last `lines` lines of `text` start
+fn line_start_before(text: &str, lines: usize) -> usize {
+    text.match_indices('\n')
+        .rev()
+        .nth(lines)
+        .map_or(0, |(idx, _)| idx + 1)
+}
+
+/// Byte index where the first `lines` lines of `text` end
+fn line_end_after(text: &str, lines: usize) -> usize {
+    text.match_indices('\n')
+        .nth(lines)
+        .map_or(text.len(), |(idx, _)| idx + 1)
+}
@@ -1,57 +1,216 @@
+use crate::config::{Config, SummarizeConfig};
+use crate::fs::docs::{self, DocsIndex};
+use crate::fs::relocate;
 use crate::fs::search::CodeSearch;
+use crate::fs::storage::Storage;
+use crate::llm::client::{LlmClient, BYTES_PER_TOKEN};
+use crate::llm::embeddings::EmbeddingClient;
+use crate::llm::summarize::{self, SummaryCache, Summarizer};
+use crate::llm::vendor;
+use colored::Colorize;
 use anyhow::Result;
-use std::path::Path;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use crate::memory::ProjectMemory;
-use crate::analysis::structure::{ProjectAnalyzer, ProjectType, ProjectStructure, SpecificProjectInfo};
+use crate::analysis::commands as project_commands;
+use crate::analysis::languages;
+use crate::analysis::plugins::ProjectPlugins;
+use crate::analysis::testmap::{self, TestMap};
+use crate::analysis::syntax::{self, Definition, Grammar};
+use crate::analysis::structure::{ProjectAnalyzer, ProjectType, ProjectStructure, SpecificProjectInfo, SubProject};
+use crate::analysis::tree::FileTree;
+use crate::analysis::xref::{self, Location, SymbolIndex, SymbolUsage};
+use crate::git::commands::GitCommands;
+use crate::git::history::GitHistory;
+use crate::git::GitSupport;
+use crate::lsp::LspManager;
+use crate::ui::interface::UserInterface;
+use std::sync::{Arc, Mutex};
+
+/// Depth and line budget for the file tree included in the context
+const TREE_MAX_DEPTH: usize = 3;
+const TREE_MAX_LINES: usize = 60;
+
+/// Code symbols from the command whose definitions are looked up, and how
+/// many files containing them are offered to the language server
+const MAX_SYMBOLS: usize = 5;
+const SYMBOL_SEARCH_FILES: usize = 10;
+const DEFINITION_SNIPPET_LINES: usize = 15;
+/// Lines shown of a long section of a context file, and how much a keyword
+/// in a definition's name outweighs one in its body
+const MAX_SECTION_LINES: usize = 80;
+const NAME_MATCH_WEIGHT: usize = 10;
+/// Lines shown of a file with no matching definitions
+const PREVIEW_LINES: usize = 20;
+/// Relevant files past the top ones that are listed with their summaries
+const MAX_SUMMARIZED_FILES: usize = 5;
+/// Characters shown per line, so minified files don't flood the context
+const MAX_LINE_CHARS: usize = 300;
+/// Build targets listed for C/C++ projects and projects listed for .NET solutions
+const MAX_BUILD_TARGETS: usize = 20;
+/// Eloquent models or Doctrine entities listed for Laravel and Symfony applications
+const MAX_FRAMEWORK_MODELS: usize = 30;
+/// Dependencies listed per manifest section
+const MAX_DEPENDENCIES: usize = 40;
+/// Services and routes listed for Drupal modules
+const MAX_DRUPAL_DEFINITIONS: usize = 30;
+/// Twig templates listed for Drupal themes
+const MAX_THEME_TEMPLATES: usize = 40;
+/// Documentation sections included for documentation questions, and the
+/// lines shown of each
+const MAX_DOC_SECTIONS: usize = 4;
+const MAX_DOC_SECTION_LINES: usize = 60;
+/// README, CONTRIBUTING and docs overview pages included for architecture
+/// questions, and the lines kept of each once trimmed
+const MAX_OVERVIEW_PAGES: usize = 3;
+const MAX_OVERVIEW_LINES: usize = 80;
+/// Call sites listed per symbol in the context and in usage answers
+const MAX_CALL_SITES: usize = 10;
+const MAX_ANSWER_CALL_SITES: usize = 50;
+/// Sub-projects listed for monorepos
+const MAX_SUB_PROJECTS: usize = 20;
+/// Build, test and other project commands listed in the context
+const MAX_PROJECT_COMMANDS: usize = 30;
+/// Languages listed in the context's line count table
+const MAX_LANGUAGE_ROWS: usize = 8;
+/// Test files included for edit requests, at most one per context file
+const MAX_TEST_FILES: usize = 2;
+/// Files imported by the top context files that are included along with them
+const MAX_IMPORTED_FILES: usize = 3;
+
+/// The Drupal module a command is about
+pub enum ModuleTarget {
+    /// Not a site with several modules, or the command is about several
+    All,
+    Module(String),
+    /// The command names none of the site's modules (name and path of each)
+    Ambiguous(Vec<(String, PathBuf)>),
+}
+
+/// Code context gathered for a command
+pub struct GatheredContext {
+    /// Human readable project type, e.g. "Rust project"
+    pub project_type: Option<String>,
+    /// Type of the part of the project the command is about: the sub-project
+    /// or Drupal module the context was scoped to, or the whole project
+    pub kind: Option<ProjectType>,
+    pub text: String,
+}
+
+/// Analyzed structures by directory and forced type, with the fingerprint
+/// of the directory they were analyzed at
+type StructureCache = HashMap<(PathBuf, Option<ProjectType>), (u64, ProjectStructure)>;
 
 pub struct ContextManager {
     code_search: CodeSearch,
     pub project_memory: ProjectMemory,  // Made public
     project_analyzer: ProjectAnalyzer,
+    structures: Mutex<StructureCache>,
+    /// Project type plugins run after the built-in analyzers
+    pub plugins: ProjectPlugins,
+    embedder: Option<EmbeddingClient>,
+    semantic_weight: f32,
+    symbol_index: Arc<Mutex<SymbolIndex>>,
+    lsp: Option<Arc<LspManager>>,
+    git: GitSupport,
+    /// Strip comments and blank lines from included code
+    dense: bool,
+    /// Commits whose changed files rank higher (`context.recent_commits`)
+    recent_commits: usize,
+    /// Files whose code is included (`context.max_files`)
+    max_files: usize,
+    /// Bytes of code included per file (`context.max_bytes_per_file`)
+    max_bytes_per_file: usize,
+    /// Estimated tokens of the whole context, 0 for no limit
+    /// (`context.max_total_tokens`)
+    max_total_tokens: usize,
+    /// Include the README and docs overview pages for architecture questions
+    /// (`context.include_docs`)
+    include_docs: bool,
+    /// LLM-written summaries of source files
+    pub summaries: SummaryCache,
+    /// `[project] type` or `--project-type`, used instead of detection
+    project_type: Option<ProjectType>,
+    /// `[project] module` or `--module`: the Drupal module to work on
+    module: Option<String>,
+    summarize: SummarizeConfig,
+    ui: Arc<dyn UserInterface>,
 }
 
 impl ContextManager {
-    pub fn new() -> Self {
+    pub fn new(config: &Config, lsp: Option<Arc<LspManager>>, git: GitSupport,
+               symbol_index: Arc<Mutex<SymbolIndex>>, storage: Storage, ui: Arc<dyn UserInterface>) -> Self {
+        let embedder = if config.embeddings.enabled {
+            Some(EmbeddingClient::new(&config.embeddings))
+        } else {
+            None
+        };
+        
         Self {
-            code_search: CodeSearch::new(),
+            code_search: CodeSearch::new(storage),
             project_memory: ProjectMemory::new(),
             project_analyzer: ProjectAnalyzer {},
+            structures: Mutex::new(HashMap::new()),
+            plugins: ProjectPlugins::from_config(config, &std::env::current_dir().unwrap_or_default(), ui.as_ref()),
+            embedder,
+            semantic_weight: config.embeddings.semantic_weight,
+            symbol_index,
+            lsp,
+            git,
+            dense: config.context.dense,
+            recent_commits: config.context.recent_commits,
+            max_files: config.context.max_files,
+            max_bytes_per_file: config.context.max_bytes_per_file,
+            max_total_tokens: config.context.max_total_tokens,
+            include_docs: config.context.include_docs,
+            summaries: SummaryCache::new(&std::env::current_dir().unwrap_or_default(), storage),
+            summarize: config.summarize.clone(),
+            // Validated when the configuration is loaded
+            project_type: config.project.project_type.as_deref().and_then(|name| ProjectType::parse(name).ok()),
+            module: config.project.module.clone(),
+            ui,
         }
     }
     
-    /// Add file count information for all supported languages
-    fn add_file_count_info(&self, context: &mut String, project_structure: &ProjectStructure) {
-        // Add counts for each language
-        let language_extensions = [
-            ("rs", "Rust"),
-            ("py", "Python"),
-            ("js", "JavaScript"),
-            ("ts", "TypeScript"),
-            ("jsx", "React JSX"),
-            ("tsx", "React TSX"),
-            ("go", "Go"),
-            ("php", "PHP"),
-            ("java", "Java"),
-            ("cpp", "C++"),
-            ("h", "C/C++ header")
-        ];
-        
-        for (ext, lang) in &language_extensions {
-            if let Some(files) = project_structure.files_by_type.get(*ext) {
-                if !files.is_empty() {
-                    context.push_str(&format!("{} files count: {}\n", lang, files.len()));
-                }
-            }
+    /// Add the lines of code, comments and blanks per language
+    fn add_language_stats(&self, context: &mut String, project_structure: &ProjectStructure) {
+        if project_structure.languages.is_empty() {
+            return;
         }
+        context.push_str("Languages (lines of code, comments and blanks):\n");
+        context.push_str(&languages::format_stats(&project_structure.languages, MAX_LANGUAGE_ROWS));
     }
     
     /// Add Rust project information to context
     fn add_rust_project_info(&self, context: &mut String, project_structure: &ProjectStructure) -> Result<()> {
         if let SpecificProjectInfo::Rust(Some(rust_info)) = &project_structure.specific_info {
-            context.push_str(&format!("Rust package: {}\n", rust_info.name));
+            if !rust_info.name.is_empty() {
+                context.push_str(&format!("Rust package: {}\n", rust_info.name));
+            }
             if !rust_info.version.is_empty() {
                 context.push_str(&format!("Version: {}\n", rust_info.version));
             }
+            if let Some(edition) = &rust_info.edition {
+                context.push_str(&format!("Edition: {}\n", edition));
+            }
+            if !rust_info.features.is_empty() {
+                context.push_str(&format!("Features: {}\n", rust_info.features.join(", ")));
+            }
+            add_dependencies(context, &rust_info.dependencies, &rust_info.dev_dependencies);
+            if let Some(workspace) = &rust_info.workspace {
+                context.push_str(&format!("Cargo workspace with {} members:\n", workspace.members.len()));
+                for (dir, name, version) in &workspace.members {
+                    let mut line = format!("- {}", dir.display());
+                    if !name.is_empty() {
+                        line.push_str(&format!(": {}", name));
+                    }
+                    if !version.is_empty() {
+                        line.push_str(&format!(" {}", version));
+                    }
+                    context.push_str(&format!("{}\n", line));
+                }
+            }
             
             context.push_str(&format!("Contains {} modules, {} structs\n", 
                               rust_info.module_count, 
@@ -91,10 +250,133 @@ impl ContextManager {
         Ok(())
     }
     
+    /// Add Java/Kotlin project information to context
+    fn add_java_project_info(&self, context: &mut String, project_structure: &ProjectStructure) -> Result<()> {
+        if let SpecificProjectInfo::Java(Some(java_info)) = &project_structure.specific_info {
+            context.push_str(&format!("{} project: {}\n", java_info.build_tool, java_info.name));
+            context.push_str(&format!("Contains {} classes, {} interfaces in {} Java and {} Kotlin files\n", 
+                              java_info.class_count, 
+                              java_info.interface_count,
+                              java_info.java_file_count,
+                              java_info.kotlin_file_count));
+            
+            if java_info.is_spring_boot {
+                context.push_str("Spring Boot application\n");
+            }
+            
+            if !java_info.spring_annotations.is_empty() {
+                let annotations: Vec<String> = java_info.spring_annotations
+                    .iter()
+                    .map(|(name, count)| format!("{} @{}", count, name))
+                    .collect();
+                context.push_str(&format!("Spring components: {}\n", annotations.join(", ")));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Add Laravel or Symfony application information to context
+    fn add_php_framework_info(&self, context: &mut String, project_structure: &ProjectStructure) -> Result<()> {
+        if let SpecificProjectInfo::PhpFramework(Some(php_info)) = &project_structure.specific_info {
+            context.push_str(&format!("{} application: {}{}\n", 
+                              php_info.framework, 
+                              php_info.name,
+                              php_info.framework_version.as_ref().map_or(String::new(), |v| format!(" ({})", v))));
+            context.push_str(&format!("Contains {} PHP files, {} controllers, {} routes\n", 
+                              php_info.php_file_count, 
+                              php_info.controller_count,
+                              php_info.route_count));
+            
+            if !php_info.route_files.is_empty() {
+                let files: Vec<String> = php_info.route_files.iter().map(|p| p.display().to_string()).collect();
+                context.push_str(&format!("Route files: {}\n", files.join(", ")));
+            }
+            
+            if !php_info.models.is_empty() {
+                let kind = if php_info.framework == "Laravel" { "Eloquent models" } else { "Doctrine entities" };
+                let more = php_info.models.len().saturating_sub(MAX_FRAMEWORK_MODELS);
+                context.push_str(&format!("{}: {}{}\n", 
+                                  kind, 
+                                  php_info.models.iter().take(MAX_FRAMEWORK_MODELS).cloned().collect::<Vec<_>>().join(", "),
+                                  if more > 0 { format!(" and {} more", more) } else { String::new() }));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Add .NET project information to context
+    fn add_dotnet_project_info(&self, context: &mut String, project_structure: &ProjectStructure) -> Result<()> {
+        if let SpecificProjectInfo::DotNet(Some(dotnet_info)) = &project_structure.specific_info {
+            match &dotnet_info.solution {
+                Some(solution) => context.push_str(&format!(".NET solution: {} ({})\n", dotnet_info.name, solution.display())),
+                None => context.push_str(&format!(".NET project: {}\n", dotnet_info.name)),
+            }
+            context.push_str(&format!("Contains {} classes, {} interfaces, {} controllers in {} C# files\n", 
+                              dotnet_info.class_count, 
+                              dotnet_info.interface_count,
+                              dotnet_info.controller_count,
+                              dotnet_info.cs_file_count));
+            
+            if dotnet_info.is_aspnet {
+                context.push_str("ASP.NET Core application\n");
+            }
+            
+            if !dotnet_info.projects.is_empty() {
+                let projects: Vec<String> = dotnet_info.projects
+                    .iter()
+                    .take(MAX_BUILD_TARGETS)
+                    .map(|(name, frameworks)| if frameworks.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{} ({})", name, frameworks)
+                    })
+                    .collect();
+                let more = dotnet_info.projects.len().saturating_sub(MAX_BUILD_TARGETS);
+                context.push_str(&format!("Projects: {}{}\n", 
+                                  projects.join(", "),
+                                  if more > 0 { format!(" and {} more", more) } else { String::new() }));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Add C/C++ project information to context
+    fn add_cpp_project_info(&self, context: &mut String, project_structure: &ProjectStructure) -> Result<()> {
+        if let SpecificProjectInfo::Cpp(Some(cpp_info)) = &project_structure.specific_info {
+            context.push_str(&format!("{} project: {}\n", cpp_info.build_system, cpp_info.name));
+            context.push_str(&format!("Contains {} C sources, {} C++ sources, {} headers\n", 
+                              cpp_info.c_file_count, 
+                              cpp_info.cpp_file_count,
+                              cpp_info.header_count));
+            
+            if !cpp_info.targets.is_empty() {
+                let targets: Vec<String> = cpp_info.targets
+                    .iter()
+                    .take(MAX_BUILD_TARGETS)
+                    .map(|(name, kind)| format!("{} ({})", name, kind))
+                    .collect();
+                let more = cpp_info.targets.len().saturating_sub(MAX_BUILD_TARGETS);
+                context.push_str(&format!("Build targets: {}{}\n", 
+                                  targets.join(", "),
+                                  if more > 0 { format!(" and {} more", more) } else { String::new() }));
+            }
+        }
+        Ok(())
+    }
+    
     /// Add Angular project information to context
     fn add_angular_project_info(&self, context: &mut String, project_structure: &ProjectStructure) -> Result<()> {
         if let SpecificProjectInfo::Angular(Some(angular_info)) = &project_structure.specific_info {
             context.push_str(&format!("Angular project: {}\n", angular_info.name));
+            if angular_info.projects.len() > 1 {
+                context.push_str(&format!("Angular workspace with {} projects:\n", angular_info.projects.len()));
+            }
+            for project in &angular_info.projects {
+                let kind = project.kind.as_deref().unwrap_or("project");
+                let root = if project.root.is_empty() { "." } else { project.root.as_str() };
+                context.push_str(&format!("- {} ({} in {}), targets: {}\n", project.name, kind, root, project.targets.join(", ")));
+            }
+            add_dependencies(context, &angular_info.dependencies, &angular_info.dev_dependencies);
             context.push_str(&format!("Contains {} components, {} services\n", 
                               angular_info.component_count, 
                               angular_info.service_count));
@@ -114,6 +396,10 @@ impl ContextManager {
     fn add_react_project_info(&self, context: &mut String, project_structure: &ProjectStructure) -> Result<()> {
         if let SpecificProjectInfo::React(Some(react_info)) = &project_structure.specific_info {
             context.push_str(&format!("React project: {}\n", react_info.name));
+            if !react_info.version.is_empty() {
+                context.push_str(&format!("Version: {}\n", react_info.version));
+            }
+            add_dependencies(context, &react_info.dependencies, &react_info.dev_dependencies);
             context.push_str(&format!("Contains approximately {} components\n", react_info.component_count));
             
             if react_info.has_redux {
@@ -158,6 +444,56 @@ impl ContextManager {
             }
         }
         
+        // List the site's themes
+        let themes = self.project_analyzer.find_all_drupal_themes(cwd)?;
+        if !themes.is_empty() {
+            context.push_str(&format!("\nDetected Drupal themes ({}):\n", themes.len()));
+            for (theme_name, theme_path) in &themes {
+                let relative_path = theme_path.strip_prefix(cwd).unwrap_or(theme_path);
+                context.push_str(&format!("- {}: {}\n", theme_name, relative_path.display()));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Add Drupal theme information to context
+    fn add_drupal_theme_info(&self, context: &mut String, project_structure: &ProjectStructure) -> Result<()> {
+        if let SpecificProjectInfo::DrupalTheme(Some(theme_info)) = &project_structure.specific_info {
+            context.push_str(&format!("Drupal Theme: {}\n", theme_info.name));
+            if !theme_info.description.is_empty() {
+                context.push_str(&format!("Description: {}\n", theme_info.description));
+            }
+            if let Some(base_theme) = &theme_info.base_theme {
+                context.push_str(&format!("Base theme: {}\n", base_theme));
+            }
+            context.push_str(&format!("Info file: {}\n", theme_info.info_file.display()));
+            if let Some(theme_file) = &theme_info.theme_file {
+                context.push_str(&format!("Theme file: {}\n", theme_file.display()));
+            }
+            
+            if !theme_info.regions.is_empty() {
+                context.push_str("Regions:\n");
+                for (region, label) in &theme_info.regions {
+                    context.push_str(&format!("- {}: {}\n", region, label));
+                }
+            }
+            
+            if !theme_info.libraries.is_empty() {
+                context.push_str(&format!("Libraries: {}\n", theme_info.libraries.join(", ")));
+            }
+            
+            if !theme_info.templates.is_empty() {
+                context.push_str(&format!("Twig templates ({}):\n", theme_info.templates.len()));
+                for template in theme_info.templates.iter().take(MAX_THEME_TEMPLATES) {
+                    context.push_str(&format!("- {}\n", template.display()));
+                }
+                if theme_info.templates.len() > MAX_THEME_TEMPLATES {
+                    context.push_str(&format!("- ... and {} more\n", theme_info.templates.len() - MAX_THEME_TEMPLATES));
+                }
+            }
+        }
+        
         Ok(())
     }
     
@@ -179,6 +515,14 @@ impl ContextManager {
                 context.push_str(&format!("Info file: {}\n", info_file.display()));
             }
             
+            if let Some(requirement) = &module_info.core_version_requirement {
+                context.push_str(&format!("Core version requirement: {}\n", requirement));
+            }
+            
+            if !module_info.dependencies.is_empty() {
+                context.push_str(&format!("Dependencies: {}\n", module_info.dependencies.join(", ")));
+            }
+            
             // Add hooks implemented
             if !module_info.hooks.is_empty() {
                 context.push_str("Implements hooks:\n");
@@ -192,8 +536,32 @@ impl ContextManager {
                 context.push_str("Contains plugins: Yes\n");
             }
             
-            if module_info.has_services {
-                context.push_str("Contains services: Yes\n");
+            if !module_info.services.is_empty() {
+                context.push_str(&format!("Services ({}):\n", module_info.services.len()));
+                for service in module_info.services.iter().take(MAX_DRUPAL_DEFINITIONS) {
+                    let mut line = format!("- {}", service.id);
+                    if let Some(class) = &service.class {
+                        line.push_str(&format!(": {}", class));
+                    }
+                    if !service.tags.is_empty() {
+                        line.push_str(&format!(" [{}]", service.tags.join(", ")));
+                    }
+                    context.push_str(&format!("{}\n", line));
+                }
+            }
+            
+            if !module_info.routes.is_empty() {
+                context.push_str(&format!("Routes ({}):\n", module_info.routes.len()));
+                for route in module_info.routes.iter().take(MAX_DRUPAL_DEFINITIONS) {
+                    let mut line = format!("- {}: {}", route.name, route.path);
+                    if let Some(handler) = &route.handler {
+                        line.push_str(&format!(" -> {}", handler));
+                    }
+                    if let Some(permission) = &route.permission {
+                        line.push_str(&format!(" (permission: {})", permission));
+                    }
+                    context.push_str(&format!("{}\n", line));
+                }
             }
             
             // Add config schema info
@@ -362,7 +730,7 @@ impl ContextManager {
         }
         
         // Report PHP file count to balance JS bias
-        let php_count = self.count_php_files_in_module(&module_path)?;
+        let php_count = self.count_php_files_in_module(module_path)?;
         if php_count > 0 {
             context.push_str(&format!("PHP file count: {}\n", php_count));
         }
@@ -391,7 +759,137 @@ impl ContextManager {
         Ok(count)
     }
     
-    pub fn gather_context(&self, command: &str) -> Result<String> {
+    /// The project type to use instead of the detected one and where it was
+    /// set: the configuration (or `--project-type`) first, then CAULK.md
+    pub fn project_type_override(&self) -> Option<(ProjectType, &'static str)> {
+        if let Some(project_type) = self.project_type {
+            return Some((project_type, "configuration"));
+        }
+        let memory = self.project_memory.load().ok()?;
+        match ProjectType::parse(memory.project_type()?) {
+            Ok(project_type) => Some((project_type, "CAULK.md")),
+            Err(e) => {
+                self.ui.error(&format!("{} Ignoring project.type in CAULK.md: {}", "!".yellow(), e));
+                None
+            }
+        }
+    }
+
+    /// Structure of the project at `cwd`, analyzed as the overriding project
+    /// type when one is set
+    pub fn project_structure(&self, cwd: &Path) -> Result<ProjectStructure> {
+        let forced_type = self.project_type_override().map(|(project_type, _)| project_type);
+        self.project_structure_as(cwd, forced_type)
+    }
+
+    /// Structure of the project at `cwd` as `forced_type` (detected when
+    /// None), with the info of the plugin recognizing it. It is analyzed
+    /// again only once a file under `cwd` was added, removed or modified.
+    pub fn project_structure_as(&self, cwd: &Path, forced_type: Option<ProjectType>) -> Result<ProjectStructure> {
+        let key = (cwd.to_path_buf(), forced_type);
+        let fingerprint = self.project_analyzer.fingerprint(cwd);
+        if let Some((cached, structure)) = self.structures.lock().unwrap().get(&key) {
+            if *cached == fingerprint {
+                debug!("Reusing the analysis of {}", cwd.display());
+                return Ok(structure.clone());
+            }
+        }
+
+        let mut structure = self.project_analyzer.analyze_project_structure_as(cwd, forced_type)?;
+        structure.plugin = self.plugins.analyze(cwd, &structure, self.ui.as_ref());
+        self.structures.lock().unwrap().insert(key, (fingerprint, structure.clone()));
+        Ok(structure)
+    }
+
+    /// Framework and build details of the detected project type: package,
+    /// dependencies, modules, targets and the like
+    pub fn project_details(&self, project_structure: &ProjectStructure, cwd: &Path) -> Result<String> {
+        let mut details = String::new();
+        let project_type = match &project_structure.project_type {
+            Some(project_type) => project_type,
+            None => return Ok(details),
+        };
+        match project_type {
+            ProjectType::Rust => {
+                self.add_rust_project_info(&mut details, project_structure)?;
+            },
+            ProjectType::Python => {
+                self.add_python_project_info(&mut details, project_structure)?;
+            },
+            ProjectType::Java | ProjectType::Kotlin => {
+                self.add_java_project_info(&mut details, project_structure)?;
+            },
+            ProjectType::CSharp => {
+                self.add_dotnet_project_info(&mut details, project_structure)?;
+            },
+            ProjectType::C | ProjectType::Cpp => {
+                self.add_cpp_project_info(&mut details, project_structure)?;
+            },
+            ProjectType::Laravel | ProjectType::Symfony => {
+                self.add_php_framework_info(&mut details, project_structure)?;
+            },
+            ProjectType::Angular => {
+                self.add_angular_project_info(&mut details, project_structure)?;
+            },
+            ProjectType::React => {
+                self.add_react_project_info(&mut details, project_structure)?;
+            },
+            ProjectType::Drupal => {
+                self.add_drupal_project_info(&mut details, project_structure, cwd)?;
+            },
+            ProjectType::DrupalModule => {
+                self.add_drupal_module_project_info(&mut details, project_structure, cwd)?;
+            },
+            ProjectType::DrupalTheme => {
+                self.add_drupal_theme_info(&mut details, project_structure)?;
+            },
+            _ => {}
+        }
+        if let Some(plugin) = &project_structure.plugin {
+            if !plugin.details.trim().is_empty() {
+                details.push_str(&format!("{}:\n{}\n", plugin.name, plugin.details.trim_end()));
+            }
+        }
+        Ok(details)
+    }
+
+    /// The Drupal module a command is about when the site has several:
+    /// `--module` (or `[project] module`), else the one module the command
+    /// names by name or path
+    pub fn module_target(&self, command: &str) -> Result<ModuleTarget> {
+        let cwd = std::env::current_dir()?;
+        let modules = self.project_analyzer.find_all_drupal_modules(&cwd)?;
+        if modules.len() < 2 {
+            return Ok(ModuleTarget::All);
+        }
+        if let Some(name) = &self.module {
+            if !modules.iter().any(|(module, _)| module == name) {
+                let names: Vec<&str> = modules.iter().map(|(module, _)| module.as_str()).collect();
+                return Err(anyhow::anyhow!("Unknown Drupal module '{}' (expected one of: {})", name, names.join(", ")));
+            }
+            return Ok(ModuleTarget::Module(name.clone()));
+        }
+
+        let lower = command.to_lowercase();
+        let words: Vec<&str> = lower.split(|c: char| !c.is_alphanumeric() && c != '_').collect();
+        let named: Vec<&(String, PathBuf)> = modules
+            .iter()
+            .filter(|(name, path)| {
+                let relative = path.strip_prefix(&cwd).unwrap_or(path).to_string_lossy().to_lowercase();
+                words.contains(&name.to_lowercase().as_str()) || lower.contains(relative.as_str())
+            })
+            .collect();
+        Ok(match named.as_slice() {
+            [(name, _)] => ModuleTarget::Module(name.clone()),
+            [] => ModuleTarget::Ambiguous(modules),
+            _ => ModuleTarget::All,
+        })
+    }
+
+    /// Context for a command. With an LLM client, relevant files that have no
+    /// cached summary yet are summarized first. In a Drupal site, `module`
+    /// scopes the context to that module.
+    pub async fn gather_context(&self, command: &str, llm: Option<&LlmClient>, module: Option<&str>) -> Result<GatheredContext> {
         // No longer trying to load project memory here
         // That's now handled in App::gather_context
         
@@ -408,123 +906,778 @@ impl ContextManager {
         context.push_str(&format!("Working directory: {}\n", cwd.display()));
         
         // Analyze project structure to detect project type
-        if let Ok(project_structure) = self.project_analyzer.analyze_project_structure(&cwd) {
+        let mut detected_type = None;
+        let mut kind = None;
+        let mut sub_projects = Vec::new();
+        let mut module_root = None;
+        if let Ok(project_structure) = self.project_structure(&cwd) {
             if let Some(project_type) = &project_structure.project_type {
-                let type_str = match project_type {
-                    ProjectType::Drupal => "Drupal site",
-                    ProjectType::DrupalModule => "Drupal module",
-                    ProjectType::Rust => "Rust project",
-                    ProjectType::Python => "Python project",
-                    ProjectType::JavaScript => "JavaScript project",
-                    ProjectType::TypeScript => "TypeScript project",
-                    ProjectType::Go => "Go project",
-                    ProjectType::PHP => "PHP project",
-                    ProjectType::Angular => "Angular application",
-                    ProjectType::React => "React application",
-                    ProjectType::Generic => "Generic project",
-                };
-                context.push_str(&format!("\nProject type: {}\n", type_str));
+                let type_str = project_type.description();
+                match &project_structure.plugin {
+                    Some(plugin) => context.push_str(&format!("\nProject type: {} ({})\n", plugin.name, type_str)),
+                    None => context.push_str(&format!("\nProject type: {}\n", type_str)),
+                }
+                detected_type = Some(type_str.to_string());
+                kind = Some(*project_type);
                 
-                // Add language-specific file counts
-                self.add_file_count_info(&mut context, &project_structure);
+                // Add the lines of code per language
+                self.add_language_stats(&mut context, &project_structure);
                 
-                // Add more specific information based on project type
-                match project_type {
-                    ProjectType::Rust => {
-                        self.add_rust_project_info(&mut context, &project_structure)?;
-                    },
-                    ProjectType::Python => {
-                        self.add_python_project_info(&mut context, &project_structure)?;
-                    },
-                    ProjectType::Angular => {
-                        self.add_angular_project_info(&mut context, &project_structure)?;
-                    },
-                    ProjectType::React => {
-                        self.add_react_project_info(&mut context, &project_structure)?;
-                    },
-                    ProjectType::Drupal => {
-                        self.add_drupal_project_info(&mut context, &project_structure, &cwd)?;
-                    },
-                    ProjectType::DrupalModule => {
-                        self.add_drupal_module_project_info(&mut context, &project_structure, &cwd)?;
-                    },
-                    _ => {
-                        // For other project types, add generic info about the directory structure
-                        let directories_count = project_structure.directories.len();
-                        if directories_count > 0 {
-                            context.push_str(&format!("Project contains {} directories\n", directories_count));
-                            // List top-level directories
-                            let top_level = project_structure.directories.iter()
-                                .filter(|p| p.components().count() == 1)
-                                .take(5)
-                                .collect::<Vec<_>>();
-                            
-                            if !top_level.is_empty() {
-                                context.push_str("Top-level directories:\n");
-                                for dir in top_level {
-                                    context.push_str(&format!("- {}\n", dir.display()));
-                                }
-                            }
-                        }
+                // Add more specific information based on project type, only
+                // about the targeted module of a Drupal site
+                module_root = module
+                    .filter(|_| project_type == &ProjectType::Drupal)
+                    .and_then(|name| project_structure.modules.iter().find(|(module, _)| module == name))
+                    .map(|(_, path)| path.clone());
+                match (&module_root, module) {
+                    (Some(root), Some(name)) => {
+                        context.push_str(&format!(
+                            "Working in module {} ({}); the site's other modules are left out\n",
+                            name,
+                            root.strip_prefix(&cwd).unwrap_or(root).display()
+                        ));
+                        let structure = self.project_structure_as(root, Some(ProjectType::DrupalModule))?;
+                        kind = Some(ProjectType::DrupalModule);
+                        context.push_str(&self.project_details(&structure, root)?);
                     }
+                    _ => context.push_str(&self.project_details(&project_structure, &cwd)?),
                 }
+                
+                if !project_structure.sub_projects.is_empty() {
+                    context.push_str("Sub-projects:\n");
+                    for sub_project in project_structure.sub_projects.iter().take(MAX_SUB_PROJECTS) {
+                        context.push_str(&format!("- {}/ ({})\n", sub_project.path.display(), sub_project.project_type.description()));
+                    }
+                    sub_projects = project_structure.sub_projects.clone();
+                }
+                
+                add_project_commands(&mut context, &cwd, &cwd);
+                
+                // Add a budgeted map of the repository layout
+                context.push_str(&format!("Project contains {} directories\n", project_structure.directories.len()));
+                let file_tree = FileTree::from_structure(&project_structure, &cwd);
+                context.push_str(&format!("\nProject file tree (depth {}):\n", TREE_MAX_DEPTH));
+                context.push_str(&file_tree.render(TREE_MAX_DEPTH, TREE_MAX_LINES));
             }
             
-            context.push_str("\n");
+            context.push('\n');
         }
         
         // Find relevant files
-        let relevant_files = self.code_search.find_relevant_files(&cwd, &keywords)?;
+        let mut scored_files = self.code_search.find_scored_files(&cwd, &keywords)?;
+        self.boost_recent_changes(&cwd, &mut scored_files);
+        let symbols = self.symbol_usages(&cwd, &extract_symbols(command));
         
-        // Add file contents or summaries to context
-        for file_path in relevant_files.iter().take(3) {  // Limit to top 3 files to avoid context explosion
+        // Blend in semantic similarity when an embeddings endpoint is configured
+        let mut relevant_files = match &self.embedder {
+            Some(embedder) => {
+                match self.code_search.rank_semantically(&cwd, command, scored_files.clone(), embedder, self.semantic_weight).await {
+                    Ok(ranked) => ranked,
+                    Err(e) => {
+                        warn!("Semantic ranking failed, using keyword relevance: {}", e);
+                        scored_files.into_iter().map(|(path, _)| path).collect()
+                    }
+                }
+            }
+            None => scored_files.into_iter().map(|(path, _)| path).collect(),
+        };
+        
+        // Files defining and calling the symbols named in the command come first
+        relevant_files = self.code_search.with_symbol_files(relevant_files, &symbols);
+
+        // Files of the targeted Drupal module
+        if let Some(root) = &module_root {
+            if relevant_files.iter().any(|path| path.starts_with(root)) {
+                relevant_files.retain(|path| path.starts_with(root));
+            }
+        }
+
+        // In a monorepo, keep to the sub-project the command is about
+        if let Some(sub_project) = command_sub_project(command, &cwd, &sub_projects, &relevant_files) {
+            let root = cwd.join(&sub_project.path);
+            if relevant_files.iter().any(|path| path.starts_with(&root)) {
+                relevant_files.retain(|path| path.starts_with(&root));
+            }
+            context.push_str(&format!(
+                "Working in sub-project {}/ ({})\n",
+                sub_project.path.display(),
+                sub_project.project_type.description()
+            ));
+            kind = Some(sub_project.project_type);
+            if let Ok(structure) = self.project_structure_as(&root, Some(sub_project.project_type)) {
+                context.push_str(&self.project_details(&structure, &root)?);
+            }
+            add_project_commands(&mut context, &cwd, &root);
+            context.push('\n');
+        }
+
+        // Architecture questions start from how the project describes itself
+        if self.include_docs && summarize::wants_overview(command) {
+            add_overview_docs(&mut context, &cwd);
+        }
+
+        // Documentation questions are answered from the docs first, code second
+        if docs::wants_docs(command) {
+            if let Some(index) = DocsIndex::build(&cwd) {
+                if add_docs_sections(&mut context, &cwd, &index, &keywords) {
+                    relevant_files.retain(|path| !index.contains(path));
+                }
+            }
+        }
+        
+        // Add the sections of each file that match the command
+        if self.dense && !relevant_files.is_empty() {
+            context.push_str("(Comments and blank lines are left out of the code below; line numbers are those of the files.)\n");
+        }
+        let summaries = self.file_summaries(&cwd, &relevant_files, llm).await;
+        let budget = match self.max_total_tokens {
+            0 => usize::MAX,
+            tokens => tokens * BYTES_PER_TOKEN,
+        };
+        let top_files = &relevant_files[..relevant_files.len().min(self.max_files)];
+        let mut left_out = 0;
+        for file_path in top_files {
             if let Ok(content) = std::fs::read_to_string(file_path) {
                 let relative_path = file_path.strip_prefix(&cwd).unwrap_or(file_path);
-                context.push_str(&format!("File: {}\n", relative_path.display()));
-                
-                // Include only first ~500 chars to avoid overly large contexts
-                let preview = if content.len() > 500 {
-                    format!("{}... (truncated)", &content[..500])
-                } else {
-                    content
-                };
-                
-                context.push_str(&format!("{}\n\n", preview));
+                let mut entry = format!("File: {}\n", relative_path.display());
+                // Long files with a summary show matching definitions only, no preview
+                let summary = summaries.get(file_path);
+                if let Some(summary) = summary {
+                    entry.push_str(&format!("Summary: {}\n", summary));
+                }
+                let sections = relevant_sections(file_path, &content, &keywords, self.dense, summary.is_none(), self.max_bytes_per_file);
+                entry.push_str(&format!("{}\n", sections));
+                // The most relevant file is always included
+                if context.len() + entry.len() > budget && left_out + 1 < top_files.len() {
+                    left_out += 1;
+                    continue;
+                }
+                context.push_str(&entry);
+            }
+        }
+        if left_out > 0 {
+            context.push_str(&format!(
+                "({} more relevant file{} left out to stay within context.max_total_tokens)\n\n",
+                left_out,
+                if left_out == 1 { "" } else { "s" }
+            ));
+        }
+        if context.len() < budget {
+            // Helpers the top files import, for changes spanning both
+            add_imported_files(&mut context, &cwd, top_files, &keywords, self.dense, self.max_bytes_per_file);
+            // Changes to a file should keep its tests passing
+            if testmap::wants_tests(command) {
+                add_test_files(&mut context, &cwd, top_files, &keywords, self.dense, self.max_bytes_per_file);
+            }
+        }
+        let other_files: Vec<String> = relevant_files
+            .iter()
+            .skip(self.max_files)
+            .filter_map(|path| summaries.get(path).map(|summary| (path, summary)))
+            .map(|(path, summary)| format!("- {}: {}\n", path.strip_prefix(&cwd).unwrap_or(path).display(), summary))
+            .collect();
+        if !other_files.is_empty() {
+            context.push_str("Other relevant files:\n");
+            context.push_str(&other_files.concat());
+            context.push('\n');
+        }
+        
+        // Definitions and call sites of code symbols named in the command
+        self.add_symbol_definitions(&mut context, &cwd, &symbols).await;
+        add_call_sites(&mut context, &cwd, &symbols);
+        
+        // Vendored packages explicitly requested with @vendor:<package>
+        let packages = vendor::vendor_references(command);
+        if packages.len() > vendor::MAX_PACKAGES_PER_REQUEST {
+            self.ui.print(&format!(
+                "{} Only the first {} @vendor packages are included",
+                "!".yellow(),
+                vendor::MAX_PACKAGES_PER_REQUEST
+            ));
+        }
+        for package in packages.iter().take(vendor::MAX_PACKAGES_PER_REQUEST) {
+            match vendor::package_excerpt(&cwd, package, &keywords) {
+                Ok(excerpt) => context.push_str(&format!("{}\n", excerpt)),
+                Err(e) => self.ui.print(&format!("{} {}", "!".yellow(), e)),
             }
         }
         
         // Add git status if relevant
-        if command.contains("git") || command.contains("commit") || command.contains("merge") {
-            if let Ok(git_status) = self.get_git_status(&cwd) {
-                context.push_str(&format!("Git status:\n{}\n\n", git_status));
+        let mentions_git = command.contains("git") || command.contains("commit") || command.contains("merge");
+        if mentions_git && self.git.is_enabled() {
+            match GitCommands::status(&cwd) {
+                Ok(git_status) => context.push_str(&format!("Git status:\n{}\n\n", git_status)),
+                Err(e) => debug!("Failed to read git status: {}", e),
             }
         }
         
-        Ok(context)
+        if context.len() > budget {
+            context = vendor::truncate(&context, budget);
+        }
+
+        Ok(GatheredContext {
+            project_type: detected_type,
+            kind,
+            text: context,
+        })
+    }
+    
+    /// Ranks files with uncommitted changes, then files changed in the last
+    /// `recent_commits` commits, higher than equally relevant ones, as they
+    /// are most likely what a command is about
+    fn boost_recent_changes(&self, cwd: &Path, scored_files: &mut [(PathBuf, usize)]) {
+        if self.recent_commits == 0 || !self.git.is_enabled() || scored_files.is_empty() {
+            return;
+        }
+        let dirty = GitCommands::changed_files(cwd).unwrap_or_else(|e| {
+            debug!("Failed to read uncommitted changes: {}", e);
+            Vec::new()
+        });
+        let recent = GitHistory::change_counts(cwd, self.recent_commits).unwrap_or_else(|e| {
+            debug!("Failed to read recent commits: {}", e);
+            HashMap::new()
+        });
+        for (path, relevance) in scored_files.iter_mut() {
+            let relative = path.strip_prefix(cwd).unwrap_or(path);
+            if dirty.iter().any(|changed| changed == relative) {
+                *relevance *= 2;
+            } else if recent.contains_key(relative) {
+                *relevance += *relevance / 2;
+            }
+        }
+        scored_files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    }
+
+    /// Answers a question like "where is `ContextManager` used" from the
+    /// symbol index. None when the command isn't a usage question or names no
+    /// symbol the project contains, so the model answers instead.
+    pub fn answer_usage_query(&self, command: &str) -> Result<Option<String>> {
+        if !xref::is_usage_query(command) {
+            return Ok(None);
+        }
+        let cwd = std::env::current_dir()?;
+        let symbol = match self.symbol_usages(&cwd, &extract_symbols(command)).into_iter().next() {
+            Some(symbol) => symbol,
+            None => return Ok(None),
+        };
+
+        let mut answer = String::new();
+        if symbol.definitions.is_empty() {
+            answer.push_str(&format!("`{}` is not defined in this project\n", symbol.name));
+        } else {
+            answer.push_str(&format!("`{}` is defined at:\n", symbol.name));
+            for definition in &symbol.definitions {
+                let relative = definition.path.strip_prefix(&cwd).unwrap_or(&definition.path);
+                let kind = definition.kind.as_deref().unwrap_or("definition");
+                answer.push_str(&format!("  {}:{} ({})\n", relative.display(), definition.line, kind));
+            }
+        }
+
+        if symbol.references.is_empty() {
+            answer.push_str("\nIt is not referenced anywhere else\n");
+        } else {
+            let mut files: Vec<&std::path::PathBuf> = symbol.references.iter().map(|r| &r.path).collect();
+            files.dedup();
+            answer.push_str(&format!(
+                "\nReferenced on {} line{} in {} file{}:\n",
+                symbol.references.len(),
+                if symbol.references.len() == 1 { "" } else { "s" },
+                files.len(),
+                if files.len() == 1 { "" } else { "s" }
+            ));
+            answer.push_str(&format_call_sites(&cwd, &symbol.references, MAX_ANSWER_CALL_SITES));
+        }
+        Ok(Some(answer))
+    }
+
+    /// Definitions and references of each symbol from the refreshed index,
+    /// dropping symbols the project doesn't contain
+    fn symbol_usages(&self, cwd: &Path, names: &[String]) -> Vec<SymbolUsage> {
+        if names.is_empty() {
+            return Vec::new();
+        }
+        let mut index = self.symbol_index.lock().unwrap();
+        if let Err(e) = index.refresh(cwd) {
+            debug!("Failed to refresh the symbol index: {}", e);
+        }
+        names.iter().map(|name| index.usage(name)).filter(SymbolUsage::is_known).collect()
+    }
+
+    /// Summaries of the long top files and the next few relevant files. Files
+    /// not summarized before are summarized with `llm`, or left out without it.
+    async fn file_summaries(&self, cwd: &Path, relevant_files: &[PathBuf], llm: Option<&LlmClient>) -> HashMap<PathBuf, String> {
+        if !self.summarize.file_summaries {
+            return HashMap::new();
+        }
+        let files: Vec<PathBuf> = relevant_files
+            .iter()
+            .take(self.max_files + MAX_SUMMARIZED_FILES)
+            .enumerate()
+            .filter(|(rank, path)| {
+                *rank >= self.max_files || std::fs::read_to_string(path).is_ok_and(|content| content.lines().count() > MAX_SECTION_LINES)
+            })
+            .map(|(_, path)| path.clone())
+            .collect();
+        match llm {
+            Some(client) => Summarizer::new(client, &self.summarize, &self.summaries, self.ui.as_ref()).file_summaries(cwd, files).await,
+            None => files
+                .into_iter()
+                .filter_map(|path| {
+                    let summary = self.summaries.get(&std::fs::read_to_string(&path).ok()?)?;
+                    Some((path, summary))
+                })
+                .collect(),
+        }
+    }
+
+    /// Adds the definition of each code symbol mentioned in the command, using
+    /// the language server when one is available and the symbol index otherwise
+    async fn add_symbol_definitions(&self, context: &mut String, cwd: &Path, symbols: &[SymbolUsage]) {
+        let mut section = String::new();
+        for symbol in symbols {
+            let mut found = None;
+
+            if let Some(lsp) = &self.lsp {
+                let mut paths: Vec<&std::path::PathBuf> =
+                    symbol.definitions.iter().chain(&symbol.references).map(|l| &l.path).collect();
+                paths.dedup();
+                for path in paths.into_iter().take(SYMBOL_SEARCH_FILES) {
+                    let content = match std::fs::read_to_string(path) {
+                        Ok(content) => content,
+                        Err(_) => continue,
+                    };
+                    if let Some((line, character)) = find_symbol(&content, &symbol.name) {
+                        if let Some((location, hover)) = lsp.definition(path, line, character).await {
+                            found = Some((location.path, location.line, hover));
+                            break;
+                        }
+                    }
+                }
+            }
+
+            if found.is_none() {
+                found = symbol.definitions.first().map(|d| (d.path.clone(), d.line, None));
+            }
+
+            let (path, line, hover) = match found {
+                Some(found) => found,
+                None => continue,
+            };
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            let relative = path.strip_prefix(cwd).unwrap_or(&path);
+            section.push_str(&format!("{} ({}:{}):\n", symbol.name, relative.display(), line));
+            if let Some(hover) = hover {
+                section.push_str(&format!("Type: {}\n", hover));
+            }
+            let content = if self.dense { strip_comments(&path, &content) } else { content };
+            let snippet: Vec<&str> = content
+                .lines()
+                .skip(line.saturating_sub(1))
+                .filter(|line| !self.dense || !line.trim().is_empty())
+                .map(|line| if self.dense { line.trim_end() } else { line })
+                .take(DEFINITION_SNIPPET_LINES)
+                .collect();
+            section.push_str(&format!("{}\n\n", snippet.join("\n")));
+        }
+
+        if !section.is_empty() {
+            context.push_str(&format!("Symbol definitions:\n{}", section));
+        }
     }
     
     fn extract_keywords(&self, command: &str) -> Vec<String> {
         // Simple keyword extraction - in a real implementation this would be more sophisticated
         command
             .split_whitespace()
-            .filter(|word| word.len() > 3)
+            .filter(|word| word.len() > 3 && !word.starts_with("@vendor:"))
             .map(|word| word.to_lowercase())
             .collect()
     }
     
-    fn get_git_status(&self, path: &Path) -> Result<String> {
-        use std::process::Command;
-        
-        let output = Command::new("git")
-            .current_dir(path)
-            .args(&["status", "--short"])
-            .output()?;
-        
-        if output.status.success() {
-            let git_status = String::from_utf8_lossy(&output.stdout).to_string();
-            Ok(git_status)
+}
+
+/// Words in the command that look like code identifiers: quoted in
+/// backticks, called with `()`, or written in snake_case or camelCase
+fn extract_symbols(command: &str) -> Vec<String> {
+    let mut symbols: Vec<String> = Vec::new();
+    for word in command.split_whitespace() {
+        let quoted = word.contains('`') || word.contains("()");
+        let name = word
+            .trim_matches(|c: char| !c.is_alphanumeric() && c != '_')
+            .trim_end_matches("()");
+        let is_identifier = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        let looks_like_code = quoted || name.contains('_') || name.chars().skip(1).any(|c| c.is_uppercase());
+        if is_identifier && looks_like_code && name.len() > 2 && !symbols.iter().any(|s| s == name) {
+            symbols.push(name.to_string());
+        }
+    }
+    symbols.truncate(MAX_SYMBOLS);
+    symbols
+}
+
+/// `content` without comments and docstrings, for languages with a grammar;
+/// other files are returned as they are
+fn strip_comments(path: &Path, content: &str) -> String {
+    Grammar::for_path(path)
+        .and_then(|grammar| syntax::strip_comments(grammar, content).ok())
+        .unwrap_or_else(|| content.to_string())
+}
+
+/// The functions, classes and other definitions of a file that mention the
+/// keywords, with line numbers. Files that don't parse or have no matching
+/// definition show the lines around the first keyword match instead, unless
+/// `preview` is false (the file has a summary), which shows nothing.
+/// Definitions are added best first while they fit in `max_bytes`, and the
+/// text is cut there. `dense` leaves out comments and blank lines, keeping
+/// the line numbers.
+fn relevant_sections(path: &Path, content: &str, keywords: &[String], dense: bool, preview: bool, max_bytes: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.is_empty() {
+        return "(empty file)\n".to_string();
+    }
+
+    let keywords: Vec<String> = keywords
+        .iter()
+        .map(|k| k.trim_matches(|c: char| !c.is_alphanumeric() && c != '_').to_lowercase())
+        .filter(|k| !k.is_empty())
+        .collect();
+    let line_matches: Vec<usize> = lines
+        .iter()
+        .map(|line| {
+            let line = line.to_lowercase();
+            keywords.iter().map(|k| line.matches(k.as_str()).count()).sum()
+        })
+        .collect();
+
+    let grammar = Grammar::for_path(path);
+    let definitions = grammar
+        .and_then(|g| syntax::outline(g, content).ok())
+        .map(|outline| outline.definitions)
+        .unwrap_or_default();
+
+    // Long impl blocks and classes are represented by their members
+    let is_container = |d: &Definition| {
+        d.end_line - d.line >= MAX_SECTION_LINES
+            && definitions.iter().any(|o| o.line > d.line && o.end_line <= d.end_line)
+    };
+    let mut candidates: Vec<(usize, &Definition)> = definitions
+        .iter()
+        .filter(|d| d.line <= d.end_line && d.end_line <= lines.len() && !is_container(d))
+        .map(|d| {
+            let name = d.name.to_lowercase();
+            let name_matches = keywords.iter().filter(|k| name.contains(k.as_str())).count();
+            let body_matches: usize = line_matches[d.line - 1..d.end_line].iter().sum();
+            (name_matches * NAME_MATCH_WEIGHT + body_matches, d)
+        })
+        .filter(|(score, _)| *score > 0)
+        .collect();
+    // Best score first, then the tightest definition
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then((a.1.end_line - a.1.line).cmp(&(b.1.end_line - b.1.line))));
+
+    // Bytes a section takes up once numbered and cut to its shown lines
+    let section_bytes = |start: usize, end: usize| -> usize {
+        lines[start - 1..end.min(start + MAX_SECTION_LINES - 1)]
+            .iter()
+            .map(|line| line.len().min(MAX_LINE_CHARS) + 9)
+            .sum()
+    };
+    let mut sections: Vec<(usize, usize, String)> = Vec::new();
+    let mut used = 0;
+    for (_, definition) in candidates {
+        if sections.iter().any(|(start, end, _)| definition.line <= *end && *start <= definition.end_line) {
+            continue;
+        }
+        let bytes = section_bytes(definition.line, definition.end_line);
+        if !sections.is_empty() && used + bytes > max_bytes {
+            continue;
+        }
+        used += bytes;
+        let label = format!("{} {}", definition.kind, definition.name);
+        sections.push((definition.line, definition.end_line, label));
+    }
+    sections.sort_by_key(|(start, _, _)| *start);
+
+    if sections.is_empty() {
+        if !preview {
+            return String::new();
+        }
+        let first_match = line_matches.iter().position(|&count| count > 0).unwrap_or(0);
+        let start = first_match.saturating_sub(PREVIEW_LINES / 4) + 1;
+        let end = (start + PREVIEW_LINES - 1).min(lines.len());
+        sections.push((start, end, String::new()));
+    }
+
+    // Scoring above uses the comments too; only the shown code is stripped
+    let stripped = if dense { strip_comments(path, content) } else { String::new() };
+    let shown_lines: Vec<&str> = if dense { stripped.lines().collect() } else { lines };
+
+    let mut text = String::new();
+    for (start, end, label) in sections {
+        if text.len() >= max_bytes {
+            break;
+        }
+        if label.is_empty() {
+            text.push_str(&format!("Lines {}-{} of {}:\n", start, end, shown_lines.len()));
         } else {
-            Ok("Not a git repository or git command failed".to_string())
+            text.push_str(&format!("Lines {}-{} ({}):\n", start, end, label));
+        }
+        // Dense sections fit MAX_SECTION_LINES lines of code rather than of file
+        let mut shown = 0;
+        let mut number = start;
+        while number <= end && shown < MAX_SECTION_LINES && text.len() < max_bytes {
+            let line = shown_lines[number - 1];
+            number += 1;
+            if dense && line.trim().is_empty() {
+                continue;
+            }
+            let line = if dense { line.trim_end() } else { line };
+            let visible: String = line.chars().take(MAX_LINE_CHARS).collect();
+            let cut = if visible.len() < line.len() { " ..." } else { "" };
+            text.push_str(&format!("{:>5} | {}{}\n", number - 1, visible, cut));
+            shown += 1;
+        }
+        if number <= end {
+            text.push_str(&format!("      ... ({} more lines)\n", end + 1 - number));
+        }
+    }
+    text
+}
+
+/// Lists a manifest's dependencies and dev dependencies as "name version"
+fn add_dependencies(context: &mut String, dependencies: &[(String, String)], dev_dependencies: &[(String, String)]) {
+    for (label, dependencies) in [("Dependencies", dependencies), ("Dev dependencies", dev_dependencies)] {
+        if dependencies.is_empty() {
+            continue;
+        }
+        let mut listed: Vec<String> = dependencies
+            .iter()
+            .take(MAX_DEPENDENCIES)
+            .map(|(name, source)| if source.is_empty() { name.clone() } else { format!("{} {}", name, source) })
+            .collect();
+        if dependencies.len() > MAX_DEPENDENCIES {
+            listed.push(format!("... and {} more", dependencies.len() - MAX_DEPENDENCIES));
+        }
+        context.push_str(&format!("{} ({}): {}\n", label, dependencies.len(), listed.join(", ")));
+    }
+}
+
+/// Lists the build, test and other commands the project at `root` defines,
+/// run from `cwd`
+fn add_project_commands(context: &mut String, cwd: &Path, root: &Path) {
+    let commands = project_commands::detect_commands(root);
+    if commands.is_empty() {
+        return;
+    }
+    let prefix = match root.strip_prefix(cwd) {
+        Ok(dir) if !dir.as_os_str().is_empty() => format!("cd {} && ", dir.display()),
+        _ => String::new(),
+    };
+    context.push_str("Available project commands (use these in execute_command):\n");
+    for command in commands.iter().take(MAX_PROJECT_COMMANDS) {
+        context.push_str(&format!("- {}{}: {}\n", prefix, command.command, vendor::truncate(&command.description, 120)));
+    }
+    if commands.len() > MAX_PROJECT_COMMANDS {
+        context.push_str(&format!("... and {} more\n", commands.len() - MAX_PROJECT_COMMANDS));
+    }
+}
+
+/// Lists where each symbol is referenced, a few call sites per symbol
+fn add_call_sites(context: &mut String, cwd: &Path, symbols: &[SymbolUsage]) {
+    for symbol in symbols.iter().filter(|s| !s.references.is_empty()) {
+        context.push_str(&format!("Call sites of {}:\n", symbol.name));
+        context.push_str(&format_call_sites(cwd, &symbol.references, MAX_CALL_SITES));
+        context.push('\n');
+    }
+}
+
+/// Adds the documentation sections best matching the keywords, each headed
+/// with its page and line so the answer can cite them. False when none match.
+/// The sub-project a command is about: the one holding a path the command
+/// names (`frontend/src/App.tsx`, `backend`), else the one holding the most
+/// relevant file
+fn command_sub_project<'a>(command: &str, cwd: &Path, sub_projects: &'a [SubProject], relevant_files: &[PathBuf]) -> Option<&'a SubProject> {
+    // The innermost sub-project holding a path
+    let holding = |path: &Path| {
+        sub_projects
+            .iter()
+            .filter(|sub_project| path.starts_with(cwd.join(&sub_project.path)))
+            .max_by_key(|sub_project| sub_project.path.components().count())
+    };
+    let named = command
+        .split(|c: char| c.is_whitespace() || "`'\"".contains(c))
+        .map(|word| word.trim_matches(|c: char| ",.:;?!()".contains(c)).trim_start_matches("./"))
+        .filter(|word| !word.is_empty())
+        .find_map(|word| {
+            holding(&cwd.join(word)).or_else(|| {
+                sub_projects.iter().find(|sub_project| sub_project.path.file_name().is_some_and(|name| name == word))
+            })
+        });
+    named.or_else(|| relevant_files.first().and_then(|path| holding(path)))
+}
+
+/// Adds the project files the top context files import directly that
+/// aren't context files themselves
+fn add_imported_files(context: &mut String, cwd: &Path, top_files: &[PathBuf], keywords: &[String], dense: bool, max_bytes: usize) {
+    let top_files: Vec<PathBuf> = top_files
+        .iter()
+        .map(|path| path.strip_prefix(cwd).unwrap_or(path).to_path_buf())
+        .collect();
+    let imports = match relocate::direct_imports(cwd, &top_files) {
+        Ok(imports) => imports,
+        Err(e) => {
+            debug!("Could not read the imports of the context files: {}", e);
+            return;
+        }
+    };
+    let mut added: Vec<&PathBuf> = Vec::new();
+    for source in &top_files {
+        let targets = match imports.get(source) {
+            Some(targets) => targets,
+            None => continue,
+        };
+        for target in targets {
+            if added.len() >= MAX_IMPORTED_FILES {
+                return;
+            }
+            if top_files.contains(target) || added.contains(&target) {
+                continue;
+            }
+            let content = match std::fs::read_to_string(cwd.join(target)) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            context.push_str(&format!("Imported: {} (imported by {})\n", target.display(), source.display()));
+            context.push_str(&format!("{}\n", relevant_sections(target, &content, keywords, dense, true, max_bytes)));
+            added.push(target);
+        }
+    }
+}
+
+/// Adds the tests of the top context files that aren't context files
+/// themselves
+fn add_test_files(context: &mut String, cwd: &Path, top_files: &[PathBuf], keywords: &[String], dense: bool, max_bytes: usize) {
+    let test_map = TestMap::build(cwd);
+    let tests: Vec<(&PathBuf, PathBuf)> = top_files
+        .iter()
+        .filter_map(|source| {
+            let test = test_map.tests_of(source).into_iter().find(|test| !top_files.contains(test))?;
+            Some((source, test))
+        })
+        .take(MAX_TEST_FILES)
+        .collect();
+    for (source, test) in tests {
+        let content = match std::fs::read_to_string(&test) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        context.push_str(&format!(
+            "Test: {} (tests of {})\n",
+            test.strip_prefix(cwd).unwrap_or(&test).display(),
+            source.strip_prefix(cwd).unwrap_or(source).display()
+        ));
+        context.push_str(&format!("{}\n", relevant_sections(&test, &content, keywords, dense, true, max_bytes)));
+    }
+}
+
+fn add_docs_sections(context: &mut String, cwd: &Path, index: &DocsIndex, keywords: &[String]) -> bool {
+    let keywords: Vec<String> = keywords
+        .iter()
+        .map(|k| k.trim_matches(|c: char| !c.is_alphanumeric()).to_string())
+        .filter(|k| k.len() > 3)
+        .collect();
+    let sections = index.search(&keywords, MAX_DOC_SECTIONS);
+    if sections.is_empty() {
+        return false;
+    }
+
+    let source = match index.generator {
+        Some(generator) => format!("{} site", generator),
+        None => "project docs".to_string(),
+    };
+    context.push_str(&format!(
+        "Documentation ({}, {} pages). Answer from these sections first and cite the pages and lines you used as sources:\n",
+        source,
+        index.page_count()
+    ));
+    for section in sections {
+        let relative = section.path.strip_prefix(cwd).unwrap_or(&section.path);
+        context.push_str(&format!("Doc: {}:{} ({})\n", relative.display(), section.line, section.title));
+        let lines: Vec<&str> = section.text.lines().collect();
+        for (offset, line) in lines.iter().take(MAX_DOC_SECTION_LINES).enumerate() {
+            let visible: String = line.chars().take(MAX_LINE_CHARS).collect();
+            let cut = if visible.len() < line.len() { " ..." } else { "" };
+            context.push_str(&format!("{:>5} | {}{}\n", section.line + offset, visible, cut));
         }
+        if lines.len() > MAX_DOC_SECTION_LINES {
+            context.push_str(&format!("      ... ({} more lines)\n", lines.len() - MAX_DOC_SECTION_LINES));
+        }
+        context.push('\n');
     }
+    context.push_str("Code:\n");
+    true
+}
+
+/// Adds trimmed README, CONTRIBUTING, architecture and docs overview pages
+fn add_overview_docs(context: &mut String, cwd: &Path) {
+    let pages = docs::overview_pages(cwd);
+    if pages.is_empty() {
+        return;
+    }
+    context.push_str("Project documentation (badges, images and long code blocks left out):\n");
+    for page in pages.iter().take(MAX_OVERVIEW_PAGES) {
+        let content = match std::fs::read_to_string(page) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let lines = docs::trimmed_lines(&content);
+        context.push_str(&format!("Doc: {}\n", page.strip_prefix(cwd).unwrap_or(page).display()));
+        for (number, line) in lines.iter().take(MAX_OVERVIEW_LINES) {
+            let visible: String = line.chars().take(MAX_LINE_CHARS).collect();
+            let cut = if visible.len() < line.len() { " ..." } else { "" };
+            context.push_str(&format!("{:>5} | {}{}\n", number, visible, cut));
+        }
+        if lines.len() > MAX_OVERVIEW_LINES {
+            context.push_str(&format!("      ... ({} more lines)\n", lines.len() - MAX_OVERVIEW_LINES));
+        }
+        context.push('\n');
+    }
+}
+
+/// One "path:line: code" line per location, up to `limit`
+fn format_call_sites(cwd: &Path, locations: &[Location], limit: usize) -> String {
+    let mut contents: HashMap<&Path, String> = HashMap::new();
+    let mut text = String::new();
+    for location in locations.iter().take(limit) {
+        let content = contents
+            .entry(&location.path)
+            .or_insert_with(|| std::fs::read_to_string(&location.path).unwrap_or_default());
+        let relative = location.path.strip_prefix(cwd).unwrap_or(&location.path);
+        text.push_str(&format!(
+            "  {}:{}: {}\n",
+            relative.display(),
+            location.line,
+            xref::line_text(content, location.line)
+        ));
+    }
+    if locations.len() > limit {
+        text.push_str(&format!("  ... and {} more\n", locations.len() - limit));
+    }
+    text
+}
+
+/// First whole-word occurrence of `symbol` as a 0-based line and UTF-16 column
+fn find_symbol(content: &str, symbol: &str) -> Option<(usize, usize)> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    content.lines().enumerate().find_map(|(line_idx, line)| {
+        line.match_indices(symbol).find_map(|(start, _)| {
+            let before = line[..start].chars().next_back();
+            let after = line[start + symbol.len()..].chars().next();
+            (!before.is_some_and(is_ident) && !after.is_some_and(is_ident))
+                .then(|| (line_idx, line[..start].encode_utf16().count()))
+        })
+    })
 }
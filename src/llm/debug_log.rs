@@ -0,0 +1,61 @@
+use crate::fs::storage::Storage;
+use log::warn;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Writes every prompt and raw response to `.caulk/logs/` (`--debug-llm`),
+/// tagged with a request ID that is printed so users can attach the exact
+/// payloads to bug reports
+pub struct DebugLog {
+    dir: PathBuf,
+    counter: AtomicU32,
+    storage: Storage,
+}
+
+impl DebugLog {
+    pub fn new(project_root: &Path, storage: Storage) -> Self {
+        Self {
+            dir: Self::dir(project_root),
+            counter: AtomicU32::new(0),
+            storage,
+        }
+    }
+
+    /// Directory the payloads of the project at `project_root` are written to
+    pub fn dir(project_root: &Path) -> PathBuf {
+        project_root.join(".caulk").join("logs")
+    }
+
+    /// Saves the request body sent to `url` and returns its ID
+    pub fn request(&self, url: &str, body: &impl Serialize) -> String {
+        let id = format!(
+            "{}-{}",
+            chrono::Local::now().format("%Y%m%d-%H%M%S-%3f"),
+            self.counter.fetch_add(1, Ordering::SeqCst) + 1
+        );
+        let content = serde_json::json!({ "url": url, "body": body });
+        let content = serde_json::to_string_pretty(&content).unwrap_or_default();
+        self.write(&format!("{}-request.json", id), &content);
+        id
+    }
+
+    /// Where the payloads of this session are written
+    pub fn payload_dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Saves the raw response body for a request ID
+    pub fn response(&self, id: &str, status: u16, body: &str) {
+        self.write(&format!("{}-response.txt", id), &format!("HTTP {}\n\n{}", status, body));
+    }
+
+    fn write(&self, name: &str, content: &str) {
+        let result = std::fs::create_dir_all(&self.dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| self.storage.write(&self.dir.join(name), content));
+        if let Err(e) = result {
+            warn!("Failed to write LLM debug log {}: {}", name, e);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::config::{EmbeddingProvider, EmbeddingsConfig};
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiEmbeddingResponse {
+    data: Vec<OpenAiEmbedding>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiEmbedding {
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaEmbeddingResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+/// Client for OpenAI-compatible and Ollama embedding endpoints
+pub struct EmbeddingClient {
+    client: Client,
+    config: EmbeddingsConfig,
+}
+
+impl EmbeddingClient {
+    pub fn new(config: &EmbeddingsConfig) -> Self {
+        Self {
+            client: Client::new(),
+            config: config.clone(),
+        }
+    }
+
+    /// The embedding model, so stored vectors from another model aren't compared
+    pub fn model(&self) -> &str {
+        &self.config.model
+    }
+
+    /// Returns one embedding vector per input text, in order
+    pub async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let endpoint = match self.config.provider {
+            EmbeddingProvider::OpenAi => "embeddings",
+            EmbeddingProvider::Ollama => "embed",
+        };
+        let url = format!("{}/{}", self.config.api_url.trim_end_matches('/'), endpoint);
+
+        debug!("Requesting {} embeddings from {}", inputs.len(), url);
+
+        let response = self
+            .client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", self.config.api_key))
+            .json(&EmbeddingRequest {
+                model: &self.config.model,
+                input: inputs,
+            })
+            .send()
+            .await
+            .context("Failed to send request to embeddings API")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await?;
+            return Err(anyhow!("Embeddings API error: {} - {}", status, text));
+        }
+
+        let embeddings = match self.config.provider {
+            EmbeddingProvider::OpenAi => {
+                let parsed: OpenAiEmbeddingResponse = response
+                    .json()
+                    .await
+                    .context("Failed to parse embeddings response")?;
+                parsed.data.into_iter().map(|d| d.embedding).collect::<Vec<_>>()
+            }
+            EmbeddingProvider::Ollama => {
+                let parsed: OllamaEmbeddingResponse = response
+                    .json()
+                    .await
+                    .context("Failed to parse embeddings response")?;
+                parsed.embeddings
+            }
+        };
+
+        if embeddings.len() != inputs.len() {
+            return Err(anyhow!(
+                "Embeddings API returned {} vectors for {} inputs",
+                embeddings.len(),
+                inputs.len()
+            ));
+        }
+
+        Ok(embeddings)
+    }
+}
+
+/// Cosine similarity between two vectors (0.0 when either is empty or zero)
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
@@ -1,3 +1,13 @@
+pub mod cache;
 pub mod client;
+pub mod embeddings;
 pub mod prompt;
 pub mod context;
+pub mod vendor;
+pub mod reasoning;
+pub mod summarize;
+pub mod completion;
+pub mod compare;
+pub mod debug_log;
+pub mod codeblocks;
+pub mod scaffold;
@@ -1,26 +1,154 @@
-pub struct PromptBuilder;
+use crate::analysis::structure::ProjectType;
+use crate::commands::policy::ActionPolicy;
+use anyhow::{Context, Result};
+use minijinja::{context, Environment};
+use serde::Serialize;
+use std::path::PathBuf;
+
+const SYSTEM_TEMPLATE: &str = "system.jinja";
+const USER_TEMPLATE: &str = "user.jinja";
+
+const DEFAULT_SYSTEM_TEMPLATE: &str = r#"You are CodeAssist, an AI coding assistant that helps users with their codebase. You analyze the context and the user's command, and respond with specific actions to take. Respond in JSON format with the following structure: {"action": "<action_type>", "details": {...action specific details...}}. Possible actions: {{ allowed_actions | join(", ") }}.
+{%- if "multi_edit" in allowed_actions %} multi_edit details are {"edits": [...]}, a list of edit_file details applied in order; use it for a change spanning several files, which is applied completely or not at all.{% endif %}
+{%- if "create_file" in allowed_actions %} create_file details are {"file_path": "...", "content": "...", "if_exists": "fail"}, where if_exists is fail, skip or overwrite; use it for new files and edit_file for existing ones.{% endif %}
+{%- if "rename_file" in allowed_actions %} delete_file details are {"file_path": "..."} and rename_file details are {"file_path": "...", "new_path": "..."}; renaming also updates the imports, mod declarations and includes that refer to the file. The user confirms both first.{% endif %}
+{%- if "execute_command" in allowed_actions %} execute_command details are {"command": "...", "working_dir": "..."}, where working_dir is a directory inside the project (the project root when omitted); commands run without API keys or tokens in their environment and are stopped after a time limit.{% endif %}
+{%- if "answer_question" in allowed_actions %} answer_question details are {"answer": "...", "sources": [{"path": "...", "start_line": 1, "end_line": 10}]}, where sources lists the files and line ranges from the provided context the answer is based on; leave sources empty when the context doesn't support the answer.{% endif %}
+{%- if "read_file_at_revision" in allowed_actions %} read_file_at_revision details are {"file_path": "...", "revision": "HEAD~1"}, where revision is a commit, branch, tag or HEAD~n; use it to compare the current code with an earlier version, e.g. when diagnosing a regression. The file as of that revision is sent back to you, and you then respond with your next action.{% endif %}
+{%- if "find_symbol" in allowed_actions %} find_symbol details are {"name": "..."}, where name is a function, type or constant, or Type::method to narrow it to one parent; use it to find a definition that isn't in the context. Where it is defined, with its signature, is sent back to you, and you then respond with your next action.{% endif %}
+{%- if policy_instructions %} {{ policy_instructions }}{% endif %}
+{%- for custom in custom_prompts %}
+
+{{ custom }}
+{%- endfor %}"#;
+
+const DEFAULT_USER_TEMPLATE: &str = r#"Command: {{ command }}
+
+Context from codebase:
+{% if memory %}# Project Memory
+{{ memory }}
+
+{% endif %}{{ context }}"#;
+
+/// Values available to prompt templates besides the policy-derived ones
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PromptContext {
+    /// The user's natural language command
+    pub command: String,
+    /// Detected project type, e.g. "Rust project"
+    pub project_type: Option<String>,
+    /// Combined CAULK.md project memory
+    pub memory: String,
+    /// `## System Prompt` sections from CAULK.md
+    pub memory_system_prompt: Option<String>,
+    /// Coding conventions of the project's ecosystem: the `## Conventions`
+    /// sections of CAULK.md, or the built-in ones for the project type
+    pub conventions: Option<String>,
+    /// Gathered code context (files, structure, search results)
+    pub context: String,
+}
+
+/// Conventions of a project type's ecosystem added to the system prompt, so
+/// generated code looks like the code around it
+pub fn conventions(project_type: ProjectType) -> Option<&'static str> {
+    let conventions = match project_type {
+        ProjectType::Rust => "Follow Rust conventions: propagate errors with `?` instead of `unwrap()` or `expect()` \
+outside tests, prefer borrowing over cloning, use iterators over index loops, and keep code `cargo fmt` and \
+`cargo clippy` clean.",
+        ProjectType::Drupal | ProjectType::DrupalModule | ProjectType::DrupalTheme => "Follow Drupal conventions: \
+inject services through the constructor and `create()` instead of calling `\\Drupal::service()` in classes, follow \
+the Drupal coding standards (two-space indentation, doc blocks on every function and hook), translate user-facing \
+strings with `t()` or `$this->t()`, and declare services, routes and permissions in the module's YAML files.",
+        ProjectType::React => "Follow React conventions: write function components with hooks, not class \
+components, keep state as local as possible, give list items stable keys, and keep side effects in `useEffect` \
+with complete dependency arrays.",
+        ProjectType::Angular => "Follow Angular conventions: inject dependencies through constructors or \
+`inject()`, keep logic in services rather than components, use RxJS operators instead of nested subscriptions, and \
+follow the Angular style guide's file naming (`*.component.ts`, `*.service.ts`).",
+        ProjectType::TypeScript => "Follow TypeScript conventions: keep strict types, avoid `any`, prefer `const`, \
+and use `async`/`await` over promise chains.",
+        ProjectType::JavaScript => "Follow modern JavaScript conventions: use `const` and `let`, ES modules, \
+`async`/`await` over callbacks, and strict equality.",
+        ProjectType::Python => "Follow Python conventions: PEP 8 style, type hints on function signatures, \
+f-strings, context managers for resources, and specific exceptions instead of bare `except:`.",
+        ProjectType::Go => "Follow Go conventions: return errors instead of panicking, wrap them with \
+`fmt.Errorf(\"...: %w\", err)`, keep code `gofmt` clean, and pass `context.Context` as the first parameter of \
+functions that do I/O.",
+        ProjectType::Java | ProjectType::Kotlin => "Follow JVM conventions: constructor injection, immutable \
+data where possible, no swallowed exceptions, and the naming and package layout already used in the project.",
+        ProjectType::CSharp => "Follow .NET conventions: PascalCase public members, `async`/`await` with the \
+`Async` suffix, dependency injection through constructors, and `using` for disposable resources.",
+        ProjectType::C | ProjectType::Cpp => "Follow the project's C/C++ conventions: check every return value \
+and allocation, avoid undefined behavior, and in C++ prefer RAII and smart pointers over manual memory management.",
+        ProjectType::Laravel => "Follow Laravel conventions: use Eloquent, form requests for validation, \
+dependency injection over facades in classes, and PSR-12 style.",
+        ProjectType::Symfony => "Follow Symfony conventions: autowired constructor injection, attributes for \
+routes, services configured in `config/services.yaml`, and PSR-12 style.",
+        ProjectType::PHP => "Follow PHP conventions: PSR-12 style, strict types, typed properties and \
+signatures, and dependency injection over globals.",
+        ProjectType::Generic => return None,
+    };
+    Some(conventions)
+}
+
+/// Renders the system and user prompts from minijinja templates.
+///
+/// Built-in templates can be replaced by `system.jinja` / `user.jinja` in
+/// `~/.config/code-assist/prompts/`. Templates see `command`, `project_type`,
+/// `memory`, `conventions`, `context`, `allowed_actions`,
+/// `policy_instructions` and `custom_prompts` (which start with the
+/// conventions).
+pub struct PromptBuilder {
+    env: Environment<'static>,
+}
 
 impl PromptBuilder {
-    pub fn build_system_prompt() -> String {
-        r#"You are CodeAssist, an AI coding assistant integrated into the terminal.
-Your goal is to help the user with coding tasks through natural language commands.
-Analyze their request and provide detailed, actionable responses.
-
-You can help with:
-1. Editing files and fixing bugs across the codebase
-2. Answering questions about code architecture and logic
-3. Executing and fixing tests, linting, and other commands
-4. Searching through git history, resolving merge conflicts, and creating commits/PRs
-
-Format your responses in JSON to be parsed by the CodeAssist tool.
-"#.to_string()
+    pub fn new() -> Result<Self> {
+        let mut env = Environment::new();
+
+        for (name, default) in [(SYSTEM_TEMPLATE, DEFAULT_SYSTEM_TEMPLATE), (USER_TEMPLATE, DEFAULT_USER_TEMPLATE)] {
+            let source = match Self::templates_dir().map(|dir| dir.join(name)).filter(|path| path.exists()) {
+                Some(path) => std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read prompt template: {}", path.display()))?,
+                None => default.to_string(),
+            };
+            env.add_template_owned(name, source)
+                .with_context(|| format!("Invalid prompt template: {}", name))?;
+        }
+
+        Ok(Self { env })
     }
-    
-    pub fn build_user_prompt(command: &str, context: &str) -> String {
-        format!(
-            "Command: {}\n\nCurrent context:\n{}",
-            command,
-            context
-        )
+
+    /// Directory holding user-provided prompt templates
+    pub fn templates_dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("code-assist").join("prompts"))
+    }
+
+    /// Builds the system prompt from the instructions for the given policy,
+    /// followed by any custom prompts (config file, CAULK.md) in order
+    pub fn build_system_prompt(&self, policy: &ActionPolicy, custom_prompts: &[String], prompt_context: &PromptContext) -> Result<String> {
+        let custom_prompts: Vec<&str> = custom_prompts
+            .iter()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .collect();
+
+        self.render(SYSTEM_TEMPLATE, context! {
+            allowed_actions => policy.allowed_actions(),
+            policy_instructions => policy.prompt_instructions(),
+            custom_prompts => custom_prompts,
+            ..minijinja::Value::from_serialize(prompt_context)
+        })
+    }
+
+    pub fn build_user_prompt(&self, prompt_context: &PromptContext) -> Result<String> {
+        self.render(USER_TEMPLATE, minijinja::Value::from_serialize(prompt_context))
+    }
+
+    fn render(&self, name: &str, values: minijinja::Value) -> Result<String> {
+        self.env
+            .get_template(name)
+            .and_then(|template| template.render(values))
+            .with_context(|| format!("Failed to render prompt template: {}", name))
     }
 }
@@ -0,0 +1,85 @@
+use crate::ui::interface::UserInterface;
+use colored::Colorize;
+
+/// Tags reasoning models wrap their thinking in, checked in order
+const REASONING_TAGS: &[(&str, &str)] = &[
+    ("<think>", "</think>"),
+    ("<thinking>", "</thinking>"),
+    ("<reasoning>", "</reasoning>"),
+];
+
+/// Splits a reply into its inline reasoning and the remaining content.
+///
+/// Some chat templates emit only the closing tag, so everything before it is
+/// treated as reasoning. A reply cut off while still thinking has no content.
+pub fn split_reasoning(text: &str) -> (Option<String>, String) {
+    for (open, close) in REASONING_TAGS {
+        if let Some(end) = text.find(close) {
+            let before = &text[..end];
+            let reasoning = before.find(open).map(|start| &before[start + open.len()..]).unwrap_or(before);
+            let content = text[end + close.len()..].trim().to_string();
+            return (non_empty(reasoning), content);
+        }
+        if let Some(rest) = text.trim_start().strip_prefix(open) {
+            return (non_empty(rest), String::new());
+        }
+    }
+    (None, text.to_string())
+}
+
+/// Reasoning visible so far in a partially received reply with inline tags.
+/// A possibly incomplete closing tag at the end is held back.
+fn partial_reasoning(text: &str) -> &str {
+    let trimmed = text.trim_start();
+    for (open, close) in REASONING_TAGS {
+        if let Some(rest) = trimmed.strip_prefix(open) {
+            if let Some(end) = rest.find(close) {
+                return &rest[..end];
+            }
+            let mut cut = rest.len().saturating_sub(close.len() - 1);
+            while !rest.is_char_boundary(cut) {
+                cut -= 1;
+            }
+            return &rest[..cut];
+        }
+    }
+    ""
+}
+
+fn non_empty(reasoning: &str) -> Option<String> {
+    Some(reasoning.trim().to_string()).filter(|r| !r.is_empty())
+}
+
+/// Prints reasoning dimmed, a line at a time, as a streamed reply arrives,
+/// whether the provider sends it in a separate field or inline in the content
+#[derive(Default)]
+pub struct LiveReasoning {
+    shown_field: usize,
+    shown_inline: usize,
+    /// Received text of a line not yet printed
+    line: String,
+}
+
+impl LiveReasoning {
+    /// Called after each chunk with everything received so far
+    pub fn update(&mut self, ui: &dyn UserInterface, reasoning_field: &str, content: &str) {
+        let inline = partial_reasoning(content);
+        self.line.push_str(&reasoning_field[self.shown_field..]);
+        self.line.push_str(&inline[self.shown_inline.min(inline.len())..]);
+        self.shown_field = reasoning_field.len();
+        self.shown_inline = inline.len();
+
+        while let Some(end) = self.line.find('\n') {
+            let line: String = self.line.drain(..=end).collect();
+            ui.print(&line.trim_end().dimmed().to_string());
+        }
+    }
+
+    /// Prints the rest of the reasoning once the reply is complete
+    pub fn finish(&mut self, ui: &dyn UserInterface) {
+        if !self.line.trim().is_empty() {
+            ui.print(&self.line.trim_end().dimmed().to_string());
+        }
+        self.line.clear();
+    }
+}
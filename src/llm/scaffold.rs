@@ -0,0 +1,105 @@
+use crate::llm::client::LlmClient;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Task name used to route scaffolding to a model (`[llm.routes]`)
+const TASK: &str = "scaffold";
+/// Files a scaffold may have at most
+const MAX_FILES: usize = 60;
+
+const SYSTEM_PROMPT: &str = "You create the starting files of a new software project from a description. \
+Pick the language and tools the description asks for, or the most common ones for that kind of project. \
+Include the build configuration, a README, a .gitignore, a minimal working entry point and one test, laid out \
+the way that ecosystem expects. Keep the code short and idiomatic. Reply with JSON only, no code fences: \
+{\"language\": \"...\", \"summary\": \"one sentence about the layout\", \
+\"files\": [{\"path\": \"relative/path\", \"content\": \"...\"}]}";
+
+/// A project layout proposed by the model
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scaffold {
+    #[serde(default)]
+    pub language: String,
+    #[serde(default)]
+    pub summary: String,
+    pub files: Vec<ScaffoldFile>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScaffoldFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// Asks the model for the files of a new project matching `description`.
+/// Paths must stay inside the project: absolute paths and `..` are refused.
+pub async fn propose(client: &LlmClient, description: &str) -> Result<Scaffold> {
+    let reply = client.complete(TASK, SYSTEM_PROMPT, &format!("Project description: {}", description)).await?;
+    // Models wrap JSON in prose or fences despite being asked not to
+    let json = match (reply.find('{'), reply.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &reply[start..=end],
+        _ => return Err(anyhow!("The model did not reply with a project layout:\n{}", reply.trim())),
+    };
+    let scaffold: Scaffold = serde_json::from_str(json).context("Failed to parse the proposed project layout")?;
+
+    if scaffold.files.is_empty() {
+        return Err(anyhow!("The model proposed no files"));
+    }
+    if scaffold.files.len() > MAX_FILES {
+        return Err(anyhow!("The model proposed {} files; at most {} are accepted", scaffold.files.len(), MAX_FILES));
+    }
+    for file in &scaffold.files {
+        let inside = file.path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !inside || file.path.as_os_str().is_empty() {
+            return Err(anyhow!("The proposed path {} is outside the project", file.path.display()));
+        }
+    }
+    Ok(scaffold)
+}
+
+impl Scaffold {
+    /// The files as an indented tree, directories first, with line counts
+    pub fn tree(&self) -> String {
+        let mut root = TreeNode::default();
+        for file in &self.files {
+            let mut node = &mut root;
+            let components: Vec<String> = file.path.iter().map(|c| c.to_string_lossy().to_string()).collect();
+            for (idx, name) in components.iter().enumerate() {
+                if idx + 1 == components.len() {
+                    node.files.insert(name.clone(), file.content.lines().count());
+                } else {
+                    node = node.dirs.entry(name.clone()).or_default();
+                }
+            }
+        }
+        let mut output = String::new();
+        root.render(0, &mut output);
+        output
+    }
+
+    /// Files of the scaffold that already exist under `dir`
+    pub fn conflicts(&self, dir: &Path) -> Vec<PathBuf> {
+        self.files.iter().map(|file| file.path.clone()).filter(|path| dir.join(path).exists()).collect()
+    }
+}
+
+#[derive(Default)]
+struct TreeNode {
+    dirs: BTreeMap<String, TreeNode>,
+    /// File names with their line counts
+    files: BTreeMap<String, usize>,
+}
+
+impl TreeNode {
+    fn render(&self, depth: usize, output: &mut String) {
+        let indent = "  ".repeat(depth);
+        for (name, dir) in &self.dirs {
+            output.push_str(&format!("{}{}/\n", indent, name));
+            dir.render(depth + 1, output);
+        }
+        for (name, lines) in &self.files {
+            output.push_str(&format!("{}{} ({} lines)\n", indent, name, lines));
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use crate::config::SummarizeConfig;
+use crate::fs;
+use crate::fs::storage::Storage;
+use crate::llm::client::LlmClient;
+use crate::llm::vendor;
+use crate::ui::interface::UserInterface;
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use futures::stream::{self, StreamExt};
+use log::debug;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Task name used to route summarization to a cheaper model (`[llm.routes]`)
+const TASK: &str = "summarize";
+
+/// Phrases that ask about the codebase as a whole rather than specific code
+const OVERVIEW_PHRASES: &[&str] = &[
+    "architecture",
+    "overview",
+    "high-level",
+    "high level",
+    "walk me through",
+    "explain the codebase",
+    "explain the project",
+    "explain this codebase",
+    "explain this project",
+    "how is the code organized",
+    "how is the project structured",
+    "how is the codebase structured",
+];
+
+const FILE_SYSTEM_PROMPT: &str = "You summarize source files for a developer who is learning a codebase. \
+Reply with one plain-text paragraph of at most 5 sentences: the file's purpose, its main types and functions, \
+and which other parts of the code it uses or is used by.";
+
+const MERGE_SYSTEM_PROMPT: &str = "You merge summaries of source files into one shorter summary of that part \
+of the codebase. Keep the names of important files, modules, types and functions, and keep what helps answer \
+the question. Reply in plain text.";
+
+/// Whether a command asks for an overview that needs the whole codebase
+pub fn wants_overview(command: &str) -> bool {
+    let command = command.to_lowercase();
+    OVERVIEW_PHRASES.iter().any(|phrase| command.contains(phrase))
+}
+
+/// Summaries of source files in `.caulk/summaries/`, one file per summary
+/// named after the hash of the summarized content, so a file is summarized
+/// again only once it changes
+#[derive(Clone)]
+pub struct SummaryCache {
+    dir: PathBuf,
+    storage: Storage,
+}
+
+impl SummaryCache {
+    pub fn new(project_root: &Path, storage: Storage) -> Self {
+        Self { dir: Self::dir(project_root), storage }
+    }
+
+    /// Directory the summaries of the project at `project_root` are saved in
+    pub fn dir(project_root: &Path) -> PathBuf {
+        project_root.join(".caulk").join("summaries")
+    }
+
+    pub fn get(&self, content: &str) -> Option<String> {
+        self.storage.read(&self.entry_path(content)).ok()
+    }
+
+    pub fn put(&self, content: &str, summary: &str) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        self.storage.write(&self.entry_path(content), summary)
+    }
+
+    fn entry_path(&self, content: &str) -> PathBuf {
+        self.dir.join(format!("{:x}.txt", Sha256::digest(content.as_bytes())))
+    }
+}
+
+/// Map-reduce summarization: source files are summarized in parallel LLM
+/// calls, then the summaries are merged in batches until they fit the budget,
+/// so the final prompt carries summaries instead of raw file contents
+pub struct Summarizer<'a> {
+    client: &'a LlmClient,
+    config: &'a SummarizeConfig,
+    cache: &'a SummaryCache,
+    ui: &'a dyn UserInterface,
+}
+
+impl<'a> Summarizer<'a> {
+    pub fn new(client: &'a LlmClient, config: &'a SummarizeConfig, cache: &'a SummaryCache, ui: &'a dyn UserInterface) -> Self {
+        Self { client, config, cache, ui }
+    }
+
+    /// Summary of the project under `root` with `question` in mind
+    pub async fn summarize_codebase(&self, root: &Path, question: &str) -> Result<String> {
+        let files = self.source_files(root);
+        if files.is_empty() {
+            return Err(anyhow!("No source files to summarize"));
+        }
+        self.ui.print(&format!("Summarizing {} files...", files.len()).bright_blue().to_string());
+
+        // Map: one summary per file
+        let results = self.summarize_files(root, files).await;
+
+        let mut summaries = Vec::new();
+        let mut failed = 0;
+        for (path, result) in results {
+            match result {
+                Ok(summary) => summaries.push(format!("{}:\n{}", path.display(), summary.trim())),
+                Err(e) => {
+                    debug!("Failed to summarize {}: {}", path.display(), e);
+                    failed += 1;
+                }
+            }
+        }
+        if failed > 0 {
+            self.ui.print(&format!("{} {} file(s) could not be summarized", "!".yellow(), failed));
+        }
+        if summaries.is_empty() {
+            return Err(anyhow!("No file could be summarized"));
+        }
+
+        // Reduce: merge batches of summaries until they fit the budget
+        let file_count = summaries.len();
+        while total_len(&summaries) > self.config.max_summary_bytes && summaries.len() > 1 {
+            summaries = stream::iter(batch(summaries, self.config.max_summary_bytes))
+                .map(|batch| async move {
+                    let joined = batch.join("\n\n");
+                    self.merge(&joined, question).await.unwrap_or_else(|e| {
+                        debug!("Failed to merge summaries: {}", e);
+                        joined
+                    })
+                })
+                .buffered(self.config.concurrency.max(1))
+                .collect()
+                .await;
+        }
+
+        let text = vendor::truncate(&summaries.join("\n\n"), self.config.max_summary_bytes);
+        Ok(format!("Codebase summary ({} files summarized):\n{}\n", file_count, text))
+    }
+
+    /// Summaries of the given files, in parallel
+    pub async fn summarize_files(&self, root: &Path, files: Vec<PathBuf>) -> Vec<(PathBuf, Result<String>)> {
+        stream::iter(files)
+            .map(|path| async move {
+                let summary = self.summarize_file(root, &path).await;
+                (path, summary)
+            })
+            .buffered(self.config.concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Summaries of those of `files` that could be summarized, by path
+    pub async fn file_summaries(&self, root: &Path, files: Vec<PathBuf>) -> HashMap<PathBuf, String> {
+        let mut summaries = HashMap::new();
+        for (path, result) in self.summarize_files(root, files).await {
+            match result {
+                Ok(summary) => {
+                    summaries.insert(path, summary.trim().to_string());
+                }
+                Err(e) => debug!("Failed to summarize {}: {}", path.display(), e),
+            }
+        }
+        summaries
+    }
+
+    /// Summary of one file, from the cache when its content was summarized before
+    async fn summarize_file(&self, root: &Path, path: &Path) -> Result<String> {
+        let content = std::fs::read_to_string(root.join(path))?;
+        if content.trim().is_empty() {
+            return Ok("(empty file)".to_string());
+        }
+        if let Some(summary) = self.cache.get(&content) {
+            return Ok(summary);
+        }
+        let user_message = format!(
+            "File: {}\n{}",
+            path.strip_prefix(root).unwrap_or(path).display(),
+            vendor::truncate(&content, self.config.max_file_bytes)
+        );
+        let summary = self.client.complete(TASK, FILE_SYSTEM_PROMPT, &user_message).await?;
+        if let Err(e) = self.cache.put(&content, summary.trim()) {
+            debug!("Failed to cache the summary of {}: {}", path.display(), e);
+        }
+        Ok(summary)
+    }
+
+    async fn merge(&self, summaries: &str, question: &str) -> Result<String> {
+        let user_message = format!("Question: {}\n\nSummaries:\n{}", question, summaries);
+        self.client.complete(TASK, MERGE_SYSTEM_PROMPT, &user_message).await
+    }
+
+    /// Source files relative to `root`, shallowest first, skipping ignored
+    /// and vendored paths
+    fn source_files(&self, root: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = fs::walk_parallel(&fs::project_walker(root))
+            .into_iter()
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .filter(|entry| vendor::is_source_file(entry.path()))
+            .map(|entry| entry.path().strip_prefix(root).unwrap_or(entry.path()).to_path_buf())
+            .filter(|path| {
+                !path
+                    .components()
+                    .any(|c| vendor::VENDOR_DIRS.contains(&c.as_os_str().to_string_lossy().as_ref()))
+            })
+            .collect();
+
+        files.sort_by(|a, b| a.components().count().cmp(&b.components().count()).then_with(|| a.cmp(b)));
+        files.truncate(self.config.max_files);
+        files
+    }
+}
+
+fn total_len(summaries: &[String]) -> usize {
+    summaries.iter().map(|s| s.len()).sum()
+}
+
+/// Groups summaries into batches of at most `max_bytes` each (and at least
+/// two summaries where possible, so every round shrinks the list)
+fn batch(summaries: Vec<String>, max_bytes: usize) -> Vec<Vec<String>> {
+    let mut batches: Vec<Vec<String>> = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    for summary in summaries {
+        if current.len() >= 2 && total_len(&current) + summary.len() > max_bytes {
+            batches.push(std::mem::take(&mut current));
+        }
+        current.push(summary);
+    }
+    if !current.is_empty() {
+        batches.push(current);
+    }
+    batches
+}
@@ -0,0 +1,156 @@
+use crate::analysis::languages;
+use crate::analysis::tree::format_size;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Vendored code is excluded from context unless asked for with `@vendor:<package>`,
+/// and even then only a capped excerpt is included
+pub const MAX_PACKAGES_PER_REQUEST: usize = 2;
+const MAX_LISTED_FILES: usize = 40;
+const MAX_EXCERPT_FILES: usize = 5;
+const MAX_MANIFEST_BYTES: usize = 1500;
+const MAX_FILE_BYTES: usize = 4000;
+const MAX_TOTAL_BYTES: usize = 20000;
+/// Files larger than this are listed but never read (bundles, generated code)
+const MAX_SCANNED_FILE_BYTES: u64 = 256 * 1024;
+
+pub const VENDOR_DIRS: &[&str] = &["node_modules", "vendor"];
+const MANIFESTS: &[&str] = &["package.json", "composer.json", "Cargo.toml", "go.mod", "setup.py", "pyproject.toml"];
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "js", "mjs", "cjs", "ts", "jsx", "tsx", "php", "inc", "module", "rs", "go", "py", "rb", "java", "kt", "cs", "c", "h", "cpp", "hpp",
+];
+
+/// Packages referenced with `@vendor:<package>` in a command, in order of appearance
+pub fn vendor_references(command: &str) -> Vec<String> {
+    let pattern = Regex::new(r"@vendor:([A-Za-z0-9@._/\-]+)").expect("valid vendor reference pattern");
+    let mut packages: Vec<String> = Vec::new();
+    for captures in pattern.captures_iter(command) {
+        let package = captures[1].trim_end_matches(['.', '/']).to_string();
+        if !packages.contains(&package) {
+            packages.push(package);
+        }
+    }
+    packages
+}
+
+/// Finds the directory of a vendored package under node_modules/ or vendor/
+pub fn find_package_dir(project_root: &Path, package: &str) -> Result<PathBuf> {
+    let relative = Path::new(package);
+    if relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(anyhow!("Invalid package name: {}", package));
+    }
+
+    VENDOR_DIRS
+        .iter()
+        .map(|dir| project_root.join(dir).join(relative))
+        .find(|path| path.is_dir())
+        .ok_or_else(|| anyhow!("Package '{}' not found in {}", package, VENDOR_DIRS.join(" or ")))
+}
+
+/// Builds a size-capped excerpt of a vendored package: its manifest, a file
+/// listing, and the source files that best match the command keywords
+pub fn package_excerpt(project_root: &Path, package: &str, keywords: &[String]) -> Result<String> {
+    let package_dir = find_package_dir(project_root, package)?;
+    let relative_dir = package_dir.strip_prefix(project_root).unwrap_or(&package_dir);
+
+    let mut excerpt = format!(
+        "Vendored package {} ({}), excerpt capped at {}:\n",
+        package,
+        relative_dir.display(),
+        format_size(MAX_TOTAL_BYTES as u64)
+    );
+
+    if let Some(manifest) = MANIFESTS.iter().map(|m| package_dir.join(m)).find(|p| p.is_file()) {
+        if let Ok(content) = std::fs::read_to_string(&manifest) {
+            excerpt.push_str(&format!(
+                "\n{}:\n{}\n",
+                manifest.file_name().unwrap_or_default().to_string_lossy(),
+                truncate(content.trim_end(), MAX_MANIFEST_BYTES)
+            ));
+        }
+    }
+
+    // Nested dependencies are never included
+    let mut sources: Vec<(PathBuf, u64)> = WalkDir::new(&package_dir)
+        .into_iter()
+        .filter_entry(|e| !VENDOR_DIRS.contains(&e.file_name().to_string_lossy().as_ref()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| is_source_file(e.path()))
+        .map(|e| {
+            let size = e.metadata().map(|m| m.len()).unwrap_or(0);
+            (e.into_path(), size)
+        })
+        .collect();
+    sources.sort();
+
+    excerpt.push_str(&format!("\nSource files ({} total):\n", sources.len()));
+    for (path, size) in sources.iter().take(MAX_LISTED_FILES) {
+        let relative = path.strip_prefix(&package_dir).unwrap_or(path);
+        excerpt.push_str(&format!("  {} ({})\n", relative.display(), format_size(*size)));
+    }
+    if sources.len() > MAX_LISTED_FILES {
+        excerpt.push_str(&format!("  ... ({} more)\n", sources.len() - MAX_LISTED_FILES));
+    }
+
+    let mut scored: Vec<(usize, PathBuf, String)> = sources
+        .into_iter()
+        .filter(|(_, size)| *size <= MAX_SCANNED_FILE_BYTES)
+        .filter_map(|(path, _)| {
+            let content = std::fs::read_to_string(&path).ok()?;
+            let score = relevance(&path, &content, keywords);
+            (score > 0).then_some((score, path, content))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+    let mut budget = MAX_TOTAL_BYTES.saturating_sub(excerpt.len());
+    for (_, path, content) in scored.into_iter().take(MAX_EXCERPT_FILES) {
+        if budget == 0 {
+            break;
+        }
+        let relative = path.strip_prefix(&package_dir).unwrap_or(&path);
+        let snippet = truncate(content.trim_end(), MAX_FILE_BYTES.min(budget));
+        budget = budget.saturating_sub(snippet.len());
+        excerpt.push_str(&format!("\nFile: {}\n{}\n", relative.display(), snippet));
+    }
+
+    Ok(excerpt)
+}
+
+pub fn is_source_file(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if name.ends_with(".min.js") || name.ends_with(".map") {
+        return false;
+    }
+    languages::detected_extension(path)
+        .map(|ext| SOURCE_EXTENSIONS.contains(&ext.as_str()))
+        .unwrap_or(false)
+}
+
+/// Keyword hits in the path count more than hits in the content
+fn relevance(path: &Path, content: &str, keywords: &[String]) -> usize {
+    let path_str = path.to_string_lossy().to_lowercase();
+    let content = content.to_lowercase();
+    keywords
+        .iter()
+        .map(|k| {
+            let in_path = if path_str.contains(k.as_str()) { 10 } else { 0 };
+            in_path + content.matches(k.as_str()).count().min(10)
+        })
+        .sum()
+}
+
+/// Cuts text to at most `max_bytes`, on a character boundary
+pub fn truncate(text: &str, max_bytes: usize) -> String {
+    if text.len() <= max_bytes {
+        return text.to_string();
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}... (truncated)", &text[..end])
+}
@@ -0,0 +1,361 @@
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{oneshot, Notify};
+use url::Url;
+
+type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value>>>>>;
+
+/// A diagnostic published by the language server
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    /// 1-based line number
+    pub line: u64,
+    /// 1 = error, 2 = warning, 3 = information, 4 = hint
+    pub severity: u64,
+    pub message: String,
+}
+
+/// Where a symbol is defined
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub path: PathBuf,
+    /// 1-based line number
+    pub line: usize,
+}
+
+/// Minimal JSON-RPC client for a language server speaking LSP over stdio
+pub struct LspClient {
+    name: String,
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    next_id: AtomicU64,
+    pending: PendingRequests,
+    diagnostics: Arc<Mutex<HashMap<Url, Vec<Diagnostic>>>>,
+    diagnostics_changed: Arc<Notify>,
+    /// Open documents and their current version
+    documents: Mutex<HashMap<Url, i64>>,
+    timeout: Duration,
+    // Killed when the client is dropped
+    _child: Child,
+}
+
+impl LspClient {
+    /// Starts the server with `command` (program and arguments separated by
+    /// spaces) and performs the initialize handshake for `root`
+    pub async fn start(command: &str, root: &Path, timeout: Duration) -> Result<Self> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().ok_or_else(|| anyhow!("Empty language server command"))?;
+
+        let mut child = Command::new(program)
+            .args(parts)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start language server: {}", command))?;
+
+        let stdin = Arc::new(tokio::sync::Mutex::new(
+            child.stdin.take().ok_or_else(|| anyhow!("Language server has no stdin"))?,
+        ));
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("Language server has no stdout"))?;
+
+        let client = Self {
+            name: program.to_string(),
+            stdin,
+            next_id: AtomicU64::new(1),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            diagnostics: Arc::new(Mutex::new(HashMap::new())),
+            diagnostics_changed: Arc::new(Notify::new()),
+            documents: Mutex::new(HashMap::new()),
+            timeout,
+            _child: child,
+        };
+        client.spawn_reader(stdout);
+
+        let root_uri = Url::from_directory_path(root)
+            .map_err(|_| anyhow!("Invalid project root: {}", root.display()))?;
+        client
+            .request(
+                "initialize",
+                json!({
+                    "processId": std::process::id(),
+                    "rootUri": root_uri,
+                    "capabilities": {
+                        "textDocument": {
+                            "hover": { "contentFormat": ["plaintext", "markdown"] },
+                            "definition": { "linkSupport": true },
+                            "publishDiagnostics": {}
+                        }
+                    }
+                }),
+            )
+            .await
+            .with_context(|| format!("{} did not initialize", program))?;
+        client.notify("initialized", json!({})).await?;
+
+        Ok(client)
+    }
+
+    /// Opens `path` with `text`, or sends the new text if it is already open
+    pub async fn open(&self, path: &Path, language_id: &str, text: &str) -> Result<()> {
+        let uri = file_uri(path)?;
+        let version = {
+            let mut documents = self.documents.lock().map_err(|_| anyhow!("LSP state poisoned"))?;
+            let version = documents.entry(uri.clone()).or_insert(0);
+            *version += 1;
+            *version
+        };
+
+        if version == 1 {
+            self.notify(
+                "textDocument/didOpen",
+                json!({
+                    "textDocument": { "uri": uri, "languageId": language_id, "version": version, "text": text }
+                }),
+            )
+            .await
+        } else {
+            self.notify(
+                "textDocument/didChange",
+                json!({
+                    "textDocument": { "uri": uri, "version": version },
+                    "contentChanges": [{ "text": text }]
+                }),
+            )
+            .await
+        }
+    }
+
+    /// Go-to-definition at a 0-based line and UTF-16 column
+    pub async fn definition(&self, path: &Path, line: usize, character: usize) -> Result<Vec<Location>> {
+        let result = self
+            .request("textDocument/definition", position_params(path, line, character)?)
+            .await?;
+
+        // Location | Location[] | LocationLink[] | null
+        let entries = match result {
+            Value::Array(entries) => entries,
+            Value::Null => Vec::new(),
+            single => vec![single],
+        };
+
+        Ok(entries
+            .iter()
+            .filter_map(|entry| {
+                let uri = entry.get("uri").or_else(|| entry.get("targetUri"))?.as_str()?;
+                let range = entry.get("range").or_else(|| entry.get("targetSelectionRange"))?;
+                let line = range["start"]["line"].as_u64()? as usize;
+                let path = Url::parse(uri).ok()?.to_file_path().ok()?;
+                Some(Location { path, line: line + 1 })
+            })
+            .collect())
+    }
+
+    /// Hover text (usually the type or signature) at a 0-based line and UTF-16 column
+    pub async fn hover(&self, path: &Path, line: usize, character: usize) -> Result<Option<String>> {
+        let result = self
+            .request("textDocument/hover", position_params(path, line, character)?)
+            .await?;
+
+        // MarkupContent | MarkedString | MarkedString[]
+        let text = match &result["contents"] {
+            Value::String(text) => text.clone(),
+            Value::Object(content) => content.get("value").and_then(|v| v.as_str()).unwrap_or("").to_string(),
+            Value::Array(items) => items
+                .iter()
+                .filter_map(|item| item.as_str().or_else(|| item.get("value").and_then(|v| v.as_str())))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => String::new(),
+        };
+
+        Ok(Some(text.trim().to_string()).filter(|t| !t.is_empty()))
+    }
+
+    /// Diagnostics for `path`, waiting up to `wait` for the server to publish them
+    pub async fn diagnostics(&self, path: &Path, wait: Duration) -> Result<Option<Vec<Diagnostic>>> {
+        let uri = file_uri(path)?;
+        let deadline = tokio::time::Instant::now() + wait;
+
+        loop {
+            // Register interest before checking so a publish in between is not missed
+            let changed = self.diagnostics_changed.notified();
+            if let Some(found) = self.diagnostics.lock().ok().and_then(|d| d.get(&uri).cloned()) {
+                return Ok(Some(found));
+            }
+            if tokio::time::timeout_at(deadline, changed).await.is_err() {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Forgets diagnostics for `path` so the next `diagnostics` call waits for fresh ones
+    pub fn clear_diagnostics(&self, path: &Path) {
+        if let (Ok(uri), Ok(mut diagnostics)) = (file_uri(path), self.diagnostics.lock()) {
+            diagnostics.remove(&uri);
+        }
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = oneshot::channel();
+        self.pending
+            .lock()
+            .map_err(|_| anyhow!("LSP state poisoned"))?
+            .insert(id, sender);
+
+        write_message(&self.stdin, &json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })).await?;
+
+        match tokio::time::timeout(self.timeout, receiver).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(anyhow!("{} exited", self.name)),
+            Err(_) => {
+                if let Ok(mut pending) = self.pending.lock() {
+                    pending.remove(&id);
+                }
+                Err(anyhow!("{} timed out on {}", self.name, method))
+            }
+        }
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        write_message(&self.stdin, &json!({ "jsonrpc": "2.0", "method": method, "params": params })).await
+    }
+
+    /// Reads messages from the server, completing pending requests, storing
+    /// published diagnostics and answering server-to-client requests
+    fn spawn_reader(&self, stdout: ChildStdout) {
+        let pending = self.pending.clone();
+        let diagnostics = self.diagnostics.clone();
+        let diagnostics_changed = self.diagnostics_changed.clone();
+        let stdin = self.stdin.clone();
+
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stdout);
+            loop {
+                let message = match read_message(&mut reader).await {
+                    Ok(Some(message)) => message,
+                    Ok(None) => break,
+                    Err(e) => {
+                        debug!("Failed to read from language server: {}", e);
+                        break;
+                    }
+                };
+
+                let method = message.get("method").and_then(|m| m.as_str());
+                match (message.get("id"), method) {
+                    // Response to one of our requests
+                    (Some(id), None) => {
+                        let sender = id.as_u64().and_then(|id| pending.lock().ok()?.remove(&id));
+                        if let Some(sender) = sender {
+                            let result = match message.get("error") {
+                                Some(error) => Err(anyhow!("Language server error: {}", error)),
+                                None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                            };
+                            let _ = sender.send(result);
+                        }
+                    }
+                    // Request from the server; reply with an empty result
+                    (Some(id), Some(method)) => {
+                        let result = if method == "workspace/configuration" {
+                            let items = message["params"]["items"].as_array().map(|i| i.len()).unwrap_or(0);
+                            Value::Array(vec![Value::Null; items])
+                        } else {
+                            Value::Null
+                        };
+                        let reply = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                        if write_message(&stdin, &reply).await.is_err() {
+                            break;
+                        }
+                    }
+                    (None, Some("textDocument/publishDiagnostics")) => {
+                        let params = &message["params"];
+                        if let Some(uri) = params["uri"].as_str().and_then(|u| Url::parse(u).ok()) {
+                            let found = params["diagnostics"]
+                                .as_array()
+                                .map(|items| items.iter().map(parse_diagnostic).collect())
+                                .unwrap_or_default();
+                            if let Ok(mut diagnostics) = diagnostics.lock() {
+                                diagnostics.insert(uri, found);
+                            }
+                            diagnostics_changed.notify_waiters();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            // Fail outstanding requests instead of letting them time out
+            if let Ok(mut pending) = pending.lock() {
+                pending.clear();
+            }
+        });
+    }
+}
+
+fn file_uri(path: &Path) -> Result<Url> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    Url::from_file_path(&absolute).map_err(|_| anyhow!("Invalid file path: {}", path.display()))
+}
+
+fn position_params(path: &Path, line: usize, character: usize) -> Result<Value> {
+    Ok(json!({
+        "textDocument": { "uri": file_uri(path)? },
+        "position": { "line": line, "character": character }
+    }))
+}
+
+fn parse_diagnostic(item: &Value) -> Diagnostic {
+    Diagnostic {
+        line: item["range"]["start"]["line"].as_u64().unwrap_or(0) + 1,
+        severity: item["severity"].as_u64().unwrap_or(1),
+        message: item["message"].as_str().unwrap_or("").to_string(),
+    }
+}
+
+async fn write_message(stdin: &tokio::sync::Mutex<ChildStdin>, message: &Value) -> Result<()> {
+    let body = message.to_string();
+    let mut stdin = stdin.lock().await;
+    stdin
+        .write_all(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).as_bytes())
+        .await?;
+    stdin.flush().await?;
+    Ok(())
+}
+
+/// Reads one `Content-Length` framed message; None at end of stream
+async fn read_message(reader: &mut BufReader<ChildStdout>) -> Result<Option<Value>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = Some(length.trim().parse::<usize>()?);
+        }
+    }
+
+    let length = content_length.ok_or_else(|| anyhow!("Message without Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
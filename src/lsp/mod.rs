@@ -0,0 +1,108 @@
+pub mod client;
+
+use crate::analysis::languages;
+use crate::config::LspConfig;
+use crate::ui::interface::UserInterface;
+use client::{Diagnostic, LspClient, Location};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// How long to wait for diagnostics after an edit before giving up
+const DIAGNOSTICS_WAIT: Duration = Duration::from_secs(3);
+
+/// Language and LSP `languageId` for a file, based on its extension or, for
+/// scripts without one, their shebang line
+pub fn language_for(path: &Path) -> Option<(&'static str, &'static str)> {
+    let ext = languages::detected_extension(path)?;
+    Some(match ext.as_str() {
+        "rs" => ("rust", "rust"),
+        "py" => ("python", "python"),
+        "php" | "module" | "inc" | "install" | "theme" => ("php", "php"),
+        "ts" => ("typescript", "typescript"),
+        "tsx" => ("typescript", "typescriptreact"),
+        "js" | "mjs" | "cjs" => ("javascript", "javascript"),
+        "jsx" => ("javascript", "javascriptreact"),
+        "go" => ("go", "go"),
+        _ => return None,
+    })
+}
+
+/// Starts language servers on demand, one per language, and falls back
+/// quietly (callers get `None`) when a server is missing or fails
+pub struct LspManager {
+    config: LspConfig,
+    root: PathBuf,
+    ui: Arc<dyn UserInterface>,
+    /// `None` records a server that failed to start so it is not retried
+    clients: Mutex<HashMap<&'static str, Option<Arc<LspClient>>>>,
+}
+
+impl LspManager {
+    /// Returns a manager when LSP support is enabled in the config
+    pub fn new(config: &LspConfig, root: &Path, ui: Arc<dyn UserInterface>) -> Option<Self> {
+        config.enabled.then(|| Self {
+            config: config.clone(),
+            root: root.to_path_buf(),
+            ui,
+            clients: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Client for the language of `path`, starting its server if needed
+    async fn client_for(&self, path: &Path) -> Option<Arc<LspClient>> {
+        let (language, _) = language_for(path)?;
+        let mut clients = self.clients.lock().await;
+        if let Some(client) = clients.get(language) {
+            return client.clone();
+        }
+
+        let client = match self.config.servers.get(language) {
+            Some(command) => {
+                let timeout = Duration::from_secs(self.config.timeout_secs);
+                match LspClient::start(command, &self.root, timeout).await {
+                    Ok(client) => Some(Arc::new(client)),
+                    Err(e) => {
+                        self.ui.print(&format!("{} {:#}; using the built-in parser for {} files", "!".yellow(), e, language));
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        clients.insert(language, client.clone());
+        client
+    }
+
+    /// Opens `path` in its language server with the current file contents
+    async fn open(&self, path: &Path) -> Option<Arc<LspClient>> {
+        let (_, language_id) = language_for(path)?;
+        let client = self.client_for(path).await?;
+        let text = std::fs::read_to_string(self.root.join(path)).ok()?;
+        client.open(&self.root.join(path), language_id, &text).await.ok()?;
+        Some(client)
+    }
+
+    /// Definition of the symbol at a 0-based line and UTF-16 column, with its hover text
+    pub async fn definition(&self, path: &Path, line: usize, character: usize) -> Option<(Location, Option<String>)> {
+        let client = self.open(path).await?;
+        let absolute = self.root.join(path);
+        let location = client.definition(&absolute, line, character).await.ok()?.into_iter().next()?;
+        let hover = client.hover(&absolute, line, character).await.ok().flatten();
+        Some((location, hover))
+    }
+
+    /// Sends the new contents of an edited file and returns the diagnostics the
+    /// server publishes for it, or `None` if no server answered in time
+    pub async fn diagnostics_after_edit(&self, path: &Path) -> Option<Vec<Diagnostic>> {
+        let absolute = self.root.join(path);
+        if let Some(client) = self.client_for(path).await {
+            client.clear_diagnostics(&absolute);
+        }
+        let client = self.open(path).await?;
+        client.diagnostics(&absolute, DIAGNOSTICS_WAIT).await.ok().flatten()
+    }
+}
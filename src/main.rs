@@ -1,6 +1,7 @@
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 use anyhow::Result;
+use colored::Colorize;
 
 mod app;
 mod config;
@@ -11,6 +12,12 @@ mod fs;
 mod analysis;
 mod commands;
 mod memory;
+mod lsp;
+mod events;
+mod session;
+mod wasm;
+#[cfg(feature = "test-support")]
+mod test_support;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -23,6 +30,30 @@ struct Cli {
     #[arg(short, long)]
     verbose: bool,
 
+    /// When to use colored output (auto honours NO_COLOR and disables color when piped)
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ui::display::ColorChoice::Auto, global = true)]
+    color: ui::display::ColorChoice,
+
+    /// Write each full prompt and raw LLM response to .caulk/logs/, tagged with a printed request ID
+    #[arg(long, global = true)]
+    debug_llm: bool,
+
+    /// Strip comments, docstrings and blank lines from code in the context to fit more of it
+    #[arg(long, global = true)]
+    dense_context: bool,
+
+    /// Analyze the project as this type instead of detecting it (e.g. php, rust, angular)
+    #[arg(long, global = true, value_name = "TYPE")]
+    project_type: Option<String>,
+
+    /// Always query the LLM instead of reusing a cached response
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Drupal module to work on when the site has several
+    #[arg(long, global = true, value_name = "NAME")]
+    module: Option<String>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -42,23 +73,255 @@ enum Commands {
         /// Set the LLM model to use
         #[arg(long)]
         model: Option<String>,
+
+        /// List the models offered by the configured provider
+        #[arg(long)]
+        list_models: bool,
     },
     
     /// Execute a one-off command without entering interactive mode
+    ///
+    /// Exit status: 0 when the action ran and all commands succeeded, 1 on error,
+    /// 2 when a command run by the model failed, 3 when nothing was executed.
     Exec {
         /// The natural language command to execute
         #[arg(required = true)]
         command: Vec<String>,
+
+        /// Print a final JSON line describing what was done
+        #[arg(long)]
+        summary: bool,
+
+        /// Print the actions the model proposes (diffs, commands, git
+        /// operations) without applying any of them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Model to use for this request instead of the configured one
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Sampling temperature for this request
+        #[arg(long)]
+        temperature: Option<f32>,
+
+        /// Maximum number of tokens to generate for this request
+        #[arg(long)]
+        max_tokens: Option<usize>,
+    },
+
+    /// Ask a question about the codebase; never edits files or runs commands
+    Ask {
+        /// The question to answer
+        #[arg(required = true)]
+        question: Vec<String>,
+
+        /// Summarize every source file first (map-reduce) so the answer covers the whole codebase
+        #[arg(long)]
+        summarize: bool,
+    },
+
+    /// Create a new project from a description: the model proposes the
+    /// files, and once the tree is approved they are written and committed
+    New {
+        /// What the project is, e.g. "a CLI in Rust that renames photos by date"
+        #[arg(required = true)]
+        description: Vec<String>,
+
+        /// Directory to create the project in
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+
+        /// Create the files without asking
+        #[arg(long, short)]
+        yes: bool,
+    },
+
+    /// Apply an instruction as an edit to specific files and print the diff
+    Edit {
+        /// What to change
+        instruction: String,
+
+        /// Files the model is allowed to edit
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+
+    /// Summarize a branch's changes per file, explain files on request and
+    /// write a reviewer checklist
+    Compare {
+        /// Branch or revision the head branch forked from
+        base: String,
+
+        /// Branch or revision to review
+        #[arg(default_value = "HEAD")]
+        head: String,
+
+        /// Print a reviewer checklist after the summaries
+        #[arg(long)]
+        checklist: bool,
+    },
+
+    /// Complete the code at a cursor position using the surrounding code and
+    /// project context, and insert it into the file
+    Complete {
+        /// Cursor position as <file>:<line>[:<column>] (end of the line without a column)
+        location: String,
+
+        /// Print the insertion to stdout instead of editing the file
+        #[arg(long)]
+        print: bool,
+    },
+
+    /// Write a shareable report of the project's structure, metrics,
+    /// dependencies, hotspots and TODOs with an executive summary
+    Report {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = analysis::report::ReportFormat::Md)]
+        format: analysis::report::ReportFormat,
+
+        /// File to write (default: code-assist-report.<format>)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Leave out the LLM-written executive summary
+        #[arg(long)]
+        no_summary: bool,
+    },
+
+    /// Show what the tool detects in the current directory: project type,
+    /// files per language, modules and framework details
+    Analyze {
+        /// Print JSON instead of text
+        #[arg(long, conflicts_with = "markdown")]
+        json: bool,
+
+        /// Print markdown instead of text
+        #[arg(long)]
+        markdown: bool,
+    },
+
+    /// Search the project's files for a regular expression, skipping ignored
+    /// and binary files
+    Search {
+        /// Regular expression matched against each line
+        pattern: String,
+
+        /// File or directory to search
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = fs::search::SearchFormat::Text)]
+        format: fs::search::SearchFormat,
+    },
+
+    /// Print where a symbol is defined, with its signature
+    FindSymbol {
+        /// Name of the function, type or constant; `Type::method` or
+        /// `Class.method` narrows it to one parent
+        name: String,
+
+        /// Print JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Implement the `TODO(ai): <instruction>` comments in a file or
+    /// directory one at a time, removing each once its edit is made
+    ImplementTodos {
+        /// File or directory to search for TODO(ai) comments
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
+        /// Keep every edit without asking
+        #[arg(long, short)]
+        yes: bool,
     },
 
     /// Initialize a CAULK.md file in the current directory
     Init,
+
+    /// Delete locally stored data
+    #[command(group(clap::ArgGroup::new("data").required(true).multiple(true)))]
+    Purge {
+        /// Everything below, plus the events log
+        #[arg(long, group = "data")]
+        all: bool,
+
+        /// LLM transcripts of this project (.caulk/logs/)
+        #[arg(long, group = "data")]
+        sessions: bool,
+
+        /// Cached LLM responses (shared by all projects) and file summaries of this project
+        #[arg(long, group = "data")]
+        cache: bool,
+
+        /// Embeddings index of this project
+        #[arg(long, group = "data")]
+        index: bool,
+    },
+
+    /// Fix the imports of source files
+    Imports {
+        #[command(subcommand)]
+        action: ImportsAction,
+    },
+
+    /// Move or rename a file and update the mod declarations, imports and
+    /// includes that refer to it, then stage the change in git
+    Mv {
+        /// File to move
+        from: PathBuf,
+
+        /// New path, or a directory to move the file into
+        to: PathBuf,
+    },
+
+    /// Revert the last action applied to the project, or the one with the
+    /// given id, restoring the files it changed, created or deleted
+    Undo {
+        /// Id of the action, as listed by --list
+        id: Option<u64>,
+
+        /// List the actions that can be undone
+        #[arg(long, conflicts_with = "id")]
+        list: bool,
+
+        /// Restore the files even when they changed after the action
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Run snapshot fixtures against the mock LLM provider and compare the
+    /// resulting file trees and diffs with the saved snapshots
+    #[cfg(feature = "test-support")]
+    Snapshot {
+        /// Fixture directories (workspace/, command.txt, responses/, expected/)
+        #[arg(required = true)]
+        fixtures: Vec<PathBuf>,
+
+        /// Rewrite the snapshots instead of comparing against them
+        #[arg(long)]
+        update: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ImportsAction {
+    /// Add missing and remove unused imports in a Rust, Python or
+    /// JavaScript/TypeScript file, then sort them per project conventions
+    Fix {
+        /// File to fix
+        file: PathBuf,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     env_logger::init();
     let cli = Cli::parse();
+    ui::display::init_color(cli.color);
     
     // Load configuration
     let config_path = cli.config.unwrap_or_else(|| {
@@ -68,30 +331,290 @@ async fn main() -> Result<()> {
     });
     
     let config = config::load_or_create_config(&config_path)?;
+    let mut config = config::apply_project_config(config, &std::env::current_dir()?)?;
+    if cli.debug_llm {
+        config.llm.debug_log = true;
+    }
+    if cli.no_cache {
+        config.cache.enabled = false;
+    }
+    fs::init_scan(&config.scan);
+    ui::display::init_diff(&config.diff);
+    if cli.dense_context {
+        config.context.dense = true;
+    }
+    if let Some(project_type) = &cli.project_type {
+        config.project.project_type = Some(project_type.clone());
+    }
+    if let Some(module) = &cli.module {
+        config.project.module = Some(module.clone());
+    }
+    if let Some(project_type) = &config.project.project_type {
+        analysis::structure::ProjectType::parse(project_type)?;
+    }
     
     // Handle subcommands
     match &cli.command {
-        Some(Commands::Config { api_url, api_key, model }) => {
+        Some(Commands::Config { api_url, api_key, model, list_models }) => {
+            if *list_models {
+                let client = llm::client::LlmClient::new(&config, std::sync::Arc::new(ui::interface::TerminalUi::new()))?;
+                let models = client.list_models().await?;
+                let fallbacks: Vec<&str> = config.llm.fallbacks.iter().map(|f| f.model.as_str()).collect();
+                let rows: Vec<Vec<String>> = models
+                    .iter()
+                    .map(|name| {
+                        let role = if llm::client::model_is_listed(&config.llm.model, std::slice::from_ref(name)) {
+                            "primary".bright_green().to_string()
+                        } else if fallbacks.iter().any(|f| llm::client::model_is_listed(f, std::slice::from_ref(name))) {
+                            "fallback".yellow().to_string()
+                        } else {
+                            String::new()
+                        };
+                        vec![name.clone(), role]
+                    })
+                    .collect();
+                print!("{}", ui::display::format_table(&["MODEL", "CONFIGURED AS"], &rows));
+                return Ok(());
+            }
             config::update_config(&config_path, api_url, api_key, model)?;
             println!("Configuration updated successfully.");
             return Ok(());
         }
-        Some(Commands::Exec { command }) => {
+        Some(Commands::Exec { command, summary, dry_run, model, temperature, max_tokens }) => {
+            config.llm.apply_overrides(&config::LlmOverrides {
+                model: model.clone(),
+                temperature: *temperature,
+                max_tokens: *max_tokens,
+            });
             let command_str = command.join(" ");
             let app = app::App::new(config)?;
-            app.execute_command(&command_str).await?;
+            app.check_model().await;
+            app.set_dry_run(*dry_run);
+            
+            let result = app.execute_command(&command_str).await;
+            app.shutdown().await;
+
+            let report = match result {
+                Ok(report) => report,
+                Err(e) => {
+                    if *summary {
+                        println!("{}", serde_json::json!({ "executed": false, "error": e.to_string(), "exit_code": 1 }));
+                    }
+                    return Err(e);
+                }
+            };
+            
+            if *summary {
+                println!("{}", report.summary());
+            }
+            
+            let exit_code = report.exit_code();
+            if exit_code != 0 {
+                std::process::exit(exit_code);
+            }
+            return Ok(());
+        }
+        Some(Commands::Ask { question, summarize }) => {
+            let question_str = question.join(" ");
+            let app = app::App::new(config)?;
+            app.check_model().await;
+            let result = app.ask(&question_str, *summarize).await;
+            app.shutdown().await;
+            result?;
+            return Ok(());
+        }
+        Some(Commands::New { description, dir, yes }) => {
+            let app = app::App::new(config)?;
+            app.check_model().await;
+            let result = app.new_project(&description.join(" "), dir, *yes).await;
+            app.shutdown().await;
+            result?;
+            return Ok(());
+        }
+        Some(Commands::Edit { instruction, files }) => {
+            let app = app::App::new(config)?;
+            app.check_model().await;
+            let result = app.edit(instruction, files).await;
+            app.shutdown().await;
+            result?;
+            return Ok(());
+        }
+        Some(Commands::Compare { base, head, checklist }) => {
+            let app = app::App::new(config)?;
+            app.check_model().await;
+            let result = app.compare(base, head, *checklist).await;
+            app.shutdown().await;
+            result?;
+            return Ok(());
+        }
+        Some(Commands::Complete { location, print }) => {
+            let cursor = llm::completion::Cursor::parse(location)?;
+            let app = app::App::new(config)?;
+            let result = app.complete(&cursor, !*print).await;
+            app.shutdown().await;
+            match result? {
+                Some(insertion) if *print => print!("{}", insertion),
+                _ => {}
+            }
+            return Ok(());
+        }
+        Some(Commands::Report { format, output, no_summary }) => {
+            let app = app::App::new(config)?;
+            if !*no_summary {
+                app.check_model().await;
+            }
+            let result = app.report(*format, output.clone(), !*no_summary).await;
+            app.shutdown().await;
+            result?;
+            return Ok(());
+        }
+        Some(Commands::Purge { all, sessions, cache, index }) => {
+            use fs::retention::DataKind;
+            let kinds: Vec<DataKind> = [
+                (DataKind::Cache, *cache),
+                (DataKind::Sessions, *sessions),
+                (DataKind::Index, *index),
+                (DataKind::AuditLog, false),
+            ]
+            .into_iter()
+            .filter(|(_, selected)| *all || *selected)
+            .map(|(kind, _)| kind)
+            .collect();
+            let removed = fs::retention::purge(&config, &std::env::current_dir()?, &kinds)?;
+            if removed.is_empty() {
+                println!("Nothing to delete");
+            }
+            for removed in removed {
+                println!(
+                    "{} Deleted {} ({} file(s), {}) at {}",
+                    "✓".bright_green(),
+                    removed.kind.label(),
+                    removed.files,
+                    analysis::tree::format_size(removed.bytes),
+                    removed.path.display()
+                );
+            }
+            return Ok(());
+        }
+        Some(Commands::Analyze { json, markdown }) => {
+            let format = if *json {
+                app::AnalysisFormat::Json
+            } else if *markdown {
+                app::AnalysisFormat::Markdown
+            } else {
+                app::AnalysisFormat::Text
+            };
+            let app = app::App::new(config)?;
+            let result = app.analyze(format);
+            app.shutdown().await;
+            result?;
+            return Ok(());
+        }
+        Some(Commands::Search { pattern, path, format }) => {
+            let cwd = std::env::current_dir()?;
+            let search = fs::search::CodeSearch::new(fs::storage::Storage::plain());
+            let results = search.search_in_files(&cwd.join(path), pattern)?;
+            let found = !results.is_empty();
+            print!("{}", fs::search::format_results(results, &cwd, *format)?);
+            // Like grep, exit 1 when nothing matched
+            if !found {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Commands::FindSymbol { name, json }) => {
+            let cwd = std::env::current_dir()?;
+            let definitions = analysis::xref::SymbolIndex::new().lookup(&cwd, name)?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&definitions)?);
+            } else if definitions.is_empty() {
+                return Err(anyhow::anyhow!("No definition of `{}` found", name));
+            } else {
+                print!("{}", analysis::xref::format_definitions(&definitions));
+            }
+            return Ok(());
+        }
+        Some(Commands::ImplementTodos { path, yes }) => {
+            let app = app::App::new(config)?;
+            app.check_model().await;
+            let result = app.implement_todos(path, *yes).await;
+            app.shutdown().await;
+            result?;
             return Ok(());
         }
         Some(Commands::Init) => {
             let cwd = std::env::current_dir()?;
             let memory = memory::ProjectMemory::new();
-            memory.init_caulk_file(&cwd)?;
+            memory.init_caulk_file(&cwd, &ui::interface::TerminalUi::new())?;
+            return Ok(());
+        }
+        Some(Commands::Imports { action: ImportsAction::Fix { file } }) => {
+            if !analysis::imports::is_supported(file) {
+                return Err(anyhow::anyhow!("Imports can only be fixed in Rust, Python, JavaScript and TypeScript files"));
+            }
+            let cwd = std::env::current_dir()?;
+            let symbols = std::sync::Mutex::new(analysis::xref::SymbolIndex::new());
+            match commands::middleware::organize_file(&cwd.join(file), &cwd, &symbols)? {
+                Some(summary) => println!("{} {}: {}", "✓".bright_green(), file.display(), summary),
+                None => println!("{} imports are already in order", file.display()),
+            }
+            return Ok(());
+        }
+        Some(Commands::Undo { id, list, force }) => {
+            let app = app::App::new(config)?;
+            let result = if *list { app.list_undo() } else { app.undo(*id, *force) };
+            app.shutdown().await;
+            result?;
+            return Ok(());
+        }
+        Some(Commands::Mv { from, to }) => {
+            let cwd = std::env::current_dir()?;
+            let report = fs::relocate::move_file(&cwd, from, to)?;
+            for (old, new) in &report.moved {
+                println!("{} Moved {} → {}", "✓".bright_green(), old.display(), new.display());
+            }
+            if !report.updated.is_empty() {
+                println!("Updated references in {} file(s):", report.updated.len());
+                for file in &report.updated {
+                    println!("  {}", file.display());
+                }
+            }
+            for warning in &report.warnings {
+                println!("{} {}", "!".yellow(), warning);
+            }
+
+            if git::GitSupport::detect(&cwd, config.git.enable_git_features).is_enabled() {
+                let mut paths: Vec<String> = Vec::new();
+                for (old, new) in &report.moved {
+                    if git::commands::GitCommands::is_tracked(&cwd, old) {
+                        paths.push(old.to_string_lossy().to_string());
+                    }
+                    paths.push(new.to_string_lossy().to_string());
+                }
+                paths.extend(report.updated.iter().map(|p| p.to_string_lossy().to_string()));
+                paths.dedup();
+                let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+                match git::commands::GitCommands::add(&cwd, &paths) {
+                    Ok(_) => println!("{} Staged the move", "✓".bright_green()),
+                    Err(e) => println!("{} Could not stage the move: {}", "!".yellow(), e),
+                }
+            }
+            return Ok(());
+        }
+        #[cfg(feature = "test-support")]
+        Some(Commands::Snapshot { fixtures, update }) => {
+            if !test_support::run_all(fixtures, &config, *update).await? {
+                std::process::exit(1);
+            }
             return Ok(());
         }
         None => {
             // No subcommand, enter interactive mode
             let mut app = app::App::new(config)?;
-            app.run().await?;
+            app.check_model().await;
+            let result = app.run().await;
+            app.shutdown().await;
+            result?;
         }
     }
     
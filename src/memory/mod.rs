@@ -1,8 +1,16 @@
 // src/memory/mod.rs
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use anyhow::{Result, Context};
 use colored::Colorize;
+use log::debug;
+use crate::ui::interface::UserInterface;
+
+/// How deep subdirectories are searched for CAULK.md files
+const DISCOVERY_MAX_DEPTH: usize = 3;
+/// How long the discovered CAULK.md locations are reused before searching again
+const DISCOVERY_MAX_AGE: Duration = Duration::from_secs(10 * 60);
 
 #[derive(Default, Clone)]
 pub struct ProjectMemory {
@@ -10,6 +18,10 @@ pub struct ProjectMemory {
     combined_memory: String,
     // Tracks which files have been loaded
     loaded_files: Vec<PathBuf>,
+    // CAULK.md files in subdirectories, which apply only when working there
+    discovered_files: Vec<PathBuf>,
+    // `project.type:` from the nearest CAULK.md naming one
+    project_type: Option<String>,
 }
 
 impl ProjectMemory {
@@ -18,6 +30,8 @@ impl ProjectMemory {
         Self {
             combined_memory: String::new(),
             loaded_files: Vec::new(),
+            discovered_files: Vec::new(),
+            project_type: None,
         }
     }
 
@@ -34,13 +48,16 @@ impl ProjectMemory {
             }
         }
 
-        // 2. Load from current directory and any parent directories
+        // 2. Load from current directory and any parent directories; a
+        // project type set there wins over the user's
+        let user_project_type = result.project_type.take();
         let cwd = std::env::current_dir()?;
         result.load_directory_and_parents(&cwd)?;
+        result.project_type = result.project_type.or(user_project_type);
 
         // 3. Look for CAULK.md in subdirectories of current directory
-        // (we don't automatically load these, but we track them for reference)
-        result.find_subdirectory_files(&cwd)?;
+        // (we don't automatically load these; /memory lists them)
+        result.discovered_files = Self::discover_subdirectory_files(&cwd, false);
 
         Ok(result)
     }
@@ -55,6 +72,11 @@ impl ProjectMemory {
         self.combined_memory.push_str(&content);
         self.combined_memory.push_str("\n\n");
         
+        // Files are loaded nearest first, so the first project type found wins
+        if self.project_type.is_none() {
+            self.project_type = project_type_key(&content);
+        }
+
         // Track that we've loaded this file
         self.loaded_files.push(path.to_path_buf());
         
@@ -76,21 +98,45 @@ impl ProjectMemory {
         Ok(())
     }
 
-    /// Finds but doesn't load CAULK.md files in subdirectories
-    fn find_subdirectory_files(&mut self, dir: &Path) -> Result<()> {
-        for entry in walkdir::WalkDir::new(dir)
-            .min_depth(1) // Skip the root dir
-            .max_depth(3) // Don't go too deep
-            .into_iter()
-            .filter_map(|e| e.ok()) {
-                
-            if entry.file_type().is_file() && entry.file_name() == "CAULK.md" {
-                // Don't load, just track for reference
-                self.loaded_files.push(entry.path().to_path_buf());
+    /// CAULK.md files in subdirectories of `dir`, skipping ignored paths.
+    /// The locations are cached in `.caulk/memory_files.json` and searched
+    /// for again once the cache is older than a few minutes, or on `refresh`.
+    pub fn discover_subdirectory_files(dir: &Path, refresh: bool) -> Vec<PathBuf> {
+        let cache_path = Self::discovery_cache_path(dir);
+        let cache_age = fs::metadata(&cache_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok());
+        if !refresh && cache_age.is_some_and(|age| age < DISCOVERY_MAX_AGE) {
+            let cached = fs::read_to_string(&cache_path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<Vec<PathBuf>>(&content).ok());
+            if let Some(files) = cached {
+                return files.into_iter().map(|file| dir.join(file)).filter(|path| path.is_file()).collect();
             }
         }
-        
-        Ok(())
+
+        let mut walker = crate::fs::project_walker(dir);
+        walker.max_depth(Some(DISCOVERY_MAX_DEPTH.min(crate::fs::scan_config().max_depth)));
+        let files: Vec<PathBuf> = crate::fs::walk_parallel(&walker)
+            .into_iter()
+            .filter(|entry| entry.depth() > 1 && entry.file_name() == "CAULK.md")
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .map(|entry| entry.into_path())
+            .collect();
+
+        let relative: Vec<&Path> = files.iter().filter_map(|path| path.strip_prefix(dir).ok()).collect();
+        let saved = fs::create_dir_all(dir.join(".caulk"))
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(fs::write(&cache_path, serde_json::to_string(&relative)?)?));
+        if let Err(e) = saved {
+            debug!("Failed to cache CAULK.md locations in {}: {}", cache_path.display(), e);
+        }
+        files
+    }
+
+    fn discovery_cache_path(dir: &Path) -> PathBuf {
+        dir.join(".caulk").join("memory_files.json")
     }
 
     /// Returns the combined memory content
@@ -98,17 +144,95 @@ impl ProjectMemory {
         &self.combined_memory
     }
 
-    /// Returns a list of all tracked CAULK.md files
+    /// Returns the contents of every `## System Prompt` section in the loaded
+    /// CAULK.md files, joined in load order
+    pub fn system_prompt(&self) -> Option<String> {
+        let sections: Vec<String> = self.sections("system prompt").into_iter().filter(|s| !s.is_empty()).collect();
+        if sections.is_empty() {
+            None
+        } else {
+            Some(sections.join("\n\n"))
+        }
+    }
+
+    /// The `## Conventions` sections of the loaded CAULK.md files, which
+    /// replace the built-in conventions of the project type. None when there
+    /// are none; an empty section turns the conventions off.
+    pub fn conventions(&self) -> Option<String> {
+        let sections = self.sections("conventions");
+        if sections.is_empty() {
+            return None;
+        }
+        let sections: Vec<String> = sections.into_iter().filter(|s| !s.is_empty()).collect();
+        Some(sections.join("\n\n"))
+    }
+
+    /// Contents of every section headed `title` (case-insensitive) in load order
+    fn sections(&self, title: &str) -> Vec<String> {
+        let mut sections = Vec::new();
+        let mut current: Option<Vec<&str>> = None;
+
+        for line in self.combined_memory.lines() {
+            if line.starts_with("## ") || line.starts_with("# ") {
+                if let Some(section) = current.take() {
+                    sections.push(section.join("\n").trim().to_string());
+                }
+                if line.trim_start_matches('#').trim().eq_ignore_ascii_case(title) {
+                    current = Some(Vec::new());
+                }
+            } else if let Some(section) = current.as_mut() {
+                section.push(line);
+            }
+        }
+        if let Some(section) = current {
+            sections.push(section.join("\n").trim().to_string());
+        }
+        sections
+    }
+
+    /// The project type set with a `project.type: <type>` line, overriding
+    /// the detected one
+    pub fn project_type(&self) -> Option<&str> {
+        self.project_type.as_deref()
+    }
+
+    /// Returns a list of all loaded CAULK.md files
     pub fn get_loaded_files(&self) -> &[PathBuf] {
         &self.loaded_files
     }
 
+    /// CAULK.md files found in subdirectories but not loaded
+    pub fn discovered_files(&self) -> &[PathBuf] {
+        &self.discovered_files
+    }
+
+    /// Whether a loaded CAULK.md already lists `command` on a line of its own
+    pub fn knows_command(&self, command: &str) -> bool {
+        let command = command.trim();
+        self.combined_memory.lines().any(|line| line.trim().trim_start_matches("$ ") == command)
+    }
+
+    /// Adds `command` to the `## Frequently Used Commands` code block of the
+    /// CAULK.md in `dir`, creating the file, the section or the block when
+    /// missing. Returns the file's path.
+    pub fn record_command(&self, dir: &Path, command: &str) -> Result<PathBuf> {
+        let caulk_path = dir.join("CAULK.md");
+        let content = match fs::read_to_string(&caulk_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => "# Project Memory for CodeAssist\n".to_string(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read memory file: {}", caulk_path.display())),
+        };
+        fs::write(&caulk_path, with_recorded_command(&content, command.trim()))
+            .with_context(|| format!("Failed to write memory file: {}", caulk_path.display()))?;
+        Ok(caulk_path)
+    }
+
     /// Initializes a new CAULK.md file in the specified directory
-    pub fn init_caulk_file(&self, dir: &Path) -> Result<()> {
+    pub fn init_caulk_file(&self, dir: &Path, ui: &dyn UserInterface) -> Result<()> {
         let caulk_path = dir.join("CAULK.md");
         
         if caulk_path.exists() {
-            println!("{} {} already exists", "!".yellow(), caulk_path.display());
+            ui.print(&format!("{} {} already exists", "!".yellow(), caulk_path.display()));
             return Ok(());
         }
         
@@ -142,8 +266,94 @@ cargo clippy
         fs::write(&caulk_path, template)
             .with_context(|| format!("Failed to create CAULK.md at {}", caulk_path.display()))?;
             
-        println!("{} Created project memory file at {}", "✓".green(), caulk_path.display());
+        ui.print(&format!("{} Created project memory file at {}", "✓".green(), caulk_path.display()));
         
         Ok(())
     }
 }
+
+/// `content` with `command` added at the end of the first code block of its
+/// `## Frequently Used Commands` section
+fn with_recorded_command(content: &str, command: &str) -> String {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let is_heading = |line: &str| line.starts_with("# ") || line.starts_with("## ");
+    let section = lines
+        .iter()
+        .position(|line| is_heading(line) && line.trim_start_matches('#').trim().eq_ignore_ascii_case("frequently used commands"));
+    let section = match section {
+        Some(section) => section,
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.extend(["## Frequently Used Commands".to_string(), "```".to_string(), command.to_string(), "```".to_string()]);
+            return lines.join("\n") + "\n";
+        }
+    };
+
+    // The closing fence of the section's first code block, or where the
+    // section ends when it has none
+    let mut in_code = false;
+    let mut insert_at = None;
+    let mut section_end = lines.len();
+    for (idx, line) in lines.iter().enumerate().skip(section + 1) {
+        if line.trim_start().starts_with("```") {
+            if in_code {
+                insert_at = Some(idx);
+                break;
+            }
+            in_code = true;
+        } else if !in_code && is_heading(line) {
+            section_end = idx;
+            break;
+        }
+    }
+    match insert_at {
+        Some(idx) => {
+            // Separated from the entry above like those of the template
+            if !lines[idx - 1].trim().is_empty() && !lines[idx - 1].trim_start().starts_with("```") {
+                lines.insert(idx, String::new());
+                lines.insert(idx + 1, command.to_string());
+            } else {
+                lines.insert(idx, command.to_string());
+            }
+        }
+        None => {
+            let mut end = section_end;
+            while end > section + 1 && lines[end - 1].trim().is_empty() {
+                end -= 1;
+            }
+            let mut block = vec!["```".to_string(), command.to_string(), "```".to_string()];
+            if end == section_end && end < lines.len() {
+                block.push(String::new());
+            }
+            lines.splice(end..end, block);
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Value of a `project.type: <type>` (or `project.type = <type>`) line
+/// outside code blocks
+fn project_type_key(content: &str) -> Option<String> {
+    let mut in_code = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let value = match line.trim_start_matches(['-', '*', ' ']).strip_prefix("project.type") {
+            Some(rest) => rest.trim_start().trim_start_matches([':', '=']),
+            None => continue,
+        };
+        let value = value.trim().trim_matches(['"', '\'', '`']);
+        if !value.is_empty() {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
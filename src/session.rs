@@ -0,0 +1,160 @@
+use crate::commands::executor::ExecutionReport;
+use crate::fs::storage::Storage;
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Matches listed by `/search`, newest first
+pub const MAX_SEARCH_RESULTS: usize = 20;
+
+/// One command of a session and what came of it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEntry {
+    /// RFC 3339 time the command finished
+    pub time: String,
+    pub command: String,
+    #[serde(default)]
+    pub action: Option<String>,
+    #[serde(default)]
+    pub answer: Option<String>,
+    /// Files the action changed
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+    /// Shell commands the action ran
+    #[serde(default)]
+    pub commands: Vec<String>,
+}
+
+impl SessionEntry {
+    pub fn new(command: &str, report: &ExecutionReport) -> Self {
+        Self {
+            time: chrono::Local::now().to_rfc3339(),
+            command: command.trim().to_string(),
+            action: report.action_type.clone(),
+            answer: report.answer.clone(),
+            files: report.file_changes.iter().map(|change| change.path.clone()).collect(),
+            commands: report.commands.iter().map(|run| run.command.clone()).collect(),
+        }
+    }
+
+    /// `YYYY-MM-DD HH:MM` in local time
+    pub fn short_time(&self) -> String {
+        chrono::DateTime::parse_from_rfc3339(&self.time)
+            .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|_| self.time.clone())
+    }
+
+    /// What the command produced: the answer, or the files and commands the
+    /// action touched
+    pub fn outcome(&self) -> String {
+        if let Some(answer) = &self.answer {
+            return answer.clone();
+        }
+        let mut parts: Vec<String> = self.files.iter().map(|path| format!("changed {}", path.display())).collect();
+        parts.extend(self.commands.iter().map(|command| format!("ran `{}`", command)));
+        if parts.is_empty() {
+            parts.push(self.action.clone().unwrap_or_else(|| "no action".to_string()));
+        }
+        parts.join(", ")
+    }
+
+    fn matches(&self, terms: &[String]) -> bool {
+        let text = format!("{}\n{}", self.command, self.outcome()).to_lowercase();
+        terms.iter().all(|term| text.contains(term))
+    }
+}
+
+/// Commands and answers of every session in `.caulk/sessions/`, one JSON
+/// line per command and one file per session, so past answers can be found
+/// with `/search` instead of scrolling the terminal
+pub struct SessionStore {
+    dir: PathBuf,
+    storage: Storage,
+    /// File of the current session, named after its first recorded command
+    current: Mutex<Option<(PathBuf, Vec<SessionEntry>)>>,
+}
+
+impl SessionStore {
+    pub fn new(project_root: &Path, storage: Storage) -> Self {
+        Self {
+            dir: Self::dir(project_root),
+            storage,
+            current: Mutex::new(None),
+        }
+    }
+
+    /// Directory the sessions of the project at `project_root` are saved in
+    pub fn dir(project_root: &Path) -> PathBuf {
+        project_root.join(".caulk").join("sessions")
+    }
+
+    /// Appends an entry to the current session. The file is rewritten whole
+    /// so it can be encrypted.
+    pub fn record(&self, entry: SessionEntry) {
+        let mut current = match self.current.lock() {
+            Ok(current) => current,
+            Err(_) => return,
+        };
+        let (path, entries) = current.get_or_insert_with(|| {
+            let name = format!("{}.jsonl", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+            (self.dir.join(name), Vec::new())
+        });
+        entries.push(entry);
+
+        let content: String = entries
+            .iter()
+            .filter_map(|entry| serde_json::to_string(entry).ok())
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let result = std::fs::create_dir_all(&self.dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| self.storage.write(path, &content));
+        if let Err(e) = result {
+            warn!("Failed to save session {}: {}", path.display(), e);
+        }
+    }
+
+    /// Entries whose command or outcome contains every word of `query`
+    /// (case-insensitive), newest first
+    pub fn search(&self, query: &str) -> Result<Vec<SessionEntry>> {
+        let terms: Vec<String> = query.split_whitespace().map(|term| term.to_lowercase()).collect();
+        let mut matches: Vec<SessionEntry> = self
+            .load_all()?
+            .into_iter()
+            .filter(|entry| entry.matches(&terms))
+            .collect();
+        matches.sort_by(|a, b| b.time.cmp(&a.time));
+        matches.truncate(MAX_SEARCH_RESULTS);
+        Ok(matches)
+    }
+
+    /// Entries of the current session and every saved one
+    fn load_all(&self) -> Result<Vec<SessionEntry>> {
+        let (current_path, mut entries) = match self.current.lock().ok().and_then(|current| current.clone()) {
+            Some((path, entries)) => (Some(path), entries),
+            None => (None, Vec::new()),
+        };
+
+        let files = match std::fs::read_dir(&self.dir) {
+            Ok(files) => files,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(entries),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", self.dir.display())),
+        };
+        for path in files.filter_map(|f| f.ok()).map(|f| f.path()) {
+            if current_path.as_ref() == Some(&path) || path.extension().is_none_or(|ext| ext != "jsonl") {
+                continue;
+            }
+            let content = match self.storage.read(&path) {
+                Ok(content) => content,
+                Err(e) => {
+                    debug!("Skipping session {}: {:#}", path.display(), e);
+                    continue;
+                }
+            };
+            entries.extend(content.lines().filter_map(|line| serde_json::from_str::<SessionEntry>(line).ok()));
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,281 @@
+use crate::app::App;
+use crate::config::{self, Config, LlmProvider};
+use crate::ui::interface::UserInterface;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use similar::TextDiff;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use walkdir::WalkDir;
+
+const TREE_SNAPSHOT: &str = "tree.txt";
+const DIFF_SNAPSHOT: &str = "diff.patch";
+const ERROR_SNAPSHOT: &str = "error.txt";
+
+/// A snapshot test case, one directory per case:
+///
+/// - `workspace/`: files the command runs against, including an optional
+///   `.caulk/config.toml` with the templates and policies under test
+/// - `command.txt`: the natural language command
+/// - `responses/`: mock LLM replies, used in file name order
+/// - `expected/`: `tree.txt` and `diff.patch` snapshots, and `error.txt`
+///   when the command is expected to fail
+pub struct Fixture {
+    pub name: String,
+    dir: PathBuf,
+    command: String,
+    responses: Vec<String>,
+}
+
+impl Fixture {
+    pub fn load(dir: &Path) -> Result<Self> {
+        let command = std::fs::read_to_string(dir.join("command.txt"))
+            .with_context(|| format!("Failed to read {}", dir.join("command.txt").display()))?;
+
+        let mut response_files: Vec<PathBuf> = std::fs::read_dir(dir.join("responses"))
+            .with_context(|| format!("Failed to read {}", dir.join("responses").display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        response_files.sort();
+        let responses = response_files
+            .iter()
+            .map(|path| std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display())))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            name: dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            dir: dir.to_path_buf(),
+            command: command.trim().to_string(),
+            responses,
+        })
+    }
+
+    fn workspace(&self) -> PathBuf {
+        self.dir.join("workspace")
+    }
+
+    fn expected(&self) -> PathBuf {
+        self.dir.join("expected")
+    }
+}
+
+/// File tree and diff left behind by a fixture's command, and its error
+/// when it failed
+pub struct Outcome {
+    pub tree: String,
+    pub diff: String,
+    pub error: String,
+}
+
+/// Output of the app, kept instead of printed
+#[derive(Default)]
+pub struct CapturedUi {
+    output: Mutex<String>,
+}
+
+impl CapturedUi {
+    pub fn output(&self) -> String {
+        self.output.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl UserInterface for CapturedUi {
+    async fn read_line(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn print(&self, text: &str) {
+        let mut output = self.output.lock().unwrap();
+        output.push_str(text);
+        output.push('\n');
+    }
+
+    fn error(&self, text: &str) {
+        self.print(text);
+    }
+}
+
+/// Runs the fixture's command in a temporary copy of its workspace against
+/// the mock provider. The app works in the current directory, so fixtures
+/// must run one at a time. A command that fails is part of the outcome; an
+/// error is returned only when the fixture can't be run.
+pub async fn run(fixture: &Fixture, base_config: &Config) -> Result<Outcome> {
+    let temp = tempfile::tempdir().context("Failed to create a temporary workspace")?;
+    let workspace = temp.path().to_path_buf();
+    copy_tree(&fixture.workspace(), &workspace)?;
+
+    let mut config = config::apply_project_config(base_config.clone(), &workspace)?;
+    config.llm.provider = LlmProvider::Mock;
+    config.llm.mock_responses = fixture.responses.clone();
+    config.llm.fallbacks.clear();
+    config.llm.debug_log = false;
+    config.cache.enabled = false;
+    config.embeddings.enabled = false;
+    config.events.log_file = None;
+    // Nobody is there to approve the fixture's edits and commands
+    config.safety.auto_approve = vec!["edit_file".to_string(), "execute_command".to_string()];
+
+    let previous_dir = std::env::current_dir()?;
+    std::env::set_current_dir(&workspace)?;
+    let ui = Arc::new(CapturedUi::default());
+    let result = match App::with_ui(config, ui.clone()) {
+        Ok(app) => {
+            let result = app.execute_command(&fixture.command).await;
+            app.shutdown().await;
+            Ok(result)
+        }
+        Err(e) => Err(e),
+    };
+    std::env::set_current_dir(previous_dir)?;
+
+    let error = match result {
+        Ok(Ok(_)) => String::new(),
+        Ok(Err(e)) => format!("{:#}\n", e),
+        Err(e) => return Err(anyhow!("{:#}\nOutput:\n{}", e, ui.output())),
+    };
+
+    let before = read_tree(&fixture.workspace())?;
+    let after = read_tree(&workspace)?;
+    Ok(Outcome {
+        tree: format_tree(&after),
+        diff: diff_trees(&before, &after),
+        error,
+    })
+}
+
+/// Compares the outcome with the fixture's snapshots, or rewrites them when
+/// `update` is set. Returns whether the snapshots matched.
+pub fn check(fixture: &Fixture, outcome: &Outcome, update: bool) -> Result<bool> {
+    let expected_dir = fixture.expected();
+    let mut matched = true;
+
+    for (file, actual) in [(TREE_SNAPSHOT, &outcome.tree), (DIFF_SNAPSHOT, &outcome.diff), (ERROR_SNAPSHOT, &outcome.error)] {
+        let path = expected_dir.join(file);
+        let expected = std::fs::read_to_string(&path).unwrap_or_default();
+        if &expected == actual {
+            continue;
+        }
+        if update {
+            std::fs::create_dir_all(&expected_dir)?;
+            std::fs::write(&path, actual).with_context(|| format!("Failed to write {}", path.display()))?;
+            println!("{} Updated {}", "✓".bright_green(), path.display());
+        } else {
+            matched = false;
+            println!("{} {} differs from the snapshot:", "✗".red(), path.display());
+            print!(
+                "{}",
+                TextDiff::from_lines(expected.as_str(), actual.as_str())
+                    .unified_diff()
+                    .header("expected", "actual")
+            );
+        }
+    }
+
+    Ok(matched)
+}
+
+/// Runs and checks every fixture directory, printing a line per fixture.
+/// Returns whether all of them passed.
+pub async fn run_all(dirs: &[PathBuf], base_config: &Config, update: bool) -> Result<bool> {
+    let mut passed = 0;
+    for dir in dirs {
+        let fixture = Fixture::load(dir)?;
+        let ok = match run(&fixture, base_config).await {
+            Ok(outcome) => check(&fixture, &outcome, update)?,
+            Err(e) => {
+                println!("{} {} failed to run: {}", "✗".red(), fixture.name, e);
+                false
+            }
+        };
+        if ok {
+            passed += 1;
+            println!("{} {}", "✓".bright_green(), fixture.name);
+        }
+    }
+
+    println!("{}/{} snapshot fixtures passed", passed, dirs.len());
+    Ok(passed == dirs.len())
+}
+
+fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Text files under `root` by relative path, skipping the app's own `.caulk/`
+/// state (caches, logs, memory)
+fn read_tree(root: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(root).into_iter().filter_entry(|e| e.file_name() != ".caulk") {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root)?.to_string_lossy().replace('\\', "/");
+        let content = match std::fs::read(entry.path()) {
+            Ok(bytes) => String::from_utf8(bytes).unwrap_or_else(|_| "<binary>".to_string()),
+            Err(e) => format!("<unreadable: {}>", e),
+        };
+        files.insert(relative, content);
+    }
+    Ok(files)
+}
+
+/// Each path with its line count, so the snapshot shows the shape of the
+/// tree while the diff shows the content
+fn format_tree(files: &BTreeMap<String, String>) -> String {
+    files
+        .iter()
+        .map(|(path, content)| format!("{} ({} lines)\n", path, content.lines().count()))
+        .collect()
+}
+
+fn diff_trees(before: &BTreeMap<String, String>, after: &BTreeMap<String, String>) -> String {
+    let mut paths: Vec<&String> = before.keys().chain(after.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut diff = String::new();
+    for path in paths {
+        let old = before.get(path).map(String::as_str).unwrap_or("");
+        let new = after.get(path).map(String::as_str).unwrap_or("");
+        if old == new {
+            continue;
+        }
+        let old_name = if before.contains_key(path) { format!("a/{}", path) } else { "/dev/null".to_string() };
+        let new_name = if after.contains_key(path) { format!("b/{}", path) } else { "/dev/null".to_string() };
+        diff.push_str(&TextDiff::from_lines(old, new).unified_diff().header(&old_name, &new_name).to_string());
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn fixtures_match_their_snapshots() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+        let mut dirs: Vec<PathBuf> = std::fs::read_dir(&root)
+            .unwrap()
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        dirs.sort();
+        assert!(!dirs.is_empty(), "no fixtures in {}", root.display());
+        assert!(run_all(&dirs, &Config::default(), false).await.unwrap());
+    }
+}
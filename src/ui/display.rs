@@ -1,17 +1,349 @@
-use colored::{Colorize, ColoredString};
+use crate::config::{DiffAlgorithm, DiffConfig};
+use colored::{Color, Colorize, ColoredString};
+use once_cell::sync::OnceCell;
+use regex::Regex;
+use similar::{Algorithm, ChangeTag, DiffTag, TextDiff};
+use std::io::IsTerminal;
+use unicode_width::UnicodeWidthChar;
 
-pub fn format_code(code: &str) -> ColoredString {
-    code.bright_white()
+/// When to emit ANSI color codes
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
 }
 
-pub fn format_success(message: &str) -> ColoredString {
-    message.bright_green()
+/// Applies the color choice globally for every `Colorize` call site.
+/// `auto` disables color when NO_COLOR is set or stdout is not a terminal.
+pub fn init_color(choice: ColorChoice) {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+            !no_color && std::io::stdout().is_terminal()
+        }
+    };
+    colored::control::set_override(enabled);
 }
 
-pub fn format_error(message: &str) -> ColoredString {
-    message.bright_red()
+static DIFF: OnceCell<DiffConfig> = OnceCell::new();
+
+/// Applies the `[diff]` settings to every diff shown
+pub fn init_diff(config: &DiffConfig) {
+    let _ = DIFF.set(config.clone());
+}
+
+/// Renders a colored unified diff between two versions of a file. With
+/// `[diff] word_level`, the words that differ within a changed line are
+/// highlighted, so a small change in a long line stands out.
+pub fn format_diff(path: &str, old: &str, new: &str) -> String {
+    let config = DIFF.get_or_init(DiffConfig::default);
+    let diff = TextDiff::configure().algorithm(diff_algorithm()).diff_lines(old, new);
+    let mut output = format!("{}\n{}\n", format!("--- a/{}", path).bold(), format!("+++ b/{}", path).bold());
+
+    for group in diff.grouped_ops(3) {
+        let first = &group[0];
+        let last = &group[group.len() - 1];
+        output.push_str(&format!(
+            "{}\n",
+            format!(
+                "@@ -{},{} +{},{} @@",
+                first.old_range().start + 1,
+                last.old_range().end - first.old_range().start,
+                first.new_range().start + 1,
+                last.new_range().end - first.new_range().start
+            )
+            .cyan()
+        ));
+
+        for op in &group {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            // Lines replaced one for one are compared word by word
+            if config.word_level && tag == DiffTag::Replace && old_range.len() == new_range.len() {
+                let pairs: Vec<(&str, &str)> = old_range
+                    .clone()
+                    .zip(new_range.clone())
+                    .map(|(old_idx, new_idx)| (diff.old_slices()[old_idx], diff.new_slices()[new_idx]))
+                    .collect();
+                let words: Vec<Option<(String, String)>> = pairs
+                    .iter()
+                    .map(|(old_line, new_line)| highlight_words(old_line.trim_end_matches('\n'), new_line.trim_end_matches('\n')))
+                    .collect();
+                for (idx, (old_line, _)) in pairs.iter().enumerate() {
+                    match &words[idx] {
+                        Some((highlighted, _)) => push_diff_line(&mut output, "-".red(), highlighted, Color::Red, false),
+                        None => push_diff_line(&mut output, "-".red(), old_line.trim_end_matches('\n'), Color::Red, true),
+                    }
+                }
+                for (idx, (_, new_line)) in pairs.iter().enumerate() {
+                    match &words[idx] {
+                        Some((_, highlighted)) => push_diff_line(&mut output, "+".green(), highlighted, Color::Green, false),
+                        None => push_diff_line(&mut output, "+".green(), new_line.trim_end_matches('\n'), Color::Green, true),
+                    }
+                }
+                continue;
+            }
+
+            for change in diff.iter_changes(op) {
+                let line = change.to_string_lossy();
+                let line = line.trim_end_matches('\n');
+                match change.tag() {
+                    ChangeTag::Delete => push_diff_line(&mut output, "-".red(), line, Color::Red, true),
+                    ChangeTag::Insert => push_diff_line(&mut output, "+".green(), line, Color::Green, true),
+                    ChangeTag::Equal => push_diff_line(&mut output, " ".normal(), line, Color::White, false),
+                }
+            }
+        }
+    }
+
+    output
+}
+
+/// Adds a diff line after `marker`, wrapped to the terminal width. Wrapping
+/// comes before coloring so continuation lines keep the marker and color;
+/// `colorize` is false for context lines and lines colored word by word.
+fn push_diff_line(output: &mut String, marker: ColoredString, line: &str, color: Color, colorize: bool) {
+    let pieces = match terminal_width() {
+        Some(width) => wrap_line(line, width.saturating_sub(1)),
+        None => vec![line.to_string()],
+    };
+    for piece in pieces {
+        if colorize {
+            output.push_str(&format!("{}{}\n", marker, piece.color(color)));
+        } else {
+            output.push_str(&format!("{}{}\n", marker, piece));
+        }
+    }
+}
+
+/// An old and a new line in red and green with the words that differ
+/// between them reversed. None when the lines have too little in common for
+/// that to help. Each word is colored on its own, so wrapping never splits a
+/// color sequence.
+fn highlight_words(old: &str, new: &str) -> Option<(String, String)> {
+    let token = Regex::new(r"\w+|\s+|[^\w\s]").expect("valid diff token pattern");
+    let old_tokens: Vec<&str> = token.find_iter(old).map(|m| m.as_str()).collect();
+    let new_tokens: Vec<&str> = token.find_iter(new).map(|m| m.as_str()).collect();
+    let ops = similar::capture_diff_slices(diff_algorithm(), &old_tokens, &new_tokens);
+
+    let mut common = 0;
+    let mut old_line = String::new();
+    let mut new_line = String::new();
+    for op in &ops {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        let changed = tag != DiffTag::Equal;
+        for old_token in &old_tokens[old_range] {
+            if !changed {
+                common += old_token.trim().len();
+            }
+            push_word(&mut old_line, old_token, Color::Red, changed);
+        }
+        for new_token in &new_tokens[new_range] {
+            push_word(&mut new_line, new_token, Color::Green, changed);
+        }
+    }
+    // Mostly rewritten lines read better as a plain removal and addition
+    let visible = old.trim().len().max(new.trim().len());
+    if common * 2 < visible {
+        return None;
+    }
+    Some((old_line, new_line))
+}
+
+fn push_word(line: &mut String, word: &str, color: Color, changed: bool) {
+    if word.trim().is_empty() {
+        line.push_str(word);
+    } else if changed {
+        line.push_str(&word.color(color).reversed().to_string());
+    } else {
+        line.push_str(&word.color(color).to_string());
+    }
+}
+
+fn diff_algorithm() -> Algorithm {
+    match DIFF.get_or_init(DiffConfig::default).algorithm {
+        DiffAlgorithm::Myers => Algorithm::Myers,
+        DiffAlgorithm::Patience => Algorithm::Patience,
+        DiffAlgorithm::Lcs => Algorithm::Lcs,
+    }
+}
+
+/// Column count of the terminal, or None when stdout is not a terminal
+/// (output that is piped or redirected is never wrapped)
+pub fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    crossterm::terminal::size()
+        .ok()
+        .map(|(columns, _)| columns as usize)
+        .filter(|columns| *columns > 0)
+}
+
+/// Soft-wraps text to the terminal width, keeping each line's indentation.
+/// Lines inside ``` fences are left untouched so code is never reflowed.
+pub fn wrap_text(text: &str) -> String {
+    let width = match terminal_width() {
+        Some(width) => width,
+        None => return text.to_string(),
+    };
+
+    let mut in_code_block = false;
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            lines.push(line.to_string());
+        } else if in_code_block {
+            lines.push(line.to_string());
+        } else {
+            lines.extend(wrap_line(line, width));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Splits one line into pieces no wider than `width` columns, breaking at
+/// spaces where possible. Continuation pieces repeat the line's indentation.
+pub fn wrap_line(line: &str, width: usize) -> Vec<String> {
+    if width == 0 || display_width(line) <= width {
+        return vec![line.to_string()];
+    }
+
+    let leading = &line[..line.len() - line.trim_start().len()];
+    // Deeply indented lines would leave no room for text on continuation lines
+    let indent = if display_width(leading) * 2 > width { "" } else { leading };
+
+    let mut pieces = Vec::new();
+    let mut current = leading.to_string();
+
+    for word in line.split_whitespace() {
+        let at_line_start = current.trim().is_empty();
+        if !at_line_start && display_width(&current) + 1 + display_width(word) > width {
+            pieces.push(std::mem::replace(&mut current, indent.to_string()));
+        } else if !at_line_start {
+            current.push(' ');
+        }
+
+        // Words longer than a whole line are broken between characters
+        let mut word = word;
+        while display_width(&current) + display_width(word) > width {
+            let (head, tail) = split_at_width(word, width.saturating_sub(display_width(&current)));
+            if head.is_empty() {
+                break;
+            }
+            current.push_str(head);
+            pieces.push(std::mem::replace(&mut current, indent.to_string()));
+            word = tail;
+        }
+        current.push_str(word);
+    }
+
+    if !current.trim().is_empty() || pieces.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// Cuts a line down to `width` columns, ending it with `…` when shortened.
+/// ANSI escape sequences are kept intact.
+pub fn truncate_to_width(line: &str, width: usize) -> String {
+    if display_width(line) <= width {
+        return line.to_string();
+    }
+    let (head, _) = split_at_width(line, width.saturating_sub(1));
+    if head.contains('\x1b') {
+        // Make sure a truncated color does not leak into the next line
+        format!("{}…\x1b[0m", head)
+    } else {
+        format!("{}…", head)
+    }
+}
+
+/// Lays out rows in aligned columns, truncating each line to the terminal width
+pub fn format_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| display_width(h)).collect();
+    for row in rows {
+        for (idx, cell) in row.iter().enumerate() {
+            if idx < widths.len() {
+                widths[idx] = widths[idx].max(display_width(cell));
+            }
+        }
+    }
+
+    let render_row = |cells: Vec<String>| -> String {
+        let padded: Vec<String> = cells
+            .iter()
+            .enumerate()
+            .map(|(idx, cell)| {
+                let padding = widths.get(idx).copied().unwrap_or(0).saturating_sub(display_width(cell));
+                format!("{}{}", cell, " ".repeat(padding))
+            })
+            .collect();
+        let line = padded.join("  ").trim_end().to_string();
+        match terminal_width() {
+            Some(width) => truncate_to_width(&line, width),
+            None => line,
+        }
+    };
+
+    let mut output = render_row(headers.iter().map(|h| h.bold().to_string()).collect());
+    output.push('\n');
+    for row in rows {
+        output.push_str(&render_row(row.clone()));
+        output.push('\n');
+    }
+    output
+}
+
+/// Number of terminal columns a string occupies, ignoring ANSI escape sequences
+pub fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut idx = 0;
+    while let Some(rest) = text.get(idx..).filter(|rest| !rest.is_empty()) {
+        if rest.starts_with('\x1b') {
+            idx += escape_len(rest);
+            continue;
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        width += c.width().unwrap_or(0);
+        idx += c.len_utf8();
+    }
+    width
+}
+
+/// Splits a string after at most `width` visible columns without breaking
+/// an escape sequence
+fn split_at_width(text: &str, width: usize) -> (&str, &str) {
+    let mut used = 0;
+    let mut idx = 0;
+    while let Some(rest) = text.get(idx..).filter(|rest| !rest.is_empty()) {
+        if rest.starts_with('\x1b') {
+            idx += escape_len(rest);
+            continue;
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width > width {
+            break;
+        }
+        used += char_width;
+        idx += c.len_utf8();
+    }
+    text.split_at(idx)
 }
 
-pub fn format_info(message: &str) -> ColoredString {
-    message.bright_blue()
+/// Byte length of the escape sequence at the start of `text` (`ESC [ ... final byte`)
+fn escape_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    if bytes.get(1) != Some(&b'[') {
+        return 1;
+    }
+    match bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)) {
+        Some(pos) => pos + 3,
+        None => text.len(),
+    }
 }
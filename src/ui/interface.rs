@@ -0,0 +1,78 @@
+use crate::ui::prompt::Prompt;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+/// Where the app reads commands from and writes its output to.
+///
+/// The REPL uses [`TerminalUi`]; a TUI, an HTTP server or a test harness
+/// provides its own implementation to `App::with_ui` to drive the same app
+/// with injected input and captured output.
+#[async_trait]
+pub trait UserInterface: Send + Sync {
+    /// Next line of input, or None when there is no more (EOF, disconnect)
+    async fn read_line(&self) -> Result<Option<String>>;
+
+    /// Regular output; `text` may contain ANSI colors
+    fn print(&self, text: &str);
+
+    /// Error output
+    fn error(&self, text: &str);
+}
+
+/// Reads from stdin at the `>>` prompt and writes to stdout/stderr
+pub struct TerminalUi {
+    prompt: Prompt,
+    /// A read interrupted by Ctrl-C; the next read_line returns its line
+    pending: Mutex<Option<oneshot::Receiver<Result<String>>>>,
+}
+
+impl TerminalUi {
+    pub fn new() -> Self {
+        Self { prompt: Prompt::new(), pending: Mutex::new(None) }
+    }
+}
+
+#[async_trait]
+impl UserInterface for TerminalUi {
+    async fn read_line(&self) -> Result<Option<String>> {
+        let pending = self.pending.lock().map_err(|_| anyhow!("Input lock poisoned"))?.take();
+        let mut read = match pending {
+            Some(read) => {
+                self.prompt.show()?;
+                read
+            }
+            None => {
+                // Reading stdin blocks and can't be cancelled, so it runs on its
+                // own thread, which neither the runtime nor the exit waits for
+                let (sender, receiver) = oneshot::channel();
+                let prompt = self.prompt.clone();
+                std::thread::spawn(move || sender.send(prompt.get_input()));
+                receiver
+            }
+        };
+        // Ctrl-C ends the input like EOF, so the caller can shut down cleanly
+        let input = tokio::select! {
+            input = &mut read => input.map_err(|_| anyhow!("Reading input failed"))??,
+            _ = tokio::signal::ctrl_c() => {
+                println!();
+                if let Ok(mut pending) = self.pending.lock() {
+                    *pending = Some(read);
+                }
+                return Ok(None);
+            }
+        };
+
+        // read_line returns nothing at all (not even a newline) at EOF
+        Ok(if input.is_empty() { None } else { Some(input) })
+    }
+
+    fn print(&self, text: &str) {
+        println!("{}", text);
+    }
+
+    fn error(&self, text: &str) {
+        eprintln!("{}", text);
+    }
+}
@@ -0,0 +1,46 @@
+use crate::ui::interface::UserInterface;
+use colored::Colorize;
+use log::debug;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Key that asks a run to pause, for hints
+pub const PAUSE_KEY: &str = "Ctrl-\\";
+
+/// Pause requests made while a run of several steps is working. Ctrl-\
+/// (SIGQUIT) sets a flag the run checks between steps, where it stops to
+/// take a correction; Ctrl-C still aborts the whole run.
+#[derive(Clone, Default)]
+pub struct SoftInterrupt {
+    requested: Arc<AtomicBool>,
+}
+
+impl SoftInterrupt {
+    /// Starts listening for Ctrl-\, acknowledging it on `ui`. Only Unix
+    /// terminals send it; elsewhere a run never pauses.
+    pub fn listen(ui: Arc<dyn UserInterface>) -> Self {
+        let interrupt = Self::default();
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::quit()) {
+                Ok(mut signals) => {
+                    let requested = interrupt.requested.clone();
+                    tokio::spawn(async move {
+                        while signals.recv().await.is_some() {
+                            requested.store(true, Ordering::SeqCst);
+                            ui.print(&format!("\n{}", "Pausing after this step...".yellow()));
+                        }
+                    });
+                }
+                Err(e) => debug!("Pausing with {} is unavailable: {}", PAUSE_KEY, e),
+            }
+        }
+        interrupt
+    }
+
+    /// Whether a pause was asked for since the last call
+    pub fn take(&self) -> bool {
+        self.requested.swap(false, Ordering::SeqCst)
+    }
+}
@@ -1,2 +1,6 @@
 pub mod prompt;
 pub mod display;
+pub mod notify;
+pub mod slash;
+pub mod interface;
+pub mod interrupt;
@@ -0,0 +1,41 @@
+use crate::config::{NotificationConfig, NotificationMode};
+use log::debug;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Tells the user that a slow task finished while they may be in another window
+#[derive(Clone)]
+pub struct Notifier {
+    mode: NotificationMode,
+    min_duration: Duration,
+}
+
+impl Notifier {
+    pub fn new(config: &NotificationConfig) -> Self {
+        Self {
+            mode: config.mode,
+            min_duration: Duration::from_secs(config.min_duration_secs),
+        }
+    }
+
+    /// Notifies if the task started at `started` took longer than the configured minimum
+    pub fn finished(&self, started: Instant, message: &str) {
+        if self.mode == NotificationMode::Off || started.elapsed() < self.min_duration {
+            return;
+        }
+
+        if self.mode == NotificationMode::Desktop {
+            match notify_rust::Notification::new()
+                .summary("code-assist")
+                .body(message)
+                .show()
+            {
+                Ok(_) => return,
+                Err(e) => debug!("Desktop notification failed, ringing the bell instead: {}", e),
+            }
+        }
+
+        print!("\x07");
+        let _ = std::io::stdout().flush();
+    }
+}
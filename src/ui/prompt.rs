@@ -2,6 +2,7 @@ use std::io::{self, Write};
 use anyhow::Result;
 use colored::Colorize;
 
+#[derive(Clone)]
 pub struct Prompt;
 
 impl Prompt {
@@ -10,12 +11,18 @@ impl Prompt {
     }
     
     pub fn get_input(&self) -> Result<String> {
-        print!("{} ", ">>".bright_green().bold());
-        io::stdout().flush()?;
+        self.show()?;
         
         let mut input = String::new();
         io::stdin().read_line(&mut input)?;
         
         Ok(input)
     }
+
+    /// Prints the `>>` prompt
+    pub fn show(&self) -> Result<()> {
+        print!("{} ", ">>".bright_green().bold());
+        io::stdout().flush()?;
+        Ok(())
+    }
 }
@@ -0,0 +1,420 @@
+use crate::app::App;
+use crate::ui::display;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// What the REPL should do after a slash command ran
+#[derive(Debug, PartialEq)]
+pub enum SlashOutcome {
+    Continue,
+    Exit,
+}
+
+/// Code run for a slash command. Built-in and plugin-provided commands both
+/// implement this and are registered in the [`SlashCommandRegistry`].
+#[async_trait]
+pub trait SlashHandler: Send + Sync {
+    async fn run(&self, app: &mut App, args: &SlashArgs) -> Result<SlashOutcome>;
+}
+
+/// A positional argument accepted by a slash command
+pub struct ArgSpec {
+    pub name: &'static str,
+    pub required: bool,
+    /// Consumes the rest of the line (spaces included)
+    pub rest: bool,
+    /// Known values, used for usage text and completion
+    pub choices: &'static [&'static str],
+}
+
+impl ArgSpec {
+    pub fn optional(name: &'static str) -> Self {
+        Self { name, required: false, rest: false, choices: &[] }
+    }
+
+    pub fn required(name: &'static str) -> Self {
+        Self { name, required: true, rest: false, choices: &[] }
+    }
+
+    /// Makes the argument take the rest of the line
+    pub fn rest(mut self) -> Self {
+        self.rest = true;
+        self
+    }
+
+    pub fn choices(mut self, choices: &'static [&'static str]) -> Self {
+        self.choices = choices;
+        self
+    }
+
+    fn usage(&self) -> String {
+        let label = if self.choices.is_empty() {
+            self.name.to_string()
+        } else {
+            self.choices.join("|")
+        };
+        let label = if self.rest { format!("{}...", label) } else { label };
+        if self.required {
+            format!("<{}>", label)
+        } else {
+            format!("[{}]", label)
+        }
+    }
+}
+
+/// Parsed arguments of a slash command invocation
+#[derive(Debug, Default)]
+pub struct SlashArgs {
+    values: Vec<String>,
+}
+
+impl SlashArgs {
+    /// Positional argument by index
+    pub fn get(&self, idx: usize) -> Option<&str> {
+        self.values.get(idx).map(|v| v.as_str())
+    }
+}
+
+/// An interactive command available at the `>>` prompt
+pub struct SlashCommand {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub args: Vec<ArgSpec>,
+    pub description: String,
+    handler: Arc<dyn SlashHandler>,
+}
+
+impl SlashCommand {
+    pub fn new(name: &str, description: &str, handler: impl SlashHandler + 'static) -> Self {
+        Self {
+            name: name.to_string(),
+            aliases: Vec::new(),
+            args: Vec::new(),
+            description: description.to_string(),
+            handler: Arc::new(handler),
+        }
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.aliases.push(alias.to_string());
+        self
+    }
+
+    pub fn arg(mut self, arg: ArgSpec) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    pub fn usage(&self) -> String {
+        std::iter::once(format!("/{}", self.name))
+            .chain(self.args.iter().map(|a| a.usage()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Splits the argument string according to the command's ArgSpecs
+    pub fn parse_args(&self, raw: &str) -> Result<SlashArgs> {
+        let mut values = Vec::new();
+        let mut remaining = raw.trim();
+
+        for spec in &self.args {
+            if remaining.is_empty() {
+                if spec.required {
+                    return Err(anyhow!("Missing <{}>. Usage: {}", spec.name, self.usage()));
+                }
+                break;
+            }
+
+            let value = if spec.rest {
+                std::mem::take(&mut remaining)
+            } else {
+                let (value, rest) = remaining.split_once(char::is_whitespace).unwrap_or((remaining, ""));
+                remaining = rest.trim_start();
+                value
+            };
+            values.push(value.to_string());
+        }
+
+        if !remaining.is_empty() {
+            return Err(anyhow!("Unexpected argument '{}'. Usage: {}", remaining, self.usage()));
+        }
+
+        Ok(SlashArgs { values })
+    }
+}
+
+/// All slash commands known to the REPL; /help is generated from it
+pub struct SlashCommandRegistry {
+    commands: Vec<SlashCommand>,
+}
+
+impl SlashCommandRegistry {
+    /// Registry with the built-in commands
+    pub fn new() -> Self {
+        let mut registry = Self { commands: Vec::new() };
+
+        registry.register(
+            SlashCommand::new("help", "List commands and the current session settings", HelpCommand)
+                .alias("h")
+                .alias("?"),
+        );
+        registry.register(SlashCommand::new(
+            "init",
+            "Create a CAULK.md project memory file in the current directory",
+            InitCommand,
+        ));
+        registry.register(
+            SlashCommand::new("memory", "List the CAULK.md files in use and those found in subdirectories", MemoryCommand)
+                .arg(ArgSpec::optional("action").choices(&["refresh"])),
+        );
+        registry.register(
+            SlashCommand::new("set", "Show or change LLM settings for the rest of the session", SetCommand)
+                .arg(ArgSpec::optional("key").choices(&["model", "temperature", "max_tokens"]))
+                .arg(ArgSpec::optional("value")),
+        );
+        registry.register(
+            SlashCommand::new("undo", "Revert the last applied action, or the action with an id from /undo list", UndoCommand)
+                .arg(ArgSpec::optional("id|list")),
+        );
+        registry.register(
+            SlashCommand::new("dryrun", "Show the actions the model proposes instead of applying them", DryRunCommand)
+                .arg(ArgSpec::optional("mode").choices(&["on", "off"])),
+        );
+        registry.register(
+            SlashCommand::new("cache", "Turn the response cache on or off, or delete the cached responses", CacheCommand)
+                .arg(ArgSpec::optional("mode").choices(&["on", "off", "clear"])),
+        );
+        registry.register(
+            SlashCommand::new("search", "Find past commands and answers, then show one or run it again", SearchCommand)
+                .arg(ArgSpec::required("text").rest()),
+        );
+        registry.register(
+            SlashCommand::new("also", "Ask a follow-up with the same context as the previous request, without searching again", AlsoCommand)
+                .arg(ArgSpec::required("question").rest()),
+        );
+        registry.register(
+            SlashCommand::new("debug", "Show the request, raw reply and parsed action of the previous turn", DebugCommand)
+                .arg(ArgSpec::required("what").choices(&["last"])),
+        );
+        registry.register(
+            SlashCommand::new("exit", "Leave interactive mode (plain `exit` works too)", ExitCommand)
+                .alias("quit")
+                .alias("q"),
+        );
+
+        registry
+    }
+
+    /// Adds a command, replacing any existing command with the same name
+    pub fn register(&mut self, command: SlashCommand) {
+        self.commands.retain(|c| c.name != command.name);
+        self.commands.push(command);
+    }
+
+    fn find(&self, name: &str) -> Option<&SlashCommand> {
+        self.commands
+            .iter()
+            .find(|c| c.name == name || c.aliases.iter().any(|a| a == name))
+    }
+
+    /// Parses `input` (e.g. `/set model x`) and returns the handler to run
+    /// together with its arguments
+    pub fn resolve(&self, input: &str) -> Result<(Arc<dyn SlashHandler>, SlashArgs)> {
+        let input = input.trim().strip_prefix('/').unwrap_or(input);
+        let (name, raw_args) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+
+        let command = match self.find(name) {
+            Some(command) => command,
+            None => {
+                let suggestions = self.complete(&format!("/{}", name));
+                return Err(if suggestions.is_empty() {
+                    anyhow!("Unknown command /{} (type /help)", name)
+                } else {
+                    anyhow!("Unknown command /{}; did you mean {}?", name, suggestions.join(", "))
+                });
+            }
+        };
+
+        Ok((command.handler.clone(), command.parse_args(raw_args)?))
+    }
+
+    /// Completions for a partially typed command line: command names first,
+    /// then the known choices of the next argument
+    pub fn complete(&self, partial: &str) -> Vec<String> {
+        let partial = match partial.strip_prefix('/') {
+            Some(partial) => partial,
+            None => return Vec::new(),
+        };
+
+        match partial.split_once(' ') {
+            None => {
+                let mut names: Vec<String> = self
+                    .commands
+                    .iter()
+                    .flat_map(|c| std::iter::once(&c.name).chain(c.aliases.iter()))
+                    .filter(|n| n.starts_with(partial) && n.len() > 1)
+                    .map(|n| format!("/{}", n))
+                    .collect();
+                names.sort();
+                names
+            }
+            Some((name, args)) => {
+                let command = match self.find(name) {
+                    Some(command) => command,
+                    None => return Vec::new(),
+                };
+                let words: Vec<&str> = args.split(' ').collect();
+                let current = words.last().copied().unwrap_or("");
+                command
+                    .args
+                    .get(words.len() - 1)
+                    .map(|spec| {
+                        spec.choices
+                            .iter()
+                            .filter(|c| c.starts_with(current))
+                            .map(|c| c.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default()
+            }
+        }
+    }
+
+    /// Table of all commands with their aliases and descriptions
+    pub fn help_text(&self) -> String {
+        let rows: Vec<Vec<String>> = self
+            .commands
+            .iter()
+            .map(|c| {
+                let aliases = c.aliases.iter().map(|a| format!("/{}", a)).collect::<Vec<_>>().join(", ");
+                vec![c.usage(), aliases, c.description.clone()]
+            })
+            .collect();
+
+        display::format_table(&["COMMAND", "ALIASES", "DESCRIPTION"], &rows)
+    }
+}
+
+struct HelpCommand;
+
+#[async_trait]
+impl SlashHandler for HelpCommand {
+    async fn run(&self, app: &mut App, _args: &SlashArgs) -> Result<SlashOutcome> {
+        app.print_help();
+        Ok(SlashOutcome::Continue)
+    }
+}
+
+struct InitCommand;
+
+#[async_trait]
+impl SlashHandler for InitCommand {
+    async fn run(&self, app: &mut App, _args: &SlashArgs) -> Result<SlashOutcome> {
+        app.init_caulk_file()?;
+        Ok(SlashOutcome::Continue)
+    }
+}
+
+struct MemoryCommand;
+
+#[async_trait]
+impl SlashHandler for MemoryCommand {
+    async fn run(&self, app: &mut App, args: &SlashArgs) -> Result<SlashOutcome> {
+        match args.get(0) {
+            None => app.show_memory_files(false)?,
+            Some("refresh") => app.show_memory_files(true)?,
+            Some(other) => return Err(anyhow!("Unknown /memory action '{}' (expected refresh)", other)),
+        }
+        Ok(SlashOutcome::Continue)
+    }
+}
+
+struct SetCommand;
+
+#[async_trait]
+impl SlashHandler for SetCommand {
+    async fn run(&self, app: &mut App, args: &SlashArgs) -> Result<SlashOutcome> {
+        app.set_llm_option(args.get(0), args.get(1))?;
+        Ok(SlashOutcome::Continue)
+    }
+}
+
+struct UndoCommand;
+
+#[async_trait]
+impl SlashHandler for UndoCommand {
+    async fn run(&self, app: &mut App, args: &SlashArgs) -> Result<SlashOutcome> {
+        match args.get(0) {
+            None => app.undo(None, false)?,
+            Some("list") => app.list_undo()?,
+            Some(id) => {
+                let id = id.parse().map_err(|_| anyhow!("Unknown /undo argument '{}' (expected an action id or list)", id))?;
+                app.undo(Some(id), false)?
+            }
+        }
+        Ok(SlashOutcome::Continue)
+    }
+}
+
+struct DryRunCommand;
+
+#[async_trait]
+impl SlashHandler for DryRunCommand {
+    async fn run(&self, app: &mut App, args: &SlashArgs) -> Result<SlashOutcome> {
+        app.toggle_dry_run(args.get(0))?;
+        Ok(SlashOutcome::Continue)
+    }
+}
+
+struct CacheCommand;
+
+#[async_trait]
+impl SlashHandler for CacheCommand {
+    async fn run(&self, app: &mut App, args: &SlashArgs) -> Result<SlashOutcome> {
+        app.set_cache(args.get(0))?;
+        Ok(SlashOutcome::Continue)
+    }
+}
+
+struct SearchCommand;
+
+#[async_trait]
+impl SlashHandler for SearchCommand {
+    async fn run(&self, app: &mut App, args: &SlashArgs) -> Result<SlashOutcome> {
+        app.search_sessions(args.get(0).unwrap_or("")).await?;
+        Ok(SlashOutcome::Continue)
+    }
+}
+
+struct AlsoCommand;
+
+#[async_trait]
+impl SlashHandler for AlsoCommand {
+    async fn run(&self, app: &mut App, args: &SlashArgs) -> Result<SlashOutcome> {
+        app.ask_also(args.get(0).unwrap_or("")).await?;
+        Ok(SlashOutcome::Continue)
+    }
+}
+
+struct DebugCommand;
+
+#[async_trait]
+impl SlashHandler for DebugCommand {
+    async fn run(&self, app: &mut App, args: &SlashArgs) -> Result<SlashOutcome> {
+        match args.get(0) {
+            Some("last") => app.show_last_turn(),
+            other => return Err(anyhow!("Unknown /debug target '{}' (expected last)", other.unwrap_or(""))),
+        }
+        Ok(SlashOutcome::Continue)
+    }
+}
+
+struct ExitCommand;
+
+#[async_trait]
+impl SlashHandler for ExitCommand {
+    async fn run(&self, _app: &mut App, _args: &SlashArgs) -> Result<SlashOutcome> {
+        Ok(SlashOutcome::Exit)
+    }
+}
@@ -0,0 +1,167 @@
+use crate::config::{PluginsConfig, WasmHook, WasmPluginConfig};
+use crate::ui::interface::UserInterface;
+use anyhow::{bail, Result};
+use colored::Colorize;
+use serde_json::Value;
+use std::path::{Component, Path, PathBuf};
+
+/// Where the project is mounted inside the sandbox
+pub const GUEST_ROOT: &str = "/project";
+
+/// A WebAssembly plugin from `[[plugins.wasm]]`. Each call runs the module
+/// (a WASI command) in a fresh sandbox with the JSON request on stdin and
+/// returns its stdout. The sandbox has no network and sees only the project
+/// directories the plugin was granted, read-only unless listed under `write`,
+/// so community plugins can be installed without trusting them.
+pub struct WasmPlugin {
+    config: WasmPluginConfig,
+    #[cfg_attr(not(feature = "wasm-plugins"), allow(dead_code))]
+    module_path: PathBuf,
+    /// Host directories, the guest paths they are mounted at and whether
+    /// they are writable
+    #[cfg_attr(not(feature = "wasm-plugins"), allow(dead_code))]
+    mounts: Vec<(PathBuf, String, bool)>,
+    /// Compiled on the first call
+    #[cfg(feature = "wasm-plugins")]
+    module: once_cell::sync::OnceCell<(wasmtime::Engine, wasmtime::Module)>,
+}
+
+impl WasmPlugin {
+    pub fn new(config: WasmPluginConfig, project_root: &Path) -> Result<Self> {
+        let mut mounts = Vec::new();
+        for (dirs, writable) in [(&config.read, false), (&config.write, true)] {
+            for dir in dirs {
+                let relative = Path::new(dir);
+                if relative.is_absolute() || relative.components().any(|c| c == Component::ParentDir) {
+                    bail!("Plugin '{}' may only be granted directories inside the project, not {}", config.name, dir);
+                }
+                let dir = dir.trim_start_matches("./").trim_end_matches('/');
+                let guest = if dir.is_empty() || dir == "." {
+                    GUEST_ROOT.to_string()
+                } else {
+                    format!("{}/{}", GUEST_ROOT, dir)
+                };
+                mounts.push((project_root.join(relative), guest, writable));
+            }
+        }
+        Ok(Self {
+            module_path: project_root.join(&config.path),
+            config,
+            mounts,
+            #[cfg(feature = "wasm-plugins")]
+            module: once_cell::sync::OnceCell::new(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Runs the plugin with `request` on stdin and returns what it printed
+    pub fn call(&self, request: &Value) -> Result<String> {
+        // The WASI runtime blocks on its own executor, which can't be
+        // entered from a thread of the app's async runtime
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| self.run(request.to_string().into_bytes()))
+                .join()
+                .unwrap_or_else(|_| bail!("Plugin '{}' panicked", self.name()))
+        })
+    }
+
+    #[cfg(not(feature = "wasm-plugins"))]
+    fn run(&self, _input: Vec<u8>) -> Result<String> {
+        bail!("Plugin '{}' needs a build with the `wasm-plugins` feature", self.name())
+    }
+}
+
+/// Plugins of `[[plugins.wasm]]` called for `hook`. Misconfigured plugins
+/// are reported and skipped.
+pub fn load_plugins(config: &PluginsConfig, project_root: &Path, hook: WasmHook, ui: &dyn UserInterface) -> Vec<WasmPlugin> {
+    config
+        .wasm
+        .iter()
+        .filter(|plugin| plugin.hooks.contains(&hook))
+        .filter_map(|plugin| match WasmPlugin::new(plugin.clone(), project_root) {
+            Ok(plugin) => Some(plugin),
+            Err(e) => {
+                ui.error(&format!("{} {}", "!".yellow(), e));
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "wasm-plugins")]
+mod runtime {
+    use super::WasmPlugin;
+    use anyhow::{bail, Context, Result};
+    use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+    use wasmtime_wasi::pipe::{MemoryInputPipe, MemoryOutputPipe};
+    use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+    use wasmtime_wasi::{DirPerms, FilePerms, I32Exit, WasiCtxBuilder};
+
+    /// Instructions a call may execute, and the memory it may use
+    const MAX_FUEL: u64 = 10_000_000_000;
+    const MAX_MEMORY_BYTES: usize = 256 * 1024 * 1024;
+    /// Output kept from stdout and stderr
+    const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+    struct State {
+        wasi: WasiP1Ctx,
+        limits: StoreLimits,
+    }
+
+    impl WasmPlugin {
+        pub(super) fn run(&self, input: Vec<u8>) -> Result<String> {
+            let (engine, module) = self.module.get_or_try_init(|| -> Result<_> {
+                let mut config = Config::new();
+                config.consume_fuel(true);
+                let engine = Engine::new(&config)?;
+                let module = Module::from_file(&engine, &self.module_path)
+                    .with_context(|| format!("Failed to load plugin {}", self.module_path.display()))?;
+                Ok((engine, module))
+            })?;
+
+            let stdout = MemoryOutputPipe::new(MAX_OUTPUT_BYTES);
+            let stderr = MemoryOutputPipe::new(MAX_OUTPUT_BYTES);
+            let mut wasi = WasiCtxBuilder::new();
+            wasi.stdin(MemoryInputPipe::new(input)).stdout(stdout.clone()).stderr(stderr.clone());
+            for (host, guest, writable) in &self.mounts {
+                let (dir_perms, file_perms) = if *writable {
+                    (DirPerms::all(), FilePerms::all())
+                } else {
+                    (DirPerms::READ, FilePerms::READ)
+                };
+                wasi.preopened_dir(host, guest, dir_perms, file_perms)
+                    .with_context(|| format!("Failed to grant plugin '{}' access to {}", self.name(), host.display()))?;
+            }
+
+            let state = State {
+                wasi: wasi.build_p1(),
+                limits: StoreLimitsBuilder::new().memory_size(MAX_MEMORY_BYTES).build(),
+            };
+            let mut store = Store::new(engine, state);
+            store.limiter(|state| &mut state.limits);
+            store.set_fuel(MAX_FUEL)?;
+
+            let mut linker: Linker<State> = Linker::new(engine);
+            preview1::add_to_linker_sync(&mut linker, |state| &mut state.wasi)?;
+            let instance = linker.instantiate(&mut store, module)?;
+            let start = instance.get_typed_func::<(), ()>(&mut store, "_start")?;
+            if let Err(e) = start.call(&mut store, ()) {
+                match e.downcast_ref::<I32Exit>() {
+                    Some(I32Exit(0)) => {}
+                    Some(I32Exit(code)) => bail!(
+                        "Plugin '{}' exited with {}: {}",
+                        self.name(),
+                        code,
+                        String::from_utf8_lossy(&stderr.contents()).trim()
+                    ),
+                    None => return Err(e).with_context(|| format!("Plugin '{}' failed", self.name())),
+                }
+            }
+            Ok(String::from_utf8_lossy(&stdout.contents()).into_owned())
+        }
+    }
+}